@@ -0,0 +1,14 @@
+//! Usage statements and invoice exports for the LimitLayer protocol.
+//!
+//! Statements are built from the events the indexer stored ([`statement`]):
+//! one line per finalized checkpoint, with its billed requests and cost, the
+//! protocol fee in effect and the signatures of the transactions behind it.
+//! [`render`] prints them as CSV, JSON or a printable statement, and
+//! [`verify`] checks the lines against the checkpoint accounts on chain.
+
+pub mod render;
+pub mod statement;
+pub mod verify;
+
+pub use statement::{statement, KeyStatement, Period, Statement, StatementLine, Totals};
+pub use verify::verify;
@@ -0,0 +1,98 @@
+//! `limitlayer-billing`: prints usage statements from an indexer database.
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
+use limitlayer_billing::{render, statement, verify, Period};
+use limitlayer_client::rpc::{Commitment, RpcClient};
+use limitlayer_indexer::Store;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Printable statement
+    Text,
+    /// One row per finalized checkpoint
+    Csv,
+    Json,
+}
+
+#[derive(Parser)]
+#[command(
+    name = "limitlayer-billing",
+    version,
+    about = "Export usage statements from finalized LimitLayer checkpoints"
+)]
+struct Cli {
+    /// Database written by limitlayer-indexer
+    #[arg(long, env = "LIMITLAYER_INDEXER_DB", default_value = "limitlayer.db")]
+    db: PathBuf,
+
+    /// Calendar month to bill, as YYYY-MM
+    #[arg(long, required_unless_present = "from", conflicts_with_all = ["from", "to"])]
+    month: Option<String>,
+    /// First day of the period, as YYYY-MM-DD
+    #[arg(long, requires = "to")]
+    from: Option<String>,
+    /// Day after the period, as YYYY-MM-DD
+    #[arg(long, requires = "from")]
+    to: Option<String>,
+
+    /// Only keys of this service
+    #[arg(long)]
+    service: Option<Pubkey>,
+    /// Only this key
+    #[arg(long)]
+    api_key: Option<Pubkey>,
+
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Check every line against its checkpoint account before printing
+    #[arg(long)]
+    verify: bool,
+    #[arg(
+        long,
+        short = 'u',
+        env = "LIMITLAYER_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let period = match (cli.month, cli.from, cli.to) {
+        (Some(month), _, _) => Period::month(&month)?,
+        (None, Some(start), Some(end)) => Period { start, end },
+        _ => bail!("give --month or --from and --to"),
+    };
+    if !cli.db.exists() {
+        bail!("no indexer database at {}", cli.db.display());
+    }
+    let store = Store::open(&cli.db)?;
+    let statement = statement(&store, &period, cli.service.as_ref(), cli.api_key.as_ref())?;
+
+    if cli.verify {
+        let rpc = RpcClient::new(cli.url).with_commitment(Commitment::Finalized);
+        let differences = verify(&statement, &rpc)?;
+        if !differences.is_empty() {
+            for difference in &differences {
+                eprintln!("{difference}");
+            }
+            bail!(
+                "the statement differs from chain state in {} places",
+                differences.len()
+            );
+        }
+    }
+
+    let output = match cli.format {
+        Format::Text => render::text(&statement),
+        Format::Csv => render::csv(&statement),
+        Format::Json => render::json(&statement) + "\n",
+    };
+    print!("{output}");
+    Ok(())
+}
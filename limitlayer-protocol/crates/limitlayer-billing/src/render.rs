@@ -0,0 +1,137 @@
+//! Statement output formats.
+
+use std::fmt::Write;
+
+use crate::statement::{Statement, StatementLine, Totals};
+
+pub const CSV_HEADER: &str = "period_start,period_end,service,api_key,usage_checkpoint,\
+checkpoint_seq,finalized_utc,request_count,cost,reported_request_count,reported_cost,fee_bps,\
+protocol_fee,net,applied_signature,dispute_signature,finalized_signature,fee_signature";
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+/// One row per line. No field can contain a comma or quote, so none is
+/// quoted.
+pub fn csv(statement: &Statement) -> String {
+    let mut out = format!("{CSV_HEADER}\n");
+    for key in &statement.keys {
+        for line in &key.lines {
+            let fields = [
+                statement.period.start.clone(),
+                statement.period.end.clone(),
+                key.service.clone(),
+                key.api_key.clone(),
+                line.usage_checkpoint.clone(),
+                line.checkpoint_seq.to_string(),
+                line.finalized_utc.clone(),
+                line.request_count.to_string(),
+                line.cost.to_string(),
+                optional(&line.reported_request_count),
+                optional(&line.reported_cost),
+                line.fee_bps.to_string(),
+                line.protocol_fee.to_string(),
+                line.net.to_string(),
+                optional(&line.applied_signature),
+                optional(&line.dispute_signature),
+                line.finalized_signature.clone(),
+                line.fee_signature.clone(),
+            ];
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+pub fn json(statement: &Statement) -> String {
+    serde_json::to_string_pretty(statement).expect("statements serialize")
+}
+
+fn totals_row(out: &mut String, label: &str, totals: &Totals) {
+    writeln!(
+        out,
+        "  {label:<24} {:>6} {:>12} {:>14} {:>8} {:>14} {:>14}",
+        "", totals.request_count, totals.cost, "", totals.protocol_fee, totals.net
+    )
+    .unwrap();
+}
+
+fn line_row(out: &mut String, line: &StatementLine) {
+    writeln!(
+        out,
+        "  {:<24} {:>6} {:>12} {:>14} {:>8} {:>14} {:>14}",
+        line.finalized_utc,
+        line.checkpoint_seq,
+        line.request_count,
+        line.cost,
+        line.fee_bps,
+        line.protocol_fee,
+        line.net
+    )
+    .unwrap();
+    let disputed = match (line.reported_request_count, line.reported_cost) {
+        (Some(requests), Some(cost)) if requests != line.request_count || cost != line.cost => {
+            format!(", reduced by dispute from {requests} requests / {cost} cost")
+        }
+        _ => String::new(),
+    };
+    writeln!(out, "      checkpoint {}{disputed}", line.usage_checkpoint).unwrap();
+    writeln!(out, "      finalized  {}", line.finalized_signature).unwrap();
+    if let Some(signature) = &line.applied_signature {
+        writeln!(out, "      applied    {signature}").unwrap();
+    }
+    if let Some(signature) = &line.dispute_signature {
+        writeln!(out, "      resolved   {signature}").unwrap();
+    }
+}
+
+/// A statement to print: one section per key, each line followed by the
+/// transactions backing it.
+pub fn text(statement: &Statement) -> String {
+    let mut out = String::new();
+    writeln!(out, "LimitLayer usage statement").unwrap();
+    writeln!(
+        out,
+        "Period   {} to {} (exclusive, UTC)",
+        statement.period.start, statement.period.end
+    )
+    .unwrap();
+    if let Some(service) = &statement.service {
+        writeln!(out, "Service  {service}").unwrap();
+    }
+    if let Some(api_key) = &statement.api_key {
+        writeln!(out, "API key  {api_key}").unwrap();
+    }
+
+    for key in &statement.keys {
+        writeln!(out).unwrap();
+        writeln!(out, "API key {} (service {})", key.api_key, key.service).unwrap();
+        writeln!(
+            out,
+            "  {:<24} {:>6} {:>12} {:>14} {:>8} {:>14} {:>14}",
+            "Finalized (UTC)", "Seq", "Requests", "Cost", "Fee bps", "Protocol fee", "Net"
+        )
+        .unwrap();
+        for line in &key.lines {
+            line_row(&mut out, line);
+        }
+        totals_row(&mut out, "Total", &key.totals);
+    }
+
+    writeln!(out).unwrap();
+    if statement.keys.is_empty() {
+        writeln!(out, "No checkpoints were finalized in this period.").unwrap();
+    } else {
+        writeln!(
+            out,
+            "{} checkpoints across {} keys",
+            statement.totals.checkpoints,
+            statement.keys.len()
+        )
+        .unwrap();
+        totals_row(&mut out, "Statement total", &statement.totals);
+    }
+    out
+}
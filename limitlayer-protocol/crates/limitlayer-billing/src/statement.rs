@@ -0,0 +1,284 @@
+//! Statements built from the indexer's database.
+//!
+//! A line is a finalized checkpoint: `UsageCheckpointFinalized` carries the
+//! billed request count and cost, which the program accumulated from the
+//! key's `cost_per_request` and which a dispute may have reduced. Lines
+//! fall in the period containing the block time of the finalizing
+//! transaction, in UTC, as in the indexer's `key_usage_history`. The
+//! protocol fee is `cost * protocol_fee_bps / 10_000`, rounded down, with
+//! the fee set by the last `ProtocolInitialized` or `ProtocolUpdated` before
+//! the finalization. Nothing depends on when the statement is built, so a
+//! complete index always gives the same statement.
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Context, Result};
+use limitlayer_indexer::Store;
+use rusqlite::{params, types::Value, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+const MAX_BPS: u128 = 10_000;
+
+/// UTC days `start` up to but excluding `end`, both `YYYY-MM-DD`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Period {
+    pub start: String,
+    pub end: String,
+}
+
+impl Period {
+    /// The calendar month `month`, given as `YYYY-MM`.
+    pub fn month(month: &str) -> Result<Self> {
+        let parsed = month
+            .split_once('-')
+            .filter(|(year, month)| year.len() == 4 && month.len() == 2)
+            .and_then(|(year, month)| Some((year.parse::<u32>().ok()?, month.parse::<u32>().ok()?)))
+            .filter(|(_, month)| (1..=12).contains(month));
+        let Some((year, month)) = parsed else {
+            bail!("invalid month {month}, expected YYYY-MM");
+        };
+        let (next_year, next_month) = match month {
+            12 => (year + 1, 1),
+            month => (year, month + 1),
+        };
+        Ok(Self {
+            start: format!("{year:04}-{month:02}-01"),
+            end: format!("{next_year:04}-{next_month:02}-01"),
+        })
+    }
+}
+
+/// One finalized checkpoint, with the transactions it can be checked
+/// against.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StatementLine {
+    pub usage_checkpoint: String,
+    pub checkpoint_seq: u64,
+    /// Block time of the finalization
+    pub finalized_at: i64,
+    /// The same, as `YYYY-MM-DD HH:MM:SS` in UTC
+    pub finalized_utc: String,
+    pub request_count: u64,
+    pub cost: u64,
+    /// As applied, before a dispute reduced them; `None` when the
+    /// application is not indexed
+    pub reported_request_count: Option<u64>,
+    pub reported_cost: Option<u64>,
+    pub fee_bps: u16,
+    pub protocol_fee: u64,
+    /// `cost` less the protocol fee
+    pub net: u64,
+    pub applied_signature: Option<String>,
+    pub dispute_signature: Option<String>,
+    pub finalized_signature: String,
+    /// Transaction that set `fee_bps`
+    pub fee_signature: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Totals {
+    pub checkpoints: u64,
+    pub request_count: u128,
+    pub cost: u128,
+    pub protocol_fee: u128,
+    pub net: u128,
+}
+
+impl Totals {
+    fn add(&mut self, line: &StatementLine) {
+        self.checkpoints += 1;
+        self.request_count += line.request_count as u128;
+        self.cost += line.cost as u128;
+        self.protocol_fee += line.protocol_fee as u128;
+        self.net += line.net as u128;
+    }
+
+    fn merge(&mut self, other: &Totals) {
+        self.checkpoints += other.checkpoints;
+        self.request_count += other.request_count;
+        self.cost += other.cost;
+        self.protocol_fee += other.protocol_fee;
+        self.net += other.net;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct KeyStatement {
+    pub api_key: String,
+    pub service: String,
+    /// In the order they were finalized
+    pub lines: Vec<StatementLine>,
+    pub totals: Totals,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Statement {
+    pub period: Period,
+    pub service: Option<String>,
+    pub api_key: Option<String>,
+    /// Ordered by API key
+    pub keys: Vec<KeyStatement>,
+    pub totals: Totals,
+}
+
+/// A `u64` column, stored as text when above `i64::MAX`.
+fn u64_column(row: &Row, index: usize) -> rusqlite::Result<Option<u64>> {
+    let invalid =
+        || rusqlite::Error::InvalidColumnType(index, "u64".into(), rusqlite::types::Type::Text);
+    match row.get::<_, Value>(index)? {
+        Value::Null => Ok(None),
+        Value::Integer(value) => u64::try_from(value).map(Some).map_err(|_| invalid()),
+        Value::Text(text) => text.parse().map(Some).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+fn check_day(conn: &Connection, day: &str) -> Result<()> {
+    let valid: bool = conn.query_row("SELECT date(?1) IS ?1", [day], |row| row.get(0))?;
+    if !valid {
+        bail!("invalid date {day}, expected YYYY-MM-DD");
+    }
+    Ok(())
+}
+
+/// Fee in effect just before the event at (`slot`, `tx`, `event_index`),
+/// with the signature that set it.
+fn fee_at(
+    conn: &Connection,
+    slot: i64,
+    tx: i64,
+    event_index: i64,
+) -> Result<Option<(u16, String)>> {
+    Ok(conn
+        .query_row(
+            "SELECT bps, signature FROM (
+                 SELECT p.slot, t.rowid AS tx, p.event_index, p.protocol_fee_bps AS bps,
+                        p.signature
+                 FROM protocol_initialized p JOIN transactions t USING (signature)
+                 UNION ALL
+                 SELECT p.slot, t.rowid, p.event_index, p.new_fee_bps, p.signature
+                 FROM protocol_updated p JOIN transactions t USING (signature)
+                 WHERE p.new_fee_bps IS NOT NULL
+             )
+             WHERE (slot, tx, event_index) < (?1, ?2, ?3)
+             ORDER BY slot DESC, tx DESC, event_index DESC LIMIT 1",
+            params![slot, tx, event_index],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?)
+}
+
+/// Builds the statement for `period`, for every key or only those of
+/// `service` / the single `api_key`.
+pub fn statement(
+    store: &Store,
+    period: &Period,
+    service: Option<&Pubkey>,
+    api_key: Option<&Pubkey>,
+) -> Result<Statement> {
+    let conn = store.connection();
+    check_day(conn, &period.start)?;
+    check_day(conn, &period.end)?;
+    if period.start >= period.end {
+        bail!(
+            "period start {} is not before its end {}",
+            period.start,
+            period.end
+        );
+    }
+    let service = service.map(Pubkey::to_string);
+    let api_key = api_key.map(Pubkey::to_string);
+
+    let undated: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM usage_checkpoint_finalized
+         WHERE block_time IS NULL AND (?1 IS NULL OR service = ?1) AND (?2 IS NULL OR api_key = ?2)",
+        params![service, api_key],
+        |row| row.get(0),
+    )?;
+    if undated > 0 {
+        bail!(
+            "{undated} finalized checkpoints have no block time and cannot be placed in a period"
+        );
+    }
+
+    let mut query = conn.prepare(
+        "SELECT f.api_key, f.service, f.usage_checkpoint, f.checkpoint_seq,
+                f.billed_request_count, f.billed_cost, f.block_time, f.signature,
+                f.slot, t.rowid, f.event_index,
+                a.request_count, a.cost, a.signature, r.signature,
+                strftime('%Y-%m-%d %H:%M:%S', f.block_time, 'unixepoch')
+         FROM usage_checkpoint_finalized f
+         JOIN transactions t USING (signature)
+         LEFT JOIN usage_checkpoint_applied a ON a.usage_checkpoint = f.usage_checkpoint
+         LEFT JOIN usage_checkpoint_dispute_resolved r ON r.usage_checkpoint = f.usage_checkpoint
+         WHERE date(f.block_time, 'unixepoch') >= ?1 AND date(f.block_time, 'unixepoch') < ?2
+           AND (?3 IS NULL OR f.service = ?3) AND (?4 IS NULL OR f.api_key = ?4)
+         ORDER BY f.api_key, f.slot, t.rowid, f.event_index",
+    )?;
+    let rows = query.query_map(params![period.start, period.end, service, api_key], |row| {
+        let line = StatementLine {
+            usage_checkpoint: row.get(2)?,
+            checkpoint_seq: u64_column(row, 3)?.unwrap_or_default(),
+            request_count: u64_column(row, 4)?.unwrap_or_default(),
+            cost: u64_column(row, 5)?.unwrap_or_default(),
+            finalized_at: row.get(6)?,
+            finalized_utc: row.get(15)?,
+            finalized_signature: row.get(7)?,
+            reported_request_count: u64_column(row, 11)?,
+            reported_cost: u64_column(row, 12)?,
+            applied_signature: row.get(13)?,
+            dispute_signature: row.get(14)?,
+            fee_bps: 0,
+            protocol_fee: 0,
+            net: 0,
+            fee_signature: String::new(),
+        };
+        let position: (i64, i64, i64) = (row.get(8)?, row.get(9)?, row.get(10)?);
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            position,
+            line,
+        ))
+    })?;
+
+    let mut keys: Vec<KeyStatement> = Vec::new();
+    let mut totals = Totals::default();
+    for row in rows {
+        let (api_key, service, (slot, tx, event_index), mut line) = row?;
+        let (fee_bps, fee_signature) = fee_at(conn, slot, tx, event_index)?.with_context(|| {
+            format!(
+                "no protocol fee is indexed before checkpoint {} (slot {slot}); \
+                     index the program's history from its ProtocolInitialized",
+                line.usage_checkpoint
+            )
+        })?;
+        line.fee_bps = fee_bps;
+        line.fee_signature = fee_signature;
+        line.protocol_fee = (line.cost as u128 * fee_bps as u128 / MAX_BPS) as u64;
+        line.net = line.cost - line.protocol_fee;
+
+        match keys.last_mut() {
+            Some(key) if key.api_key == api_key => {}
+            _ => keys.push(KeyStatement {
+                api_key,
+                service,
+                lines: Vec::new(),
+                totals: Totals::default(),
+            }),
+        }
+        let key = keys.last_mut().expect("pushed above");
+        key.totals.add(&line);
+        key.lines.push(line);
+    }
+    for key in &keys {
+        totals.merge(&key.totals);
+    }
+
+    Ok(Statement {
+        period: period.clone(),
+        service,
+        api_key,
+        keys,
+        totals,
+    })
+}
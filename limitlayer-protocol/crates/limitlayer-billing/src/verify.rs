@@ -0,0 +1,62 @@
+//! Checking statement lines against the `UsageCheckpoint` accounts on
+//! chain.
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use limitlayer_client::{
+    program::{CheckpointStatus, UsageCheckpoint},
+    rpc::RpcClient,
+};
+
+use crate::statement::Statement;
+
+/// Compares every line with its checkpoint account: it must be finalized,
+/// belong to the line's key and have billed what the line says. Returns
+/// one message per difference. `rpc` should read finalized state, which
+/// is what statements are built from.
+pub fn verify(statement: &Statement, rpc: &RpcClient) -> Result<Vec<String>> {
+    let lines: Vec<_> = statement
+        .keys
+        .iter()
+        .flat_map(|key| key.lines.iter().map(move |line| (key, line)))
+        .collect();
+    let addresses = lines
+        .iter()
+        .map(|(_, line)| {
+            line.usage_checkpoint
+                .parse()
+                .with_context(|| format!("invalid checkpoint address {}", line.usage_checkpoint))
+        })
+        .collect::<Result<Vec<Pubkey>>>()?;
+
+    let mut differences = Vec::new();
+    let checkpoints = rpc.accounts::<UsageCheckpoint>(&addresses)?;
+    for ((key, line), checkpoint) in lines.iter().zip(checkpoints) {
+        let address = &line.usage_checkpoint;
+        let Some(checkpoint) = checkpoint else {
+            differences.push(format!("checkpoint {address} does not exist"));
+            continue;
+        };
+        if checkpoint.api_key.to_string() != key.api_key {
+            differences.push(format!(
+                "checkpoint {address} belongs to key {}, not {}",
+                checkpoint.api_key, key.api_key
+            ));
+        }
+        if checkpoint.status != CheckpointStatus::Finalized {
+            differences.push(format!("checkpoint {address} is not finalized"));
+        }
+        if (checkpoint.billed_request_count, checkpoint.billed_cost)
+            != (line.request_count, line.cost)
+        {
+            differences.push(format!(
+                "checkpoint {address} billed {} requests / {} cost, the statement {} / {}",
+                checkpoint.billed_request_count,
+                checkpoint.billed_cost,
+                line.request_count,
+                line.cost
+            ));
+        }
+    }
+    Ok(differences)
+}
@@ -0,0 +1,460 @@
+//! Builds statements from indexed checkpoint and protocol fee events, and
+//! checks them against canned checkpoint accounts.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    thread,
+};
+
+use anchor_lang::{prelude::Pubkey, AccountSerialize, Event};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use limitlayer_billing::{render, statement, verify, Period, Statement};
+use limitlayer_client::{
+    program::{events::*, CheckpointStatus, UsageCheckpoint},
+    rpc::RpcClient,
+    ID,
+};
+use limitlayer_indexer::{RecordedTransaction, Store};
+use serde_json::{json, Value};
+
+/// 2026-01-05 10:00:00 UTC
+const JANUARY: i64 = 1_767_607_200;
+/// 2026-02-02 00:00:00 UTC
+const FEBRUARY: i64 = 1_769_990_400;
+
+fn transaction(slot: u64, block_time: Option<i64>, events: &[Vec<u8>]) -> RecordedTransaction {
+    let mut logs = vec![format!("Program {ID} invoke [1]")];
+    logs.extend(
+        events
+            .iter()
+            .map(|data| format!("Program data: {}", STANDARD.encode(data))),
+    );
+    logs.push(format!("Program {ID} success"));
+    RecordedTransaction {
+        slot,
+        signature: format!("signature-{slot}"),
+        block_time,
+        failed: false,
+        logs,
+    }
+}
+
+struct Checkpoint {
+    address: Pubkey,
+    api_key: Pubkey,
+    service: Pubkey,
+    seq: u64,
+}
+
+impl Checkpoint {
+    fn new(api_key: Pubkey, service: Pubkey, seq: u64) -> Self {
+        Self {
+            address: Pubkey::new_unique(),
+            api_key,
+            service,
+            seq,
+        }
+    }
+
+    fn applied(&self, request_count: u64, cost: u64) -> Vec<u8> {
+        UsageCheckpointApplied {
+            usage_checkpoint: self.address,
+            api_key: self.api_key,
+            service: self.service,
+            checkpoint_seq: self.seq,
+            request_count,
+            cost,
+            request_log_root: [0; 32],
+            request_log_truncated: false,
+            finalizes_at: JANUARY,
+        }
+        .data()
+    }
+
+    fn finalized(&self, billed_request_count: u64, billed_cost: u64) -> Vec<u8> {
+        UsageCheckpointFinalized {
+            usage_checkpoint: self.address,
+            api_key: self.api_key,
+            service: self.service,
+            checkpoint_seq: self.seq,
+            billed_request_count,
+            billed_cost,
+        }
+        .data()
+    }
+
+    fn account(&self, billed_request_count: u64, billed_cost: u64) -> UsageCheckpoint {
+        UsageCheckpoint {
+            api_key: self.api_key,
+            checkpoint_seq: self.seq,
+            window_start: JANUARY,
+            request_count: billed_request_count,
+            cost_accumulated: billed_cost,
+            last_updated: JANUARY,
+            request_log_root: [0; 32],
+            status: CheckpointStatus::Finalized,
+            finalizes_at: JANUARY,
+            disputer: Pubkey::default(),
+            dispute_reason: None,
+            evidence_hash: [0; 32],
+            billed_request_count,
+            billed_cost,
+            lagging: false,
+            request_log_truncated: false,
+            bump: 255,
+        }
+    }
+}
+
+fn fee_update(new_fee_bps: Option<u16>) -> Vec<u8> {
+    ProtocolUpdated {
+        protocol: Pubkey::new_unique(),
+        new_fee_bps,
+        new_treasury: None,
+        paused: Some(false),
+    }
+    .data()
+}
+
+struct Fixture {
+    store: Store,
+    search: Pubkey,
+    key: Pubkey,
+    other: Pubkey,
+    checkpoints: Vec<Checkpoint>,
+}
+
+/// Three checkpoints finalized in January across two services, under two
+/// fee rates, one of them reduced by a dispute; one more in February.
+fn fixture() -> Fixture {
+    let (search, billing) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (key, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let checkpoints = vec![
+        Checkpoint::new(key, search, 0),
+        Checkpoint::new(key, search, 1),
+        Checkpoint::new(other, billing, 0),
+        Checkpoint::new(key, search, 2),
+    ];
+    let [first, disputed, elsewhere, later] = &checkpoints[..] else {
+        unreachable!()
+    };
+    let initialized = ProtocolInitialized {
+        protocol: Pubkey::new_unique(),
+        admin: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        protocol_fee_bps: 250,
+    }
+    .data();
+    let resolved = UsageCheckpointDisputeResolved {
+        usage_checkpoint: disputed.address,
+        api_key: key,
+        reported_request_count: 50,
+        reported_cost: 101,
+        billed_request_count: 40,
+        billed_cost: 81,
+    }
+    .data();
+
+    let at = Some(JANUARY);
+    let transactions = [
+        transaction(1, at, &[initialized]),
+        transaction(2, at, &[first.applied(100, 200)]),
+        transaction(3, at, &[first.finalized(100, 200)]),
+        transaction(4, at, &[fee_update(Some(100))]),
+        // Pausing leaves the fee alone
+        transaction(5, at, &[fee_update(None), disputed.applied(50, 101)]),
+        transaction(6, at, &[resolved, disputed.finalized(40, 81)]),
+        transaction(7, at, &[elsewhere.applied(10, 1_000)]),
+        transaction(8, at, &[elsewhere.finalized(10, 1_000)]),
+        transaction(9, Some(FEBRUARY), &[later.applied(1, 2)]),
+        transaction(10, Some(FEBRUARY), &[later.finalized(1, 2)]),
+    ];
+    let mut store = Store::open_in_memory().unwrap();
+    store.ingest(&transactions).unwrap();
+    Fixture {
+        store,
+        search,
+        key,
+        other,
+        checkpoints,
+    }
+}
+
+fn january(fixture: &Fixture) -> Statement {
+    statement(
+        &fixture.store,
+        &Period::month("2026-01").unwrap(),
+        None,
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn builds_statement_from_finalized_checkpoints() {
+    let fixture = fixture();
+    let statement = january(&fixture);
+
+    let key = statement
+        .keys
+        .iter()
+        .find(|key| key.api_key == fixture.key.to_string())
+        .unwrap();
+    assert_eq!(key.service, fixture.search.to_string());
+    let lines: Vec<_> = key
+        .lines
+        .iter()
+        .map(|line| {
+            (
+                line.checkpoint_seq,
+                line.request_count,
+                line.cost,
+                line.fee_bps,
+                line.protocol_fee,
+                line.net,
+                line.fee_signature.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            (0, 100, 200, 250, 5, 195, "signature-1"),
+            // 81 * 1% rounds down to nothing
+            (1, 40, 81, 100, 0, 81, "signature-4"),
+        ]
+    );
+    let disputed = &key.lines[1];
+    assert_eq!(
+        (disputed.reported_request_count, disputed.reported_cost),
+        (Some(50), Some(101))
+    );
+    assert_eq!(disputed.applied_signature.as_deref(), Some("signature-5"));
+    assert_eq!(disputed.dispute_signature.as_deref(), Some("signature-6"));
+    assert_eq!(disputed.finalized_signature, "signature-6");
+    assert_eq!(disputed.finalized_utc, "2026-01-05 10:00:00");
+
+    let other = statement
+        .keys
+        .iter()
+        .find(|key| key.api_key == fixture.other.to_string())
+        .unwrap();
+    assert_eq!((other.totals.protocol_fee, other.totals.net), (10, 990));
+    assert_eq!(statement.keys.len(), 2);
+    assert_eq!(
+        (
+            statement.totals.checkpoints,
+            statement.totals.request_count,
+            statement.totals.cost,
+            statement.totals.protocol_fee,
+            statement.totals.net,
+        ),
+        (3, 150, 1_281, 15, 1_266)
+    );
+
+    // Built again, the statement is the same
+    assert_eq!(january(&fixture), statement);
+
+    let search_only = statement_for(&fixture, Some(&fixture.search));
+    assert_eq!(search_only.keys.len(), 1);
+    assert_eq!(search_only.totals.cost, 281);
+
+    let february = limitlayer_billing::statement(
+        &fixture.store,
+        &Period::month("2026-02").unwrap(),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(february.totals.checkpoints, 1);
+    assert_eq!(february.keys[0].lines[0].fee_bps, 100);
+}
+
+fn statement_for(fixture: &Fixture, service: Option<&Pubkey>) -> Statement {
+    statement(
+        &fixture.store,
+        &Period::month("2026-01").unwrap(),
+        service,
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn renders_csv_json_and_text() {
+    let fixture = fixture();
+    let statement = statement_for(&fixture, Some(&fixture.search));
+
+    let csv = render::csv(&statement);
+    let rows: Vec<Vec<&str>> = csv.lines().map(|row| row.split(',').collect()).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].join(","), render::CSV_HEADER);
+    let column = |name: &str| rows[0].iter().position(|header| *header == name).unwrap();
+    let disputed = &rows[2];
+    assert_eq!(disputed[column("period_start")], "2026-01-01");
+    assert_eq!(disputed[column("period_end")], "2026-02-01");
+    assert_eq!(disputed[column("cost")], "81");
+    assert_eq!(disputed[column("reported_cost")], "101");
+    assert_eq!(disputed[column("dispute_signature")], "signature-6");
+    assert_eq!(rows[1][column("dispute_signature")], "");
+
+    let json: Value = serde_json::from_str(&render::json(&statement)).unwrap();
+    assert_eq!(json["totals"]["protocol_fee"], 5);
+    assert_eq!(
+        json["keys"][0]["lines"][0]["usage_checkpoint"],
+        fixture.checkpoints[0].address.to_string()
+    );
+
+    let text = render::text(&statement);
+    assert!(text.contains("Period   2026-01-01 to 2026-02-01"));
+    assert!(text.contains("reduced by dispute from 50 requests / 101 cost"));
+    assert!(text.contains("2 checkpoints across 1 keys"));
+    let total = text
+        .lines()
+        .find(|line| line.trim_start().starts_with("Statement total"))
+        .unwrap();
+    assert_eq!(
+        total.split_whitespace().collect::<Vec<_>>(),
+        ["Statement", "total", "140", "281", "5", "276"]
+    );
+}
+
+#[test]
+fn refuses_statements_it_cannot_reproduce() {
+    let key = Checkpoint::new(Pubkey::new_unique(), Pubkey::new_unique(), 0);
+    let period = Period::month("2026-01").unwrap();
+
+    // No fee is known before the checkpoint
+    let mut store = Store::open_in_memory().unwrap();
+    store
+        .ingest(&[transaction(1, Some(JANUARY), &[key.finalized(1, 10)])])
+        .unwrap();
+    let error = statement(&store, &period, None, None).unwrap_err();
+    assert!(error.to_string().contains("no protocol fee is indexed"));
+
+    // A checkpoint that cannot be placed in any period
+    let mut store = Store::open_in_memory().unwrap();
+    store
+        .ingest(&[
+            transaction(1, Some(JANUARY), &[fee_update(Some(10))]),
+            transaction(2, None, &[key.finalized(1, 10)]),
+        ])
+        .unwrap();
+    let error = statement(&store, &period, None, None).unwrap_err();
+    assert!(error.to_string().contains("have no block time"));
+
+    let invalid = |start: &str, end: &str| {
+        let period = Period {
+            start: start.to_string(),
+            end: end.to_string(),
+        };
+        statement(&store, &period, None, None)
+            .unwrap_err()
+            .to_string()
+    };
+    assert!(invalid("2026-02-30", "2026-03-01").contains("invalid date 2026-02-30"));
+    assert!(invalid("2026-03-01", "2026-02-01").contains("not before its end"));
+
+    assert_eq!(Period::month("2026-12").unwrap().end, "2027-01-01");
+    assert!(Period::month("2026-13").is_err());
+    assert!(Period::month("26-01").is_err());
+}
+
+/// Serves getMultipleAccounts from `accounts`, in the order asked for.
+fn serve(accounts: Vec<(Pubkey, UsageCheckpoint)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let request: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(request["method"], "getMultipleAccounts");
+            let values: Vec<Value> = request["params"][0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|address| {
+                    let address: Pubkey = address.as_str().unwrap().parse().unwrap();
+                    match accounts.iter().find(|(a, _)| *a == address) {
+                        Some((_, account)) => {
+                            let mut data = Vec::new();
+                            account.try_serialize(&mut data).unwrap();
+                            json!({ "data": [STANDARD.encode(data), "base64"] })
+                        }
+                        None => Value::Null,
+                    }
+                })
+                .collect();
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "value": values },
+            })
+            .to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+        }
+    });
+    url
+}
+
+#[test]
+fn verifies_lines_against_checkpoint_accounts() {
+    let fixture = fixture();
+    let statement = january(&fixture);
+    let [first, disputed, elsewhere, _] = &fixture.checkpoints[..] else {
+        unreachable!()
+    };
+
+    let matching = vec![
+        (first.address, first.account(100, 200)),
+        (disputed.address, disputed.account(40, 81)),
+        (elsewhere.address, elsewhere.account(10, 1_000)),
+    ];
+    let rpc = RpcClient::new(serve(matching));
+    assert_eq!(verify(&statement, &rpc).unwrap(), Vec::<String>::new());
+
+    let mut pending = disputed.account(40, 81);
+    pending.status = CheckpointStatus::Disputed;
+    let differing = vec![
+        (first.address, first.account(100, 199)),
+        (disputed.address, pending),
+    ];
+    let rpc = RpcClient::new(serve(differing));
+    let mut differences = verify(&statement, &rpc).unwrap();
+    differences.sort();
+    let mut expected = [
+        format!(
+            "checkpoint {} billed 100 requests / 199 cost, the statement 100 / 200",
+            first.address
+        ),
+        format!("checkpoint {} is not finalized", disputed.address),
+        format!("checkpoint {} does not exist", elsewhere.address),
+    ];
+    expected.sort();
+    assert_eq!(differences, expected);
+}
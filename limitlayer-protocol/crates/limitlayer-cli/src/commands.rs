@@ -0,0 +1,1020 @@
+//! One subcommand per program instruction. Each resolves the accounts it
+//! needs from chain and turns into a [`Plan`] that main signs and sends.
+
+use anyhow::{anyhow, bail, Result};
+use clap::{Subcommand, ValueEnum};
+use limitlayer_client::{
+    instructions::{self, Submitter, UsageBatchEntry},
+    pda,
+    program::{
+        self, instruction as ix, ApiKeyAccount, DelegatedUsageAccount, ProtocolState,
+        RateLimitPolicy, RequestLogLeaf, ServiceAccount, UsageReceipt,
+    },
+    rpc::RpcClient,
+};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+};
+
+use crate::read_keypair;
+
+/// Instructions to send, and keypairs that must sign besides the payer.
+pub struct Plan {
+    pub instructions: Vec<Instruction>,
+    pub signers: Vec<Keypair>,
+}
+
+impl From<Instruction> for Plan {
+    fn from(instruction: Instruction) -> Self {
+        Self {
+            instructions: vec![instruction],
+            signers: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ServiceStatusArg {
+    Active,
+    Paused,
+    Disabled,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ApiKeyStatusArg {
+    Active,
+    Throttled,
+    Blocked,
+    Revoked,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum RegionStatusArg {
+    Active,
+    Disabled,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DisputeReasonArg {
+    ConflictingReceipt,
+    InvalidMerkleProof,
+    Other,
+}
+
+impl From<ServiceStatusArg> for program::ServiceStatus {
+    fn from(status: ServiceStatusArg) -> Self {
+        match status {
+            ServiceStatusArg::Active => Self::Active,
+            ServiceStatusArg::Paused => Self::Paused,
+            ServiceStatusArg::Disabled => Self::Disabled,
+        }
+    }
+}
+
+impl From<ApiKeyStatusArg> for program::ApiKeyStatus {
+    fn from(status: ApiKeyStatusArg) -> Self {
+        match status {
+            ApiKeyStatusArg::Active => Self::Active,
+            ApiKeyStatusArg::Throttled => Self::Throttled,
+            ApiKeyStatusArg::Blocked => Self::Blocked,
+            ApiKeyStatusArg::Revoked => Self::Revoked,
+        }
+    }
+}
+
+impl From<RegionStatusArg> for program::ExecutionRegionStatus {
+    fn from(status: RegionStatusArg) -> Self {
+        match status {
+            RegionStatusArg::Active => Self::Active,
+            RegionStatusArg::Disabled => Self::Disabled,
+        }
+    }
+}
+
+impl From<DisputeReasonArg> for program::DisputeReason {
+    fn from(reason: DisputeReasonArg) -> Self {
+        match reason {
+            DisputeReasonArg::ConflictingReceipt => Self::ConflictingReceipt,
+            DisputeReasonArg::InvalidMerkleProof => Self::InvalidMerkleProof,
+            DisputeReasonArg::Other => Self::Other,
+        }
+    }
+}
+
+/// 32 bytes as 64 hex characters.
+fn parse_hash(s: &str) -> Result<[u8; 32], String> {
+    hex::decode(s)
+        .map_err(|e| e.to_string())?
+        .try_into()
+        .map_err(|_| "expected 32 bytes".to_string())
+}
+
+/// `API_KEY:AMOUNT[:REQUEST_ID]`
+fn parse_batch_entry(s: &str) -> Result<(Pubkey, u64, Option<u64>), String> {
+    let mut parts = s.split(':');
+    let (Some(api_key), Some(amount)) = (parts.next(), parts.next()) else {
+        return Err("expected API_KEY:AMOUNT[:REQUEST_ID]".to_string());
+    };
+    let request_id = parts
+        .next()
+        .map(|id| id.parse::<u64>().map_err(|e| e.to_string()))
+        .transpose()?;
+    Ok((
+        api_key.parse().map_err(|e| format!("{e}"))?,
+        amount.parse().map_err(|e| format!("{e}"))?,
+        request_id,
+    ))
+}
+
+/// `signer` reporting usage for `service`: as its authority, or otherwise
+/// as one of its registered gateways.
+fn submitter(rpc: &RpcClient, signer: Pubkey, service: Pubkey) -> Result<Submitter> {
+    let account: ServiceAccount = rpc.fetch(&service)?;
+    Ok(match account.authority == signer {
+        true => Submitter::authority(signer, service),
+        false => Submitter::gateway(signer, service),
+    })
+}
+
+#[derive(Subcommand)]
+pub enum InstructionCommand {
+    // PROTOCOL
+    /// Create the protocol state, signed by the future admin
+    InitializeProtocol {
+        #[arg(long)]
+        fee_bps: u16,
+        #[arg(long)]
+        treasury: Pubkey,
+    },
+    /// Change protocol fee, treasury or pause state (admin)
+    UpdateProtocol {
+        #[arg(long)]
+        fee_bps: Option<u16>,
+        #[arg(long)]
+        treasury: Option<Pubkey>,
+        #[arg(long)]
+        paused: Option<bool>,
+    },
+
+    // SERVICE
+    /// Register a service owned by the configured keypair
+    CreateService {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        default_policy: Pubkey,
+    },
+    /// Change a service's authority, default policy or challenge period
+    UpdateService {
+        service: Pubkey,
+        #[arg(long)]
+        authority: Option<Pubkey>,
+        #[arg(long)]
+        default_policy: Option<Pubkey>,
+        #[arg(long)]
+        challenge_period_seconds: Option<u64>,
+    },
+    /// Activate, pause or disable a service
+    SetServiceStatus {
+        service: Pubkey,
+        #[arg(long, value_enum)]
+        status: ServiceStatusArg,
+    },
+
+    // POLICY
+    /// Create a rate limit policy for a service
+    CreatePolicy {
+        service: Pubkey,
+        #[arg(long)]
+        requests_per_window: u64,
+        #[arg(long)]
+        window_seconds: u64,
+        #[arg(long)]
+        burst_limit: u64,
+        #[arg(long)]
+        cost_per_request: u64,
+        #[arg(long, default_value_t = 0)]
+        daily_quota: u64,
+        #[arg(long, default_value_t = 0)]
+        monthly_quota: u64,
+    },
+    /// Change fields of a policy; omitted fields are kept
+    UpdatePolicy {
+        policy: Pubkey,
+        #[arg(long)]
+        requests_per_window: Option<u64>,
+        #[arg(long)]
+        window_seconds: Option<u64>,
+        #[arg(long)]
+        burst_limit: Option<u64>,
+        #[arg(long)]
+        cost_per_request: Option<u64>,
+        #[arg(long)]
+        daily_quota: Option<u64>,
+        #[arg(long)]
+        monthly_quota: Option<u64>,
+    },
+    /// Switch an API key to another policy of its service
+    AttachPolicyToKey { api_key: Pubkey, policy: Pubkey },
+
+    // API KEY
+    /// Issue an API key; defaults to the service's default policy
+    CreateApiKey {
+        service: Pubkey,
+        /// Key owner; defaults to the configured keypair
+        #[arg(long)]
+        owner: Option<Pubkey>,
+        #[arg(long)]
+        policy: Option<Pubkey>,
+    },
+    /// Permanently revoke an API key
+    RevokeApiKey { api_key: Pubkey },
+    /// Set an API key's status
+    SetApiKeyStatus {
+        api_key: Pubkey,
+        #[arg(long, value_enum)]
+        status: ApiKeyStatusArg,
+    },
+
+    // EXECUTION REGIONS
+    /// Register an ephemeral rollup validator (admin)
+    RegisterExecutionRegion {
+        validator: Pubkey,
+        #[arg(long)]
+        region_code: String,
+    },
+    /// Enable or disable an execution region (admin)
+    SetExecutionRegionStatus {
+        validator: Pubkey,
+        #[arg(long, value_enum)]
+        status: RegionStatusArg,
+    },
+
+    // GATEWAYS
+    /// Register a gateway receipt signing key for a service
+    RegisterGateway {
+        service: Pubkey,
+        gateway_key: Pubkey,
+    },
+    /// Remove a gateway registration
+    RevokeGateway {
+        service: Pubkey,
+        gateway_key: Pubkey,
+    },
+    /// Sign a usage receipt with a gateway keypair and apply it
+    ApplyUsageReceipt {
+        api_key: Pubkey,
+        /// Keypair of a registered gateway
+        #[arg(long)]
+        gateway_keypair: String,
+        #[arg(long)]
+        window_start: i64,
+        #[arg(long)]
+        request_count: u64,
+        #[arg(long)]
+        cost: u64,
+        /// Hex; all zeros when no request log is kept
+        #[arg(long, value_parser = parse_hash)]
+        request_log_root: Option<[u8; 32]>,
+        #[arg(long)]
+        nonce: u64,
+    },
+
+    // MAGICBLOCK DELEGATION
+    /// Create the key's next usage shard
+    CreateUsageShard { api_key: Pubkey },
+    /// Bind a usage account to a region before delegating it
+    PrepareDelegation {
+        api_key: Pubkey,
+        #[arg(long)]
+        validator: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+        #[arg(long)]
+        commit_frequency_ms: u32,
+        #[arg(long)]
+        lease_seconds: u64,
+    },
+    /// Delegate a prepared usage account to its region
+    DelegateUsage {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+    },
+    /// Record usage on a delegated account (ephemeral rollup RPC)
+    RecordUsageRealtime {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        request_id: Option<u64>,
+    },
+    /// Record usage on the base layer for a key that is not delegated
+    RecordUsageDirect {
+        api_key: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        request_id: Option<u64>,
+    },
+    /// Hold usage ahead of a request (ephemeral rollup RPC)
+    ReserveUsage {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+        #[arg(long)]
+        amount: u64,
+        /// 0 uses the program default
+        #[arg(long, default_value_t = 0)]
+        ttl_seconds: u64,
+    },
+    /// Charge a reservation (ephemeral rollup RPC)
+    SettleUsage {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+        #[arg(long)]
+        reservation_id: u64,
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Drop a reservation without charging it (ephemeral rollup RPC)
+    ReleaseUsage {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+        #[arg(long)]
+        reservation_id: u64,
+    },
+    /// Record usage for several keys' primary accounts at once
+    RecordUsageBatch {
+        /// API_KEY:AMOUNT[:REQUEST_ID], repeatable
+        #[arg(long = "entry", value_parser = parse_batch_entry, required = true)]
+        entries: Vec<(Pubkey, u64, Option<u64>)>,
+    },
+    /// Close the key's window and commit it (ephemeral rollup RPC)
+    SubmitUsageCheckpoint {
+        api_key: Pubkey,
+        /// Hex; all zeros when no request log is kept
+        #[arg(long, value_parser = parse_hash)]
+        request_log_root: Option<[u8; 32]>,
+    },
+    /// Apply the key's latest committed checkpoint on the base layer
+    ApplyUsageCheckpoint { api_key: Pubkey },
+    /// Check a request log entry against a checkpoint's Merkle root
+    VerifyRequestInclusion {
+        api_key: Pubkey,
+        #[arg(long)]
+        checkpoint_seq: u64,
+        #[arg(long)]
+        request_id: u64,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        cost: u64,
+        #[arg(long)]
+        timestamp: i64,
+        /// Sibling hash, leaf to root; repeatable
+        #[arg(long, value_parser = parse_hash)]
+        proof: Vec<[u8; 32]>,
+    },
+    /// Commit and undelegate a usage account (ephemeral rollup RPC)
+    UndelegateUsage {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+    },
+    /// Start moving a key's usage accounts to another region (ephemeral
+    /// rollup RPC)
+    BeginRegionMigration {
+        api_key: Pubkey,
+        #[arg(long)]
+        validator: Pubkey,
+    },
+    /// Finish a migration once the key's usage accounts are back on the base
+    /// layer
+    CompleteRegionMigration { api_key: Pubkey },
+    /// Extend a delegation lease (ephemeral rollup RPC)
+    RenewDelegationLease {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+        #[arg(long)]
+        lease_seconds: u64,
+    },
+    /// Undelegate a usage account whose lease ran out (ephemeral rollup RPC)
+    ExpireDelegationLease {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+    },
+    /// Clear the delegation of a usage account handed back without its
+    /// rollup's final commit
+    ReconcileReturnedDelegation {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+    },
+
+    // CHECKPOINT FINALIZATION
+    /// Allow a third party to dispute a service's checkpoints
+    RegisterAuditor { service: Pubkey, auditor: Pubkey },
+    /// Withdraw an auditor registration
+    RevokeAuditor { service: Pubkey, auditor: Pubkey },
+    /// Finalize a checkpoint whose challenge period is over
+    FinalizeUsageCheckpoint {
+        api_key: Pubkey,
+        #[arg(long)]
+        checkpoint_seq: u64,
+    },
+    /// Dispute a pending checkpoint as key owner or auditor
+    DisputeUsageCheckpoint {
+        api_key: Pubkey,
+        #[arg(long)]
+        checkpoint_seq: u64,
+        #[arg(long, value_enum)]
+        reason: DisputeReasonArg,
+        /// Hex hash of the off-chain evidence
+        #[arg(long, value_parser = parse_hash)]
+        evidence_hash: Option<[u8; 32]>,
+        /// Sign as a registered auditor of the service
+        #[arg(long)]
+        as_auditor: bool,
+    },
+    /// Finalize a disputed checkpoint with agreed amounts
+    ResolveCheckpointDispute {
+        api_key: Pubkey,
+        #[arg(long)]
+        checkpoint_seq: u64,
+        #[arg(long)]
+        billed_request_count: u64,
+        #[arg(long)]
+        billed_cost: u64,
+        /// Protocol admin keypair; defaults to the configured keypair
+        #[arg(long)]
+        admin_keypair: Option<String>,
+    },
+
+    // ENFORCEMENT
+    /// Re-evaluate a key's status against its policy
+    EvaluateEnforcement { api_key: Pubkey },
+    /// Block a key until manually unblocked
+    ManualBlockKey { api_key: Pubkey },
+    /// Lift a manual block
+    ManualUnblockKey { api_key: Pubkey },
+
+    // ABUSE / REPUTATION
+    /// Record an abuse signal against a key owner
+    EmitAbuseSignal {
+        service: Pubkey,
+        subject: Pubkey,
+        #[arg(long)]
+        severity: u8,
+        #[arg(long)]
+        category: u32,
+    },
+    /// Adjust a subject's reputation score
+    UpdateReputation {
+        subject: Pubkey,
+        #[arg(long, allow_hyphen_values = true)]
+        delta: i64,
+    },
+}
+
+impl InstructionCommand {
+    /// Resolves accounts and builds the instructions. `signer` is the
+    /// configured keypair, which pays and signs as authority or admin.
+    pub fn plan(self, rpc: &RpcClient, signer: Pubkey) -> Result<Plan> {
+        let plan = match self {
+            // PROTOCOL
+            Self::InitializeProtocol { fee_bps, treasury } => {
+                instructions::initialize_protocol(signer, fee_bps, treasury).into()
+            }
+            Self::UpdateProtocol {
+                fee_bps,
+                treasury,
+                paused,
+            } => instructions::update_protocol(signer, fee_bps, treasury, paused).into(),
+
+            // SERVICE
+            Self::CreateService {
+                name,
+                default_policy,
+            } => {
+                let protocol: ProtocolState = rpc.fetch(&pda::protocol())?;
+                instructions::create_service(signer, protocol.service_count, name, default_policy)
+                    .into()
+            }
+            Self::UpdateService {
+                service,
+                authority,
+                default_policy,
+                challenge_period_seconds,
+            } => instructions::update_service(
+                signer,
+                service,
+                authority,
+                default_policy,
+                challenge_period_seconds,
+            )
+            .into(),
+            Self::SetServiceStatus { service, status } => {
+                instructions::set_service_status(signer, service, status.into()).into()
+            }
+
+            // POLICY
+            Self::CreatePolicy {
+                service,
+                requests_per_window,
+                window_seconds,
+                burst_limit,
+                cost_per_request,
+                daily_quota,
+                monthly_quota,
+            } => {
+                let account: ServiceAccount = rpc.fetch(&service)?;
+                instructions::create_policy(
+                    signer,
+                    service,
+                    account.policy_count,
+                    ix::CreatePolicy {
+                        requests_per_window,
+                        window_seconds,
+                        burst_limit,
+                        cost_per_request,
+                        daily_quota,
+                        monthly_quota,
+                    },
+                )
+                .into()
+            }
+            Self::UpdatePolicy {
+                policy,
+                requests_per_window,
+                window_seconds,
+                burst_limit,
+                cost_per_request,
+                daily_quota,
+                monthly_quota,
+            } => {
+                let account: RateLimitPolicy = rpc.fetch(&policy)?;
+                instructions::update_policy(
+                    signer,
+                    account.service,
+                    policy,
+                    ix::UpdatePolicy {
+                        requests_per_window,
+                        window_seconds,
+                        burst_limit,
+                        cost_per_request,
+                        daily_quota,
+                        monthly_quota,
+                    },
+                )
+                .into()
+            }
+            Self::AttachPolicyToKey { api_key, policy } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::attach_policy_to_key(signer, key.service, policy, api_key).into()
+            }
+
+            // API KEY
+            Self::CreateApiKey {
+                service,
+                owner,
+                policy,
+            } => {
+                let protocol: ProtocolState = rpc.fetch(&pda::protocol())?;
+                let policy = match policy {
+                    Some(policy) => policy,
+                    None => rpc.fetch::<ServiceAccount>(&service)?.default_policy,
+                };
+                instructions::create_api_key(
+                    signer,
+                    service,
+                    protocol.api_key_count,
+                    owner.unwrap_or(signer),
+                    policy,
+                )
+                .into()
+            }
+            Self::RevokeApiKey { api_key } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::revoke_api_key(signer, key.service, api_key).into()
+            }
+            Self::SetApiKeyStatus { api_key, status } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::set_api_key_status(signer, key.service, api_key, status.into()).into()
+            }
+
+            // EXECUTION REGIONS
+            Self::RegisterExecutionRegion {
+                validator,
+                region_code,
+            } => instructions::register_execution_region(signer, validator, region_code).into(),
+            Self::SetExecutionRegionStatus { validator, status } => {
+                instructions::set_execution_region_status(signer, validator, status.into()).into()
+            }
+
+            // GATEWAYS
+            Self::RegisterGateway {
+                service,
+                gateway_key,
+            } => instructions::register_gateway(signer, service, gateway_key).into(),
+            Self::RevokeGateway {
+                service,
+                gateway_key,
+            } => instructions::revoke_gateway(signer, service, gateway_key).into(),
+            Self::ApplyUsageReceipt {
+                api_key,
+                gateway_keypair,
+                window_start,
+                request_count,
+                cost,
+                request_log_root,
+                nonce,
+            } => {
+                let gateway = read_keypair(&gateway_keypair)?;
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                let usage: DelegatedUsageAccount = rpc.fetch(&pda::delegated_usage(&api_key))?;
+                let receipt = UsageReceipt {
+                    api_key,
+                    window_start,
+                    request_count,
+                    cost,
+                    request_log_root: request_log_root.unwrap_or_default(),
+                    nonce,
+                };
+                let message = receipt.message()?;
+                let signature: [u8; 64] = gateway.sign_message(&message).into();
+
+                Plan {
+                    instructions: vec![
+                        instructions::ed25519_verify(&gateway.pubkey(), &signature, &message),
+                        instructions::apply_usage_receipt(
+                            signer,
+                            key.service,
+                            key.policy,
+                            gateway.pubkey(),
+                            usage.checkpoint_seq,
+                            receipt,
+                        ),
+                    ],
+                    signers: vec![],
+                }
+            }
+
+            // MAGICBLOCK DELEGATION
+            Self::CreateUsageShard { api_key } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::create_usage_shard(signer, key.service, api_key, key.usage_shards)
+                    .into()
+            }
+            Self::PrepareDelegation {
+                api_key,
+                validator,
+                shard,
+                commit_frequency_ms,
+                lease_seconds,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::prepare_delegation(
+                    signer,
+                    key.service,
+                    api_key,
+                    key.policy,
+                    validator,
+                    shard,
+                    ix::PrepareDelegation {
+                        commit_frequency_ms,
+                        lease_seconds,
+                    },
+                )
+                .into()
+            }
+            Self::DelegateUsage { api_key, shard } => {
+                let usage: DelegatedUsageAccount = rpc.fetch(&pda::usage_shard(&api_key, shard))?;
+                if !usage.delegated {
+                    bail!("usage account is not prepared; run prepare-delegation first");
+                }
+                instructions::delegate_usage(signer, api_key, shard, usage.execution_region).into()
+            }
+            Self::RecordUsageRealtime {
+                api_key,
+                shard,
+                amount,
+                request_id,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::record_usage_realtime(
+                    submitter(rpc, signer, key.service)?,
+                    pda::usage_shard(&api_key, shard),
+                    api_key,
+                    key.policy,
+                    amount,
+                    request_id,
+                )
+                .into()
+            }
+            Self::RecordUsageDirect {
+                api_key,
+                amount,
+                request_id,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::record_usage_direct(
+                    submitter(rpc, signer, key.service)?,
+                    api_key,
+                    key.policy,
+                    amount,
+                    request_id,
+                )
+                .into()
+            }
+            Self::ReserveUsage {
+                api_key,
+                shard,
+                amount,
+                ttl_seconds,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::reserve_usage(
+                    submitter(rpc, signer, key.service)?,
+                    pda::usage_shard(&api_key, shard),
+                    api_key,
+                    key.policy,
+                    amount,
+                    ttl_seconds,
+                )
+                .into()
+            }
+            Self::SettleUsage {
+                api_key,
+                shard,
+                reservation_id,
+                amount,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::settle_usage(
+                    submitter(rpc, signer, key.service)?,
+                    pda::usage_shard(&api_key, shard),
+                    api_key,
+                    key.policy,
+                    reservation_id,
+                    amount,
+                )
+                .into()
+            }
+            Self::ReleaseUsage {
+                api_key,
+                shard,
+                reservation_id,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::release_usage(
+                    submitter(rpc, signer, key.service)?,
+                    pda::usage_shard(&api_key, shard),
+                    api_key,
+                    reservation_id,
+                )
+                .into()
+            }
+            Self::RecordUsageBatch { entries } => {
+                let mut service = None;
+                let entries = entries
+                    .into_iter()
+                    .map(|(api_key, amount, request_id)| {
+                        let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                        if *service.get_or_insert(key.service) != key.service {
+                            bail!("all entries must be keys of the same service");
+                        }
+                        Ok(UsageBatchEntry {
+                            delegated_usage: pda::delegated_usage(&api_key),
+                            api_key,
+                            policy: key.policy,
+                            amount,
+                            request_id,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let Some(service) = service else {
+                    bail!("at least one --entry is required");
+                };
+                instructions::record_usage_batch(submitter(rpc, signer, service)?, &entries).into()
+            }
+            Self::SubmitUsageCheckpoint {
+                api_key,
+                request_log_root,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::submit_usage_checkpoint(
+                    submitter(rpc, signer, key.service)?,
+                    api_key,
+                    key.usage_shards,
+                    request_log_root.unwrap_or_default(),
+                )
+                .into()
+            }
+            Self::ApplyUsageCheckpoint { api_key } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                let usage: DelegatedUsageAccount = rpc.fetch(&pda::delegated_usage(&api_key))?;
+                instructions::apply_usage_checkpoint(
+                    signer,
+                    key.service,
+                    api_key,
+                    key.policy,
+                    usage.checkpoint_seq,
+                )
+                .into()
+            }
+            Self::VerifyRequestInclusion {
+                api_key,
+                checkpoint_seq,
+                request_id,
+                amount,
+                cost,
+                timestamp,
+                proof,
+            } => instructions::verify_request_inclusion(
+                pda::usage_checkpoint(&api_key, checkpoint_seq),
+                RequestLogLeaf {
+                    request_id,
+                    amount,
+                    cost,
+                    timestamp,
+                },
+                proof,
+            )
+            .into(),
+            Self::UndelegateUsage { api_key, shard } => {
+                instructions::undelegate_usage(signer, pda::usage_shard(&api_key, shard)).into()
+            }
+            Self::BeginRegionMigration { api_key, validator } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::begin_region_migration(
+                    signer,
+                    signer,
+                    key.service,
+                    api_key,
+                    key.usage_shards,
+                    validator,
+                )
+                .into()
+            }
+            Self::CompleteRegionMigration { api_key } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                let usage_address = pda::delegated_usage(&api_key);
+                let usage: DelegatedUsageAccount = rpc.fetch(&usage_address)?;
+                if usage.migration_target == Pubkey::default() {
+                    bail!("no region migration in progress for {api_key}");
+                }
+                instructions::complete_region_migration(
+                    signer,
+                    key.service,
+                    api_key,
+                    key.usage_shards,
+                    usage.migration_target,
+                )
+                .into()
+            }
+            Self::RenewDelegationLease {
+                api_key,
+                shard,
+                lease_seconds,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::renew_delegation_lease(
+                    signer,
+                    key.service,
+                    api_key,
+                    pda::usage_shard(&api_key, shard),
+                    lease_seconds,
+                )
+                .into()
+            }
+            Self::ExpireDelegationLease { api_key, shard } => {
+                instructions::expire_delegation_lease(signer, pda::usage_shard(&api_key, shard))
+                    .into()
+            }
+            Self::ReconcileReturnedDelegation { api_key, shard } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::reconcile_returned_delegation(
+                    signer,
+                    key.service,
+                    api_key,
+                    pda::usage_shard(&api_key, shard),
+                )
+                .into()
+            }
+
+            // CHECKPOINT FINALIZATION
+            Self::RegisterAuditor { service, auditor } => {
+                instructions::register_auditor(signer, service, auditor).into()
+            }
+            Self::RevokeAuditor { service, auditor } => {
+                instructions::revoke_auditor(signer, service, auditor).into()
+            }
+            Self::FinalizeUsageCheckpoint {
+                api_key,
+                checkpoint_seq,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::finalize_usage_checkpoint(
+                    key.service,
+                    api_key,
+                    pda::usage_checkpoint(&api_key, checkpoint_seq),
+                )
+                .into()
+            }
+            Self::DisputeUsageCheckpoint {
+                api_key,
+                checkpoint_seq,
+                reason,
+                evidence_hash,
+                as_auditor,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::dispute_usage_checkpoint(
+                    signer,
+                    key.service,
+                    api_key,
+                    pda::usage_checkpoint(&api_key, checkpoint_seq),
+                    as_auditor,
+                    reason.into(),
+                    evidence_hash.unwrap_or_default(),
+                )
+                .into()
+            }
+            Self::ResolveCheckpointDispute {
+                api_key,
+                checkpoint_seq,
+                billed_request_count,
+                billed_cost,
+                admin_keypair,
+            } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                let admin = admin_keypair.as_deref().map(read_keypair).transpose()?;
+                let instruction = instructions::resolve_checkpoint_dispute(
+                    signer,
+                    admin.as_ref().map_or(signer, Keypair::pubkey),
+                    key.service,
+                    api_key,
+                    pda::usage_checkpoint(&api_key, checkpoint_seq),
+                    billed_request_count,
+                    billed_cost,
+                );
+                Plan {
+                    instructions: vec![instruction],
+                    signers: admin.into_iter().collect(),
+                }
+            }
+
+            // ENFORCEMENT
+            Self::EvaluateEnforcement { api_key } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::evaluate_enforcement(api_key, key.policy, key.usage_shards).into()
+            }
+            Self::ManualBlockKey { api_key } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::manual_block_key(signer, key.service, api_key).into()
+            }
+            Self::ManualUnblockKey { api_key } => {
+                let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+                instructions::manual_unblock_key(signer, key.service, api_key).into()
+            }
+
+            // ABUSE / REPUTATION
+            Self::EmitAbuseSignal {
+                service,
+                subject,
+                severity,
+                category,
+            } => {
+                // The signal address is seeded by the cluster clock; if the
+                // transaction lands in a later second it fails and can be retried.
+                let clock = rpc
+                    .account_data(&sysvar::clock::ID)?
+                    .ok_or_else(|| anyhow!("clock sysvar not found"))?;
+                let unix_timestamp = i64::from_le_bytes(clock[32..40].try_into()?);
+                instructions::emit_abuse_signal(
+                    signer,
+                    service,
+                    subject,
+                    unix_timestamp,
+                    severity,
+                    category,
+                )
+                .into()
+            }
+            Self::UpdateReputation { subject, delta } => {
+                instructions::update_reputation(subject, delta).into()
+            }
+        };
+        Ok(plan)
+    }
+}
@@ -0,0 +1,306 @@
+//! `limitlayer`: admin CLI for the LimitLayer protocol.
+//!
+//! Every program instruction is a subcommand that looks up the accounts it
+//! needs, then signs with the configured keypair and sends. `--dry-run`
+//! simulates instead and prints the instructions that would be sent.
+//! `plan` and `apply` reconcile a service with a policy spec file.
+
+mod commands;
+mod output;
+mod show;
+mod spec;
+
+use std::{
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand};
+use limitlayer_client::{
+    events::{parse_logs, LimitLayerEvent},
+    pda,
+    rpc::RpcClient,
+};
+use serde_json::{json, Value};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+
+use crate::{
+    commands::{InstructionCommand, Plan},
+    output::Output,
+    spec::Spec,
+};
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+const CONFIRM_POLL: Duration = Duration::from_millis(500);
+
+#[derive(Parser)]
+#[command(
+    name = "limitlayer",
+    version,
+    about = "Operate LimitLayer services, policies and keys"
+)]
+struct Cli {
+    /// JSON-RPC endpoint; use the ephemeral rollup's for delegated usage
+    #[arg(
+        long,
+        short = 'u',
+        global = true,
+        env = "LIMITLAYER_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+
+    /// Keypair that signs and pays
+    #[arg(
+        long,
+        short = 'k',
+        global = true,
+        env = "LIMITLAYER_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+
+    #[arg(long, global = true, value_enum, default_value_t = Output::Display)]
+    output: Output,
+
+    /// Simulate the transaction instead of sending it
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a program account
+    #[command(subcommand)]
+    Show(ShowCommand),
+
+    /// Diff a policy spec against the service on chain and print the
+    /// instructions that would reconcile them
+    Plan {
+        /// TOML, or YAML if it ends in .yaml or .yml
+        spec: PathBuf,
+    },
+    /// Send the instructions `plan` prints, signed by the service authority
+    Apply { spec: PathBuf },
+
+    #[command(flatten)]
+    Instruction(InstructionCommand),
+}
+
+#[derive(Subcommand)]
+enum ShowCommand {
+    /// Protocol state
+    Protocol,
+    /// ServiceAccount
+    Service { address: Pubkey },
+    /// ApiKeyAccount
+    ApiKey { address: Pubkey },
+    /// DelegatedUsageAccount of a key
+    Usage {
+        api_key: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        shard: u8,
+    },
+    /// ReputationAccount of a key owner
+    Reputation { owner: Pubkey },
+}
+
+pub(crate) fn read_keypair(path: &str) -> Result<Keypair> {
+    let path = match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ => path.to_string(),
+    };
+    read_keypair_file(&path).map_err(|e| anyhow!("failed to read keypair {path}: {e}"))
+}
+
+fn show(rpc: &RpcClient, command: ShowCommand) -> Result<Value> {
+    Ok(match command {
+        ShowCommand::Protocol => {
+            let address = pda::protocol();
+            show::protocol(&address, &rpc.fetch(&address)?)
+        }
+        ShowCommand::Service { address } => show::service(&address, &rpc.fetch(&address)?),
+        ShowCommand::ApiKey { address } => show::api_key(&address, &rpc.fetch(&address)?),
+        ShowCommand::Usage { api_key, shard } => {
+            let address = pda::usage_shard(&api_key, shard);
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+            show::delegated_usage(&address, &rpc.fetch(&address)?, now)
+        }
+        ShowCommand::Reputation { owner } => {
+            let address = pda::reputation(&owner);
+            show::reputation(&address, &rpc.fetch(&address)?)
+        }
+    })
+}
+
+fn instruction_json(instruction: &Instruction) -> Value {
+    json!({
+        "program_id": instruction.program_id.to_string(),
+        "accounts": instruction.accounts.iter().map(|meta| json!({
+            "pubkey": meta.pubkey.to_string(),
+            "signer": meta.is_signer,
+            "writable": meta.is_writable,
+        })).collect::<Vec<_>>(),
+        "data": hex::encode(&instruction.data),
+    })
+}
+
+/// Event names, plus values a caller needs to act on next.
+fn events_json(logs: &[String], result: &mut serde_json::Map<String, Value>) -> Result<()> {
+    let events = parse_logs(logs)?;
+    for event in &events {
+        if let LimitLayerEvent::UsageReserved(reserved) = event {
+            result.insert("reservation_id".into(), json!(reserved.reservation_id));
+        }
+    }
+    result.insert(
+        "events".into(),
+        json!(events.iter().map(LimitLayerEvent::name).collect::<Vec<_>>()),
+    );
+    Ok(())
+}
+
+/// Sends `transaction` and waits for it to confirm. Returns its logs.
+fn send_and_confirm(
+    rpc: &RpcClient,
+    transaction: &Transaction,
+) -> Result<(Signature, Vec<String>)> {
+    let signature = rpc.send_transaction(transaction)?;
+    let started = Instant::now();
+    while started.elapsed() < CONFIRM_TIMEOUT {
+        if let Some(status) = rpc.transaction(&signature)? {
+            if let Some(err) = status.err {
+                bail!("transaction {signature} failed: {err}");
+            }
+            return Ok((signature, status.logs));
+        }
+        thread::sleep(CONFIRM_POLL);
+    }
+    bail!("transaction {signature} was not confirmed within {CONFIRM_TIMEOUT:?}")
+}
+
+/// Signs `plan` with `payer`, then simulates or sends it.
+fn submit(
+    rpc: &RpcClient,
+    payer: &Keypair,
+    dry_run: bool,
+    plan: &Plan,
+) -> Result<serde_json::Map<String, Value>> {
+    let mut signers: Vec<&Keypair> = vec![payer];
+    signers.extend(plan.signers.iter());
+    let transaction = Transaction::new_signed_with_payer(
+        &plan.instructions,
+        Some(&payer.pubkey()),
+        &signers,
+        rpc.latest_blockhash()?,
+    );
+
+    let mut result = serde_json::Map::new();
+    if dry_run {
+        let simulation = rpc.simulate_transaction(&transaction)?;
+        result.insert(
+            "instructions".into(),
+            json!(plan
+                .instructions
+                .iter()
+                .map(instruction_json)
+                .collect::<Vec<_>>()),
+        );
+        result.insert("error".into(), simulation.err.unwrap_or(Value::Null));
+        result.insert("units_consumed".into(), json!(simulation.units_consumed));
+        events_json(&simulation.logs, &mut result)?;
+        result.insert("logs".into(), json!(simulation.logs));
+    } else {
+        let (signature, logs) = send_and_confirm(rpc, &transaction)?;
+        result.insert("signature".into(), json!(signature.to_string()));
+        events_json(&logs, &mut result)?;
+    }
+    Ok(result)
+}
+
+fn execute(
+    rpc: &RpcClient,
+    keypair: &str,
+    dry_run: bool,
+    command: InstructionCommand,
+) -> Result<Value> {
+    let payer = read_keypair(keypair)?;
+    let plan = command.plan(rpc, payer.pubkey())?;
+    Ok(Value::Object(submit(rpc, &payer, dry_run, &plan)?))
+}
+
+fn plan(rpc: &RpcClient, spec: &Path) -> Result<Value> {
+    let reconciliation = Spec::load(spec)?.reconcile(rpc)?;
+    Ok(json!({
+        "service": reconciliation.service.to_string(),
+        "authority": reconciliation.authority.to_string(),
+        "changes": reconciliation.changes,
+        "instructions": reconciliation
+            .instructions
+            .iter()
+            .map(instruction_json)
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// Sends the reconciling instructions, split into as many transactions as
+/// they need. Nothing is sent when the service matches the spec.
+fn apply(rpc: &RpcClient, keypair: &str, dry_run: bool, spec: &Path) -> Result<Value> {
+    let reconciliation = Spec::load(spec)?.reconcile(rpc)?;
+    let payer = read_keypair(keypair)?;
+    if payer.pubkey() != reconciliation.authority {
+        bail!(
+            "keypair {} is not the authority of service {} ({})",
+            payer.pubkey(),
+            reconciliation.service,
+            reconciliation.authority
+        );
+    }
+
+    let transactions = reconciliation
+        .transactions(&payer.pubkey())
+        .into_iter()
+        .map(|instructions| {
+            let plan = Plan {
+                instructions,
+                signers: vec![],
+            };
+            submit(rpc, &payer, dry_run, &plan).map(Value::Object)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({
+        "service": reconciliation.service.to_string(),
+        "changes": reconciliation.changes,
+        "transactions": transactions,
+    }))
+}
+
+fn main() -> Result<()> {
+    let Cli {
+        url,
+        keypair,
+        output,
+        dry_run,
+        command,
+    } = Cli::parse();
+    let rpc = RpcClient::new(url);
+
+    let value = match command {
+        Command::Show(command) => show(&rpc, command)?,
+        Command::Plan { spec } => plan(&rpc, &spec)?,
+        Command::Apply { spec } => apply(&rpc, &keypair, dry_run, &spec)?,
+        Command::Instruction(command) => execute(&rpc, &keypair, dry_run, command)?,
+    };
+    output::print(output, &value);
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Aligned `field  value` lines
+    Display,
+    /// Pretty-printed JSON
+    Json,
+}
+
+pub fn print(output: Output, value: &Value) {
+    match output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(value).unwrap()),
+        Output::Display => match value {
+            Value::Object(fields) => {
+                let width = fields.keys().map(String::len).max().unwrap_or(0);
+                for (name, value) in fields {
+                    println!("{name:<width$}  {}", display_value(value));
+                }
+            }
+            other => println!("{}", display_value(other)),
+        },
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "-".to_string(),
+        Value::Array(items) if items.iter().all(Value::is_string) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => other.to_string(),
+    }
+}
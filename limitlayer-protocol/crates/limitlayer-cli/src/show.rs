@@ -0,0 +1,130 @@
+//! JSON views of program accounts for `show` and command output.
+
+use limitlayer_client::program::{
+    ApiKeyAccount, ApiKeyStatus, DelegatedUsageAccount, ProtocolState, ReputationAccount,
+    ServiceAccount, ServiceStatus,
+};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+
+pub fn service_status(status: ServiceStatus) -> &'static str {
+    match status {
+        ServiceStatus::Active => "active",
+        ServiceStatus::Paused => "paused",
+        ServiceStatus::Disabled => "disabled",
+    }
+}
+
+pub fn api_key_status(status: ApiKeyStatus) -> &'static str {
+    match status {
+        ApiKeyStatus::Active => "active",
+        ApiKeyStatus::Throttled => "throttled",
+        ApiKeyStatus::Blocked => "blocked",
+        ApiKeyStatus::Revoked => "revoked",
+    }
+}
+
+/// Unset (all-zero) pubkeys show as null rather than as the system program.
+fn optional_key(key: &Pubkey) -> Value {
+    if *key == Pubkey::default() {
+        Value::Null
+    } else {
+        json!(key.to_string())
+    }
+}
+
+pub fn protocol(address: &Pubkey, protocol: &ProtocolState) -> Value {
+    json!({
+        "address": address.to_string(),
+        "admin_authority": protocol.admin_authority.to_string(),
+        "treasury": protocol.treasury.to_string(),
+        "protocol_fee_bps": protocol.protocol_fee_bps,
+        "paused": protocol.paused,
+        "service_count": protocol.service_count,
+        "api_key_count": protocol.api_key_count,
+        "total_usage_checkpoints": protocol.total_usage_checkpoints,
+    })
+}
+
+pub fn service(address: &Pubkey, service: &ServiceAccount) -> Value {
+    json!({
+        "address": address.to_string(),
+        "name": service.name,
+        "authority": service.authority.to_string(),
+        "status": service_status(service.status),
+        "default_policy": service.default_policy.to_string(),
+        "policy_count": service.policy_count,
+        "total_usage_units": service.total_usage_units.to_string(),
+        "total_cost_units": service.total_cost_units.to_string(),
+        "challenge_period_seconds": service.challenge_period_seconds,
+        "created_ts": service.created_ts,
+    })
+}
+
+pub fn api_key(address: &Pubkey, key: &ApiKeyAccount) -> Value {
+    json!({
+        "address": address.to_string(),
+        "service": key.service.to_string(),
+        "owner": key.owner.to_string(),
+        "policy": key.policy.to_string(),
+        "reputation": key.reputation.to_string(),
+        "status": api_key_status(key.status),
+        "lifetime_usage": key.lifetime_usage.to_string(),
+        "lifetime_cost": key.lifetime_cost.to_string(),
+        "applied_usage": key.applied_usage.to_string(),
+        "applied_cost": key.applied_cost.to_string(),
+        "applied_checkpoint_seq": key.applied_checkpoint_seq,
+        "last_checkpoint_ts": key.last_checkpoint_ts,
+        "daily_usage": key.daily_usage,
+        "monthly_usage": key.monthly_usage,
+        "quota_blocked_until": key.quota_blocked_until,
+        "usage_shards": key.usage_shards,
+    })
+}
+
+pub fn delegated_usage(address: &Pubkey, usage: &DelegatedUsageAccount, now: i64) -> Value {
+    let reservations: Vec<Value> = usage
+        .reservations
+        .iter()
+        .filter(|r| r.is_active(now))
+        .map(|r| json!({ "id": r.id, "amount": r.amount, "expires_at": r.expires_at }))
+        .collect();
+
+    json!({
+        "address": address.to_string(),
+        "api_key": usage.api_key.to_string(),
+        "shard_index": usage.shard_index,
+        "policy": usage.policy.to_string(),
+        "delegated": usage.delegated,
+        "execution_region": optional_key(&usage.execution_region),
+        "delegation_seq": usage.delegation_seq,
+        "delegated_at": usage.delegated_at,
+        "last_commit_ts": usage.last_commit_ts,
+        "commit_frequency_ms": usage.commit_frequency_ms,
+        "lease_expires_at": usage.lease_expires_at,
+        "migration_target": optional_key(&usage.migration_target),
+        "window_start_ts": usage.window_start_ts,
+        "current_window_usage": usage.current_window_usage,
+        "current_window_cost": usage.current_window_cost,
+        "burst_counter": usage.burst_counter,
+        "last_update_ts": usage.last_update_ts,
+        "checkpoint_seq": usage.checkpoint_seq,
+        "checkpointed_usage": usage.checkpointed_usage.to_string(),
+        "checkpointed_cost": usage.checkpointed_cost.to_string(),
+        "last_request_log_root": hex::encode(usage.last_request_log_root),
+        "disputed_window_usage": usage.disputed_window_usage,
+        "reservations": reservations,
+        "last_receipt_nonce": usage.last_receipt_nonce,
+    })
+}
+
+pub fn reputation(address: &Pubkey, reputation: &ReputationAccount) -> Value {
+    json!({
+        "address": address.to_string(),
+        "subject": reputation.subject.to_string(),
+        "global_score": reputation.global_score,
+        "signal_count": reputation.signal_count,
+        "last_updated_ts": reputation.last_updated_ts,
+        "flags": format!("{:#010x}", reputation.flags),
+    })
+}
@@ -0,0 +1,332 @@
+//! Policy-as-code: a TOML or YAML file describing a service's policies,
+//! its default policy and which policy each listed key uses.
+//!
+//! ```toml
+//! service = "8kZk...service PDA"
+//! default_policy = "standard"
+//! challenge_period_seconds = 86400
+//!
+//! [policies.standard]
+//! # Policies that exist already are named by address; those without one
+//! # are created
+//! address = "4nQe...policy PDA"
+//! requests_per_window = 100
+//! window_seconds = 60
+//! burst_limit = 20
+//! cost_per_request = 1
+//!
+//! [policies.premium]
+//! requests_per_window = 1000
+//! window_seconds = 60
+//! burst_limit = 200
+//! cost_per_request = 1
+//! monthly_quota = 1000000
+//!
+//! [keys]
+//! "9xTp...api key" = "premium"
+//! ```
+//!
+//! Policies have no name on chain, so names only exist in the spec. Keys
+//! and policies the spec does not mention are left alone.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use limitlayer_client::{
+    instructions, pda,
+    program::{
+        instruction as ix, ApiKeyAccount, RateLimitPolicy, ServiceAccount, MIN_WINDOW_SECONDS,
+    },
+    rpc::RpcClient,
+};
+use serde::Deserialize;
+use solana_sdk::{
+    instruction::Instruction, packet::PACKET_DATA_SIZE, pubkey::Pubkey, transaction::Transaction,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicySpec {
+    /// The policy's account, if it exists already
+    pub address: Option<String>,
+    pub requests_per_window: u64,
+    pub window_seconds: u64,
+    pub burst_limit: u64,
+    pub cost_per_request: u64,
+    #[serde(default)]
+    pub daily_quota: u64,
+    #[serde(default)]
+    pub monthly_quota: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spec {
+    pub service: String,
+    /// Name of a policy in `policies`
+    pub default_policy: Option<String>,
+    pub challenge_period_seconds: Option<u64>,
+    #[serde(default)]
+    pub policies: BTreeMap<String, PolicySpec>,
+    /// API key address to policy name
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+}
+
+/// What it takes to bring chain state in line with a spec.
+pub struct Reconciliation {
+    pub service: Pubkey,
+    /// Authority of the service, which must sign `instructions`
+    pub authority: Pubkey,
+    /// One line per difference, in instruction order
+    pub changes: Vec<String>,
+    pub instructions: Vec<Instruction>,
+}
+
+fn parse_address(value: &str, what: &str) -> Result<Pubkey> {
+    value
+        .parse()
+        .with_context(|| format!("invalid {what} address {value}"))
+}
+
+/// The checks `create_policy` and `update_policy` make.
+fn check_limits(name: &str, policy: &PolicySpec) -> Result<()> {
+    if policy.requests_per_window == 0 {
+        bail!("policy {name}: requests_per_window must be positive");
+    }
+    if policy.window_seconds < MIN_WINDOW_SECONDS {
+        bail!("policy {name}: window_seconds must be at least {MIN_WINDOW_SECONDS}");
+    }
+    if policy.burst_limit > policy.requests_per_window {
+        bail!("policy {name}: burst_limit exceeds requests_per_window");
+    }
+    if policy.daily_quota != 0
+        && policy.monthly_quota != 0
+        && policy.daily_quota > policy.monthly_quota
+    {
+        bail!("policy {name}: daily_quota exceeds monthly_quota");
+    }
+    Ok(())
+}
+
+/// `Some(desired)` if it differs from `current`, noting the change.
+fn changed<T: PartialEq + std::fmt::Display>(
+    field: &str,
+    current: T,
+    desired: T,
+    notes: &mut Vec<String>,
+) -> Option<T> {
+    (current != desired).then(|| {
+        notes.push(format!("{field} {current} -> {desired}"));
+        desired
+    })
+}
+
+impl Spec {
+    /// Reads a spec; `.yaml` and `.yml` files are YAML, anything else TOML.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let yaml = matches!(
+            path.extension().and_then(|extension| extension.to_str()),
+            Some("yaml" | "yml")
+        );
+        let spec = match yaml {
+            true => serde_yaml::from_str(&text).map_err(anyhow::Error::from),
+            false => toml::from_str(&text).map_err(anyhow::Error::from),
+        };
+        spec.with_context(|| format!("invalid spec {}", path.display()))
+    }
+
+    /// Diffs the spec against chain state and builds the instructions that
+    /// remove them: policy creations and updates, then service settings,
+    /// then key attachments.
+    pub fn reconcile(&self, rpc: &RpcClient) -> Result<Reconciliation> {
+        let service = parse_address(&self.service, "service")?;
+        let account: ServiceAccount = rpc.fetch(&service)?;
+        let authority = account.authority;
+        let mut changes = Vec::new();
+        let mut planned = Vec::new();
+
+        for (name, policy) in &self.policies {
+            check_limits(name, policy)?;
+        }
+        for name in self.default_policy.iter().chain(self.keys.values()) {
+            if !self.policies.contains_key(name) {
+                bail!("unknown policy {name}");
+            }
+        }
+
+        // New policies are seeded by the service's policy count, which each
+        // creation increments in instruction order
+        let mut policy_index = account.policy_count;
+        let mut addresses = BTreeMap::new();
+        for (name, policy) in &self.policies {
+            let Some(address) = &policy.address else {
+                let address = pda::policy(&service, policy_index);
+                changes.push(format!(
+                    "create policy {name} at {address}: requests_per_window {}, \
+                     window_seconds {}, burst_limit {}, cost_per_request {}, \
+                     daily_quota {}, monthly_quota {}",
+                    policy.requests_per_window,
+                    policy.window_seconds,
+                    policy.burst_limit,
+                    policy.cost_per_request,
+                    policy.daily_quota,
+                    policy.monthly_quota,
+                ));
+                planned.push(instructions::create_policy(
+                    authority,
+                    service,
+                    policy_index,
+                    ix::CreatePolicy {
+                        requests_per_window: policy.requests_per_window,
+                        window_seconds: policy.window_seconds,
+                        burst_limit: policy.burst_limit,
+                        cost_per_request: policy.cost_per_request,
+                        daily_quota: policy.daily_quota,
+                        monthly_quota: policy.monthly_quota,
+                    },
+                ));
+                addresses.insert(name, address);
+                policy_index += 1;
+                continue;
+            };
+
+            let address = parse_address(address, "policy")?;
+            let current: RateLimitPolicy = rpc.fetch(&address)?;
+            if current.service != service {
+                bail!(
+                    "policy {name} ({address}) belongs to service {}",
+                    current.service
+                );
+            }
+            let mut notes = Vec::new();
+            let update = ix::UpdatePolicy {
+                requests_per_window: changed(
+                    "requests_per_window",
+                    current.requests_per_window,
+                    policy.requests_per_window,
+                    &mut notes,
+                ),
+                window_seconds: changed(
+                    "window_seconds",
+                    current.window_seconds,
+                    policy.window_seconds,
+                    &mut notes,
+                ),
+                burst_limit: changed(
+                    "burst_limit",
+                    current.burst_limit,
+                    policy.burst_limit,
+                    &mut notes,
+                ),
+                cost_per_request: changed(
+                    "cost_per_request",
+                    current.cost_per_request,
+                    policy.cost_per_request,
+                    &mut notes,
+                ),
+                daily_quota: changed(
+                    "daily_quota",
+                    current.daily_quota,
+                    policy.daily_quota,
+                    &mut notes,
+                ),
+                monthly_quota: changed(
+                    "monthly_quota",
+                    current.monthly_quota,
+                    policy.monthly_quota,
+                    &mut notes,
+                ),
+            };
+            if !notes.is_empty() {
+                changes.push(format!(
+                    "update policy {name} ({address}): {}",
+                    notes.join(", ")
+                ));
+                planned.push(instructions::update_policy(
+                    authority, service, address, update,
+                ));
+            }
+            addresses.insert(name, address);
+        }
+
+        let mut notes = Vec::new();
+        let default_policy = self.default_policy.as_ref().and_then(|name| {
+            changed(
+                "default_policy",
+                account.default_policy,
+                addresses[name],
+                &mut notes,
+            )
+        });
+        let challenge_period_seconds = self.challenge_period_seconds.and_then(|seconds| {
+            changed(
+                "challenge_period_seconds",
+                account.challenge_period_seconds,
+                seconds,
+                &mut notes,
+            )
+        });
+        if !notes.is_empty() {
+            changes.push(format!("update service: {}", notes.join(", ")));
+            planned.push(instructions::update_service(
+                authority,
+                service,
+                None,
+                default_policy,
+                challenge_period_seconds,
+            ));
+        }
+
+        for (api_key, name) in &self.keys {
+            let api_key = parse_address(api_key, "api key")?;
+            let key: ApiKeyAccount = rpc.fetch(&api_key)?;
+            if key.service != service {
+                bail!("api key {api_key} belongs to service {}", key.service);
+            }
+            let policy = addresses[name];
+            if key.policy != policy {
+                changes.push(format!(
+                    "attach policy {name} to key {api_key}: {} -> {policy}",
+                    key.policy
+                ));
+                planned.push(instructions::attach_policy_to_key(
+                    authority, service, policy, api_key,
+                ));
+            }
+        }
+
+        Ok(Reconciliation {
+            service,
+            authority,
+            changes,
+            instructions: planned,
+        })
+    }
+}
+
+impl Reconciliation {
+    /// The instructions split, in order, into as few transactions paid by
+    /// `payer` as fit the packet size.
+    pub fn transactions(&self, payer: &Pubkey) -> Vec<Vec<Instruction>> {
+        let fits = |instructions: &[Instruction]| {
+            let transaction = Transaction::new_with_payer(instructions, Some(payer));
+            bincode::serialized_size(&transaction)
+                .is_ok_and(|size| size as usize <= PACKET_DATA_SIZE)
+        };
+        let mut transactions: Vec<Vec<Instruction>> = Vec::new();
+        for instruction in &self.instructions {
+            match transactions.last_mut() {
+                Some(batch)
+                    if fits(&[batch.as_slice(), std::slice::from_ref(instruction)].concat()) =>
+                {
+                    batch.push(instruction.clone())
+                }
+                _ => transactions.push(vec![instruction.clone()]),
+            }
+        }
+        transactions
+    }
+}
@@ -0,0 +1,396 @@
+//! Runs the `limitlayer` binary against a canned JSON-RPC endpoint.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    process::Command,
+    thread,
+};
+
+use anchor_lang::AccountSerialize;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use limitlayer_client::{
+    pda,
+    program::{
+        ApiKeyAccount, ApiKeyStatus, ProtocolState, RateLimitPolicy, ServiceAccount, ServiceStatus,
+    },
+};
+use serde_json::{json, Value};
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+/// Serves JSON-RPC requests with `respond(method, params) -> result`.
+fn serve(respond: impl Fn(&str, &Value) -> Value + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let request: Value = serde_json::from_slice(&body).unwrap();
+            let result = respond(request["method"].as_str().unwrap(), &request["params"]);
+            let response =
+                json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }).to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+        }
+    });
+    url
+}
+
+fn account<T: AccountSerialize>(account: &T) -> Value {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    json!({ "value": { "data": [STANDARD.encode(data), "base64"] } })
+}
+
+fn limitlayer(url: &str, args: &[&str]) -> Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_limitlayer"))
+        .args(["--url", url, "--output", "json"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn service_account(default_policy: Pubkey) -> ServiceAccount {
+    ServiceAccount {
+        authority: Pubkey::new_unique(),
+        name: "search-api".to_string(),
+        status: ServiceStatus::Active,
+        default_policy,
+        policy_count: 1,
+        total_usage_units: 1_000,
+        total_cost_units: 2_500,
+        challenge_period_seconds: 86_400,
+        created_ts: 1_700_000_000,
+        bump: 255,
+    }
+}
+
+#[test]
+fn shows_service() {
+    let service = pda::service(0);
+    let url = serve(move |method, params| {
+        assert_eq!(method, "getAccountInfo");
+        assert_eq!(params[0], service.to_string());
+        account(&service_account(Pubkey::new_unique()))
+    });
+
+    let shown = limitlayer(&url, &["show", "service", &service.to_string()]);
+    assert_eq!(shown["name"], "search-api");
+    assert_eq!(shown["status"], "active");
+    assert_eq!(shown["total_cost_units"], "2500");
+}
+
+#[test]
+fn dry_run_simulates_create_api_key() {
+    let service = pda::service(0);
+    let default_policy = Pubkey::new_unique();
+    let protocol = ProtocolState {
+        admin_authority: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        protocol_fee_bps: 250,
+        paused: false,
+        service_count: 1,
+        api_key_count: 6,
+        total_usage_checkpoints: 0,
+        bump: 255,
+    };
+
+    let url = serve(move |method, params| match method {
+        "getAccountInfo" if params[0] == pda::protocol().to_string() => account(&protocol),
+        "getAccountInfo" => account(&service_account(default_policy)),
+        "getLatestBlockhash" => json!({ "value": { "blockhash": Hash::default().to_string() } }),
+        "simulateTransaction" => json!({
+            "value": { "err": null, "logs": [], "unitsConsumed": 12_345 }
+        }),
+        other => panic!("unexpected {other}"),
+    });
+
+    let keypair = Keypair::new();
+    let keypair_path = std::env::temp_dir().join(format!("limitlayer-{}.json", keypair.pubkey()));
+    std::fs::write(
+        &keypair_path,
+        serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap(),
+    )
+    .unwrap();
+
+    let result = limitlayer(
+        &url,
+        &[
+            "--keypair",
+            keypair_path.to_str().unwrap(),
+            "--dry-run",
+            "create-api-key",
+            &service.to_string(),
+        ],
+    );
+    std::fs::remove_file(&keypair_path).unwrap();
+
+    assert_eq!(result["error"], Value::Null);
+    assert_eq!(result["units_consumed"], 12_345);
+    let accounts = &result["instructions"][0]["accounts"];
+    assert_eq!(accounts[0]["pubkey"], keypair.pubkey().to_string());
+    assert_eq!(accounts[3]["pubkey"], pda::api_key(6).to_string());
+    assert!(result["instructions"][0]["data"]
+        .as_str()
+        .unwrap()
+        .ends_with(&hex::encode(default_policy)));
+}
+
+fn policy_account(service: Pubkey, burst_limit: u64) -> RateLimitPolicy {
+    RateLimitPolicy {
+        service,
+        requests_per_window: 100,
+        window_seconds: 60,
+        burst_limit,
+        cost_per_request: 1,
+        daily_quota: 0,
+        monthly_quota: 0,
+        bump: 255,
+    }
+}
+
+fn api_key_account(service: Pubkey, policy: Pubkey) -> ApiKeyAccount {
+    ApiKeyAccount {
+        service,
+        owner: Pubkey::new_unique(),
+        policy,
+        reputation: Pubkey::new_unique(),
+        status: ApiKeyStatus::Active,
+        lifetime_usage: 0,
+        lifetime_cost: 0,
+        applied_usage: 0,
+        applied_cost: 0,
+        applied_checkpoint_seq: 0,
+        last_checkpoint_ts: 0,
+        day_start_ts: 0,
+        daily_usage: 0,
+        month_start_ts: 0,
+        monthly_usage: 0,
+        quota_blocked_until: 0,
+        usage_shards: 1,
+        bump: 255,
+    }
+}
+
+/// Serves `accounts` by address; anything else does not exist.
+fn serve_accounts(accounts: HashMap<Pubkey, Value>) -> String {
+    serve(move |method, params| match method {
+        "getAccountInfo" => {
+            let address: Pubkey = params[0].as_str().unwrap().parse().unwrap();
+            accounts
+                .get(&address)
+                .cloned()
+                .unwrap_or(json!({ "value": null }))
+        }
+        "getLatestBlockhash" => json!({ "value": { "blockhash": Hash::default().to_string() } }),
+        "simulateTransaction" => json!({
+            "value": { "err": null, "logs": [], "unitsConsumed": 4_000 }
+        }),
+        other => panic!("unexpected {other}"),
+    })
+}
+
+fn write_file(name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn plans_minimal_changes_from_toml_spec() {
+    let service = pda::service(0);
+    let standard = Pubkey::new_unique();
+    let (moving, staying) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut account = service_account(standard);
+    account.policy_count = 42;
+    // New policies take the next indexes in name order
+    let (enterprise, premium) = (pda::policy(&service, 42), pda::policy(&service, 43));
+
+    let url = serve_accounts(HashMap::from([
+        (service, self::account(&account)),
+        (standard, self::account(&policy_account(service, 10))),
+        (moving, self::account(&api_key_account(service, standard))),
+        (staying, self::account(&api_key_account(service, standard))),
+    ]));
+    let spec = write_file(
+        &format!("limitlayer-spec-{service}-{moving}.toml"),
+        &format!(
+            r#"
+            service = "{service}"
+            default_policy = "standard"
+            challenge_period_seconds = 3600
+
+            [policies.standard]
+            address = "{standard}"
+            requests_per_window = 100
+            window_seconds = 60
+            burst_limit = 20
+            cost_per_request = 1
+
+            [policies.enterprise]
+            requests_per_window = 5000
+            window_seconds = 60
+            burst_limit = 500
+            cost_per_request = 1
+
+            [policies.premium]
+            requests_per_window = 1000
+            window_seconds = 60
+            burst_limit = 200
+            cost_per_request = 1
+            monthly_quota = 1000000
+
+            [keys]
+            "{moving}" = "premium"
+            "{staying}" = "standard"
+            "#
+        ),
+    );
+
+    let plan = limitlayer(&url, &["plan", spec.to_str().unwrap()]);
+    std::fs::remove_file(&spec).unwrap();
+
+    assert_eq!(plan["authority"], account.authority.to_string());
+    assert_eq!(
+        plan["changes"],
+        json!([
+            format!(
+                "create policy enterprise at {enterprise}: requests_per_window 5000, \
+                 window_seconds 60, burst_limit 500, cost_per_request 1, \
+                 daily_quota 0, monthly_quota 0"
+            ),
+            format!(
+                "create policy premium at {premium}: requests_per_window 1000, \
+                 window_seconds 60, burst_limit 200, cost_per_request 1, \
+                 daily_quota 0, monthly_quota 1000000"
+            ),
+            format!("update policy standard ({standard}): burst_limit 10 -> 20"),
+            "update service: challenge_period_seconds 86400 -> 3600",
+            format!("attach policy premium to key {moving}: {standard} -> {premium}"),
+        ])
+    );
+    let instructions = plan["instructions"].as_array().unwrap();
+    assert_eq!(instructions.len(), 5);
+    // Authority, service, then the policy each one touches
+    assert_eq!(
+        instructions[0]["accounts"][2]["pubkey"],
+        enterprise.to_string()
+    );
+    assert_eq!(
+        instructions[1]["accounts"][2]["pubkey"],
+        premium.to_string()
+    );
+    assert_eq!(
+        instructions[2]["accounts"][2]["pubkey"],
+        standard.to_string()
+    );
+    assert_eq!(
+        instructions[4]["accounts"][2]["pubkey"],
+        premium.to_string()
+    );
+    assert_eq!(instructions[4]["accounts"][3]["pubkey"], moving.to_string());
+}
+
+#[test]
+fn applies_yaml_spec_as_the_service_authority() {
+    let authority = Keypair::new();
+    let service = pda::service(3);
+    let (standard, premium) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let key = Pubkey::new_unique();
+    let mut account = service_account(standard);
+    account.authority = authority.pubkey();
+
+    let url = serve_accounts(HashMap::from([
+        (service, self::account(&account)),
+        (standard, self::account(&policy_account(service, 10))),
+        (premium, self::account(&policy_account(service, 50))),
+        (key, self::account(&api_key_account(service, standard))),
+    ]));
+    let keypair = write_file(
+        &format!("limitlayer-{}.json", authority.pubkey()),
+        &serde_json::to_string(&authority.to_bytes().to_vec()).unwrap(),
+    );
+    let spec_file = |key_policy: &str| {
+        write_file(
+            &format!("limitlayer-spec-{service}-{key_policy}.yaml"),
+            &format!(
+                "service: {service}\n\
+                 default_policy: standard\n\
+                 policies:\n\
+                 \x20 standard: {{ address: {standard}, requests_per_window: 100, window_seconds: 60, burst_limit: 10, cost_per_request: 1 }}\n\
+                 \x20 premium: {{ address: {premium}, requests_per_window: 100, window_seconds: 60, burst_limit: 50, cost_per_request: 1 }}\n\
+                 keys:\n\
+                 \x20 {key}: {key_policy}\n"
+            ),
+        )
+    };
+    let apply = |spec: &std::path::Path| {
+        limitlayer(
+            &url,
+            &[
+                "--keypair",
+                keypair.to_str().unwrap(),
+                "--dry-run",
+                "apply",
+                spec.to_str().unwrap(),
+            ],
+        )
+    };
+
+    let spec = spec_file("premium");
+    let result = apply(&spec);
+    assert_eq!(
+        result["changes"],
+        json!([format!(
+            "attach policy premium to key {key}: {standard} -> {premium}"
+        )])
+    );
+    let transactions = result["transactions"].as_array().unwrap();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0]["error"], Value::Null);
+    assert_eq!(transactions[0]["instructions"].as_array().unwrap().len(), 1);
+
+    // Already in line with the chain: nothing to send
+    let unchanged = spec_file("standard");
+    let result = apply(&unchanged);
+    assert_eq!(result["changes"], json!([]));
+    assert_eq!(result["transactions"], json!([]));
+
+    for path in [keypair, spec, unchanged] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
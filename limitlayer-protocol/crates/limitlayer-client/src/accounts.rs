@@ -0,0 +1,70 @@
+//! Decoding of raw account data. Delegated usage accounts have the same
+//! layout on the ephemeral rollup and, while delegated, under the delegation
+//! program on the base layer, so the owner is not checked here.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use limitlayer_protocol::state::*;
+
+use crate::{Error, Result};
+
+fn split_discriminator(data: &[u8]) -> Result<([u8; 8], &[u8])> {
+    if data.len() < 8 {
+        return Err(Error::MissingDiscriminator);
+    }
+    let (discriminator, rest) = data.split_at(8);
+    Ok((discriminator.try_into().unwrap(), rest))
+}
+
+/// Decodes account data of a known type, checking its discriminator.
+pub fn decode<T: AnchorDeserialize + Discriminator>(data: &[u8]) -> Result<T> {
+    let (discriminator, mut rest) = split_discriminator(data)?;
+    if discriminator != T::DISCRIMINATOR {
+        return Err(Error::UnknownDiscriminator(discriminator));
+    }
+    Ok(T::deserialize(&mut rest)?)
+}
+
+macro_rules! program_accounts {
+    ($($variant:ident($ty:ident)),* $(,)?) => {
+        /// Any account owned by the program.
+        #[allow(clippy::large_enum_variant)]
+        #[derive(Clone)]
+        pub enum LimitLayerAccount {
+            $($variant($ty),)*
+        }
+
+        impl LimitLayerAccount {
+            /// Decodes account data of any program account type.
+            pub fn decode(data: &[u8]) -> Result<Self> {
+                let (discriminator, mut rest) = split_discriminator(data)?;
+                $(
+                    if discriminator == $ty::DISCRIMINATOR {
+                        return Ok(Self::$variant($ty::deserialize(&mut rest)?));
+                    }
+                )*
+                Err(Error::UnknownDiscriminator(discriminator))
+            }
+
+            /// Account type name, as in the IDL.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant(_) => stringify!($ty),)*
+                }
+            }
+        }
+    };
+}
+
+program_accounts! {
+    Protocol(ProtocolState),
+    Service(ServiceAccount),
+    Policy(RateLimitPolicy),
+    ApiKey(ApiKeyAccount),
+    DelegatedUsage(DelegatedUsageAccount),
+    UsageCheckpoint(UsageCheckpoint),
+    Reputation(ReputationAccount),
+    AbuseSignal(AbuseSignal),
+    ExecutionRegion(ExecutionRegionAccount),
+    Gateway(GatewayAccount),
+    Auditor(AuditorAccount),
+}
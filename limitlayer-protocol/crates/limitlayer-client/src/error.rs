@@ -0,0 +1,38 @@
+use anchor_lang::prelude::Pubkey;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("data is shorter than a discriminator")]
+    MissingDiscriminator,
+    #[error("unknown discriminator {0:?}")]
+    UnknownDiscriminator([u8; 8]),
+    #[error("failed to deserialize: {0}")]
+    Deserialize(#[from] std::io::Error),
+    #[error("invalid base64 in program data: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("{method} request failed: {source}")]
+    Http {
+        method: &'static str,
+        source: Box<ureq::Error>,
+    },
+    #[error("{method} failed: {message}")]
+    Rpc {
+        method: &'static str,
+        message: String,
+    },
+    #[error("malformed {0} response")]
+    MalformedResponse(&'static str),
+    #[error("account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("account {address} is not a {expected}: {source}")]
+    UnexpectedAccount {
+        address: Pubkey,
+        expected: &'static str,
+        source: Box<Error>,
+    },
+    #[error("failed to serialize transaction: {0}")]
+    Serialize(#[from] bincode::Error),
+}
@@ -0,0 +1,123 @@
+//! Decoding of program events. `emit!` logs each event as
+//! `Program data: <base64(discriminator || borsh)>` while the program is the
+//! innermost invocation.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use limitlayer_protocol::{events::*, ID};
+
+use crate::{Error, Result};
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+macro_rules! program_events {
+    ($($name:ident),* $(,)?) => {
+        /// Any event the program emits.
+        pub enum LimitLayerEvent {
+            $($name($name),)*
+        }
+
+        impl LimitLayerEvent {
+            /// Decodes one event from `discriminator || borsh` bytes.
+            pub fn decode(data: &[u8]) -> Result<Self> {
+                if data.len() < 8 {
+                    return Err(Error::MissingDiscriminator);
+                }
+                let (discriminator, mut rest) = data.split_at(8);
+                $(
+                    if discriminator == $name::DISCRIMINATOR {
+                        return Ok(Self::$name($name::deserialize(&mut rest)?));
+                    }
+                )*
+                Err(Error::UnknownDiscriminator(discriminator.try_into().unwrap()))
+            }
+
+            /// Event name, as in the IDL.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$name(_) => stringify!($name),)*
+                }
+            }
+        }
+    };
+}
+
+program_events! {
+    ProtocolInitialized,
+    ProtocolUpdated,
+    ServiceCreated,
+    ServiceUpdated,
+    ServiceStatusChanged,
+    PolicyCreated,
+    PolicyUpdated,
+    PolicyAttachedToKey,
+    ApiKeyCreated,
+    ApiKeyRevoked,
+    ApiKeyStatusChanged,
+    ExecutionRegionRegistered,
+    ExecutionRegionStatusChanged,
+    UsageDelegated,
+    UsageShardCreated,
+    UsageUndelegated,
+    UsageRegionMigrated,
+    DelegationReconciled,
+    DelegationLeaseRenewed,
+    DelegationLeaseExpired,
+    UsageRecordedRealtime,
+    UsageRecordedDirect,
+    DuplicateUsageIgnored,
+    UsageReserved,
+    UsageSettled,
+    UsageReleased,
+    UsageBatchRecorded,
+    UsageCheckpointSubmitted,
+    UsageCheckpointApplied,
+    UsageCheckpointLagging,
+    QuotaExhausted,
+    EnforcementEvaluated,
+    KeyManuallyBlocked,
+    KeyManuallyUnblocked,
+    AbuseSignalEmitted,
+    ReputationUpdated,
+    GatewayRegistered,
+    GatewayRevoked,
+    UsageReceiptApplied,
+    RequestInclusionVerified,
+    AuditorRegistered,
+    AuditorRevoked,
+    UsageCheckpointDisputed,
+    UsageCheckpointDisputeResolved,
+    UsageCheckpointFinalized,
+}
+
+/// Decodes the events the program emitted in one transaction's log
+/// messages, in order. Data logged by other programs, including ones the
+/// program invokes, is skipped.
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Result<Vec<LimitLayerEvent>> {
+    let program_id = ID.to_string();
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let line = line.as_ref();
+        if let Some(data) = line.strip_prefix(PROGRAM_DATA) {
+            if invocations.last() == Some(&program_id.as_str()) {
+                events.push(LimitLayerEvent::decode(&STANDARD.decode(data)?)?);
+            }
+        } else if let Some(rest) = line
+            .strip_prefix("Program ")
+            .filter(|rest| !rest.starts_with("log:") && !rest.starts_with("return:"))
+        {
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => invocations.push(program),
+                (Some(_), Some("success" | "failed:")) => {
+                    invocations.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(events)
+}
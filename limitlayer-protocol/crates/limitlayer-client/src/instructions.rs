@@ -0,0 +1,928 @@
+//! One builder per program instruction. Builders take the keys a caller
+//! actually chooses (signers, the service, the key, arguments) and derive
+//! every PDA themselves. Counters that seed new accounts (`service_count`,
+//! `api_key_count`, `policy_count`, `checkpoint_seq`) must be read from
+//! chain by the caller first.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{ed25519_program, instruction::Instruction, sysvar},
+    system_program, InstructionData,
+};
+use ephemeral_rollups_sdk::consts::{MAGIC_CONTEXT_ID, MAGIC_PROGRAM_ID};
+use limitlayer_protocol::{
+    accounts, instruction as ix, ApiKeyStatus, DisputeReason, ExecutionRegionStatus,
+    RequestLogLeaf, ServiceStatus, UsageReceipt, ID,
+};
+
+use crate::pda;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Other shards of a key, for instructions that take them through
+/// remaining_accounts.
+fn shard_metas(api_key: &Pubkey, usage_shards: u8, is_writable: bool) -> Vec<AccountMeta> {
+    (1..usage_shards)
+        .map(|index| AccountMeta {
+            pubkey: pda::usage_shard(api_key, index),
+            is_signer: false,
+            is_writable,
+        })
+        .collect()
+}
+
+/// Signer of usage reports for a service's keys: the service authority, or
+/// one of the service's registered gateways.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Submitter {
+    pub signer: Pubkey,
+    pub service: Pubkey,
+    /// `signer` is a registered gateway rather than the authority
+    pub as_gateway: bool,
+}
+
+impl Submitter {
+    pub fn authority(signer: Pubkey, service: Pubkey) -> Self {
+        Self {
+            signer,
+            service,
+            as_gateway: false,
+        }
+    }
+
+    pub fn gateway(signer: Pubkey, service: Pubkey) -> Self {
+        Self {
+            signer,
+            service,
+            as_gateway: true,
+        }
+    }
+
+    fn gateway_account(&self) -> Option<Pubkey> {
+        self.as_gateway
+            .then(|| pda::gateway(&self.service, &self.signer))
+    }
+}
+
+// PROTOCOL
+pub fn initialize_protocol(admin: Pubkey, protocol_fee_bps: u16, treasury: Pubkey) -> Instruction {
+    build(
+        accounts::InitializeProtocol {
+            admin,
+            protocol: pda::protocol(),
+            system_program: system_program::ID,
+        },
+        ix::InitializeProtocol {
+            protocol_fee_bps,
+            treasury,
+        },
+    )
+}
+
+pub fn update_protocol(
+    admin: Pubkey,
+    new_fee_bps: Option<u16>,
+    new_treasury: Option<Pubkey>,
+    paused: Option<bool>,
+) -> Instruction {
+    build(
+        accounts::UpdateProtocol {
+            admin,
+            protocol: pda::protocol(),
+        },
+        ix::UpdateProtocol {
+            new_fee_bps,
+            new_treasury,
+            paused,
+        },
+    )
+}
+
+// SERVICE
+/// `service_index` is the protocol's current `service_count`.
+pub fn create_service(
+    authority: Pubkey,
+    service_index: u64,
+    name: String,
+    default_policy: Pubkey,
+) -> Instruction {
+    build(
+        accounts::CreateService {
+            authority,
+            protocol: pda::protocol(),
+            service: pda::service(service_index),
+            system_program: system_program::ID,
+        },
+        ix::CreateService {
+            name,
+            default_policy,
+        },
+    )
+}
+
+pub fn update_service(
+    authority: Pubkey,
+    service: Pubkey,
+    new_authority: Option<Pubkey>,
+    new_default_policy: Option<Pubkey>,
+    new_challenge_period_seconds: Option<u64>,
+) -> Instruction {
+    build(
+        accounts::UpdateService { authority, service },
+        ix::UpdateService {
+            new_authority,
+            new_default_policy,
+            new_challenge_period_seconds,
+        },
+    )
+}
+
+pub fn set_service_status(
+    authority: Pubkey,
+    service: Pubkey,
+    new_status: ServiceStatus,
+) -> Instruction {
+    build(
+        accounts::SetServiceStatus { authority, service },
+        ix::SetServiceStatus { new_status },
+    )
+}
+
+// POLICY
+/// `policy_index` is the service's current `policy_count`.
+pub fn create_policy(
+    authority: Pubkey,
+    service: Pubkey,
+    policy_index: u64,
+    args: ix::CreatePolicy,
+) -> Instruction {
+    build(
+        accounts::CreatePolicy {
+            authority,
+            service,
+            policy: pda::policy(&service, policy_index),
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn update_policy(
+    authority: Pubkey,
+    service: Pubkey,
+    policy: Pubkey,
+    args: ix::UpdatePolicy,
+) -> Instruction {
+    build(
+        accounts::UpdatePolicy {
+            authority,
+            service,
+            policy,
+        },
+        args,
+    )
+}
+
+pub fn attach_policy_to_key(
+    authority: Pubkey,
+    service: Pubkey,
+    policy: Pubkey,
+    api_key: Pubkey,
+) -> Instruction {
+    build(
+        accounts::AttachPolicyToKey {
+            authority,
+            service,
+            policy,
+            api_key,
+        },
+        ix::AttachPolicyToKey {},
+    )
+}
+
+// API KEY
+/// `api_key_index` is the protocol's current `api_key_count`.
+pub fn create_api_key(
+    authority: Pubkey,
+    service: Pubkey,
+    api_key_index: u64,
+    owner: Pubkey,
+    policy: Pubkey,
+) -> Instruction {
+    let api_key = pda::api_key(api_key_index);
+    build(
+        accounts::CreateApiKey {
+            authority,
+            protocol: pda::protocol(),
+            service,
+            api_key,
+            delegated_usage: pda::delegated_usage(&api_key),
+            reputation: pda::reputation(&owner),
+            owner,
+            system_program: system_program::ID,
+        },
+        ix::CreateApiKey { policy },
+    )
+}
+
+pub fn revoke_api_key(authority: Pubkey, service: Pubkey, api_key: Pubkey) -> Instruction {
+    build(
+        accounts::RevokeApiKey {
+            authority,
+            service,
+            api_key,
+        },
+        ix::RevokeApiKey {},
+    )
+}
+
+pub fn set_api_key_status(
+    authority: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    new_status: ApiKeyStatus,
+) -> Instruction {
+    build(
+        accounts::SetApiKeyStatus {
+            authority,
+            service,
+            api_key,
+        },
+        ix::SetApiKeyStatus { new_status },
+    )
+}
+
+// EXECUTION REGIONS
+pub fn register_execution_region(
+    admin: Pubkey,
+    validator: Pubkey,
+    region_code: String,
+) -> Instruction {
+    build(
+        accounts::RegisterExecutionRegion {
+            admin,
+            protocol: pda::protocol(),
+            execution_region: pda::execution_region(&validator),
+            system_program: system_program::ID,
+        },
+        ix::RegisterExecutionRegion {
+            validator,
+            region_code,
+        },
+    )
+}
+
+pub fn set_execution_region_status(
+    admin: Pubkey,
+    validator: Pubkey,
+    new_status: ExecutionRegionStatus,
+) -> Instruction {
+    build(
+        accounts::SetExecutionRegionStatus {
+            admin,
+            protocol: pda::protocol(),
+            execution_region: pda::execution_region(&validator),
+        },
+        ix::SetExecutionRegionStatus { new_status },
+    )
+}
+
+// GATEWAYS
+pub fn register_gateway(authority: Pubkey, service: Pubkey, gateway_key: Pubkey) -> Instruction {
+    build(
+        accounts::RegisterGateway {
+            authority,
+            service,
+            gateway: pda::gateway(&service, &gateway_key),
+            system_program: system_program::ID,
+        },
+        ix::RegisterGateway { gateway_key },
+    )
+}
+
+pub fn revoke_gateway(authority: Pubkey, service: Pubkey, gateway_key: Pubkey) -> Instruction {
+    build(
+        accounts::RevokeGateway {
+            authority,
+            service,
+            gateway: pda::gateway(&service, &gateway_key),
+        },
+        ix::RevokeGateway {},
+    )
+}
+
+/// Must directly follow [`ed25519_verify`] over the receipt's message in the
+/// same transaction. `checkpoint_seq` is the key's current `checkpoint_seq`;
+/// the receipt creates the checkpoint after it.
+pub fn apply_usage_receipt(
+    payer: Pubkey,
+    service: Pubkey,
+    policy: Pubkey,
+    gateway_key: Pubkey,
+    checkpoint_seq: u64,
+    receipt: UsageReceipt,
+) -> Instruction {
+    let api_key = receipt.api_key;
+    build(
+        accounts::ApplyUsageReceipt {
+            payer,
+            protocol: pda::protocol(),
+            service,
+            api_key,
+            policy,
+            gateway: pda::gateway(&service, &gateway_key),
+            delegated_usage: pda::delegated_usage(&api_key),
+            usage_checkpoint: pda::usage_checkpoint(&api_key, checkpoint_seq + 1),
+            instructions: sysvar::instructions::ID,
+            system_program: system_program::ID,
+        },
+        ix::ApplyUsageReceipt { receipt },
+    )
+}
+
+/// Ed25519 precompile instruction verifying one signature, with the key,
+/// signature and message inline as `apply_usage_receipt` expects.
+pub fn ed25519_verify(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    const HEADER_LEN: u16 = 2 + 14;
+    let public_key_offset = HEADER_LEN;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+
+    let mut data = vec![1, 0];
+    for value in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+// MAGICBLOCK DELEGATION
+pub fn create_usage_shard(
+    authority: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    shard_index: u8,
+) -> Instruction {
+    build(
+        accounts::CreateUsageShard {
+            authority,
+            service,
+            api_key,
+            delegated_usage: pda::usage_shard(&api_key, shard_index),
+            system_program: system_program::ID,
+        },
+        ix::CreateUsageShard { shard_index },
+    )
+}
+
+pub fn prepare_delegation(
+    authority: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    policy: Pubkey,
+    validator: Pubkey,
+    shard_index: u8,
+    args: ix::PrepareDelegation,
+) -> Instruction {
+    build(
+        accounts::PrepareDelegation {
+            authority,
+            service,
+            api_key,
+            policy,
+            execution_region: pda::execution_region(&validator),
+            delegated_usage: pda::usage_shard(&api_key, shard_index),
+        },
+        args,
+    )
+}
+
+/// Delegates a prepared usage account to `validator`'s ephemeral rollup.
+pub fn delegate_usage(
+    payer: Pubkey,
+    api_key: Pubkey,
+    shard_index: u8,
+    validator: Pubkey,
+) -> Instruction {
+    let usage = pda::usage_shard(&api_key, shard_index);
+    build(
+        accounts::DelegateUsage {
+            payer,
+            api_key,
+            buffer_pda: pda::delegate_buffer(&usage),
+            delegation_record_pda: pda::delegation_record(&usage),
+            delegation_metadata_pda: pda::delegation_metadata(&usage),
+            pda: usage,
+            owner_program: ID,
+            delegation_program: ephemeral_rollups_sdk::id(),
+            system_program: system_program::ID,
+        },
+        ix::DelegateUsage {
+            execution_region: validator,
+        },
+    )
+}
+
+/// Ephemeral rollup. `delegated_usage` may be any shard of the key.
+pub fn record_usage_realtime(
+    submitter: Submitter,
+    delegated_usage: Pubkey,
+    api_key: Pubkey,
+    policy: Pubkey,
+    amount: u64,
+    request_id: Option<u64>,
+) -> Instruction {
+    build(
+        accounts::RecordUsageRealtime {
+            submitter: submitter.signer,
+            delegated_usage,
+            api_key,
+            policy,
+            service: submitter.service,
+            gateway: submitter.gateway_account(),
+        },
+        ix::RecordUsageRealtime { amount, request_id },
+    )
+}
+
+/// Base layer, for keys that are not delegated.
+pub fn record_usage_direct(
+    submitter: Submitter,
+    api_key: Pubkey,
+    policy: Pubkey,
+    amount: u64,
+    request_id: Option<u64>,
+) -> Instruction {
+    build(
+        accounts::RecordUsageDirect {
+            submitter: submitter.signer,
+            delegated_usage: pda::delegated_usage(&api_key),
+            api_key,
+            policy,
+            service: submitter.service,
+            gateway: submitter.gateway_account(),
+        },
+        ix::RecordUsageDirect { amount, request_id },
+    )
+}
+
+pub fn reserve_usage(
+    submitter: Submitter,
+    delegated_usage: Pubkey,
+    api_key: Pubkey,
+    policy: Pubkey,
+    amount: u64,
+    ttl_seconds: u64,
+) -> Instruction {
+    build(
+        accounts::ReserveUsage {
+            submitter: submitter.signer,
+            delegated_usage,
+            api_key,
+            policy,
+            service: submitter.service,
+            gateway: submitter.gateway_account(),
+        },
+        ix::ReserveUsage {
+            amount,
+            ttl_seconds,
+        },
+    )
+}
+
+pub fn settle_usage(
+    submitter: Submitter,
+    delegated_usage: Pubkey,
+    api_key: Pubkey,
+    policy: Pubkey,
+    reservation_id: u64,
+    amount: u64,
+) -> Instruction {
+    build(
+        accounts::SettleUsage {
+            submitter: submitter.signer,
+            delegated_usage,
+            api_key,
+            policy,
+            service: submitter.service,
+            gateway: submitter.gateway_account(),
+        },
+        ix::SettleUsage {
+            reservation_id,
+            amount,
+        },
+    )
+}
+
+pub fn release_usage(
+    submitter: Submitter,
+    delegated_usage: Pubkey,
+    api_key: Pubkey,
+    reservation_id: u64,
+) -> Instruction {
+    build(
+        accounts::ReleaseUsage {
+            submitter: submitter.signer,
+            delegated_usage,
+            api_key,
+            service: submitter.service,
+            gateway: submitter.gateway_account(),
+        },
+        ix::ReleaseUsage { reservation_id },
+    )
+}
+
+/// One entry of [`record_usage_batch`].
+#[derive(Clone, Copy)]
+pub struct UsageBatchEntry {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub policy: Pubkey,
+    pub amount: u64,
+    pub request_id: Option<u64>,
+}
+
+/// Every entry's key must belong to the submitter's service.
+pub fn record_usage_batch(submitter: Submitter, entries: &[UsageBatchEntry]) -> Instruction {
+    let request_ids = if entries.iter().any(|e| e.request_id.is_some()) {
+        entries.iter().map(|e| e.request_id).collect()
+    } else {
+        vec![]
+    };
+    let mut instruction = build(
+        accounts::RecordUsageBatch {
+            submitter: submitter.signer,
+            service: submitter.service,
+            gateway: submitter.gateway_account(),
+        },
+        ix::RecordUsageBatch {
+            amounts: entries.iter().map(|e| e.amount).collect(),
+            request_ids,
+        },
+    );
+    for entry in entries {
+        instruction.accounts.extend([
+            AccountMeta::new(entry.delegated_usage, false),
+            AccountMeta::new_readonly(entry.api_key, false),
+            AccountMeta::new_readonly(entry.policy, false),
+        ]);
+    }
+    instruction
+}
+
+/// Ephemeral rollup. Folds shards `1..usage_shards` into the primary
+/// account before committing.
+pub fn submit_usage_checkpoint(
+    submitter: Submitter,
+    api_key: Pubkey,
+    usage_shards: u8,
+    request_log_root: [u8; 32],
+) -> Instruction {
+    let mut instruction = build(
+        accounts::SubmitUsageCheckpoint {
+            payer: submitter.signer,
+            delegated_usage: pda::delegated_usage(&api_key),
+            api_key,
+            service: submitter.service,
+            gateway: submitter.gateway_account(),
+            magic_program: MAGIC_PROGRAM_ID,
+            magic_context: MAGIC_CONTEXT_ID,
+        },
+        ix::SubmitUsageCheckpoint { request_log_root },
+    );
+    instruction
+        .accounts
+        .extend(shard_metas(&api_key, usage_shards, true));
+    instruction
+}
+
+/// `checkpoint_seq` is the committed `checkpoint_seq` of the key's usage
+/// account.
+pub fn apply_usage_checkpoint(
+    payer: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    policy: Pubkey,
+    checkpoint_seq: u64,
+) -> Instruction {
+    build(
+        accounts::ApplyUsageCheckpoint {
+            payer,
+            protocol: pda::protocol(),
+            service,
+            api_key,
+            policy,
+            delegated_usage: pda::delegated_usage(&api_key),
+            usage_checkpoint: pda::usage_checkpoint(&api_key, checkpoint_seq),
+            system_program: system_program::ID,
+        },
+        ix::ApplyUsageCheckpoint { checkpoint_seq },
+    )
+}
+
+pub fn verify_request_inclusion(
+    usage_checkpoint: Pubkey,
+    leaf: RequestLogLeaf,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    build(
+        accounts::VerifyRequestInclusion { usage_checkpoint },
+        ix::VerifyRequestInclusion { leaf, proof },
+    )
+}
+
+pub fn undelegate_usage(payer: Pubkey, delegated_usage: Pubkey) -> Instruction {
+    build(
+        accounts::UndelegateUsage {
+            payer,
+            delegated_usage,
+            magic_program: MAGIC_PROGRAM_ID,
+            magic_context: MAGIC_CONTEXT_ID,
+        },
+        ix::UndelegateUsage {},
+    )
+}
+
+/// Ephemeral rollup. `validator` is the region the key migrates to.
+/// Migrates every usage account of the key, `usage_shards` being the key's
+/// `usage_shards`.
+pub fn begin_region_migration(
+    payer: Pubkey,
+    authority: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    usage_shards: u8,
+    validator: Pubkey,
+) -> Instruction {
+    let mut instruction = build(
+        accounts::BeginRegionMigration {
+            payer,
+            authority,
+            service,
+            api_key,
+            delegated_usage: pda::delegated_usage(&api_key),
+            execution_region: pda::execution_region(&validator),
+            magic_program: MAGIC_PROGRAM_ID,
+            magic_context: MAGIC_CONTEXT_ID,
+        },
+        ix::BeginRegionMigration {},
+    );
+    instruction
+        .accounts
+        .extend(shard_metas(&api_key, usage_shards, true));
+    instruction
+}
+
+/// `validator` is the migration target recorded by begin_region_migration.
+pub fn complete_region_migration(
+    authority: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    usage_shards: u8,
+    validator: Pubkey,
+) -> Instruction {
+    let mut instruction = build(
+        accounts::CompleteRegionMigration {
+            authority,
+            service,
+            api_key,
+            delegated_usage: pda::delegated_usage(&api_key),
+            execution_region: pda::execution_region(&validator),
+        },
+        ix::CompleteRegionMigration {},
+    );
+    instruction
+        .accounts
+        .extend(shard_metas(&api_key, usage_shards, true));
+    instruction
+}
+
+pub fn renew_delegation_lease(
+    authority: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    delegated_usage: Pubkey,
+    lease_seconds: u64,
+) -> Instruction {
+    build(
+        accounts::RenewDelegationLease {
+            authority,
+            service,
+            api_key,
+            delegated_usage,
+        },
+        ix::RenewDelegationLease { lease_seconds },
+    )
+}
+
+pub fn expire_delegation_lease(payer: Pubkey, delegated_usage: Pubkey) -> Instruction {
+    build(
+        accounts::ExpireDelegationLease {
+            payer,
+            delegated_usage,
+            magic_program: MAGIC_PROGRAM_ID,
+            magic_context: MAGIC_CONTEXT_ID,
+        },
+        ix::ExpireDelegationLease {},
+    )
+}
+
+pub fn reconcile_returned_delegation(
+    authority: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    delegated_usage: Pubkey,
+) -> Instruction {
+    build(
+        accounts::ReconcileReturnedDelegation {
+            authority,
+            service,
+            api_key,
+            delegated_usage,
+        },
+        ix::ReconcileReturnedDelegation {},
+    )
+}
+
+// CHECKPOINT FINALIZATION
+pub fn register_auditor(authority: Pubkey, service: Pubkey, auditor: Pubkey) -> Instruction {
+    build(
+        accounts::RegisterAuditor {
+            authority,
+            service,
+            auditor_account: pda::auditor(&service, &auditor),
+            system_program: system_program::ID,
+        },
+        ix::RegisterAuditor { auditor },
+    )
+}
+
+pub fn revoke_auditor(authority: Pubkey, service: Pubkey, auditor: Pubkey) -> Instruction {
+    build(
+        accounts::RevokeAuditor {
+            authority,
+            service,
+            auditor_account: pda::auditor(&service, &auditor),
+        },
+        ix::RevokeAuditor {},
+    )
+}
+
+pub fn finalize_usage_checkpoint(
+    service: Pubkey,
+    api_key: Pubkey,
+    usage_checkpoint: Pubkey,
+) -> Instruction {
+    build(
+        accounts::FinalizeUsageCheckpoint {
+            service,
+            api_key,
+            usage_checkpoint,
+        },
+        ix::FinalizeUsageCheckpoint {},
+    )
+}
+
+/// Set `as_auditor` when `disputer` is a registered auditor of the service
+/// rather than the key owner.
+pub fn dispute_usage_checkpoint(
+    disputer: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    usage_checkpoint: Pubkey,
+    as_auditor: bool,
+    reason: DisputeReason,
+    evidence_hash: [u8; 32],
+) -> Instruction {
+    build(
+        accounts::DisputeUsageCheckpoint {
+            disputer,
+            service,
+            api_key,
+            usage_checkpoint,
+            auditor: as_auditor.then(|| pda::auditor(&service, &disputer)),
+        },
+        ix::DisputeUsageCheckpoint {
+            reason,
+            evidence_hash,
+        },
+    )
+}
+
+pub fn resolve_checkpoint_dispute(
+    authority: Pubkey,
+    admin: Pubkey,
+    service: Pubkey,
+    api_key: Pubkey,
+    usage_checkpoint: Pubkey,
+    billed_request_count: u64,
+    billed_cost: u64,
+) -> Instruction {
+    build(
+        accounts::ResolveCheckpointDispute {
+            authority,
+            admin,
+            protocol: pda::protocol(),
+            service,
+            api_key,
+            usage_checkpoint,
+        },
+        ix::ResolveCheckpointDispute {
+            billed_request_count,
+            billed_cost,
+        },
+    )
+}
+
+// ENFORCEMENT
+/// Sums usage over shards `0..usage_shards` of the key.
+pub fn evaluate_enforcement(api_key: Pubkey, policy: Pubkey, usage_shards: u8) -> Instruction {
+    let mut instruction = build(
+        accounts::EvaluateEnforcement {
+            api_key,
+            policy,
+            delegated_usage: pda::delegated_usage(&api_key),
+        },
+        ix::EvaluateEnforcement {},
+    );
+    instruction
+        .accounts
+        .extend(shard_metas(&api_key, usage_shards, false));
+    instruction
+}
+
+pub fn manual_block_key(authority: Pubkey, service: Pubkey, api_key: Pubkey) -> Instruction {
+    build(
+        accounts::ManualBlockKey {
+            authority,
+            service,
+            api_key,
+        },
+        ix::ManualBlockKey {},
+    )
+}
+
+pub fn manual_unblock_key(authority: Pubkey, service: Pubkey, api_key: Pubkey) -> Instruction {
+    build(
+        accounts::ManualUnblockKey {
+            authority,
+            service,
+            api_key,
+        },
+        ix::ManualUnblockKey {},
+    )
+}
+
+// ABUSE / REPUTATION
+/// The signal PDA is seeded by the cluster time the instruction runs at, so
+/// `unix_timestamp` has to match the slot it lands in.
+pub fn emit_abuse_signal(
+    authority: Pubkey,
+    service: Pubkey,
+    subject: Pubkey,
+    unix_timestamp: i64,
+    severity: u8,
+    category: u32,
+) -> Instruction {
+    build(
+        accounts::EmitAbuseSignal {
+            authority,
+            service,
+            abuse_signal: pda::abuse_signal(&subject, unix_timestamp),
+            reputation: pda::reputation(&subject),
+            clock: sysvar::clock::ID,
+            system_program: system_program::ID,
+        },
+        ix::EmitAbuseSignal { severity, category },
+    )
+}
+
+pub fn update_reputation(subject: Pubkey, delta: i64) -> Instruction {
+    build(
+        accounts::UpdateReputation {
+            reputation: pda::reputation(&subject),
+        },
+        ix::UpdateReputation { delta },
+    )
+}
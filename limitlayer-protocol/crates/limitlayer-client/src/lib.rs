@@ -0,0 +1,21 @@
+//! Rust client for the LimitLayer protocol.
+//!
+//! Account, event and instruction types come straight from the program crate
+//! (built with `no-entrypoint`), so they cannot drift from what is deployed.
+//! This crate adds what every off-chain caller otherwise re-derives by hand:
+//!
+//! - [`pda`]: addresses for every account the program owns or delegates
+//! - [`instructions`]: one builder per instruction, with PDAs filled in
+//! - [`accounts`]: decoding raw account data into program state
+//! - [`events`]: decoding `emit!` events from transaction logs
+//! - [`rpc`]: a blocking JSON-RPC client reading and sending all of the above
+
+pub mod accounts;
+pub mod error;
+pub mod events;
+pub mod instructions;
+pub mod pda;
+pub mod rpc;
+
+pub use error::{Error, Result};
+pub use limitlayer_protocol::{self as program, ID};
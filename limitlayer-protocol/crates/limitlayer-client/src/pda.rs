@@ -0,0 +1,106 @@
+//! Program-derived addresses, mirroring the seeds in the program's account
+//! constraints.
+
+use anchor_lang::prelude::Pubkey;
+use ephemeral_rollups_sdk::pda::{
+    DELEGATE_BUFFER_TAG, DELEGATION_METADATA_TAG, DELEGATION_RECORD_TAG,
+};
+use limitlayer_protocol::{constants::*, state::shard_seed, ID};
+
+fn find(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &ID).0
+}
+
+pub fn protocol() -> Pubkey {
+    find(&[PROTOCOL_SEED.as_bytes()])
+}
+
+/// Service created when `protocol.service_count` was `service_index`.
+pub fn service(service_index: u64) -> Pubkey {
+    find(&[SERVICE_SEED.as_bytes(), &service_index.to_le_bytes()])
+}
+
+/// Policy created when `service.policy_count` was `policy_index`.
+pub fn policy(service: &Pubkey, policy_index: u64) -> Pubkey {
+    find(&[
+        POLICY_SEED.as_bytes(),
+        service.as_ref(),
+        &policy_index.to_le_bytes(),
+    ])
+}
+
+/// API key created when `protocol.api_key_count` was `api_key_index`.
+pub fn api_key(api_key_index: u64) -> Pubkey {
+    find(&[API_KEY_SEED.as_bytes(), &api_key_index.to_le_bytes()])
+}
+
+/// Primary usage account of a key (shard 0).
+pub fn delegated_usage(api_key: &Pubkey) -> Pubkey {
+    usage_shard(api_key, 0)
+}
+
+pub fn usage_shard(api_key: &Pubkey, shard_index: u8) -> Pubkey {
+    find(&[
+        DELEGATED_USAGE_SEED.as_bytes(),
+        api_key.as_ref(),
+        shard_seed(&shard_index),
+    ])
+}
+
+pub fn usage_checkpoint(api_key: &Pubkey, checkpoint_seq: u64) -> Pubkey {
+    find(&[
+        USAGE_SEED.as_bytes(),
+        api_key.as_ref(),
+        &checkpoint_seq.to_le_bytes(),
+    ])
+}
+
+pub fn reputation(owner: &Pubkey) -> Pubkey {
+    find(&[REPUTATION_SEED.as_bytes(), owner.as_ref()])
+}
+
+/// Abuse signal emitted against `subject` at `unix_timestamp`.
+pub fn abuse_signal(subject: &Pubkey, unix_timestamp: i64) -> Pubkey {
+    find(&[
+        ABUSE_SIGNAL_SEED.as_bytes(),
+        subject.as_ref(),
+        &unix_timestamp.to_le_bytes(),
+    ])
+}
+
+pub fn execution_region(validator: &Pubkey) -> Pubkey {
+    find(&[EXECUTION_REGION_SEED.as_bytes(), validator.as_ref()])
+}
+
+pub fn gateway(service: &Pubkey, gateway_key: &Pubkey) -> Pubkey {
+    find(&[
+        GATEWAY_SEED.as_bytes(),
+        service.as_ref(),
+        gateway_key.as_ref(),
+    ])
+}
+
+pub fn auditor(service: &Pubkey, auditor: &Pubkey) -> Pubkey {
+    find(&[AUDITOR_SEED.as_bytes(), service.as_ref(), auditor.as_ref()])
+}
+
+/// Buffer the delegation program copies a delegated account through.
+pub fn delegate_buffer(delegated: &Pubkey) -> Pubkey {
+    find(&[DELEGATE_BUFFER_TAG, delegated.as_ref()])
+}
+
+pub fn delegation_record(delegated: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[DELEGATION_RECORD_TAG, delegated.as_ref()],
+        &ephemeral_rollups_sdk::id(),
+    )
+    .0
+}
+
+pub fn delegation_metadata(delegated: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[DELEGATION_METADATA_TAG, delegated.as_ref()],
+        &ephemeral_rollups_sdk::id(),
+    )
+    .0
+}
@@ -0,0 +1,355 @@
+//! Blocking JSON-RPC client for the calls LimitLayer's off-chain tools make,
+//! against the base layer or an ephemeral rollup alike.
+
+use std::time::Duration;
+
+use anchor_lang::{prelude::Pubkey, solana_program::hash::Hash, AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+pub use solana_signature::Signature;
+use solana_transaction::Transaction;
+
+use crate::{accounts, Error, Result};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// getMultipleAccounts takes at most this many addresses.
+const MAX_ACCOUNTS_PER_CALL: usize = 100;
+
+/// How settled the state a client reads must be.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Commitment {
+    #[default]
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Confirmed => "confirmed",
+            Self::Finalized => "finalized",
+        }
+    }
+}
+
+/// A transaction run without landing it.
+pub struct Simulation {
+    pub err: Option<Value>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+}
+
+/// Outcome of a transaction the node has confirmed.
+pub struct TransactionStatus {
+    /// The transaction error, if it failed
+    pub err: Option<Value>,
+    pub logs: Vec<String>,
+}
+
+/// One transaction of an address's history.
+pub struct SignatureInfo {
+    pub signature: Signature,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+}
+
+#[derive(Clone)]
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+    commitment: Commitment,
+}
+
+impl RpcClient {
+    /// Client reading confirmed state, with [`DEFAULT_TIMEOUT`].
+    pub fn new(url: String) -> Self {
+        Self::with_timeout(url, DEFAULT_TIMEOUT)
+    }
+
+    pub fn with_timeout(url: String, timeout: Duration) -> Self {
+        Self {
+            url,
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+            commitment: Commitment::default(),
+        }
+    }
+
+    /// Reads state, and checks transactions, at `commitment`.
+    pub fn with_commitment(mut self, commitment: Commitment) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Sends one request and returns its result.
+    pub fn call(&self, method: &'static str, params: Value) -> Result<Value> {
+        let response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .map_err(|source| Error::Http {
+                method,
+                source: Box::new(source),
+            })?
+            .into_json()
+            .map_err(|_| Error::MalformedResponse(method))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(Error::Rpc {
+                method,
+                message: error.to_string(),
+            });
+        }
+        response
+            .get("result")
+            .cloned()
+            .ok_or(Error::MalformedResponse(method))
+    }
+
+    fn account_config(&self) -> Value {
+        json!({ "encoding": "base64", "commitment": self.commitment.as_str() })
+    }
+
+    /// Raw data of the account, `None` if it does not exist.
+    pub fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        const METHOD: &str = "getAccountInfo";
+        let result = self.call(METHOD, json!([address.to_string(), self.account_config()]))?;
+        account_data(METHOD, &result["value"])
+    }
+
+    /// Raw data of each account, `None` where it does not exist. Any number
+    /// of addresses; they are requested in batches the node accepts.
+    pub fn multiple_accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Vec<u8>>>> {
+        const METHOD: &str = "getMultipleAccounts";
+        let mut accounts = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MAX_ACCOUNTS_PER_CALL) {
+            let chunk: Vec<String> = chunk.iter().map(Pubkey::to_string).collect();
+            let result = self.call(METHOD, json!([chunk, self.account_config()]))?;
+            let values = result["value"]
+                .as_array()
+                .filter(|values| values.len() == chunk.len())
+                .ok_or(Error::MalformedResponse(METHOD))?;
+            for value in values {
+                accounts.push(account_data(METHOD, value)?);
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// Decodes the program account at `address`, `None` if it does not
+    /// exist.
+    pub fn account<T: AnchorDeserialize + Discriminator>(
+        &self,
+        address: &Pubkey,
+    ) -> Result<Option<T>> {
+        self.account_data(address)?
+            .map(|data| decode(address, &data))
+            .transpose()
+    }
+
+    /// Like [`account`](Self::account), failing if it does not exist.
+    pub fn fetch<T: AnchorDeserialize + Discriminator>(&self, address: &Pubkey) -> Result<T> {
+        self.account(address)?
+            .ok_or(Error::AccountNotFound(*address))
+    }
+
+    /// Decodes the program accounts at `addresses`, `None` for those that
+    /// do not exist.
+    pub fn accounts<T: AnchorDeserialize + Discriminator>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<T>>> {
+        addresses
+            .iter()
+            .zip(self.multiple_accounts(addresses)?)
+            .map(|(address, data)| data.map(|data| decode(address, &data)).transpose())
+            .collect()
+    }
+
+    /// Accounts of type `T` owned by `owner`, selected by discriminator.
+    pub fn program_accounts<T: AnchorDeserialize + Discriminator>(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, T)>> {
+        const METHOD: &str = "getProgramAccounts";
+        let result = self.call(
+            METHOD,
+            json!([owner.to_string(), {
+                "encoding": "base64",
+                "commitment": self.commitment.as_str(),
+                "filters": [{
+                    "memcmp": { "offset": 0, "bytes": STANDARD.encode(T::DISCRIMINATOR), "encoding": "base64" }
+                }],
+            }]),
+        )?;
+        result
+            .as_array()
+            .ok_or(Error::MalformedResponse(METHOD))?
+            .iter()
+            .map(|entry| {
+                let address: Pubkey = entry["pubkey"]
+                    .as_str()
+                    .and_then(|address| address.parse().ok())
+                    .ok_or(Error::MalformedResponse(METHOD))?;
+                let data = account_data(METHOD, &entry["account"])?
+                    .ok_or(Error::MalformedResponse(METHOD))?;
+                Ok((address, decode(&address, &data)?))
+            })
+            .collect()
+    }
+
+    pub fn latest_blockhash(&self) -> Result<Hash> {
+        const METHOD: &str = "getLatestBlockhash";
+        let result = self.call(METHOD, json!([{ "commitment": self.commitment.as_str() }]))?;
+        result["value"]["blockhash"]
+            .as_str()
+            .and_then(|hash| hash.parse().ok())
+            .ok_or(Error::MalformedResponse(METHOD))
+    }
+
+    /// Runs `transaction` without landing it. Its blockhash is replaced and
+    /// its signatures are not checked.
+    pub fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation> {
+        let result = self.call(
+            "simulateTransaction",
+            json!([encode(transaction)?, {
+                "encoding": "base64",
+                "commitment": self.commitment.as_str(),
+                "sigVerify": false,
+                "replaceRecentBlockhash": true,
+            }]),
+        )?;
+        let value = &result["value"];
+        Ok(Simulation {
+            err: error(&value["err"]),
+            logs: log_messages(&value["logs"]),
+            units_consumed: value["unitsConsumed"].as_u64(),
+        })
+    }
+
+    /// Sends `transaction` after a preflight check, without waiting for it
+    /// to land.
+    pub fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        const METHOD: &str = "sendTransaction";
+        let result = self.call(
+            METHOD,
+            json!([encode(transaction)?, {
+                "encoding": "base64",
+                "preflightCommitment": self.commitment.as_str(),
+            }]),
+        )?;
+        result
+            .as_str()
+            .and_then(|signature| signature.parse().ok())
+            .ok_or(Error::MalformedResponse(METHOD))
+    }
+
+    /// The transaction's outcome once it reaches the client's commitment,
+    /// `None` until then.
+    pub fn transaction(&self, signature: &Signature) -> Result<Option<TransactionStatus>> {
+        let result = self.call(
+            "getTransaction",
+            json!([signature.to_string(), {
+                "encoding": "json",
+                "commitment": self.commitment.as_str(),
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        let meta = &result["meta"];
+        Ok(Some(TransactionStatus {
+            err: error(&meta["err"]),
+            logs: log_messages(&meta["logMessages"]),
+        }))
+    }
+
+    /// Up to `limit` transactions touching `address`, newest first: those
+    /// before `before` if set, back to `until` if set.
+    pub fn signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<&Signature>,
+        until: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        const METHOD: &str = "getSignaturesForAddress";
+        let result = self.call(
+            METHOD,
+            json!([address.to_string(), {
+                "limit": limit,
+                "before": before.map(Signature::to_string),
+                "until": until.map(Signature::to_string),
+                "commitment": self.commitment.as_str(),
+            }]),
+        )?;
+        result
+            .as_array()
+            .ok_or(Error::MalformedResponse(METHOD))?
+            .iter()
+            .map(|entry| {
+                Ok(SignatureInfo {
+                    signature: entry["signature"]
+                        .as_str()
+                        .and_then(|signature| signature.parse().ok())
+                        .ok_or(Error::MalformedResponse(METHOD))?,
+                    slot: entry["slot"].as_u64().unwrap_or_default(),
+                    block_time: entry["blockTime"].as_i64(),
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Data of an account in a response, `None` for a missing account.
+fn account_data(method: &'static str, account: &Value) -> Result<Option<Vec<u8>>> {
+    if account.is_null() {
+        return Ok(None);
+    }
+    let data = account["data"][0]
+        .as_str()
+        .ok_or(Error::MalformedResponse(method))?;
+    Ok(Some(STANDARD.decode(data)?))
+}
+
+fn decode<T: AnchorDeserialize + Discriminator>(address: &Pubkey, data: &[u8]) -> Result<T> {
+    accounts::decode(data).map_err(|source| Error::UnexpectedAccount {
+        address: *address,
+        expected: std::any::type_name::<T>()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default(),
+        source: Box::new(source),
+    })
+}
+
+fn encode(transaction: &Transaction) -> Result<String> {
+    Ok(STANDARD.encode(bincode::serialize(transaction)?))
+}
+
+fn error(value: &Value) -> Option<Value> {
+    Some(value.clone()).filter(|err| !err.is_null())
+}
+
+fn log_messages(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|logs| {
+            logs.iter()
+                .filter_map(|line| line.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
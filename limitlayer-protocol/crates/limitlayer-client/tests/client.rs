@@ -0,0 +1,133 @@
+use anchor_lang::{prelude::*, AccountSerialize, Discriminator, Event};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use limitlayer_client::{
+    accounts::{self, LimitLayerAccount},
+    events::{parse_logs, LimitLayerEvent},
+    instructions, pda,
+    program::{instruction as ix, ApiKeyRevoked, ProtocolState, UsageRecordedRealtime},
+    ID,
+};
+
+#[test]
+fn pdas_match_program_seeds() {
+    let api_key = pda::api_key(7);
+    let (expected, _) = Pubkey::find_program_address(&[b"api_key", &7u64.to_le_bytes()], &ID);
+    assert_eq!(api_key, expected);
+
+    let (primary, _) = Pubkey::find_program_address(&[b"delegated_usage", api_key.as_ref()], &ID);
+    assert_eq!(pda::delegated_usage(&api_key), primary);
+    assert_eq!(pda::usage_shard(&api_key, 0), primary);
+
+    let (shard, _) =
+        Pubkey::find_program_address(&[b"delegated_usage", api_key.as_ref(), &[3]], &ID);
+    assert_eq!(pda::usage_shard(&api_key, 3), shard);
+}
+
+#[test]
+fn create_api_key_derives_accounts() {
+    let authority = Pubkey::new_unique();
+    let service = pda::service(0);
+    let owner = Pubkey::new_unique();
+    let policy = Pubkey::new_unique();
+
+    let instruction = instructions::create_api_key(authority, service, 4, owner, policy);
+    let api_key = pda::api_key(4);
+
+    assert_eq!(instruction.program_id, ID);
+    let keys: Vec<Pubkey> = instruction.accounts.iter().map(|m| m.pubkey).collect();
+    assert_eq!(
+        keys,
+        vec![
+            authority,
+            pda::protocol(),
+            service,
+            api_key,
+            pda::delegated_usage(&api_key),
+            pda::reputation(&owner),
+            owner,
+            anchor_lang::system_program::ID,
+        ]
+    );
+    assert!(instruction.accounts[0].is_signer && instruction.accounts[0].is_writable);
+    assert!(instruction
+        .data
+        .starts_with(ix::CreateApiKey::DISCRIMINATOR));
+    assert_eq!(&instruction.data[8..], policy.as_ref());
+}
+
+#[test]
+fn shard_accounts_are_appended() {
+    let api_key = Pubkey::new_unique();
+    let instruction = instructions::evaluate_enforcement(api_key, Pubkey::new_unique(), 3);
+
+    let shards: Vec<Pubkey> = instruction.accounts[3..].iter().map(|m| m.pubkey).collect();
+    assert_eq!(
+        shards,
+        vec![pda::usage_shard(&api_key, 1), pda::usage_shard(&api_key, 2)]
+    );
+    assert!(instruction.accounts[3..].iter().all(|m| !m.is_writable));
+}
+
+#[test]
+fn decodes_program_accounts() {
+    let state = ProtocolState {
+        admin_authority: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        protocol_fee_bps: 250,
+        paused: false,
+        service_count: 2,
+        api_key_count: 9,
+        total_usage_checkpoints: 0,
+        bump: 255,
+    };
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+
+    let decoded: ProtocolState = accounts::decode(&data).unwrap();
+    assert_eq!(decoded.api_key_count, 9);
+
+    match LimitLayerAccount::decode(&data).unwrap() {
+        LimitLayerAccount::Protocol(protocol) => assert_eq!(protocol.protocol_fee_bps, 250),
+        other => panic!("decoded as {}", other.name()),
+    }
+    assert!(LimitLayerAccount::decode(&[0; 16]).is_err());
+}
+
+#[test]
+fn parses_events_from_program_logs() {
+    let recorded = UsageRecordedRealtime {
+        delegated_usage: Pubkey::new_unique(),
+        api_key: Pubkey::new_unique(),
+        amount: 3,
+        window_usage: 12,
+    };
+    let revoked = ApiKeyRevoked {
+        api_key: Pubkey::new_unique(),
+        service: Pubkey::new_unique(),
+    };
+    let other_program = Pubkey::new_unique();
+
+    let logs = vec![
+        format!("Program {ID} invoke [1]"),
+        "Program log: Instruction: RecordUsageRealtime".to_string(),
+        format!("Program data: {}", STANDARD.encode(recorded.data())),
+        format!("Program {other_program} invoke [2]"),
+        // Emitted by the inner program, not ours
+        format!("Program data: {}", STANDARD.encode(revoked.data())),
+        format!("Program {other_program} success"),
+        format!("Program data: {}", STANDARD.encode(revoked.data())),
+        format!("Program {ID} consumed 5000 of 200000 compute units"),
+        format!("Program {ID} success"),
+    ];
+
+    let events = parse_logs(&logs).unwrap();
+    assert_eq!(events.len(), 2);
+    match &events[0] {
+        LimitLayerEvent::UsageRecordedRealtime(e) => {
+            assert_eq!(e.api_key, recorded.api_key);
+            assert_eq!(e.window_usage, 12);
+        }
+        other => panic!("unexpected {}", other.name()),
+    }
+    assert_eq!(events[1].name(), "ApiKeyRevoked");
+}
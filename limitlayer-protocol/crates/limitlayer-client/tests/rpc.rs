@@ -0,0 +1,209 @@
+//! Runs the shared RPC client against a canned JSON-RPC node.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anchor_lang::{prelude::*, AccountSerialize};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use limitlayer_client::{
+    program::{ProtocolState, UsageCheckpoint},
+    rpc::{RpcClient, Signature},
+    Error,
+};
+use serde_json::{json, Value};
+
+type Requests = Arc<Mutex<Vec<Value>>>;
+
+/// Serves each request with `respond`, which returns either a result or an
+/// `{"error": ..}` object, and records the requests it received.
+fn serve(respond: impl Fn(&Value) -> Value + Send + 'static) -> (RpcClient, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Requests::default();
+    let received = requests.clone();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let request: Value = serde_json::from_slice(&body).unwrap();
+            let mut response = json!({ "jsonrpc": "2.0", "id": request["id"] });
+            match respond(&request) {
+                Value::Object(object) if object.contains_key("error") => {
+                    response["error"] = object["error"].clone();
+                }
+                result => response["result"] = result,
+            }
+            received.lock().unwrap().push(request);
+            let response = response.to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+        }
+    });
+    (RpcClient::new(url), requests)
+}
+
+fn account(data: &[u8]) -> Value {
+    json!({ "data": [STANDARD.encode(data), "base64"] })
+}
+
+fn protocol_state(api_key_count: u64) -> Vec<u8> {
+    let state = ProtocolState {
+        admin_authority: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        protocol_fee_bps: 250,
+        paused: false,
+        service_count: 1,
+        api_key_count,
+        total_usage_checkpoints: 0,
+        bump: 255,
+    };
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn multiple_accounts_are_requested_in_batches() {
+    // Every third address exists and holds its index.
+    let (rpc, requests) = serve(|request| {
+        let values: Vec<Value> = request["params"][0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|address| {
+                let index: u8 = address
+                    .as_str()
+                    .unwrap()
+                    .parse::<Pubkey>()
+                    .unwrap()
+                    .to_bytes()[0];
+                if index % 3 == 0 {
+                    account(&[index])
+                } else {
+                    Value::Null
+                }
+            })
+            .collect();
+        json!({ "value": values })
+    });
+    let addresses: Vec<Pubkey> = (0..250u8)
+        .map(|index| Pubkey::new_from_array([index; 32]))
+        .collect();
+
+    let accounts = rpc.multiple_accounts(&addresses).unwrap();
+
+    assert_eq!(accounts.len(), 250);
+    for (index, data) in accounts.iter().enumerate() {
+        let expected = (index % 3 == 0).then(|| vec![index as u8]);
+        assert_eq!(*data, expected);
+    }
+    let batches: Vec<usize> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request["params"][0].as_array().unwrap().len())
+        .collect();
+    assert_eq!(batches, [100, 100, 50]);
+}
+
+#[test]
+fn accounts_are_decoded_by_type() {
+    let state = Pubkey::new_unique();
+    let missing = Pubkey::new_unique();
+    let (rpc, _) = serve(move |request| {
+        let address: Pubkey = request["params"][0].as_str().unwrap().parse().unwrap();
+        let value = if address == state {
+            account(&protocol_state(9))
+        } else {
+            Value::Null
+        };
+        json!({ "value": value })
+    });
+
+    let decoded: ProtocolState = rpc.fetch(&state).unwrap();
+    assert_eq!(decoded.api_key_count, 9);
+    assert!(rpc.account::<ProtocolState>(&missing).unwrap().is_none());
+    assert!(matches!(
+        rpc.fetch::<ProtocolState>(&missing),
+        Err(Error::AccountNotFound(address)) if address == missing
+    ));
+
+    let Err(error) = rpc.fetch::<UsageCheckpoint>(&state) else {
+        panic!("decoded a protocol state as a checkpoint");
+    };
+    assert!(matches!(error, Error::UnexpectedAccount { address, .. } if address == state));
+    assert!(error.to_string().contains("is not a UsageCheckpoint"));
+}
+
+#[test]
+fn node_errors_name_the_method() {
+    let (rpc, _) = serve(|_| json!({ "error": { "code": -32005, "message": "node is behind" } }));
+
+    let error = rpc.latest_blockhash().unwrap_err();
+
+    assert!(matches!(
+        error,
+        Error::Rpc {
+            method: "getLatestBlockhash",
+            ..
+        }
+    ));
+    assert!(error.to_string().contains("node is behind"));
+}
+
+#[test]
+fn transactions_are_reported_once_confirmed() {
+    let confirmed = Signature::from([1; 64]);
+    let failed = Signature::from([2; 64]);
+    let (rpc, _) = serve(move |request| {
+        let signature: Signature = request["params"][0].as_str().unwrap().parse().unwrap();
+        if signature == confirmed {
+            json!({ "meta": { "err": null, "logMessages": ["Program log: ok"] } })
+        } else if signature == failed {
+            json!({ "meta": { "err": { "InstructionError": [0, "Custom"] }, "logMessages": [] } })
+        } else {
+            Value::Null
+        }
+    });
+
+    assert!(rpc
+        .transaction(&Signature::from([3; 64]))
+        .unwrap()
+        .is_none());
+
+    let status = rpc.transaction(&confirmed).unwrap().unwrap();
+    assert!(status.err.is_none());
+    assert_eq!(status.logs, ["Program log: ok"]);
+
+    let status = rpc.transaction(&failed).unwrap().unwrap();
+    assert_eq!(
+        status.err,
+        Some(json!({ "InstructionError": [0, "Custom"] }))
+    );
+}
@@ -0,0 +1,49 @@
+//! UTC day and calendar month boundaries, in unix seconds.
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Start of the UTC day containing `ts`.
+pub fn day_start(ts: i64) -> i64 {
+    ts.div_euclid(SECONDS_PER_DAY) * SECONDS_PER_DAY
+}
+
+/// Start of the UTC calendar month containing `ts`.
+pub fn month_start(ts: i64) -> i64 {
+    let (year, month) = civil_from_days(ts.div_euclid(SECONDS_PER_DAY));
+    days_from_civil(year, month) * SECONDS_PER_DAY
+}
+
+/// Start of the UTC calendar month after the one containing `ts`.
+pub fn next_month_start(ts: i64) -> i64 {
+    let (year, month) = civil_from_days(ts.div_euclid(SECONDS_PER_DAY));
+    let (year, month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    days_from_civil(year, month) * SECONDS_PER_DAY
+}
+
+// Proleptic Gregorian conversions (H. Hinnant's days_from_civil / civil_from_days).
+fn civil_from_days(days: i64) -> (i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month)
+}
+
+/// Days since the epoch for the first day of `month` in `year`.
+fn days_from_civil(year: i64, month: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
@@ -0,0 +1,16 @@
+use crate::{KeyStatus, Policy};
+
+/// Status `evaluate_enforcement` assigns a key whose shards together hold
+/// `window_usage` units: blocked at the window limit, throttled from half
+/// of it. Dropping below half lifts a throttle but never a block.
+pub fn evaluate(status: KeyStatus, window_usage: u64, policy: &Policy) -> KeyStatus {
+    if window_usage >= policy.requests_per_window {
+        KeyStatus::Blocked
+    } else if window_usage >= policy.requests_per_window / 2 {
+        KeyStatus::Throttled
+    } else if status != KeyStatus::Blocked {
+        KeyStatus::Active
+    } else {
+        status
+    }
+}
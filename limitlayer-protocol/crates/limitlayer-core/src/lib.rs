@@ -0,0 +1,49 @@
+//! Rate-limit arithmetic of the LimitLayer protocol.
+//!
+//! The program calls these functions for every admission, enforcement and
+//! quota decision, so anything off-chain that calls them with the same
+//! inputs (gateways, simulators, tests) gets the decision the program would
+//! make, without a validator. Everything is a pure function of its
+//! arguments: no clock, no accounts, no allocation and no dependencies.
+//!
+//! - [`usage`]: charging units against a window and the burst allowance
+//! - [`enforcement`]: the status `evaluate_enforcement` assigns
+//! - [`quota`]: daily and monthly quota periods, advanced by checkpoints
+//! - [`calendar`]: the UTC day and month boundaries quotas follow
+
+#![no_std]
+
+pub mod calendar;
+pub mod enforcement;
+pub mod quota;
+pub mod usage;
+
+pub use enforcement::evaluate;
+pub use quota::{QuotaExhaustion, QuotaPeriod, QuotaState};
+pub use usage::{
+    apply_usage, apply_usage_direct, burst_allowance, lease_expired, reserve, settle, Charge,
+    Decision, Rejection, UsageState,
+};
+
+/// Limits of a `RateLimitPolicy` account.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    pub requests_per_window: u64,
+    pub window_seconds: u64,
+    pub burst_limit: u64,
+    pub cost_per_request: u64,
+    /// Requests per UTC day; 0 = unlimited
+    pub daily_quota: u64,
+    /// Requests per UTC calendar month; 0 = unlimited
+    pub monthly_quota: u64,
+}
+
+/// Status of an API key, in the program's `ApiKeyStatus` order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyStatus {
+    #[default]
+    Active,
+    Throttled,
+    Blocked,
+    Revoked,
+}
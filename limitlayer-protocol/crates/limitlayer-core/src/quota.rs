@@ -0,0 +1,112 @@
+//! Daily and monthly quotas. They count usage as checkpoints are applied on
+//! the base layer, in calendar-aligned UTC periods.
+
+use crate::{
+    calendar::{day_start, month_start, next_month_start, SECONDS_PER_DAY},
+    KeyStatus, Policy,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+/// An exhausted quota, as reported by the `QuotaExhausted` event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaExhaustion {
+    pub period: QuotaPeriod,
+    pub usage: u64,
+    pub quota: u64,
+    pub resets_at: i64,
+}
+
+/// Quota counters of an `ApiKeyAccount`, with the status they can block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaState {
+    pub status: KeyStatus,
+    pub day_start_ts: i64,
+    pub daily_usage: u64,
+    pub month_start_ts: i64,
+    pub monthly_usage: u64,
+    /// Set while the key is blocked for an exhausted quota; 0 otherwise
+    pub blocked_until: i64,
+}
+
+impl QuotaState {
+    /// Starts new quota periods once their boundary has passed and lifts a
+    /// quota block whose period has reset.
+    pub fn roll(&mut self, now: i64) {
+        let day = day_start(now);
+        if day != self.day_start_ts {
+            self.day_start_ts = day;
+            self.daily_usage = 0;
+        }
+
+        let month = month_start(now);
+        if month != self.month_start_ts {
+            self.month_start_ts = month;
+            self.monthly_usage = 0;
+        }
+
+        if self.blocked_until != 0 && now >= self.blocked_until {
+            if self.status == KeyStatus::Blocked {
+                self.status = KeyStatus::Active;
+            }
+            self.blocked_until = 0;
+        }
+    }
+
+    /// The exhausted period with the latest reset, if any.
+    pub fn exhausted(&self, policy: &Policy, now: i64) -> Option<QuotaExhaustion> {
+        if policy.monthly_quota > 0 && self.monthly_usage >= policy.monthly_quota {
+            return Some(QuotaExhaustion {
+                period: QuotaPeriod::Monthly,
+                usage: self.monthly_usage,
+                quota: policy.monthly_quota,
+                resets_at: next_month_start(now),
+            });
+        }
+
+        if policy.daily_quota > 0 && self.daily_usage >= policy.daily_quota {
+            return Some(QuotaExhaustion {
+                period: QuotaPeriod::Daily,
+                usage: self.daily_usage,
+                quota: policy.daily_quota,
+                resets_at: day_start(now) + SECONDS_PER_DAY,
+            });
+        }
+
+        None
+    }
+
+    /// Counts `request_count` units from a checkpoint applied at `now` and
+    /// blocks the key until the reset if that exhausts a quota. Returns the
+    /// exhaustion when it blocks.
+    pub fn charge(
+        &mut self,
+        policy: &Policy,
+        request_count: u64,
+        now: i64,
+    ) -> Option<QuotaExhaustion> {
+        self.roll(now);
+        self.daily_usage = self.daily_usage.saturating_add(request_count);
+        self.monthly_usage = self.monthly_usage.saturating_add(request_count);
+
+        // A manual or enforcement block (no blocked_until) is left alone,
+        // so a quota reset never lifts it.
+        let blockable = match self.status {
+            KeyStatus::Revoked => false,
+            KeyStatus::Blocked => self.blocked_until != 0,
+            KeyStatus::Active | KeyStatus::Throttled => true,
+        };
+        if !blockable {
+            return None;
+        }
+
+        let exhaustion = self.exhausted(policy, now)?;
+        self.status = KeyStatus::Blocked;
+        self.blocked_until = exhaustion.resets_at;
+        Some(exhaustion)
+    }
+}
@@ -0,0 +1,185 @@
+//! Charging usage against a window. Windows have no fixed end: they close
+//! when a checkpoint is submitted, so the only per-request limit is the
+//! burst allowance, shared out between the key's usage shards.
+
+use crate::{KeyStatus, Policy};
+
+/// A usage account's open window, with what admission reads from its key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UsageState {
+    pub status: KeyStatus,
+    /// Usage accounts counting the key, including the primary one
+    pub shards: u8,
+    /// This account's shard index, 0 for the primary
+    pub shard: u8,
+    pub delegated: bool,
+    /// Usage is refused from this time on; 0 = no lease
+    pub lease_expires_at: i64,
+    pub window_usage: u64,
+    pub window_cost: u64,
+    pub burst_counter: u64,
+    /// Units held by reservations still open at the time of the decision
+    pub reserved_units: u64,
+}
+
+/// The window counters after an accepted charge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Charge {
+    pub window_usage: u64,
+    pub window_cost: u64,
+    pub burst_counter: u64,
+}
+
+/// Why usage was refused, one variant per program error it maps to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    NotDelegated,
+    AlreadyDelegated,
+    LeaseExpired,
+    KeyBlocked,
+    BurstLimitExceeded,
+    MathOverflow,
+}
+
+pub type Decision = Result<Charge, Rejection>;
+
+/// Charges `amount` units to a delegated usage account, as
+/// `record_usage_realtime` does on the execution region. Each shard
+/// enforces its own share of the burst limit.
+pub fn apply_usage(state: &UsageState, policy: &Policy, now: i64, amount: u64) -> Decision {
+    check_delegated(state, now)?;
+    charge(state, policy, shard_allowance(state, policy), amount)
+}
+
+/// Base-layer counterpart of [`apply_usage`] for a key that is not
+/// delegated. Only the primary account records, so it gets the whole
+/// burst limit.
+pub fn apply_usage_direct(state: &UsageState, policy: &Policy, amount: u64) -> Decision {
+    if state.delegated {
+        return Err(Rejection::AlreadyDelegated);
+    }
+    charge(state, policy, policy.burst_limit, amount)
+}
+
+/// Whether `reserve_usage` may hold `amount` units: the same checks as
+/// [`apply_usage`], without charging anything.
+pub fn reserve(
+    state: &UsageState,
+    policy: &Policy,
+    now: i64,
+    amount: u64,
+) -> Result<(), Rejection> {
+    check_delegated(state, now)?;
+    admit(state, shard_allowance(state, policy), amount).map(|_| ())
+}
+
+/// Charges `amount` units when closing a reservation of `reserved`, as
+/// `settle_usage` does. Units up to the reservation were admitted when it
+/// was made, so they are charged even if the key has been blocked or its
+/// lease has run out since; only an excess is checked like [`apply_usage`].
+/// `state` must no longer count the reservation in `reserved_units`.
+pub fn settle(
+    state: &UsageState,
+    policy: &Policy,
+    now: i64,
+    reserved: u64,
+    amount: u64,
+) -> Decision {
+    if amount > reserved {
+        return apply_usage(state, policy, now, amount);
+    }
+
+    let (window_usage, window_cost) = window_totals(state, policy, amount)?;
+    let burst_counter = state
+        .burst_counter
+        .checked_add(amount)
+        .ok_or(Rejection::MathOverflow)?;
+
+    Ok(Charge {
+        window_usage,
+        window_cost,
+        burst_counter,
+    })
+}
+
+pub fn lease_expired(lease_expires_at: i64, now: i64) -> bool {
+    lease_expires_at != 0 && now >= lease_expires_at
+}
+
+/// Burst units shard `shard` of `shards` usage accounts may hold. The limit
+/// is split evenly, the first `burst_limit % shards` shards taking one unit
+/// of the remainder each, so the shares add up to exactly `burst_limit`.
+pub fn burst_allowance(policy: &Policy, shard: u8, shards: u8) -> u64 {
+    let shards = shards.max(1) as u64;
+    let share = policy.burst_limit / shards;
+    if (shard as u64) < policy.burst_limit % shards {
+        share + 1
+    } else {
+        share
+    }
+}
+
+fn shard_allowance(state: &UsageState, policy: &Policy) -> u64 {
+    burst_allowance(policy, state.shard, state.shards)
+}
+
+fn check_delegated(state: &UsageState, now: i64) -> Result<(), Rejection> {
+    if !state.delegated {
+        return Err(Rejection::NotDelegated);
+    }
+
+    if lease_expired(state.lease_expires_at, now) {
+        return Err(Rejection::LeaseExpired);
+    }
+
+    Ok(())
+}
+
+fn charge(state: &UsageState, policy: &Policy, allowance: u64, amount: u64) -> Decision {
+    let (window_usage, window_cost) = window_totals(state, policy, amount)?;
+    let burst_counter = admit(state, allowance, amount)?;
+
+    Ok(Charge {
+        window_usage,
+        window_cost,
+        burst_counter,
+    })
+}
+
+/// The window's usage and cost once `amount` more units are charged.
+fn window_totals(
+    state: &UsageState,
+    policy: &Policy,
+    amount: u64,
+) -> Result<(u64, u64), Rejection> {
+    let window_usage = state
+        .window_usage
+        .checked_add(amount)
+        .ok_or(Rejection::MathOverflow)?;
+
+    let window_cost = amount
+        .checked_mul(policy.cost_per_request)
+        .and_then(|cost| state.window_cost.checked_add(cost))
+        .ok_or(Rejection::MathOverflow)?;
+
+    Ok((window_usage, window_cost))
+}
+
+/// Checks that `amount` more units fit in `allowance` next to what is
+/// already charged and reserved, returning the new burst counter.
+fn admit(state: &UsageState, allowance: u64, amount: u64) -> Result<u64, Rejection> {
+    if matches!(state.status, KeyStatus::Blocked | KeyStatus::Revoked) {
+        return Err(Rejection::KeyBlocked);
+    }
+
+    let burst_counter = state
+        .burst_counter
+        .checked_add(amount)
+        .ok_or(Rejection::MathOverflow)?;
+
+    if burst_counter.saturating_add(state.reserved_units) > allowance {
+        return Err(Rejection::BurstLimitExceeded);
+    }
+
+    Ok(burst_counter)
+}
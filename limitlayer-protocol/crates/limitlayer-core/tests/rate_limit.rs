@@ -0,0 +1,280 @@
+use limitlayer_core::{
+    apply_usage, apply_usage_direct, burst_allowance,
+    calendar::{month_start, next_month_start},
+    evaluate, reserve, settle, Charge, KeyStatus, Policy, QuotaExhaustion, QuotaPeriod, QuotaState,
+    Rejection, UsageState,
+};
+
+/// 2026-01-05T00:00:00Z
+const MONDAY: i64 = 1_767_571_200;
+
+fn policy() -> Policy {
+    Policy {
+        requests_per_window: 100,
+        window_seconds: 60,
+        burst_limit: 10,
+        cost_per_request: 2,
+        daily_quota: 0,
+        monthly_quota: 0,
+    }
+}
+
+fn delegated() -> UsageState {
+    UsageState {
+        shards: 1,
+        delegated: true,
+        ..UsageState::default()
+    }
+}
+
+#[test]
+fn charges_window_cost_and_burst() {
+    let state = UsageState {
+        window_usage: 40,
+        window_cost: 80,
+        burst_counter: 3,
+        ..delegated()
+    };
+    assert_eq!(
+        apply_usage(&state, &policy(), MONDAY, 5),
+        Ok(Charge {
+            window_usage: 45,
+            window_cost: 90,
+            burst_counter: 8,
+        })
+    );
+}
+
+#[test]
+fn splits_the_burst_limit_between_shards() {
+    // 10 = 4 + 3 + 3: the primary takes the remainder
+    let state = UsageState {
+        shards: 3,
+        ..delegated()
+    };
+    assert!(apply_usage(&state, &policy(), MONDAY, 4).is_ok());
+    assert_eq!(
+        apply_usage(&state, &policy(), MONDAY, 5),
+        Err(Rejection::BurstLimitExceeded)
+    );
+    let last = UsageState { shard: 2, ..state };
+    assert!(apply_usage(&last, &policy(), MONDAY, 3).is_ok());
+    assert_eq!(
+        apply_usage(&last, &policy(), MONDAY, 4),
+        Err(Rejection::BurstLimitExceeded)
+    );
+
+    // Direct recording only happens on the primary account
+    let direct = UsageState {
+        delegated: false,
+        ..state
+    };
+    assert!(apply_usage_direct(&direct, &policy(), 10).is_ok());
+    assert_eq!(
+        apply_usage_direct(&state, &policy(), 1),
+        Err(Rejection::AlreadyDelegated)
+    );
+}
+
+#[test]
+fn burst_shares_add_up_to_the_limit() {
+    for burst_limit in [0, 1, 7, 10, 15, 16, 100, u64::MAX] {
+        let p = Policy {
+            burst_limit,
+            ..policy()
+        };
+        for shards in 1..=16u8 {
+            let shares: Vec<u64> = (0..shards)
+                .map(|shard| burst_allowance(&p, shard, shards))
+                .collect();
+            assert_eq!(
+                shares.iter().map(|&share| share as u128).sum::<u128>(),
+                burst_limit as u128
+            );
+            assert!(shares.iter().max().unwrap() - shares.iter().min().unwrap() <= 1);
+        }
+    }
+}
+
+#[test]
+fn counts_reservations_against_the_burst_allowance() {
+    let state = UsageState {
+        burst_counter: 4,
+        reserved_units: 5,
+        ..delegated()
+    };
+    assert!(apply_usage(&state, &policy(), MONDAY, 1).is_ok());
+    assert_eq!(
+        apply_usage(&state, &policy(), MONDAY, 2),
+        Err(Rejection::BurstLimitExceeded)
+    );
+    assert_eq!(
+        reserve(&state, &policy(), MONDAY, 2),
+        Err(Rejection::BurstLimitExceeded)
+    );
+    assert_eq!(reserve(&state, &policy(), MONDAY, 1), Ok(()));
+}
+
+#[test]
+fn settles_reserved_units_after_a_block_or_expired_lease() {
+    // The key was blocked and its lease ran out after 5 units were reserved
+    let state = UsageState {
+        status: KeyStatus::Blocked,
+        lease_expires_at: MONDAY,
+        burst_counter: 4,
+        ..delegated()
+    };
+    assert_eq!(
+        settle(&state, &policy(), MONDAY, 5, 5),
+        Ok(Charge {
+            window_usage: 5,
+            window_cost: 10,
+            burst_counter: 9,
+        })
+    );
+    assert_eq!(
+        settle(&state, &policy(), MONDAY, 5, 3)
+            .unwrap()
+            .burst_counter,
+        7
+    );
+    // An excess is checked like any other usage
+    assert_eq!(
+        settle(&state, &policy(), MONDAY, 5, 6),
+        Err(Rejection::LeaseExpired)
+    );
+    let active = UsageState {
+        burst_counter: 4,
+        ..delegated()
+    };
+    assert!(settle(&active, &policy(), MONDAY, 5, 6).is_ok());
+    assert_eq!(
+        settle(&active, &policy(), MONDAY, 5, 7),
+        Err(Rejection::BurstLimitExceeded)
+    );
+}
+
+#[test]
+fn rejects_in_program_order() {
+    let p = policy();
+    assert_eq!(
+        apply_usage(&UsageState::default(), &p, MONDAY, 1),
+        Err(Rejection::NotDelegated)
+    );
+
+    let leased = UsageState {
+        lease_expires_at: MONDAY,
+        ..delegated()
+    };
+    assert!(apply_usage(&leased, &p, MONDAY - 1, 1).is_ok());
+    assert_eq!(
+        apply_usage(&leased, &p, MONDAY, 1),
+        Err(Rejection::LeaseExpired)
+    );
+
+    // Status is checked before the burst allowance, overflow before both
+    let blocked = UsageState {
+        status: KeyStatus::Blocked,
+        ..delegated()
+    };
+    assert_eq!(
+        apply_usage(&blocked, &p, MONDAY, 50),
+        Err(Rejection::KeyBlocked)
+    );
+    let full = UsageState {
+        window_usage: u64::MAX,
+        ..blocked
+    };
+    assert_eq!(
+        apply_usage(&full, &p, MONDAY, 1),
+        Err(Rejection::MathOverflow)
+    );
+
+    let throttled = UsageState {
+        status: KeyStatus::Throttled,
+        ..delegated()
+    };
+    assert!(apply_usage(&throttled, &p, MONDAY, 1).is_ok());
+}
+
+#[test]
+fn evaluates_enforcement_thresholds() {
+    let p = policy();
+    assert_eq!(evaluate(KeyStatus::Active, 49, &p), KeyStatus::Active);
+    assert_eq!(evaluate(KeyStatus::Active, 50, &p), KeyStatus::Throttled);
+    assert_eq!(evaluate(KeyStatus::Throttled, 100, &p), KeyStatus::Blocked);
+    assert_eq!(evaluate(KeyStatus::Throttled, 10, &p), KeyStatus::Active);
+    // Low usage does not lift a block
+    assert_eq!(evaluate(KeyStatus::Blocked, 10, &p), KeyStatus::Blocked);
+}
+
+#[test]
+fn blocks_on_exhausted_quota_until_reset() {
+    let p = Policy {
+        daily_quota: 10,
+        monthly_quota: 100,
+        ..policy()
+    };
+    let mut quota = QuotaState {
+        status: KeyStatus::Active,
+        day_start_ts: MONDAY,
+        daily_usage: 6,
+        month_start_ts: month_start(MONDAY),
+        monthly_usage: 6,
+        blocked_until: 0,
+    };
+
+    let noon = MONDAY + 43_200;
+    assert_eq!(quota.charge(&p, 3, noon), None);
+    assert_eq!(
+        quota.charge(&p, 1, noon),
+        Some(QuotaExhaustion {
+            period: QuotaPeriod::Daily,
+            usage: 10,
+            quota: 10,
+            resets_at: MONDAY + 86_400,
+        })
+    );
+    assert_eq!(
+        (quota.status, quota.blocked_until),
+        (KeyStatus::Blocked, MONDAY + 86_400)
+    );
+
+    // The next day's checkpoint lifts the block and starts a new period
+    assert_eq!(quota.charge(&p, 2, MONDAY + 86_400), None);
+    assert_eq!(
+        (quota.status, quota.daily_usage, quota.monthly_usage),
+        (KeyStatus::Active, 2, 12)
+    );
+}
+
+#[test]
+fn leaves_manual_blocks_alone() {
+    let p = Policy {
+        daily_quota: 1,
+        ..policy()
+    };
+    let mut quota = QuotaState {
+        status: KeyStatus::Blocked,
+        day_start_ts: MONDAY,
+        daily_usage: 0,
+        month_start_ts: month_start(MONDAY),
+        monthly_usage: 0,
+        blocked_until: 0,
+    };
+    assert_eq!(quota.charge(&p, 5, MONDAY), None);
+    assert_eq!(quota.blocked_until, 0);
+
+    quota.roll(MONDAY + 86_400);
+    assert_eq!((quota.status, quota.daily_usage), (KeyStatus::Blocked, 0));
+}
+
+#[test]
+fn follows_calendar_months() {
+    // 2024-02-29T12:00:00Z, in a leap year
+    let leap_day = 1_709_208_000;
+    assert_eq!(month_start(leap_day), 1_706_745_600);
+    assert_eq!(next_month_start(leap_day), 1_709_251_200);
+    // 2025-12-31T23:59:59Z rolls into the next year
+    assert_eq!(next_month_start(1_767_225_599), 1_767_225_600);
+}
@@ -0,0 +1,21 @@
+//! Account polling over the shared [`RpcClient`]. Transactions are fetched
+//! with the indexer's [`RpcSource`](limitlayer_indexer::RpcSource).
+
+use anchor_lang::prelude::Pubkey;
+use limitlayer_client::{
+    program::{ApiKeyAccount, DelegatedUsageAccount},
+    rpc::RpcClient,
+    Result, ID,
+};
+
+pub fn api_keys(rpc: &RpcClient) -> Result<Vec<(Pubkey, ApiKeyAccount)>> {
+    rpc.program_accounts(&ID)
+}
+
+/// Usage accounts on the base layer. Delegated ones are owned by the
+/// delegation program there, with the data they had when last committed.
+pub fn usage_accounts(rpc: &RpcClient) -> Result<Vec<(Pubkey, DelegatedUsageAccount)>> {
+    let mut usage = rpc.program_accounts(&ID)?;
+    usage.extend(rpc.program_accounts(&ephemeral_rollups_sdk::id())?);
+    Ok(usage)
+}
@@ -0,0 +1,14 @@
+//! Prometheus exporter for the LimitLayer protocol.
+//!
+//! Program events, fetched with the indexer's RPC source from the base
+//! layer and the ephemeral rollup, drive counters for usage, enforcement,
+//! checkpoints and abuse signals. API key and usage accounts polled from the
+//! base layer drive gauges: keys by status, delegation age and checkpoint
+//! lag ([`metrics`]). [`serve`] exposes them on `/metrics`.
+
+pub mod accounts;
+pub mod metrics;
+mod server;
+
+pub use metrics::{Exporter, Layer};
+pub use server::serve;
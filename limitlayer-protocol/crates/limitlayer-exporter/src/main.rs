@@ -0,0 +1,117 @@
+//! `limitlayer-exporter`: follows the program on the base layer and,
+//! optionally, an ephemeral rollup, and serves Prometheus metrics.
+
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use clap::Parser;
+use limitlayer_client::{rpc::RpcClient, ID};
+use limitlayer_exporter::{accounts, serve, Exporter, Layer};
+use limitlayer_indexer::RpcSource;
+
+#[derive(Parser)]
+#[command(
+    name = "limitlayer-exporter",
+    version,
+    about = "Serve Prometheus metrics for the LimitLayer protocol"
+)]
+struct Cli {
+    /// Base layer RPC URL; accounts are polled here
+    #[arg(
+        long,
+        short = 'u',
+        env = "LIMITLAYER_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+    /// Ephemeral rollup RPC URL, where realtime usage and checkpoint
+    /// submissions happen
+    #[arg(long, env = "LIMITLAYER_EPHEMERAL_RPC_URL")]
+    ephemeral_url: Option<String>,
+    /// Address to serve /metrics on
+    #[arg(
+        long,
+        env = "LIMITLAYER_EXPORTER_LISTEN",
+        default_value = "0.0.0.0:9464"
+    )]
+    listen: String,
+    /// Seconds between polls
+    #[arg(long, default_value_t = 15)]
+    poll_interval: u64,
+    /// Count the program's whole history instead of starting at its latest
+    /// transaction
+    #[arg(long)]
+    replay_history: bool,
+}
+
+/// One layer's transactions, and the last one observed.
+struct Follower {
+    layer: Layer,
+    source: RpcSource,
+    cursor: Option<String>,
+}
+
+impl Follower {
+    fn new(layer: Layer, url: String, replay_history: bool) -> Result<Self> {
+        let cursor = match replay_history {
+            true => None,
+            false => RpcClient::new(url.clone())
+                .signatures_for_address(&ID, None, None, 1)?
+                .first()
+                .map(|entry| entry.signature.to_string()),
+        };
+        Ok(Self {
+            layer,
+            source: RpcSource::new(url),
+            cursor,
+        })
+    }
+
+    fn poll(&mut self, exporter: &mut Exporter) -> Result<()> {
+        let transactions = self.source.fetch_after(self.cursor.as_deref())?;
+        exporter.observe(self.layer, &transactions);
+        if let Some(last) = transactions.last() {
+            self.cursor = Some(last.signature.clone());
+        }
+        Ok(())
+    }
+}
+
+fn poll_accounts(rpc: &RpcClient, exporter: &mut Exporter) -> Result<()> {
+    let api_keys = accounts::api_keys(rpc)?;
+    let usage = accounts::usage_accounts(rpc)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    exporter.update_accounts(now, &api_keys, &usage);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut exporter = Exporter::new();
+    let address = serve(&cli.listen, exporter.registry().clone())?;
+    eprintln!("serving metrics on http://{address}/metrics");
+
+    let rpc = RpcClient::new(cli.url.clone());
+    let mut followers = vec![Follower::new(Layer::Base, cli.url, cli.replay_history)?];
+    if let Some(url) = cli.ephemeral_url {
+        followers.push(Follower::new(Layer::Ephemeral, url, cli.replay_history)?);
+    }
+
+    loop {
+        // Accounts first, so keys created since the last poll have a
+        // service to count their events under
+        if let Err(error) = poll_accounts(&rpc, &mut exporter) {
+            eprintln!("account poll failed: {error:#}");
+        }
+        // Transient RPC errors are retried on the next poll
+        for follower in &mut followers {
+            if let Err(error) = follower.poll(&mut exporter) {
+                eprintln!("transaction poll failed: {error:#}");
+            }
+        }
+        thread::sleep(Duration::from_secs(cli.poll_interval));
+    }
+}
@@ -0,0 +1,344 @@
+//! Metric definitions and how events and accounts update them.
+//!
+//! Counters come from events and only move forward; gauges are replaced by
+//! each account snapshot. Everything is labeled by service, looked up from
+//! `ApiKeyCreated` events and polled API key accounts for events that only
+//! name the key; keys not seen yet are labeled `unknown`.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use limitlayer_client::{
+    events::{parse_logs, LimitLayerEvent},
+    program::{enums::ApiKeyStatus, ApiKeyAccount, DelegatedUsageAccount},
+};
+use limitlayer_indexer::RecordedTransaction;
+use prometheus::{IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+
+const UNKNOWN_SERVICE: &str = "unknown";
+
+/// `ApiKeyStatus` as events carry it
+const ACTIVE: u8 = ApiKeyStatus::Active as u8;
+const BLOCKED: u8 = ApiKeyStatus::Blocked as u8;
+const REVOKED: u8 = ApiKeyStatus::Revoked as u8;
+
+fn status_label(status: u8) -> &'static str {
+    match status {
+        0 => "active",
+        1 => "throttled",
+        2 => "blocked",
+        _ => "revoked",
+    }
+}
+
+/// Which layer a transaction ran on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Base,
+    Ephemeral,
+}
+
+impl Layer {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Base => "base",
+            Self::Ephemeral => "ephemeral",
+        }
+    }
+}
+
+fn counter(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+    registry.register(Box::new(counter.clone())).unwrap();
+    counter
+}
+
+fn gauge(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntGaugeVec {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), labels).unwrap();
+    registry.register(Box::new(gauge.clone())).unwrap();
+    gauge
+}
+
+pub struct Exporter {
+    registry: Registry,
+    usage_units: IntCounterVec,
+    usage_requests: IntCounterVec,
+    enforcement_evaluations: IntCounterVec,
+    keys_blocked: IntCounterVec,
+    checkpoints_submitted: IntCounterVec,
+    checkpointed_units: IntCounterVec,
+    checkpointed_cost: IntCounterVec,
+    abuse_signals: IntCounterVec,
+    abuse_severity: IntCounterVec,
+    undecodable: IntCounterVec,
+    last_slot: IntGaugeVec,
+    api_keys: IntGaugeVec,
+    delegations: IntGaugeVec,
+    delegation_age: IntGaugeVec,
+    checkpoint_lag: IntGaugeVec,
+    services: HashMap<Pubkey, Pubkey>,
+    /// Status of each key as of the last event that set it
+    statuses: HashMap<Pubkey, u8>,
+}
+
+impl Default for Exporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Exporter {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        Self {
+            usage_units: counter(
+                &registry,
+                "limitlayer_usage_recorded_units_total",
+                "Units recorded on the ephemeral rollup",
+                &["service"],
+            ),
+            usage_requests: counter(
+                &registry,
+                "limitlayer_usage_recorded_requests_total",
+                "Usage recordings on the ephemeral rollup",
+                &["service"],
+            ),
+            enforcement_evaluations: counter(
+                &registry,
+                "limitlayer_enforcement_evaluations_total",
+                "Enforcement evaluations, by the status they left the key in",
+                &["service", "status"],
+            ),
+            keys_blocked: counter(
+                &registry,
+                "limitlayer_keys_blocked_total",
+                "Keys moving into the blocked status",
+                &["service", "reason"],
+            ),
+            checkpoints_submitted: counter(
+                &registry,
+                "limitlayer_checkpoints_submitted_total",
+                "Usage checkpoints submitted",
+                &["service"],
+            ),
+            checkpointed_units: counter(
+                &registry,
+                "limitlayer_checkpointed_units_total",
+                "Units closed by submitted checkpoints",
+                &["service"],
+            ),
+            checkpointed_cost: counter(
+                &registry,
+                "limitlayer_checkpointed_cost_total",
+                "Cost closed by submitted checkpoints",
+                &["service"],
+            ),
+            abuse_signals: counter(
+                &registry,
+                "limitlayer_abuse_signals_total",
+                "Abuse signals emitted",
+                &["category"],
+            ),
+            abuse_severity: counter(
+                &registry,
+                "limitlayer_abuse_signal_severity_total",
+                "Summed severity of abuse signals emitted",
+                &["category"],
+            ),
+            undecodable: counter(
+                &registry,
+                "limitlayer_exporter_undecodable_transactions_total",
+                "Transactions whose events could not be decoded",
+                &["layer"],
+            ),
+            last_slot: gauge(
+                &registry,
+                "limitlayer_exporter_last_slot",
+                "Slot of the last transaction observed",
+                &["layer"],
+            ),
+            api_keys: gauge(
+                &registry,
+                "limitlayer_api_keys",
+                "API keys, by status",
+                &["service", "status"],
+            ),
+            delegations: gauge(
+                &registry,
+                "limitlayer_delegated_usage_accounts",
+                "Usage accounts delegated to an ephemeral rollup",
+                &["service"],
+            ),
+            delegation_age: gauge(
+                &registry,
+                "limitlayer_delegation_age_max_seconds",
+                "Age of the longest-standing delegation",
+                &["service"],
+            ),
+            checkpoint_lag: gauge(
+                &registry,
+                "limitlayer_checkpoint_lag_max_seconds",
+                "Time since the least recent checkpoint of a delegated key, as committed to the base layer",
+                &["service"],
+            ),
+            registry,
+            services: HashMap::new(),
+            statuses: HashMap::new(),
+        }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn render(&self) -> Result<String> {
+        Ok(TextEncoder::new().encode_to_string(&self.registry.gather())?)
+    }
+
+    fn service(&self, api_key: &Pubkey) -> String {
+        self.services
+            .get(api_key)
+            .map_or_else(|| UNKNOWN_SERVICE.to_string(), Pubkey::to_string)
+    }
+
+    /// Counts the events of `transactions`, which ran on `layer` and follow
+    /// the ones observed before. Failed transactions emitted nothing.
+    pub fn observe(&mut self, layer: Layer, transactions: &[RecordedTransaction]) {
+        for transaction in transactions {
+            self.last_slot
+                .with_label_values(&[layer.label()])
+                .set(transaction.slot as i64);
+            if transaction.failed {
+                continue;
+            }
+            match parse_logs(&transaction.logs) {
+                Ok(events) => events.iter().for_each(|event| self.observe_event(event)),
+                Err(_) => self.undecodable.with_label_values(&[layer.label()]).inc(),
+            }
+        }
+    }
+
+    fn observe_event(&mut self, event: &LimitLayerEvent) {
+        match event {
+            LimitLayerEvent::ApiKeyCreated(e) => {
+                self.services.insert(e.api_key, e.service);
+            }
+            LimitLayerEvent::UsageRecordedRealtime(e) => {
+                let service = self.service(&e.api_key);
+                self.usage_units
+                    .with_label_values(&[&service])
+                    .inc_by(e.amount);
+                self.usage_requests.with_label_values(&[&service]).inc();
+            }
+            LimitLayerEvent::UsageCheckpointSubmitted(e) => {
+                self.services.insert(e.api_key, e.service);
+                let service = e.service.to_string();
+                self.checkpoints_submitted
+                    .with_label_values(&[&service])
+                    .inc();
+                self.checkpointed_units
+                    .with_label_values(&[&service])
+                    .inc_by(e.window_usage);
+                self.checkpointed_cost
+                    .with_label_values(&[&service])
+                    .inc_by(e.window_cost);
+            }
+            LimitLayerEvent::EnforcementEvaluated(e) => {
+                let service = self.service(&e.api_key);
+                self.enforcement_evaluations
+                    .with_label_values(&[&service, status_label(e.new_status)])
+                    .inc();
+                self.set_status(e.api_key, e.new_status, "enforcement");
+            }
+            LimitLayerEvent::QuotaExhausted(e) => self.set_status(e.api_key, BLOCKED, "quota"),
+            LimitLayerEvent::KeyManuallyBlocked(e) => self.set_status(e.api_key, BLOCKED, "manual"),
+            LimitLayerEvent::KeyManuallyUnblocked(e) => {
+                self.set_status(e.api_key, ACTIVE, "manual")
+            }
+            LimitLayerEvent::ApiKeyStatusChanged(e) => {
+                self.set_status(e.api_key, e.new_status, "status_update")
+            }
+            LimitLayerEvent::ApiKeyRevoked(e) => self.set_status(e.api_key, REVOKED, "revoked"),
+            LimitLayerEvent::AbuseSignalEmitted(e) => {
+                let category = e.category.to_string();
+                self.abuse_signals.with_label_values(&[&category]).inc();
+                self.abuse_severity
+                    .with_label_values(&[&category])
+                    .inc_by(e.severity.into());
+            }
+            _ => {}
+        }
+    }
+
+    /// Records a key's new status, counting it as blocked when it was not
+    /// before. Keys whose status no observed event has set yet count as
+    /// not blocked.
+    fn set_status(&mut self, api_key: Pubkey, status: u8, reason: &str) {
+        let previous = self.statuses.insert(api_key, status);
+        if status == BLOCKED && previous != Some(BLOCKED) {
+            let service = self.service(&api_key);
+            self.keys_blocked
+                .with_label_values(&[&service, reason])
+                .inc();
+        }
+    }
+
+    /// Replaces the account gauges with a snapshot of the base layer taken
+    /// at unix time `now`.
+    pub fn update_accounts(
+        &mut self,
+        now: i64,
+        api_keys: &[(Pubkey, ApiKeyAccount)],
+        usage: &[(Pubkey, DelegatedUsageAccount)],
+    ) {
+        let mut keys: HashMap<(String, &str), i64> = HashMap::new();
+        for (address, key) in api_keys {
+            self.services.insert(*address, key.service);
+            let status = status_label(key.status as u8);
+            *keys.entry((key.service.to_string(), status)).or_default() += 1;
+        }
+
+        #[derive(Default)]
+        struct Delegations {
+            count: i64,
+            max_age: i64,
+            max_lag: i64,
+        }
+        let mut delegations: HashMap<String, Delegations> = HashMap::new();
+        for (_, account) in usage.iter().filter(|(_, account)| account.delegated) {
+            let entry = delegations
+                .entry(self.service(&account.api_key))
+                .or_default();
+            entry.count += 1;
+            entry.max_age = entry.max_age.max(now - account.delegated_at);
+            // Windows are only reopened by checkpoints, or by delegating;
+            // shards fold into the primary account and do not checkpoint
+            if account.shard_index == 0 {
+                entry.max_lag = entry.max_lag.max(now - account.window_start_ts);
+            }
+        }
+
+        self.api_keys.reset();
+        for ((service, status), count) in keys {
+            self.api_keys
+                .with_label_values(&[&service, status])
+                .set(count);
+        }
+        self.delegations.reset();
+        self.delegation_age.reset();
+        self.checkpoint_lag.reset();
+        for (service, entry) in delegations {
+            self.delegations
+                .with_label_values(&[&service])
+                .set(entry.count);
+            self.delegation_age
+                .with_label_values(&[&service])
+                .set(entry.max_age);
+            self.checkpoint_lag
+                .with_label_values(&[&service])
+                .set(entry.max_lag);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! The `/metrics` endpoint.
+
+use std::{net::SocketAddr, thread};
+
+use anyhow::{anyhow, Result};
+use prometheus::{Registry, TextEncoder};
+use tiny_http::{Header, Response, Server};
+
+/// Serves `registry` on `/metrics` at `address` from a background thread.
+/// Returns the bound address, which differs from `address` for port 0.
+pub fn serve(address: &str, registry: Registry) -> Result<SocketAddr> {
+    let server =
+        Server::http(address).map_err(|error| anyhow!("failed to listen on {address}: {error}"))?;
+    let bound = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| anyhow!("{address} is not an IP address"))?;
+    let content_type = Header::from_bytes("Content-Type", prometheus::TEXT_FORMAT).unwrap();
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = match request.url() {
+                "/metrics" => match TextEncoder::new().encode_to_string(&registry.gather()) {
+                    Ok(body) => Response::from_string(body).with_header(content_type.clone()),
+                    Err(error) => Response::from_string(error.to_string()).with_status_code(500),
+                },
+                _ => Response::from_string("not found").with_status_code(404),
+            };
+            // The scraper hanging up is not the exporter's problem
+            let _ = request.respond(response);
+        }
+    });
+    Ok(bound)
+}
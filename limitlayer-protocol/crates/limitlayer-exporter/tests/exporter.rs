@@ -0,0 +1,422 @@
+//! Feeds the exporter recorded transactions and account snapshots, and
+//! scrapes what it serves.
+
+use std::thread;
+
+use anchor_lang::{
+    prelude::Pubkey, AccountSerialize, AnchorDeserialize, Discriminator, Event, Space,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use limitlayer_client::{
+    program::{events::*, ApiKeyAccount, ApiKeyStatus, DelegatedUsageAccount},
+    rpc::RpcClient,
+    ID,
+};
+use limitlayer_exporter::{accounts, serve, Exporter, Layer};
+use limitlayer_indexer::RecordedTransaction;
+use serde_json::{json, Value};
+use tiny_http::{Response, Server};
+
+const NOW: i64 = 1_767_607_200;
+
+fn transaction(slot: u64, events: &[&dyn Emitted]) -> RecordedTransaction {
+    let mut logs = vec![format!("Program {ID} invoke [1]")];
+    logs.extend(
+        events
+            .iter()
+            .map(|event| format!("Program data: {}", STANDARD.encode(event.bytes()))),
+    );
+    logs.push(format!("Program {ID} success"));
+    RecordedTransaction {
+        slot,
+        signature: format!("signature-{slot}"),
+        block_time: Some(NOW),
+        failed: false,
+        logs,
+    }
+}
+
+/// Object-safe `Event::data`, so one transaction can emit several types.
+trait Emitted {
+    fn bytes(&self) -> Vec<u8>;
+}
+
+impl<T: Event> Emitted for T {
+    fn bytes(&self) -> Vec<u8> {
+        self.data()
+    }
+}
+
+/// A zeroed account of type `T`, with `edit` applied.
+fn account<T: AnchorDeserialize + Space>(edit: impl FnOnce(&mut T)) -> T {
+    let mut account = T::deserialize(&mut vec![0; T::INIT_SPACE].as_slice()).unwrap();
+    edit(&mut account);
+    account
+}
+
+/// Value of the sample of `metric` with exactly `labels`, if any.
+fn sample(text: &str, metric: &str, labels: &[(&str, &str)]) -> Option<i64> {
+    let labels = labels
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{value}\""))
+        .collect::<Vec<_>>()
+        .join(",");
+    let prefix = format!("{metric}{{{labels}}} ");
+    text.lines()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .map(|value| value.parse().unwrap())
+}
+
+#[test]
+fn counts_events_by_service() {
+    let service = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+    let usage = Pubkey::new_unique();
+    let label = service.to_string();
+
+    let mut exporter = Exporter::new();
+    exporter.observe(
+        Layer::Base,
+        &[transaction(
+            1,
+            &[&ApiKeyCreated {
+                api_key: key,
+                service,
+                owner: Pubkey::new_unique(),
+                policy: Pubkey::new_unique(),
+            }],
+        )],
+    );
+    let recorded = |amount| UsageRecordedRealtime {
+        delegated_usage: usage,
+        api_key: key,
+        amount,
+        window_usage: 0,
+    };
+    let mut failed = transaction(12, &[&recorded(100)]);
+    failed.failed = true;
+    exporter.observe(
+        Layer::Ephemeral,
+        &[
+            transaction(10, &[&recorded(3)]),
+            transaction(11, &[&recorded(4)]),
+            failed,
+            transaction(
+                13,
+                &[&UsageCheckpointSubmitted {
+                    delegated_usage: usage,
+                    api_key: key,
+                    service,
+                    checkpoint_seq: 1,
+                    window_usage: 7,
+                    window_cost: 14,
+                    request_log_root: [0; 32],
+                }],
+            ),
+        ],
+    );
+
+    let evaluated = |new_status| EnforcementEvaluated {
+        api_key: key,
+        new_status,
+        usage: 7,
+    };
+    exporter.observe(
+        Layer::Base,
+        &[
+            transaction(2, &[&evaluated(1)]),
+            transaction(3, &[&evaluated(2)]),
+            // Still blocked: evaluated again, but not blocked again
+            transaction(4, &[&evaluated(2)]),
+            transaction(
+                5,
+                &[&KeyManuallyUnblocked {
+                    api_key: key,
+                    service,
+                }],
+            ),
+            transaction(
+                6,
+                &[&KeyManuallyBlocked {
+                    api_key: key,
+                    service,
+                }],
+            ),
+            transaction(
+                7,
+                &[
+                    &AbuseSignalEmitted {
+                        abuse_signal: Pubkey::new_unique(),
+                        reporter_service: service,
+                        subject: key,
+                        severity: 7,
+                        category: 4,
+                    },
+                    &AbuseSignalEmitted {
+                        abuse_signal: Pubkey::new_unique(),
+                        reporter_service: service,
+                        subject: key,
+                        severity: 2,
+                        category: 4,
+                    },
+                ],
+            ),
+            // Emitted before the exporter saw the key's service
+            transaction(
+                8,
+                &[&EnforcementEvaluated {
+                    api_key: Pubkey::new_unique(),
+                    new_status: 2,
+                    usage: 1,
+                }],
+            ),
+        ],
+    );
+
+    let text = exporter.render().unwrap();
+    let service_only = [("service", label.as_str())];
+    for (metric, value) in [
+        ("limitlayer_usage_recorded_units_total", 7),
+        ("limitlayer_usage_recorded_requests_total", 2),
+        ("limitlayer_checkpoints_submitted_total", 1),
+        ("limitlayer_checkpointed_units_total", 7),
+        ("limitlayer_checkpointed_cost_total", 14),
+    ] {
+        assert_eq!(
+            sample(&text, metric, &service_only),
+            Some(value),
+            "{metric}"
+        );
+    }
+
+    let evaluations = |status| {
+        sample(
+            &text,
+            "limitlayer_enforcement_evaluations_total",
+            &[("service", &label), ("status", status)],
+        )
+    };
+    assert_eq!(
+        (evaluations("throttled"), evaluations("blocked")),
+        (Some(1), Some(2))
+    );
+    let blocked = |service: &str, reason| {
+        sample(
+            &text,
+            "limitlayer_keys_blocked_total",
+            &[("reason", reason), ("service", service)],
+        )
+    };
+    assert_eq!(blocked(&label, "enforcement"), Some(1));
+    assert_eq!(blocked(&label, "manual"), Some(1));
+    assert_eq!(blocked("unknown", "enforcement"), Some(1));
+
+    let category = [("category", "4")];
+    assert_eq!(
+        sample(&text, "limitlayer_abuse_signals_total", &category),
+        Some(2)
+    );
+    assert_eq!(
+        sample(&text, "limitlayer_abuse_signal_severity_total", &category),
+        Some(9)
+    );
+    assert_eq!(
+        sample(
+            &text,
+            "limitlayer_exporter_last_slot",
+            &[("layer", "ephemeral")]
+        ),
+        Some(13)
+    );
+}
+
+#[test]
+fn snapshots_keys_and_delegations() {
+    let service = Pubkey::new_unique();
+    let label = service.to_string();
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let api_key = |status| {
+        account(|key: &mut ApiKeyAccount| {
+            key.service = service;
+            key.status = status;
+        })
+    };
+    let usage = |key, shard_index, delegated_at, window_start_ts| {
+        account(|usage: &mut DelegatedUsageAccount| {
+            usage.api_key = key;
+            usage.shard_index = shard_index;
+            usage.delegated = true;
+            usage.delegated_at = delegated_at;
+            usage.window_start_ts = window_start_ts;
+        })
+    };
+
+    let mut exporter = Exporter::new();
+    exporter.update_accounts(
+        NOW,
+        &[
+            (keys[0], api_key(ApiKeyStatus::Active)),
+            (keys[1], api_key(ApiKeyStatus::Active)),
+            (keys[2], api_key(ApiKeyStatus::Blocked)),
+        ],
+        &[
+            (
+                Pubkey::new_unique(),
+                usage(keys[0], 0, NOW - 3_600, NOW - 30),
+            ),
+            (Pubkey::new_unique(), usage(keys[1], 0, NOW - 600, NOW - 90)),
+            // Shards do not checkpoint; their window start says nothing
+            (
+                Pubkey::new_unique(),
+                usage(keys[1], 1, NOW - 600, NOW - 7_200),
+            ),
+            (
+                Pubkey::new_unique(),
+                account(|usage: &mut DelegatedUsageAccount| usage.api_key = keys[2]),
+            ),
+        ],
+    );
+
+    let text = exporter.render().unwrap();
+    let keys_by = |status| {
+        sample(
+            &text,
+            "limitlayer_api_keys",
+            &[("service", &label), ("status", status)],
+        )
+    };
+    assert_eq!((keys_by("active"), keys_by("blocked")), (Some(2), Some(1)));
+    let service_only = [("service", label.as_str())];
+    assert_eq!(
+        sample(&text, "limitlayer_delegated_usage_accounts", &service_only),
+        Some(3)
+    );
+    assert_eq!(
+        sample(
+            &text,
+            "limitlayer_delegation_age_max_seconds",
+            &service_only
+        ),
+        Some(3_600)
+    );
+    assert_eq!(
+        sample(
+            &text,
+            "limitlayer_checkpoint_lag_max_seconds",
+            &service_only
+        ),
+        Some(90)
+    );
+
+    // Gauges describe the latest snapshot only
+    exporter.update_accounts(NOW, &[(keys[2], api_key(ApiKeyStatus::Revoked))], &[]);
+    let text = exporter.render().unwrap();
+    assert_eq!(
+        sample(
+            &text,
+            "limitlayer_api_keys",
+            &[("service", &label), ("status", "active")]
+        ),
+        None
+    );
+    assert!(!text.contains("limitlayer_delegated_usage_accounts{"));
+}
+
+/// Serves JSON-RPC requests with `respond(method, params) -> result`.
+fn rpc(respond: impl Fn(&str, &Value) -> Value + Send + 'static) -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let request_json: Value = serde_json::from_str(&body).unwrap();
+            let result = respond(
+                request_json["method"].as_str().unwrap(),
+                &request_json["params"],
+            );
+            let response = json!({ "jsonrpc": "2.0", "id": 1, "result": result });
+            request
+                .respond(Response::from_string(response.to_string()))
+                .unwrap();
+        }
+    });
+    url
+}
+
+fn program_account<T: AccountSerialize>(address: Pubkey, account: &T) -> Value {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    json!({ "pubkey": address.to_string(), "account": { "data": [STANDARD.encode(data), "base64"] } })
+}
+
+#[test]
+fn polls_accounts_and_serves_metrics() {
+    let service = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+    let (undelegated, delegated) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let url = rpc(move |method, params| {
+        assert_eq!(method, "getProgramAccounts");
+        let owner: Pubkey = params[0].as_str().unwrap().parse().unwrap();
+        let discriminator = params[1]["filters"][0]["memcmp"]["bytes"].as_str().unwrap();
+        let usage = |delegated| {
+            account(|usage: &mut DelegatedUsageAccount| {
+                usage.api_key = key;
+                usage.delegated = delegated;
+                usage.delegated_at = NOW - 60;
+            })
+        };
+        let accounts = match (owner == ID, discriminator) {
+            (true, d) if d == STANDARD.encode(ApiKeyAccount::DISCRIMINATOR) => {
+                vec![program_account(
+                    key,
+                    &account(|key: &mut ApiKeyAccount| key.service = service),
+                )]
+            }
+            (true, _) => vec![program_account(undelegated, &usage(false))],
+            // Delegated accounts belong to the delegation program
+            (false, _) => vec![program_account(delegated, &usage(true))],
+        };
+        Value::Array(accounts)
+    });
+
+    let client = RpcClient::new(url);
+    let api_keys = accounts::api_keys(&client).unwrap();
+    let usage = accounts::usage_accounts(&client).unwrap();
+    assert_eq!(api_keys.len(), 1);
+    assert_eq!(
+        usage
+            .iter()
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>(),
+        [undelegated, delegated]
+    );
+
+    let mut exporter = Exporter::new();
+    let address = serve("127.0.0.1:0", exporter.registry().clone()).unwrap();
+    exporter.update_accounts(NOW, &api_keys, &usage);
+
+    let response = ureq::get(&format!("http://{address}/metrics"))
+        .call()
+        .unwrap();
+    assert!(response
+        .header("Content-Type")
+        .unwrap()
+        .starts_with("text/plain"));
+    let text = response.into_string().unwrap();
+    assert_eq!(
+        sample(
+            &text,
+            "limitlayer_delegation_age_max_seconds",
+            &[("service", &service.to_string())]
+        ),
+        Some(60)
+    );
+
+    let missing = ureq::get(&format!("http://{address}/health")).call();
+    assert!(matches!(missing, Err(ureq::Error::Status(404, _))));
+}
@@ -0,0 +1,326 @@
+//! Flushes admitted usage to the execution region.
+//!
+//! Usage is summed per usage account between flushes and sent as
+//! `record_usage_batch` transactions, one service's keys per batch, split so
+//! each fits in a packet. The payer signs them as a registered gateway. Every
+//! entry carries a request id, so a batch resent after an ambiguous failure
+//! is not counted twice: it is resent unchanged, to the same usage accounts
+//! with the same ids. A flush adds at most one id per usage account, so a
+//! batch resent at most `RECENT_REQUEST_IDS` times is still among the ids its
+//! accounts remember, as long as no one else records usage for its keys.
+//!
+//! A batch counts as sent once its transaction is confirmed; its
+//! `UsageBatchRecorded` event then gives each entry's result. Entries the
+//! program rejected, and batches still failing after `max_flush_attempts`,
+//! are appended to `failed_usage_path` for the operator to reconcile. They
+//! are not resent automatically: a batch that timed out may still have
+//! landed, and request ids are only remembered for the last few requests.
+
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use limitlayer_client::{
+    events::{parse_logs, LimitLayerEvent},
+    instructions::{self, Submitter, UsageBatchEntry},
+    program::{UsageRecordResult, MAX_USAGE_BATCH_SIZE, RECENT_REQUEST_IDS},
+};
+use serde_json::json;
+use solana_sdk::{
+    hash::Hash,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use tokio::{
+    sync::mpsc,
+    time::{self, Instant, MissedTickBehavior},
+};
+
+use crate::{config::Config, enforcer::Shared, error::Result, rpc::RpcClient};
+
+/// How often a sent batch's confirmation is polled for
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Usage admitted for a key of `service`
+pub(crate) struct Usage {
+    pub service: Pubkey,
+    pub entry: UsageBatchEntry,
+}
+
+struct Batch {
+    service: Pubkey,
+    entries: Vec<UsageBatchEntry>,
+    attempts: u32,
+}
+
+struct Batcher {
+    config: Config,
+    region: RpcClient,
+    shared: Arc<Mutex<Shared>>,
+    /// Usage summed per usage account since the last flush
+    open: HashMap<Pubkey, Usage>,
+    /// Batches whose send failed, resent unchanged
+    retries: Vec<Batch>,
+    next_request_id: u64,
+}
+
+pub(crate) async fn run(
+    config: Config,
+    shared: Arc<Mutex<Shared>>,
+    mut entries: mpsc::UnboundedReceiver<Usage>,
+) {
+    let mut interval = time::interval(config.flush_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut batcher = Batcher {
+        region: RpcClient::new(config.region_url.clone()),
+        config,
+        shared,
+        open: HashMap::new(),
+        retries: Vec::new(),
+        // Distinct from the ids of earlier runs of the gateway
+        next_request_id: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(1),
+    };
+
+    loop {
+        tokio::select! {
+            entry = entries.recv() => match entry {
+                Some(entry) => {
+                    batcher.add(entry);
+                    if batcher.open.len() >= MAX_USAGE_BATCH_SIZE as usize {
+                        batcher.flush().await;
+                    }
+                }
+                None => {
+                    batcher.flush().await;
+                    return;
+                }
+            },
+            _ = interval.tick() => batcher.flush().await,
+        }
+    }
+}
+
+impl Batcher {
+    fn add(&mut self, usage: Usage) {
+        self.open
+            .entry(usage.entry.delegated_usage)
+            .and_modify(|open| {
+                open.entry.amount = open.entry.amount.saturating_add(usage.entry.amount)
+            })
+            .or_insert(usage);
+    }
+
+    fn request_id(&mut self) -> u64 {
+        self.next_request_id = self.next_request_id.wrapping_add(1).max(1);
+        self.next_request_id
+    }
+
+    async fn flush(&mut self) {
+        let mut open: HashMap<Pubkey, Vec<UsageBatchEntry>> = HashMap::new();
+        for (_, mut usage) in std::mem::take(&mut self.open) {
+            usage.entry.request_id = Some(self.request_id());
+            open.entry(usage.service).or_default().push(usage.entry);
+        }
+        let payer = self.config.payer.pubkey();
+        for (service, entries) in open {
+            let submitter = Submitter::gateway(payer, service);
+            self.retries
+                .extend(split(submitter, entries).into_iter().map(|entries| Batch {
+                    service,
+                    entries,
+                    attempts: 0,
+                }));
+        }
+        if self.retries.is_empty() {
+            return;
+        }
+
+        // Send every batch, then wait for them together
+        let blockhash = self.region.latest_blockhash().await;
+        let mut sent = Vec::new();
+        for batch in std::mem::take(&mut self.retries) {
+            let signature = match &blockhash {
+                Ok(blockhash) => self
+                    .send(&batch, *blockhash)
+                    .await
+                    .map_err(|error| error.to_string()),
+                Err(error) => Err(error.to_string()),
+            };
+            match signature {
+                Ok(signature) => sent.push((batch, signature)),
+                Err(error) => self.failed(batch, error),
+            }
+        }
+
+        for (batch, signature) in sent {
+            match self.confirm(&signature).await {
+                Ok(results) => {
+                    self.check_results(&batch.entries, &signature, &results);
+                    self.shared.lock().unwrap().release(&batch.entries);
+                }
+                Err(error) => self.failed(batch, error),
+            }
+        }
+    }
+
+    async fn send(&self, batch: &Batch, blockhash: Hash) -> Result<Signature> {
+        let transaction =
+            batch_transaction(&self.config.payer, batch.service, &batch.entries, blockhash);
+        let signature = self.region.send_transaction(&transaction).await?;
+        tracing::debug!(%signature, entries = batch.entries.len(), "sent usage batch");
+        Ok(signature)
+    }
+
+    /// Waits for the batch to confirm and returns its per-entry results.
+    async fn confirm(&self, signature: &Signature) -> std::result::Result<Vec<u8>, String> {
+        let deadline = Instant::now() + self.config.confirm_timeout;
+        loop {
+            match self.region.transaction(signature).await {
+                Ok(Some(status)) => {
+                    if let Some(err) = status.err {
+                        return Err(format!("transaction {signature} failed: {err}"));
+                    }
+                    let events = parse_logs(&status.logs).map_err(|error| error.to_string())?;
+                    return events
+                        .into_iter()
+                        .find_map(|event| match event {
+                            LimitLayerEvent::UsageBatchRecorded(batch) => Some(batch.results),
+                            _ => None,
+                        })
+                        .ok_or_else(|| format!("transaction {signature} has no batch results"));
+                }
+                Ok(None) => {}
+                Err(error) => tracing::debug!(%error, %signature, "confirmation not read"),
+            }
+            if Instant::now() >= deadline {
+                return Err(format!("transaction {signature} not confirmed"));
+            }
+            time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Records the entries the program did not count. Duplicates were
+    /// counted by an earlier send.
+    fn check_results(&self, entries: &[UsageBatchEntry], signature: &Signature, results: &[u8]) {
+        let recorded = [
+            UsageRecordResult::Recorded as u8,
+            UsageRecordResult::Duplicate as u8,
+        ];
+        let rejected: Vec<(UsageBatchEntry, Option<u8>)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| match results.get(i) {
+                Some(result) if recorded.contains(result) => None,
+                result => Some((*entry, result.copied())),
+            })
+            .collect();
+        if !rejected.is_empty() {
+            let reason = format!("rejected in {signature}");
+            tracing::warn!(%signature, entries = rejected.len(), "usage batch entries rejected");
+            self.persist(&reason, &rejected);
+        }
+    }
+
+    /// Counts a failed attempt, giving the batch up once it has had
+    /// `max_flush_attempts`, or `RECENT_REQUEST_IDS` if that is fewer.
+    fn failed(&mut self, mut batch: Batch, error: String) {
+        batch.attempts += 1;
+        if batch.attempts < self.config.max_flush_attempts.min(RECENT_REQUEST_IDS) {
+            tracing::warn!(%error, attempts = batch.attempts, "usage batch not sent, retrying");
+            self.retries.push(batch);
+            return;
+        }
+        let units: u64 = batch.entries.iter().map(|entry| entry.amount).sum();
+        tracing::error!(%error, units, "giving up on usage batch");
+        let entries: Vec<_> = batch.entries.iter().map(|entry| (*entry, None)).collect();
+        self.persist(&error, &entries);
+        self.shared.lock().unwrap().release(&batch.entries);
+    }
+
+    /// Appends entries to the failed usage file, with the program's result
+    /// code where there is one.
+    fn persist(&self, reason: &str, entries: &[(UsageBatchEntry, Option<u8>)]) {
+        let failed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let lines: String = entries
+            .iter()
+            .map(|(entry, result)| {
+                let line = json!({
+                    "failed_at": failed_at,
+                    "reason": reason,
+                    "delegated_usage": entry.delegated_usage.to_string(),
+                    "api_key": entry.api_key.to_string(),
+                    "policy": entry.policy.to_string(),
+                    "amount": entry.amount,
+                    "request_id": entry.request_id,
+                    "result": result,
+                });
+                format!("{line}\n")
+            })
+            .collect();
+        let path = &self.config.failed_usage_path;
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+        if let Err(error) = written {
+            // Last resort: the usage is only in the log
+            tracing::error!(%error, path = %path.display(), usage = %lines, "failed usage not saved");
+        }
+    }
+}
+
+fn batch_transaction(
+    payer: &Keypair,
+    service: Pubkey,
+    entries: &[UsageBatchEntry],
+    blockhash: Hash,
+) -> Transaction {
+    let submitter = Submitter::gateway(payer.pubkey(), service);
+    Transaction::new_signed_with_payer(
+        &[instructions::record_usage_batch(submitter, entries)],
+        Some(&payer.pubkey()),
+        &[payer],
+        blockhash,
+    )
+}
+
+/// Splits entries into batches within the program's batch size whose
+/// transactions fit in a packet.
+fn split(submitter: Submitter, entries: Vec<UsageBatchEntry>) -> Vec<Vec<UsageBatchEntry>> {
+    let mut batches: Vec<Vec<UsageBatchEntry>> = Vec::new();
+    for entry in entries {
+        match batches.last_mut() {
+            Some(batch)
+                if batch.len() < MAX_USAGE_BATCH_SIZE as usize && fits(submitter, batch, entry) =>
+            {
+                batch.push(entry)
+            }
+            _ => batches.push(vec![entry]),
+        }
+    }
+    batches
+}
+
+fn fits(submitter: Submitter, batch: &[UsageBatchEntry], entry: UsageBatchEntry) -> bool {
+    let mut entries = batch.to_vec();
+    entries.push(entry);
+    let transaction = Transaction::new_with_payer(
+        &[instructions::record_usage_batch(submitter, &entries)],
+        Some(&submitter.signer),
+    );
+    bincode::serialized_size(&transaction).is_ok_and(|size| size as usize <= PACKET_DATA_SIZE)
+}
@@ -0,0 +1,62 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use http::HeaderName;
+use solana_sdk::signature::Keypair;
+
+/// What to do with a request whose key state cannot be read, e.g. when the
+/// RPC is down or the key's usage is not delegated to the execution region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureMode {
+    /// Forward the request; its usage is not recorded
+    Open,
+    /// Answer `503 Service Unavailable`
+    Closed,
+}
+
+#[derive(Clone)]
+pub struct Config {
+    /// Base layer RPC, for API key and policy accounts
+    pub base_url: String,
+    /// Execution region (ephemeral rollup) RPC, for delegated usage
+    pub region_url: String,
+    /// Signs and pays for usage batches. Must be registered with
+    /// `register_gateway` for the services whose keys it admits
+    pub payer: Arc<Keypair>,
+    /// Header carrying the API key: its index or its account address
+    pub header: HeaderName,
+    /// Units charged per admitted request
+    pub units_per_request: u64,
+    pub failure_mode: FailureMode,
+    /// How long fetched key state is trusted before it is read again
+    pub cache_ttl: Duration,
+    /// How often queued usage is flushed
+    pub flush_interval: Duration,
+    /// How long a sent batch may take to confirm before it counts as a
+    /// failed attempt
+    pub confirm_timeout: Duration,
+    /// Sends of a batch before its usage is given up on, at most
+    /// `RECENT_REQUEST_IDS`: usage accounts only remember that many ids, so
+    /// a later resend could be counted again
+    pub max_flush_attempts: u32,
+    /// JSON Lines file that usage given up on, or rejected by the program,
+    /// is appended to, one entry per line
+    pub failed_usage_path: PathBuf,
+}
+
+impl Config {
+    pub fn new(base_url: impl Into<String>, region_url: impl Into<String>, payer: Keypair) -> Self {
+        Self {
+            base_url: base_url.into(),
+            region_url: region_url.into(),
+            payer: Arc::new(payer),
+            header: HeaderName::from_static("x-api-key"),
+            units_per_request: 1,
+            failure_mode: FailureMode::Closed,
+            cache_ttl: Duration::from_secs(1),
+            flush_interval: Duration::from_millis(250),
+            confirm_timeout: Duration::from_secs(30),
+            max_flush_attempts: 3,
+            failed_usage_path: PathBuf::from("limitlayer-failed-usage.jsonl"),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use limitlayer_client::{accounts, instructions::UsageBatchEntry, pda, program::ApiKeyAccount};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc;
+
+use crate::{
+    batcher::{self, Usage},
+    config::Config,
+    error::{Error, Result},
+    limits::{KeyState, Verdict},
+    rpc::RpcClient,
+};
+
+struct Cached {
+    state: Arc<KeyState>,
+    fresh_until: Instant,
+}
+
+/// State shared between request handling and the batcher.
+#[derive(Default)]
+pub(crate) struct Shared {
+    cache: HashMap<Pubkey, Cached>,
+    /// Units admitted per usage account and not yet sent
+    pending: HashMap<Pubkey, u64>,
+}
+
+impl Shared {
+    /// Forgets sent or dropped usage and refetches the keys it was for, so
+    /// their counters are read back from the execution region.
+    pub(crate) fn release(&mut self, entries: &[UsageBatchEntry]) {
+        let now = Instant::now();
+        for entry in entries {
+            if let Some(pending) = self.pending.get_mut(&entry.delegated_usage) {
+                *pending = pending.saturating_sub(entry.amount);
+                if *pending == 0 {
+                    self.pending.remove(&entry.delegated_usage);
+                }
+            }
+            if let Some(cached) = self.cache.get_mut(&entry.api_key) {
+                cached.fresh_until = now;
+            }
+        }
+    }
+}
+
+pub(crate) struct Enforcer {
+    config: Config,
+    base: RpcClient,
+    region: RpcClient,
+    shared: Arc<Mutex<Shared>>,
+    queue: mpsc::UnboundedSender<Usage>,
+}
+
+impl Enforcer {
+    /// Starts the batcher on the current tokio runtime. It flushes what is
+    /// left and stops once the enforcer is dropped.
+    pub(crate) fn spawn(config: Config) -> Self {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let (queue, entries) = mpsc::unbounded_channel();
+        tokio::spawn(batcher::run(config.clone(), shared.clone(), entries));
+
+        Self {
+            base: RpcClient::new(config.base_url.clone()),
+            region: RpcClient::new(config.region_url.clone()),
+            config,
+            shared,
+            queue,
+        }
+    }
+
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Checks one request for `api_key` and queues its usage if admitted.
+    pub(crate) async fn admit(&self, api_key: Pubkey) -> Verdict {
+        let state = match self.key_state(api_key).await {
+            Ok(state) => state,
+            Err(error) => {
+                tracing::warn!(%api_key, %error, "cannot read key state");
+                return Verdict::Unavailable;
+            }
+        };
+
+        let amount = self.config.units_per_request;
+        let mut shared = self.shared.lock().unwrap();
+        let pending: Vec<u64> = state
+            .shards
+            .iter()
+            .map(|(address, _)| shared.pending.get(address).copied().unwrap_or(0))
+            .collect();
+
+        let verdict = state.check(&pending, amount, unix_now());
+        if let Verdict::Allow { shard } = verdict {
+            let delegated_usage = state.shards[shard].0;
+            *shared.pending.entry(delegated_usage).or_default() += amount;
+            // The receiver only stops once this enforcer is dropped
+            let _ = self.queue.send(Usage {
+                service: state.key.service,
+                entry: UsageBatchEntry {
+                    delegated_usage,
+                    api_key,
+                    policy: state.policy_address,
+                    amount,
+                    request_id: None,
+                },
+            });
+        }
+        verdict
+    }
+
+    async fn key_state(&self, api_key: Pubkey) -> Result<Arc<KeyState>> {
+        let cached = {
+            let shared = self.shared.lock().unwrap();
+            shared
+                .cache
+                .get(&api_key)
+                .map(|cached| (cached.state.clone(), cached.fresh_until > Instant::now()))
+        };
+        if let Some((state, true)) = cached {
+            return Ok(state);
+        }
+
+        match self.fetch(api_key).await {
+            Ok(state) => {
+                let state = Arc::new(state);
+                self.shared.lock().unwrap().cache.insert(
+                    api_key,
+                    Cached {
+                        state: state.clone(),
+                        fresh_until: Instant::now() + self.config.cache_ttl,
+                    },
+                );
+                Ok(state)
+            }
+            // Keep admitting against the last known state while the RPC is down
+            Err(error) => match cached {
+                Some((state, _)) => {
+                    tracing::debug!(%api_key, %error, "using stale key state");
+                    Ok(state)
+                }
+                None => Err(error),
+            },
+        }
+    }
+
+    async fn fetch(&self, api_key: Pubkey) -> Result<KeyState> {
+        let key: ApiKeyAccount = decode_account(
+            &api_key,
+            self.base
+                .multiple_accounts(&[api_key])
+                .await?
+                .pop()
+                .flatten(),
+        )?;
+
+        let shards: Vec<Pubkey> = (0..key.usage_shards.max(1))
+            .map(|shard| pda::usage_shard(&api_key, shard))
+            .collect();
+        let policy = [key.policy];
+        let (mut policy, usage) = tokio::try_join!(
+            self.base.multiple_accounts(&policy),
+            self.region.multiple_accounts(&shards),
+        )?;
+
+        Ok(KeyState {
+            api_key,
+            policy_address: key.policy,
+            policy: decode_account(&key.policy, policy.pop().flatten())?,
+            shards: shards
+                .iter()
+                .zip(usage)
+                .map(|(address, data)| Ok((*address, decode_account(address, data)?)))
+                .collect::<Result<_>>()?,
+            key,
+        })
+    }
+}
+
+fn decode_account<T: AnchorDeserialize + Discriminator>(
+    address: &Pubkey,
+    data: Option<Vec<u8>>,
+) -> Result<T> {
+    let data = data.ok_or(Error::AccountNotFound(*address))?;
+    Ok(accounts::decode(&data)?)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,14 @@
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Client(#[from] limitlayer_client::Error),
+    #[error("rpc call did not complete: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("account {0} not found")]
+    AccountNotFound(Pubkey),
+}
@@ -0,0 +1,117 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header::RETRY_AFTER, HeaderValue, Request, Response, StatusCode};
+use limitlayer_client::pda;
+use solana_sdk::pubkey::Pubkey;
+use tower::{Layer, Service};
+
+use crate::{
+    config::{Config, FailureMode},
+    enforcer::Enforcer,
+    limits::Verdict,
+};
+
+/// `ApiKeyAccount` address of an admitted request, added to its extensions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApiKey(pub Pubkey);
+
+#[derive(Clone)]
+pub struct LimitLayer {
+    enforcer: Arc<Enforcer>,
+}
+
+impl LimitLayer {
+    /// Must be called within a tokio runtime, which runs the usage batcher.
+    pub fn new(config: Config) -> Self {
+        Self {
+            enforcer: Arc::new(Enforcer::spawn(config)),
+        }
+    }
+}
+
+impl<S> Layer<S> for LimitLayer {
+    type Service = LimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LimitService {
+            inner,
+            enforcer: self.enforcer.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LimitService<S> {
+    inner: S,
+    enforcer: Arc<Enforcer>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for LimitService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        // The clone may not be ready; call the instance poll_ready was called on
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let enforcer = self.enforcer.clone();
+
+        Box::pin(async move {
+            let header = &enforcer.config().header;
+            let Some(api_key) = request.headers().get(header).and_then(parse_api_key) else {
+                return Ok(respond(StatusCode::UNAUTHORIZED));
+            };
+
+            match enforcer.admit(api_key).await {
+                Verdict::Allow { .. } => {}
+                Verdict::Limited { retry_after } => {
+                    let mut response = respond(StatusCode::TOO_MANY_REQUESTS);
+                    response
+                        .headers_mut()
+                        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+                    return Ok(response);
+                }
+                Verdict::Revoked => return Ok(respond(StatusCode::FORBIDDEN)),
+                Verdict::Unavailable => match enforcer.config().failure_mode {
+                    FailureMode::Open => {}
+                    FailureMode::Closed => return Ok(respond(StatusCode::SERVICE_UNAVAILABLE)),
+                },
+            }
+
+            request.extensions_mut().insert(ApiKey(api_key));
+            inner.call(request).await
+        })
+    }
+}
+
+/// A key's index (as in `pda::api_key`) or its account address.
+fn parse_api_key(value: &HeaderValue) -> Option<Pubkey> {
+    let value = value.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(index) => Some(pda::api_key(index)),
+        Err(_) => Pubkey::from_str(value).ok(),
+    }
+}
+
+fn respond<B: Default>(status: StatusCode) -> Response<B> {
+    let mut response = Response::new(B::default());
+    *response.status_mut() = status;
+    response
+}
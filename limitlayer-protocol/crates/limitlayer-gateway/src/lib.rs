@@ -0,0 +1,32 @@
+//! Tower middleware that enforces LimitLayer limits in HTTP gateways.
+//!
+//! [`LimitLayer`] reads the API key from a request header, checks the key's
+//! status and delegated usage counters against its policy, and either
+//! forwards the request or answers it:
+//!
+//! - `401` when the header is missing or is not a key
+//! - `403` when the key is revoked
+//! - `429` with `Retry-After` when a window, burst or quota limit is hit
+//! - `503` when the key's state cannot be read and the layer fails closed
+//!
+//! Admitted requests are queued and flushed to the execution region as
+//! `record_usage_batch` transactions, the batched form of
+//! `record_usage_realtime`.
+//!
+//! ```ignore
+//! let layer = LimitLayer::new(Config::new(base_url, region_url, payer));
+//! let app = Router::new().route("/search", get(search)).layer(layer);
+//! ```
+
+pub mod config;
+pub mod error;
+pub mod limits;
+pub mod rpc;
+
+mod batcher;
+mod enforcer;
+mod layer;
+
+pub use config::{Config, FailureMode};
+pub use error::{Error, Result};
+pub use layer::{ApiKey, LimitLayer, LimitService};
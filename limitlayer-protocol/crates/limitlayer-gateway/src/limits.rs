@@ -0,0 +1,119 @@
+//! Admission checks against a snapshot of a key's on-chain state.
+//!
+//! Recording decisions (status, lease, per-shard burst allowance, open
+//! reservations) come from `limitlayer-core`, the code the program runs, and
+//! are checked together with what `evaluate_enforcement` would block on
+//! (window usage), so a request the gateway admits is one the execution
+//! region will accept.
+
+use limitlayer_client::program::{
+    ApiKeyAccount, ApiKeyStatus, DelegatedUsageAccount, RateLimitPolicy,
+};
+use limitlayer_core::{apply_usage, burst_allowance, Rejection};
+use solana_sdk::pubkey::Pubkey;
+
+/// Everything needed to admit requests for one key.
+pub struct KeyState {
+    pub api_key: Pubkey,
+    pub key: ApiKeyAccount,
+    pub policy_address: Pubkey,
+    pub policy: RateLimitPolicy,
+    /// Usage accounts `0..usage_shards`, with their addresses
+    pub shards: Vec<(Pubkey, DelegatedUsageAccount)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Charge the request to `shards[shard]`
+    Allow { shard: usize },
+    /// Over a limit until `retry_after` seconds from now
+    Limited { retry_after: u64 },
+    /// The key is revoked
+    Revoked,
+    /// No shard can record usage: not delegated or lease expired
+    Unavailable,
+}
+
+impl KeyState {
+    /// Checks `amount` more units, given `pending[i]` units admitted on
+    /// `shards[i]` that are not yet reflected in the snapshot.
+    pub fn check(&self, pending: &[u64], amount: u64, now: i64) -> Verdict {
+        if self.key.status == ApiKeyStatus::Revoked {
+            return Verdict::Revoked;
+        }
+
+        // A quota block is stored as Blocked, but lifts when its period
+        // resets, so it is retried then rather than after the window
+        if self.key.quota_blocked_until > now {
+            return Verdict::Limited {
+                retry_after: (self.key.quota_blocked_until - now) as u64,
+            };
+        }
+        match self.key.admission_status(now) {
+            ApiKeyStatus::Revoked => return Verdict::Revoked,
+            ApiKeyStatus::Blocked => {
+                return Verdict::Limited {
+                    retry_after: self.window_retry_after(now),
+                }
+            }
+            ApiKeyStatus::Active | ApiKeyStatus::Throttled => {}
+        }
+
+        let window_usage = self
+            .shards
+            .iter()
+            .map(|(_, shard)| shard.current_window_usage)
+            .chain(pending.iter().copied())
+            .fold(0u64, u64::saturating_add);
+        if window_usage.saturating_add(amount) > self.policy.requests_per_window {
+            return Verdict::Limited {
+                retry_after: self.window_retry_after(now),
+            };
+        }
+
+        // Ask the core what each shard would decide, as the program will,
+        // and charge the one with the most burst room left
+        let policy = self.policy.limits();
+        let mut recording = false;
+        let mut best: Option<(usize, u64)> = None;
+        for (i, (_, shard)) in self.shards.iter().enumerate() {
+            let mut state = shard.usage_state(&self.key, now);
+            state.burst_counter = state
+                .burst_counter
+                .saturating_add(pending.get(i).copied().unwrap_or(0));
+            match apply_usage(&state, &policy, now, amount) {
+                Ok(charge) => {
+                    let room = burst_allowance(&policy, state.shard, state.shards)
+                        .saturating_sub(charge.burst_counter.saturating_add(state.reserved_units));
+                    if best.is_none_or(|(_, best_room)| room > best_room) {
+                        best = Some((i, room));
+                    }
+                    recording = true;
+                }
+                Err(Rejection::NotDelegated | Rejection::LeaseExpired) => {}
+                Err(_) => recording = true,
+            }
+        }
+
+        match best {
+            Some((shard, _)) => Verdict::Allow { shard },
+            None if recording => Verdict::Limited {
+                retry_after: self.window_retry_after(now),
+            },
+            None => Verdict::Unavailable,
+        }
+    }
+
+    /// Seconds until the primary account's window is due to close. Windows
+    /// close when a checkpoint is submitted, so an overdue one is retried
+    /// after a second.
+    fn window_retry_after(&self, now: i64) -> u64 {
+        let window_start = self
+            .shards
+            .first()
+            .map(|(_, shard)| shard.window_start_ts)
+            .unwrap_or(now);
+        let window_end = window_start.saturating_add(self.policy.window_seconds as i64);
+        window_end.saturating_sub(now).max(1) as u64
+    }
+}
@@ -0,0 +1,56 @@
+//! Async access to the shared JSON-RPC client. Its calls block, so each one
+//! runs on the runtime's blocking threads instead of stalling requests.
+
+use std::{sync::Arc, time::Duration};
+
+use limitlayer_client::rpc::{self, TransactionStatus};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use tokio::task;
+
+use crate::error::Result;
+
+/// Requests sit on the enforcement path, so they give up early.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct RpcClient {
+    inner: Arc<rpc::RpcClient>,
+}
+
+impl RpcClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            inner: Arc::new(rpc::RpcClient::with_timeout(url, TIMEOUT)),
+        }
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&rpc::RpcClient) -> limitlayer_client::Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let inner = self.inner.clone();
+        Ok(task::spawn_blocking(move || call(&inner)).await??)
+    }
+
+    /// Raw data of each account, `None` where it does not exist.
+    pub async fn multiple_accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Vec<u8>>>> {
+        let addresses = addresses.to_vec();
+        self.run(move |rpc| rpc.multiple_accounts(&addresses)).await
+    }
+
+    pub async fn latest_blockhash(&self) -> Result<Hash> {
+        self.run(|rpc| rpc.latest_blockhash()).await
+    }
+
+    pub async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let transaction = transaction.clone();
+        self.run(move |rpc| rpc.send_transaction(&transaction))
+            .await
+    }
+
+    /// The transaction's outcome once confirmed, `None` until then.
+    pub async fn transaction(&self, signature: &Signature) -> Result<Option<TransactionStatus>> {
+        let signature = *signature;
+        self.run(move |rpc| rpc.transaction(&signature)).await
+    }
+}
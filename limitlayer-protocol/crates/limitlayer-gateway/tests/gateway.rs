@@ -0,0 +1,495 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anchor_lang::{AccountSerialize, AnchorDeserialize, Discriminator, Event};
+use axum::{body::Body, extract::State, routing::get, routing::post, Json, Router};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{header::RETRY_AFTER, Request, StatusCode};
+use limitlayer_client::{
+    pda,
+    program::{
+        events::UsageBatchRecorded, instruction as ix, ApiKeyAccount, ApiKeyStatus,
+        DelegatedUsageAccount, RateLimitPolicy, UsageRecordResult, UsageReservation,
+        MAX_USAGE_RESERVATIONS, MAX_WINDOW_ROOTS, RECENT_REQUEST_IDS,
+    },
+    ID,
+};
+use limitlayer_gateway::{
+    limits::{KeyState, Verdict},
+    Config, FailureMode, LimitLayer,
+};
+use serde_json::{json, Value};
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use tower::ServiceExt;
+
+/// JSON-RPC endpoint serving canned accounts and capturing sent transactions.
+#[derive(Default)]
+struct MockRpc {
+    accounts: Mutex<HashMap<String, Vec<u8>>>,
+    sent: Mutex<Vec<Transaction>>,
+    /// Result code of every batch entry; `None` confirms nothing
+    results: Mutex<Option<u8>>,
+}
+
+impl MockRpc {
+    fn set<T: AccountSerialize>(&self, address: Pubkey, account: &T) {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        self.accounts
+            .lock()
+            .unwrap()
+            .insert(address.to_string(), data);
+    }
+
+    fn new() -> Arc<Self> {
+        let mock = Self::default();
+        *mock.results.lock().unwrap() = Some(UsageRecordResult::Recorded as u8);
+        Arc::new(mock)
+    }
+
+    /// Logs of a confirmed batch, each entry with the configured result.
+    fn batch_logs(&self, signature: &str) -> Option<Value> {
+        let result = (*self.results.lock().unwrap())?;
+        let sent = self.sent.lock().unwrap();
+        let transaction = sent
+            .iter()
+            .find(|transaction| transaction.signatures[0].to_string() == signature)?;
+        let data = &transaction.message.instructions[0].data;
+        let batch = ix::RecordUsageBatch::try_from_slice(&data[8..]).unwrap();
+        let event = UsageBatchRecorded {
+            recorded: 0,
+            rejected: 0,
+            total_amount: 0,
+            results: vec![result; batch.amounts.len()],
+        };
+        Some(json!([
+            format!("Program {ID} invoke [1]"),
+            format!("Program data: {}", STANDARD.encode(event.data())),
+            format!("Program {ID} success"),
+        ]))
+    }
+
+    fn sent_amounts(&self) -> Vec<u64> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|transaction| {
+                let data = &transaction.message.instructions[0].data;
+                assert!(data.starts_with(ix::RecordUsageBatch::DISCRIMINATOR));
+                let batch = ix::RecordUsageBatch::try_from_slice(&data[8..]).unwrap();
+                assert!(batch.request_ids.iter().all(Option::is_some));
+                batch.amounts
+            })
+            .collect()
+    }
+}
+
+async fn handle(State(mock): State<Arc<MockRpc>>, Json(request): Json<Value>) -> Json<Value> {
+    let params = &request["params"];
+    let result = match request["method"].as_str().unwrap() {
+        "getMultipleAccounts" => {
+            let accounts = mock.accounts.lock().unwrap();
+            let value: Vec<Value> = params[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|address| match accounts.get(address.as_str().unwrap()) {
+                    Some(data) => json!({ "data": [STANDARD.encode(data), "base64"] }),
+                    None => Value::Null,
+                })
+                .collect();
+            json!({ "value": value })
+        }
+        "getLatestBlockhash" => json!({ "value": { "blockhash": Hash::default().to_string() } }),
+        "sendTransaction" => {
+            let data = STANDARD.decode(params[0].as_str().unwrap()).unwrap();
+            let transaction: Transaction = bincode::deserialize(&data).unwrap();
+            let signature = transaction.signatures[0].to_string();
+            mock.sent.lock().unwrap().push(transaction);
+            json!(signature)
+        }
+        "getTransaction" => match mock.batch_logs(params[0].as_str().unwrap()) {
+            Some(logs) => json!({ "meta": { "err": null, "logMessages": logs } }),
+            None => Value::Null,
+        },
+        other => panic!("unexpected {other}"),
+    };
+    Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+}
+
+async fn serve(mock: Arc<MockRpc>) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let router = Router::new().route("/", post(handle)).with_state(mock);
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    url
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+fn policy(requests_per_window: u64, burst_limit: u64) -> RateLimitPolicy {
+    RateLimitPolicy {
+        service: pda::service(0),
+        requests_per_window,
+        window_seconds: 60,
+        burst_limit,
+        cost_per_request: 1,
+        daily_quota: 0,
+        monthly_quota: 0,
+        bump: 255,
+    }
+}
+
+fn api_key(policy: Pubkey, status: ApiKeyStatus) -> ApiKeyAccount {
+    ApiKeyAccount {
+        service: pda::service(0),
+        owner: Pubkey::new_unique(),
+        policy,
+        reputation: Pubkey::new_unique(),
+        status,
+        lifetime_usage: 0,
+        lifetime_cost: 0,
+        applied_usage: 0,
+        applied_cost: 0,
+        applied_checkpoint_seq: 0,
+        last_checkpoint_ts: 0,
+        day_start_ts: 0,
+        daily_usage: 0,
+        month_start_ts: 0,
+        monthly_usage: 0,
+        quota_blocked_until: 0,
+        usage_shards: 1,
+        bump: 255,
+    }
+}
+
+fn usage(api_key: Pubkey, policy: Pubkey, window_usage: u64) -> DelegatedUsageAccount {
+    DelegatedUsageAccount {
+        api_key,
+        shard_index: 0,
+        policy,
+        execution_region: Pubkey::new_unique(),
+        delegated: true,
+        delegation_seq: 1,
+        window_start_ts: now(),
+        current_window_usage: window_usage,
+        current_window_cost: window_usage,
+        burst_counter: window_usage,
+        last_update_ts: now(),
+        delegated_at: now(),
+        last_commit_ts: 0,
+        commit_frequency_ms: 30_000,
+        lease_expires_at: 0,
+        migration_target: Pubkey::default(),
+        checkpoint_seq: 0,
+        checkpointed_usage: 0,
+        checkpointed_cost: 0,
+        last_checkpoint_window_start: 0,
+        last_request_log_root: [0; 32],
+        window_roots: [[0; 32]; MAX_WINDOW_ROOTS as usize],
+        disputed_window_start: 0,
+        disputed_window_usage: 0,
+        disputed_window_cost: 0,
+        reservations: [UsageReservation::default(); MAX_USAGE_RESERVATIONS as usize],
+        next_reservation_id: 0,
+        recent_request_ids: [0; RECENT_REQUEST_IDS as usize],
+        recent_request_cursor: 0,
+        last_receipt_nonce: 0,
+        bump: 255,
+    }
+}
+
+/// Registers key `index` with its policy and primary usage account.
+fn add_key(mock: &MockRpc, index: u64, status: ApiKeyStatus, policy_account: RateLimitPolicy) {
+    let address = pda::api_key(index);
+    let policy = pda::policy(&pda::service(0), index);
+    mock.set(address, &api_key(policy, status));
+    mock.set(policy, &policy_account);
+    mock.set(pda::delegated_usage(&address), &usage(address, policy, 0));
+}
+
+fn app(config: Config) -> Router {
+    Router::new()
+        .route("/", get(|| async { "ok" }))
+        .layer(LimitLayer::new(config))
+}
+
+fn config(url: &str) -> Config {
+    let mut config = Config::new(url, url, Keypair::new());
+    config.flush_interval = Duration::from_millis(50);
+    config.failed_usage_path = std::env::temp_dir().join(format!(
+        "limitlayer-failed-usage-{}.jsonl",
+        Keypair::new().pubkey()
+    ));
+    config
+}
+
+/// Entries appended to the failed usage file.
+fn failed_usage(config: &Config) -> Vec<Value> {
+    std::fs::read_to_string(&config.failed_usage_path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+async fn get_with_key(app: &Router, key: Option<&str>) -> http::Response<Body> {
+    let mut request = Request::get("/");
+    if let Some(key) = key {
+        request = request.header("x-api-key", key);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn admits_requests_and_flushes_batched_usage() {
+    let mock = MockRpc::new();
+    add_key(&mock, 1, ApiKeyStatus::Active, policy(100, 10));
+    let app = app(config(&serve(mock.clone()).await));
+
+    for _ in 0..3 {
+        assert_eq!(get_with_key(&app, Some("1")).await.status(), StatusCode::OK);
+    }
+    // Keys can also be given by account address
+    let address = pda::api_key(1).to_string();
+    assert_eq!(
+        get_with_key(&app, Some(&address)).await.status(),
+        StatusCode::OK
+    );
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(mock.sent_amounts().iter().sum::<u64>(), 4);
+
+    let sent = mock.sent.lock().unwrap();
+    let message = &sent[0].message;
+    // After the submitter, its service and its gateway account
+    let [submitter, service, gateway, usage_account, ..] = message.instructions[0].accounts[..]
+    else {
+        panic!("batch has no entries");
+    };
+    // The fee payer signs as the service's gateway
+    let payer = message.account_keys[0];
+    assert_eq!(submitter, 0);
+    assert_eq!(message.account_keys[service as usize], pda::service(0));
+    assert_eq!(
+        message.account_keys[gateway as usize],
+        pda::gateway(&pda::service(0), &payer)
+    );
+    let usage_account = usage_account as usize;
+    assert_eq!(
+        message.account_keys[usage_account],
+        pda::delegated_usage(&pda::api_key(1))
+    );
+}
+
+#[tokio::test]
+async fn keeps_usage_the_program_did_not_count() {
+    let mock = MockRpc::new();
+    add_key(&mock, 1, ApiKeyStatus::Active, policy(100, 10));
+    let url = serve(mock.clone()).await;
+
+    // Confirmed, but the program rejected the entry
+    *mock.results.lock().unwrap() = Some(UsageRecordResult::BurstLimitExceeded as u8);
+    let rejecting = config(&url);
+    let rejecting_app = app(rejecting.clone());
+    assert_eq!(
+        get_with_key(&rejecting_app, Some("1")).await.status(),
+        StatusCode::OK
+    );
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let failed = failed_usage(&rejecting);
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["api_key"], pda::api_key(1).to_string());
+    assert_eq!(failed[0]["amount"], 1);
+    assert_eq!(
+        failed[0]["result"],
+        UsageRecordResult::BurstLimitExceeded as u8
+    );
+
+    // Never confirmed: resent with the same request id, then saved
+    *mock.results.lock().unwrap() = None;
+    let mut unconfirmed = config(&url);
+    unconfirmed.confirm_timeout = Duration::ZERO;
+    unconfirmed.max_flush_attempts = 2;
+    let sent_before = mock.sent.lock().unwrap().len();
+    let unconfirmed_app = app(unconfirmed.clone());
+    assert_eq!(
+        get_with_key(&unconfirmed_app, Some("1")).await.status(),
+        StatusCode::OK
+    );
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let resent: Vec<Option<u64>> = mock.sent.lock().unwrap()[sent_before..]
+        .iter()
+        .map(|transaction| {
+            let data = &transaction.message.instructions[0].data;
+            ix::RecordUsageBatch::try_from_slice(&data[8..])
+                .unwrap()
+                .request_ids[0]
+        })
+        .collect();
+    assert_eq!(resent.len(), 2);
+    assert_eq!(resent[0], resent[1]);
+    let failed = failed_usage(&unconfirmed);
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["request_id"], resent[0].unwrap());
+    assert!(failed[0]["result"].is_null());
+
+    for config in [rejecting, unconfirmed] {
+        std::fs::remove_file(config.failed_usage_path).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn resends_no_more_often_than_request_ids_are_remembered() {
+    let mock = MockRpc::new();
+    add_key(&mock, 1, ApiKeyStatus::Active, policy(100, 10));
+    *mock.results.lock().unwrap() = None;
+    let url = serve(mock.clone()).await;
+
+    let mut config = config(&url);
+    config.flush_interval = Duration::from_millis(1);
+    config.confirm_timeout = Duration::ZERO;
+    config.max_flush_attempts = 100;
+    let app = app(config.clone());
+    assert_eq!(get_with_key(&app, Some("1")).await.status(), StatusCode::OK);
+    for _ in 0..500 {
+        if !failed_usage(&config).is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Any later resend could fall out of the usage account's recent ids
+    assert_eq!(failed_usage(&config).len(), 1);
+    assert_eq!(mock.sent.lock().unwrap().len(), RECENT_REQUEST_IDS as usize);
+    std::fs::remove_file(config.failed_usage_path).unwrap();
+}
+
+#[tokio::test]
+async fn answers_429_with_retry_after_over_burst_limit() {
+    let mock = MockRpc::new();
+    add_key(&mock, 1, ApiKeyStatus::Active, policy(100, 2));
+    let app = app(config(&serve(mock).await));
+
+    assert_eq!(get_with_key(&app, Some("1")).await.status(), StatusCode::OK);
+    assert_eq!(get_with_key(&app, Some("1")).await.status(), StatusCode::OK);
+
+    let limited = get_with_key(&app, Some("1")).await;
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = limited.headers()[RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+}
+
+#[tokio::test]
+async fn rejects_missing_and_revoked_keys() {
+    let mock = MockRpc::new();
+    add_key(&mock, 1, ApiKeyStatus::Revoked, policy(100, 10));
+    let app = app(config(&serve(mock.clone()).await));
+
+    assert_eq!(
+        get_with_key(&app, None).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        get_with_key(&app, Some("not a key")).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        get_with_key(&app, Some("1")).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert!(mock.sent_amounts().is_empty());
+}
+
+#[tokio::test]
+async fn unreadable_key_state_follows_failure_mode() {
+    // Nothing listens here once the listener is dropped
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let mut open = config(&url);
+    open.failure_mode = FailureMode::Open;
+    assert_eq!(
+        get_with_key(&app(open), Some("1")).await.status(),
+        StatusCode::OK
+    );
+
+    let closed = app(config(&url));
+    assert_eq!(
+        get_with_key(&closed, Some("1")).await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+}
+
+#[test]
+fn checks_window_quota_and_delegation() {
+    let address = pda::api_key(1);
+    let policy_address = Pubkey::new_unique();
+    let state = |key: ApiKeyAccount, shard: DelegatedUsageAccount| KeyState {
+        api_key: address,
+        key,
+        policy_address,
+        policy: policy(10, 10),
+        shards: vec![(pda::delegated_usage(&address), shard)],
+    };
+    let now = now();
+
+    let full_window = state(
+        api_key(policy_address, ApiKeyStatus::Active),
+        usage(address, policy_address, 8),
+    );
+    assert_eq!(full_window.check(&[1], 1, now), Verdict::Allow { shard: 0 });
+    assert!(matches!(
+        full_window.check(&[2], 1, now),
+        Verdict::Limited { .. }
+    ));
+
+    // The program stores a quota block as Blocked until the period resets
+    let mut quota_blocked = api_key(policy_address, ApiKeyStatus::Blocked);
+    quota_blocked.quota_blocked_until = now + 3_600;
+    assert_eq!(
+        state(quota_blocked.clone(), usage(address, policy_address, 0)).check(&[0], 1, now),
+        Verdict::Limited { retry_after: 3_600 }
+    );
+    assert_eq!(
+        state(quota_blocked, usage(address, policy_address, 0)).check(&[0], 1, now + 3_600),
+        Verdict::Allow { shard: 0 }
+    );
+    assert!(matches!(
+        state(
+            api_key(policy_address, ApiKeyStatus::Blocked),
+            usage(address, policy_address, 0)
+        )
+        .check(&[0], 1, now),
+        Verdict::Limited { .. }
+    ));
+
+    let mut undelegated = usage(address, policy_address, 0);
+    undelegated.delegated = false;
+    assert_eq!(
+        state(api_key(policy_address, ApiKeyStatus::Active), undelegated).check(&[0], 1, now),
+        Verdict::Unavailable
+    );
+}
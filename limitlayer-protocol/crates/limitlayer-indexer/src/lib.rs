@@ -0,0 +1,14 @@
+//! Indexes LimitLayer program events into SQLite.
+//!
+//! Transactions are read from an RPC node or a recorded log file
+//! ([`source`]), their events decoded with `limitlayer-client` and stored as
+//! typed rows, one table per event ([`schema`]). [`Store`] keeps a cursor
+//! for resuming and refreshes the materialized views `key_usage_history`
+//! and `enforcement_timeline` after each ingest.
+
+pub mod schema;
+pub mod source;
+pub mod store;
+
+pub use source::{read_log_file, RecordedTransaction, RpcSource};
+pub use store::{Cursor, EnforcementChange, IngestStats, Store, UsageDay};
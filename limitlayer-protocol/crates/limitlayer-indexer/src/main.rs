@@ -0,0 +1,100 @@
+//! `limitlayer-indexer`: follows the program on an RPC node, or replays
+//! recorded log files, into an SQLite database.
+
+use std::{path::PathBuf, thread, time::Duration};
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use limitlayer_indexer::{read_log_file, source::append_log_file, IngestStats, RpcSource, Store};
+
+#[derive(Parser)]
+#[command(
+    name = "limitlayer-indexer",
+    version,
+    about = "Index LimitLayer program events into SQLite"
+)]
+struct Cli {
+    /// SQLite database, created if missing
+    #[arg(long, env = "LIMITLAYER_INDEXER_DB", default_value = "limitlayer.db")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Follow the program on an RPC node, resuming from the stored cursor
+    Rpc {
+        #[arg(
+            long,
+            short = 'u',
+            env = "LIMITLAYER_RPC_URL",
+            default_value = "http://127.0.0.1:8899"
+        )]
+        url: String,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 5)]
+        poll_interval: u64,
+        /// Also append fetched transactions to this log file
+        #[arg(long)]
+        record: Option<PathBuf>,
+        /// Index what is available and exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// Index recorded log files, in order
+    Replay {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+fn report(stats: &IngestStats) {
+    if *stats != IngestStats::default() {
+        eprintln!(
+            "indexed {} transactions, {} events ({} duplicate, {} undecodable)",
+            stats.transactions, stats.events, stats.duplicates, stats.undecodable
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let Cli { db, command } = Cli::parse();
+    let mut store = Store::open(&db)?;
+
+    match command {
+        Command::Rpc {
+            url,
+            poll_interval,
+            record,
+            once,
+        } => {
+            let source = RpcSource::new(url);
+            loop {
+                let cursor = store.cursor()?;
+                match source.fetch_after(cursor.as_ref().map(|c| c.signature.as_str())) {
+                    Ok(transactions) => {
+                        if let Some(path) = &record {
+                            append_log_file(path, &transactions)?;
+                        }
+                        report(&store.ingest(&transactions)?);
+                    }
+                    // Transient RPC errors are retried on the next poll
+                    Err(error) if !once => eprintln!("poll failed: {error:#}"),
+                    Err(error) => return Err(error),
+                }
+                if once {
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_secs(poll_interval));
+            }
+        }
+        Command::Replay { files } => {
+            for file in files {
+                report(&store.ingest(&read_log_file(&file)?)?);
+            }
+        }
+    }
+    Ok(())
+}
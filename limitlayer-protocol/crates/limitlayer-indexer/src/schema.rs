@@ -0,0 +1,384 @@
+//! Database schema: one typed table per program event, the indexing cursor,
+//! and the materialized views built from the event tables.
+//!
+//! Every event table starts with where the event was emitted (`signature`,
+//! `event_index`, `slot`, `block_time`) followed by the event's fields in
+//! declaration order. Pubkeys are stored base58 and hashes hex. `u64`
+//! values above `i64::MAX` do not fit an SQLite integer and are stored as
+//! text.
+
+use anchor_lang::prelude::Pubkey;
+use limitlayer_client::{events::LimitLayerEvent, program::events::*};
+use rusqlite::types::Value;
+
+/// Maps an event field to an SQLite column.
+pub trait Column {
+    const SQL_TYPE: &'static str;
+
+    fn to_sql(&self) -> Value;
+}
+
+impl Column for Pubkey {
+    const SQL_TYPE: &'static str = "TEXT";
+
+    fn to_sql(&self) -> Value {
+        Value::Text(self.to_string())
+    }
+}
+
+impl Column for String {
+    const SQL_TYPE: &'static str = "TEXT";
+
+    fn to_sql(&self) -> Value {
+        Value::Text(self.clone())
+    }
+}
+
+impl Column for bool {
+    const SQL_TYPE: &'static str = "INTEGER";
+
+    fn to_sql(&self) -> Value {
+        Value::Integer(*self as i64)
+    }
+}
+
+macro_rules! integer_columns {
+    ($($ty:ty),*) => {
+        $(
+            impl Column for $ty {
+                const SQL_TYPE: &'static str = "INTEGER";
+
+                fn to_sql(&self) -> Value {
+                    Value::Integer(*self as i64)
+                }
+            }
+        )*
+    };
+}
+
+integer_columns!(u8, u16, u32, i64);
+
+impl Column for u64 {
+    const SQL_TYPE: &'static str = "INTEGER";
+
+    fn to_sql(&self) -> Value {
+        i64::try_from(*self)
+            .map(Value::Integer)
+            .unwrap_or_else(|_| Value::Text(self.to_string()))
+    }
+}
+
+impl Column for [u8; 32] {
+    const SQL_TYPE: &'static str = "TEXT";
+
+    fn to_sql(&self) -> Value {
+        Value::Text(hex::encode(self))
+    }
+}
+
+impl Column for Vec<u8> {
+    const SQL_TYPE: &'static str = "BLOB";
+
+    fn to_sql(&self) -> Value {
+        Value::Blob(self.clone())
+    }
+}
+
+impl<T: Column> Column for Option<T> {
+    const SQL_TYPE: &'static str = T::SQL_TYPE;
+
+    fn to_sql(&self) -> Value {
+        self.as_ref().map_or(Value::Null, Column::to_sql)
+    }
+}
+
+/// SQL type of the field `field` selects, without naming its type.
+fn column_type<E, T: Column>(_field: for<'a> fn(&'a E) -> &'a T) -> &'static str {
+    T::SQL_TYPE
+}
+
+fn create_table(table: &str, columns: &[(&str, &str)]) -> String {
+    let columns: String = columns
+        .iter()
+        .map(|(name, ty)| format!(",\n    {name} {ty}"))
+        .collect();
+    let mut sql = format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER{columns},
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS {table}_slot ON {table} (slot);
+"
+    );
+    if columns.contains(",\n    api_key ") {
+        sql.push_str(&format!(
+            "CREATE INDEX IF NOT EXISTS {table}_api_key ON {table} (api_key);\n"
+        ));
+    }
+    sql
+}
+
+macro_rules! event_tables {
+    ($($event:ident => $table:ident { $($field:ident),* $(,)? }),* $(,)?) => {
+        /// Event table names, in the order of `LimitLayerEvent`.
+        pub const EVENT_TABLES: &[&str] = &[$(stringify!($table)),*];
+
+        fn create_event_tables() -> String {
+            let mut sql = String::new();
+            $(
+                sql.push_str(&create_table(
+                    stringify!($table),
+                    &[$((stringify!($field), column_type(|e: &$event| &e.$field))),*],
+                ));
+            )*
+            sql
+        }
+
+        /// Table and column values an event is stored as.
+        pub fn event_row(event: &LimitLayerEvent) -> (&'static str, Vec<(&'static str, Value)>) {
+            match event {
+                $(
+                    LimitLayerEvent::$event(e) => (
+                        stringify!($table),
+                        vec![$((stringify!($field), e.$field.to_sql())),*],
+                    ),
+                )*
+            }
+        }
+    };
+}
+
+event_tables! {
+    ProtocolInitialized => protocol_initialized { protocol, admin, treasury, protocol_fee_bps },
+    ProtocolUpdated => protocol_updated { protocol, new_fee_bps, new_treasury, paused },
+    ServiceCreated => service_created { service, protocol, authority, name, default_policy },
+    ServiceUpdated => service_updated {
+        service, new_authority, new_default_policy, new_challenge_period_seconds,
+    },
+    ServiceStatusChanged => service_status_changed { service, new_status },
+    PolicyCreated => policy_created {
+        policy, service, requests_per_window, window_seconds, burst_limit, cost_per_request,
+        daily_quota, monthly_quota,
+    },
+    PolicyUpdated => policy_updated {
+        policy, requests_per_window, window_seconds, burst_limit, cost_per_request,
+        daily_quota, monthly_quota,
+    },
+    PolicyAttachedToKey => policy_attached_to_key { api_key, policy, service },
+    ApiKeyCreated => api_key_created { api_key, service, owner, policy },
+    ApiKeyRevoked => api_key_revoked { api_key, service },
+    ApiKeyStatusChanged => api_key_status_changed { api_key, service, new_status },
+    ExecutionRegionRegistered => execution_region_registered {
+        execution_region, validator, region_code,
+    },
+    ExecutionRegionStatusChanged => execution_region_status_changed {
+        execution_region, validator, new_status,
+    },
+    UsageDelegated => usage_delegated {
+        delegated_usage, api_key, policy, execution_region, commit_frequency_ms,
+        lease_expires_at,
+    },
+    UsageShardCreated => usage_shard_created {
+        delegated_usage, api_key, shard_index, usage_shards,
+    },
+    UsageUndelegated => usage_undelegated { delegated_usage, api_key },
+    UsageRegionMigrated => usage_region_migrated {
+        delegated_usage, api_key, from_region, to_region, delegation_seq,
+    },
+    DelegationReconciled => delegation_reconciled {
+        delegated_usage, api_key, execution_region, delegation_seq, last_commit_ts,
+        disputed_window_start, disputed_window_usage, disputed_window_cost,
+    },
+    DelegationLeaseRenewed => delegation_lease_renewed {
+        delegated_usage, api_key, lease_expires_at,
+    },
+    DelegationLeaseExpired => delegation_lease_expired {
+        delegated_usage, api_key, lease_expires_at,
+    },
+    UsageRecordedRealtime => usage_recorded_realtime {
+        delegated_usage, api_key, amount, window_usage,
+    },
+    UsageRecordedDirect => usage_recorded_direct {
+        delegated_usage, api_key, amount, window_usage,
+    },
+    DuplicateUsageIgnored => duplicate_usage_ignored {
+        delegated_usage, api_key, request_id, amount,
+    },
+    UsageReserved => usage_reserved {
+        delegated_usage, api_key, reservation_id, amount, expires_at,
+    },
+    UsageSettled => usage_settled {
+        delegated_usage, api_key, reservation_id, reserved, amount, window_usage,
+    },
+    UsageReleased => usage_released { delegated_usage, api_key, reservation_id, amount },
+    UsageBatchRecorded => usage_batch_recorded { recorded, rejected, total_amount, results },
+    UsageCheckpointSubmitted => usage_checkpoint_submitted {
+        delegated_usage, api_key, service, checkpoint_seq, window_usage, window_cost,
+        request_log_root,
+    },
+    UsageCheckpointApplied => usage_checkpoint_applied {
+        usage_checkpoint, api_key, service, checkpoint_seq, request_count, cost,
+        request_log_root, request_log_truncated, finalizes_at,
+    },
+    UsageCheckpointLagging => usage_checkpoint_lagging {
+        usage_checkpoint, api_key, checkpoint_seq, window_start, lag_seconds, max_lag_seconds,
+    },
+    QuotaExhausted => quota_exhausted { api_key, policy, period, usage, quota, resets_at },
+    EnforcementEvaluated => enforcement_evaluated { api_key, new_status, usage },
+    KeyManuallyBlocked => key_manually_blocked { api_key, service },
+    KeyManuallyUnblocked => key_manually_unblocked { api_key, service },
+    AbuseSignalEmitted => abuse_signal_emitted {
+        abuse_signal, reporter_service, subject, severity, category,
+    },
+    ReputationUpdated => reputation_updated { reputation, subject, delta, new_score },
+    GatewayRegistered => gateway_registered { gateway, service, gateway_key },
+    GatewayRevoked => gateway_revoked { gateway, service, gateway_key },
+    UsageReceiptApplied => usage_receipt_applied {
+        usage_checkpoint, api_key, gateway_key, window_start, request_count, cost, nonce,
+    },
+    RequestInclusionVerified => request_inclusion_verified {
+        usage_checkpoint, api_key, checkpoint_seq, request_id, amount, cost,
+    },
+    AuditorRegistered => auditor_registered { auditor_account, service, auditor },
+    AuditorRevoked => auditor_revoked { auditor_account, service, auditor },
+    UsageCheckpointDisputed => usage_checkpoint_disputed {
+        usage_checkpoint, api_key, disputer, reason, evidence_hash,
+    },
+    UsageCheckpointDisputeResolved => usage_checkpoint_dispute_resolved {
+        usage_checkpoint, api_key, reported_request_count, reported_cost,
+        billed_request_count, billed_cost,
+    },
+    UsageCheckpointFinalized => usage_checkpoint_finalized {
+        usage_checkpoint, api_key, service, checkpoint_seq, billed_request_count, billed_cost,
+    },
+}
+
+const CORE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+    signature TEXT PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    failed INTEGER NOT NULL,
+    event_count INTEGER NOT NULL,
+    -- Set when the logs could not be decoded; no events were stored
+    decode_error TEXT
+);
+CREATE INDEX IF NOT EXISTS transactions_slot ON transactions (slot);
+
+-- Every event in emission order, pointing at its row in the event's table
+CREATE TABLE IF NOT EXISTS events (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    name TEXT NOT NULL,
+    event_table TEXT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+-- Last indexed transaction; RPC indexing resumes after it
+CREATE TABLE IF NOT EXISTS cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    slot INTEGER NOT NULL,
+    signature TEXT NOT NULL
+);
+
+-- Per key and UTC day (NULL when the block time is unknown)
+CREATE TABLE IF NOT EXISTS key_usage_history (
+    api_key TEXT NOT NULL,
+    day TEXT,
+    recorded_units INTEGER NOT NULL,
+    duplicate_requests INTEGER NOT NULL,
+    checkpoints_applied INTEGER NOT NULL,
+    applied_usage INTEGER NOT NULL,
+    applied_cost INTEGER NOT NULL,
+    billed_usage INTEGER NOT NULL,
+    billed_cost INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS key_usage_history_api_key ON key_usage_history (api_key, day);
+
+-- Status changes of each key, whatever caused them
+CREATE TABLE IF NOT EXISTS enforcement_timeline (
+    api_key TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    status TEXT NOT NULL,
+    detail TEXT
+);
+CREATE INDEX IF NOT EXISTS enforcement_timeline_api_key
+    ON enforcement_timeline (api_key, slot, event_index);
+";
+
+/// Rebuilds the materialized views from the event tables. Usage recorded
+/// through `record_usage_batch` is not attributed to keys by its event and
+/// only shows up once checkpointed.
+pub const REFRESH_VIEWS: &str = "
+DELETE FROM key_usage_history;
+INSERT INTO key_usage_history
+SELECT api_key, day, SUM(recorded), SUM(duplicates), SUM(checkpoints),
+       SUM(applied_usage), SUM(applied_cost), SUM(billed_usage), SUM(billed_cost)
+FROM (
+    SELECT api_key, date(block_time, 'unixepoch') AS day, amount AS recorded,
+           0 AS duplicates, 0 AS checkpoints, 0 AS applied_usage, 0 AS applied_cost,
+           0 AS billed_usage, 0 AS billed_cost
+    FROM usage_recorded_realtime
+    UNION ALL
+    SELECT api_key, date(block_time, 'unixepoch'), amount, 0, 0, 0, 0, 0, 0
+    FROM usage_recorded_direct
+    UNION ALL
+    SELECT api_key, date(block_time, 'unixepoch'), amount, 0, 0, 0, 0, 0, 0
+    FROM usage_settled
+    UNION ALL
+    SELECT api_key, date(block_time, 'unixepoch'), 0, 1, 0, 0, 0, 0, 0
+    FROM duplicate_usage_ignored
+    UNION ALL
+    SELECT api_key, date(block_time, 'unixepoch'), 0, 0, 1, request_count, cost, 0, 0
+    FROM usage_checkpoint_applied
+    UNION ALL
+    SELECT api_key, date(block_time, 'unixepoch'), 0, 0, 0, 0, 0,
+           billed_request_count, billed_cost
+    FROM usage_checkpoint_finalized
+)
+GROUP BY api_key, day;
+
+DELETE FROM enforcement_timeline;
+INSERT INTO enforcement_timeline
+SELECT api_key, slot, block_time, signature, event_index, 'ApiKeyStatusChanged',
+       CASE new_status WHEN 0 THEN 'active' WHEN 1 THEN 'throttled'
+                       WHEN 2 THEN 'blocked' ELSE 'revoked' END,
+       NULL
+FROM api_key_status_changed
+UNION ALL
+SELECT api_key, slot, block_time, signature, event_index, 'EnforcementEvaluated',
+       CASE new_status WHEN 0 THEN 'active' WHEN 1 THEN 'throttled'
+                       WHEN 2 THEN 'blocked' ELSE 'revoked' END,
+       'window usage ' || usage
+FROM enforcement_evaluated
+UNION ALL
+SELECT api_key, slot, block_time, signature, event_index, 'QuotaExhausted', 'blocked',
+       CASE period WHEN 0 THEN 'daily' ELSE 'monthly' END
+           || ' quota ' || quota || ' used ' || usage || ', resets at ' || resets_at
+FROM quota_exhausted
+UNION ALL
+SELECT api_key, slot, block_time, signature, event_index, 'KeyManuallyBlocked', 'blocked',
+       'manual'
+FROM key_manually_blocked
+UNION ALL
+SELECT api_key, slot, block_time, signature, event_index, 'KeyManuallyUnblocked', 'active',
+       'manual'
+FROM key_manually_unblocked
+UNION ALL
+SELECT api_key, slot, block_time, signature, event_index, 'ApiKeyRevoked', 'revoked', NULL
+FROM api_key_revoked;
+";
+
+/// Statements creating every table, safe to run on an existing database.
+pub fn create_tables() -> String {
+    format!("{CORE_TABLES}{}", create_event_tables())
+}
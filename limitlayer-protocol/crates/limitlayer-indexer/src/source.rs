@@ -0,0 +1,117 @@
+//! Where transactions come from: the program's history on an RPC node, or
+//! a log file recorded from one. Log files hold one JSON
+//! [`RecordedTransaction`] per line, oldest first.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use limitlayer_client::{
+    rpc::{RpcClient, Signature, SignatureInfo},
+    ID,
+};
+use serde::{Deserialize, Serialize};
+
+/// Signatures requested per `getSignaturesForAddress` page (the RPC maximum).
+const SIGNATURE_PAGE: usize = 1_000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedTransaction {
+    pub slot: u64,
+    pub signature: String,
+    #[serde(default)]
+    pub block_time: Option<i64>,
+    /// Failed transactions roll their events back; they are kept so the
+    /// cursor moves past them
+    #[serde(default)]
+    pub failed: bool,
+    #[serde(default)]
+    pub logs: Vec<String>,
+}
+
+pub fn read_log_file(path: &Path) -> Result<Vec<RecordedTransaction>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|(number, line)| {
+            serde_json::from_str(&line?)
+                .with_context(|| format!("{}:{}: invalid transaction", path.display(), number + 1))
+        })
+        .collect()
+}
+
+pub fn append_log_file(path: &Path, transactions: &[RecordedTransaction]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    for transaction in transactions {
+        writeln!(file, "{}", serde_json::to_string(transaction)?)?;
+    }
+    Ok(())
+}
+
+pub struct RpcSource {
+    rpc: RpcClient,
+}
+
+impl RpcSource {
+    pub fn new(url: String) -> Self {
+        Self {
+            rpc: RpcClient::new(url),
+        }
+    }
+
+    /// The program's confirmed transactions after `until` (a signature),
+    /// oldest first. Without `until`, its whole history.
+    pub fn fetch_after(&self, until: Option<&str>) -> Result<Vec<RecordedTransaction>> {
+        let until: Option<Signature> = until
+            .map(|signature| signature.parse())
+            .transpose()
+            .with_context(|| format!("invalid cursor signature {until:?}"))?;
+        let mut signatures: Vec<SignatureInfo> = Vec::new();
+        let mut before: Option<Signature> = None;
+        loop {
+            let page = self.rpc.signatures_for_address(
+                &ID,
+                before.as_ref(),
+                until.as_ref(),
+                SIGNATURE_PAGE,
+            )?;
+            before = page.last().map(|entry| entry.signature);
+            let complete = page.len() < SIGNATURE_PAGE;
+            signatures.extend(page);
+            if complete {
+                break;
+            }
+        }
+
+        signatures
+            .iter()
+            .rev()
+            .map(|entry| {
+                let mut transaction = RecordedTransaction {
+                    slot: entry.slot,
+                    signature: entry.signature.to_string(),
+                    block_time: entry.block_time,
+                    failed: entry.failed,
+                    logs: Vec::new(),
+                };
+                if !transaction.failed {
+                    transaction.logs = self
+                        .rpc
+                        .transaction(&entry.signature)?
+                        .map(|status| status.logs)
+                        .unwrap_or_default();
+                }
+                Ok(transaction)
+            })
+            .collect()
+    }
+}
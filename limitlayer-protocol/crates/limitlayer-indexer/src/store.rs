@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use limitlayer_client::events::parse_logs;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+
+use crate::{
+    schema::{self, REFRESH_VIEWS},
+    source::RecordedTransaction,
+};
+
+/// Last indexed transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub slot: u64,
+    pub signature: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    pub transactions: usize,
+    pub events: usize,
+    /// Already indexed, e.g. when a log file is replayed twice
+    pub duplicates: usize,
+    /// Logs that could not be decoded; recorded in `transactions.decode_error`
+    pub undecodable: usize,
+}
+
+/// One row of `key_usage_history`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsageDay {
+    pub day: Option<String>,
+    pub recorded_units: u64,
+    pub duplicate_requests: u64,
+    pub checkpoints_applied: u64,
+    pub applied_usage: u64,
+    pub applied_cost: u64,
+    pub billed_usage: u64,
+    pub billed_cost: u64,
+}
+
+/// One row of `enforcement_timeline`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnforcementChange {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub signature: String,
+    pub event: String,
+    pub status: String,
+    pub detail: Option<String>,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(&schema::create_tables())?;
+        Ok(Self { conn })
+    }
+
+    /// For ad-hoc queries against the event tables and views.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn cursor(&self) -> Result<Option<Cursor>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT slot, signature FROM cursor WHERE id = 0",
+                [],
+                |row| {
+                    Ok(Cursor {
+                        slot: row.get::<_, i64>(0)? as u64,
+                        signature: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Indexes transactions given oldest first, then refreshes the views.
+    /// Everything is written in one database transaction, so an interrupted
+    /// run leaves the cursor where the data ends.
+    pub fn ingest(&mut self, transactions: &[RecordedTransaction]) -> Result<IngestStats> {
+        let mut stats = IngestStats::default();
+        let tx = self.conn.transaction()?;
+
+        for transaction in transactions {
+            let (events, decode_error) = match transaction.failed {
+                true => (Vec::new(), None),
+                false => match parse_logs(&transaction.logs) {
+                    Ok(events) => (events, None),
+                    Err(error) => (Vec::new(), Some(error.to_string())),
+                },
+            };
+
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO transactions
+                 (signature, slot, block_time, failed, event_count, decode_error)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    transaction.signature,
+                    transaction.slot as i64,
+                    transaction.block_time,
+                    transaction.failed,
+                    events.len() as i64,
+                    decode_error,
+                ],
+            )?;
+            if inserted == 0 {
+                stats.duplicates += 1;
+                continue;
+            }
+            stats.transactions += 1;
+            stats.undecodable += decode_error.is_some() as usize;
+
+            for (index, event) in events.iter().enumerate() {
+                let (table, columns) = schema::event_row(event);
+                let names: String = columns
+                    .iter()
+                    .map(|(name, _)| format!(", {name}"))
+                    .collect();
+                let placeholders = ", ?".repeat(columns.len());
+                tx.execute(
+                    "INSERT INTO events (signature, event_index, slot, block_time, name, event_table)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        transaction.signature,
+                        index as i64,
+                        transaction.slot as i64,
+                        transaction.block_time,
+                        event.name(),
+                        table,
+                    ],
+                )?;
+                tx.execute(
+                    &format!(
+                        "INSERT INTO {table} (signature, event_index, slot, block_time{names})
+                         VALUES (?, ?, ?, ?{placeholders})"
+                    ),
+                    params_from_iter(
+                        [
+                            transaction.signature.clone().into(),
+                            (index as i64).into(),
+                            (transaction.slot as i64).into(),
+                            transaction.block_time.into(),
+                        ]
+                        .into_iter()
+                        .chain(columns.into_iter().map(|(_, value)| value)),
+                    ),
+                )?;
+            }
+            stats.events += events.len();
+
+            tx.execute(
+                "INSERT INTO cursor (id, slot, signature) VALUES (0, ?1, ?2)
+                 ON CONFLICT (id) DO UPDATE SET slot = excluded.slot, signature = excluded.signature
+                 WHERE excluded.slot >= cursor.slot",
+                params![transaction.slot as i64, transaction.signature],
+            )?;
+        }
+
+        if stats.transactions > 0 {
+            tx.execute_batch(REFRESH_VIEWS)?;
+        }
+        tx.commit()?;
+        Ok(stats)
+    }
+
+    /// Per-day usage of a key, oldest first.
+    pub fn usage_history(&self, api_key: &Pubkey) -> Result<Vec<UsageDay>> {
+        let mut statement = self.conn.prepare(
+            "SELECT day, recorded_units, duplicate_requests, checkpoints_applied,
+                    applied_usage, applied_cost, billed_usage, billed_cost
+             FROM key_usage_history WHERE api_key = ?1 ORDER BY day",
+        )?;
+        let rows = statement.query_map([api_key.to_string()], |row| {
+            Ok(UsageDay {
+                day: row.get(0)?,
+                recorded_units: row.get::<_, i64>(1)? as u64,
+                duplicate_requests: row.get::<_, i64>(2)? as u64,
+                checkpoints_applied: row.get::<_, i64>(3)? as u64,
+                applied_usage: row.get::<_, i64>(4)? as u64,
+                applied_cost: row.get::<_, i64>(5)? as u64,
+                billed_usage: row.get::<_, i64>(6)? as u64,
+                billed_cost: row.get::<_, i64>(7)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Status changes of a key, in the order they happened.
+    pub fn enforcement_timeline(&self, api_key: &Pubkey) -> Result<Vec<EnforcementChange>> {
+        let mut statement = self.conn.prepare(
+            "SELECT e.slot, e.block_time, e.signature, e.event, e.status, e.detail
+             FROM enforcement_timeline e JOIN transactions t USING (signature)
+             WHERE e.api_key = ?1
+             ORDER BY e.slot, t.rowid, e.event_index",
+        )?;
+        let rows = statement.query_map([api_key.to_string()], |row| {
+            Ok(EnforcementChange {
+                slot: row.get::<_, i64>(0)? as u64,
+                block_time: row.get(1)?,
+                signature: row.get(2)?,
+                event: row.get(3)?,
+                status: row.get(4)?,
+                detail: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
@@ -0,0 +1,207 @@
+//! Indexes `fixtures/lifecycle.jsonl`, recorded program logs covering key
+//! creation, realtime usage, enforcement, a checkpoint and its
+//! finalization, a failed transaction and a CPI that logs data of its own.
+
+use std::path::Path;
+
+use limitlayer_client::{pda, ID};
+use limitlayer_indexer::{
+    read_log_file, EnforcementChange, IngestStats, RecordedTransaction, Store, UsageDay,
+};
+
+fn fixture() -> Vec<RecordedTransaction> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lifecycle.jsonl");
+    read_log_file(&path).unwrap()
+}
+
+fn count(store: &Store, sql: &str) -> i64 {
+    store
+        .connection()
+        .query_row(sql, [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn indexes_events_into_typed_tables() {
+    let transactions = fixture();
+    let mut store = Store::open_in_memory().unwrap();
+
+    let stats = store.ingest(&transactions).unwrap();
+    assert_eq!(
+        stats,
+        IngestStats {
+            transactions: 11,
+            events: 11,
+            duplicates: 0,
+            undecodable: 0,
+        }
+    );
+
+    let key = pda::api_key(0).to_string();
+    let recorded: i64 = store
+        .connection()
+        .query_row(
+            "SELECT SUM(amount) FROM usage_recorded_realtime WHERE api_key = ?1",
+            [&key],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(recorded, 5);
+
+    let root: String = store
+        .connection()
+        .query_row(
+            "SELECT request_log_root FROM usage_checkpoint_applied",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(root, "ab".repeat(32));
+
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM events"), 11);
+    assert_eq!(
+        count(&store, "SELECT COUNT(*) FROM transactions WHERE failed = 1"),
+        1
+    );
+
+    let last = transactions.last().unwrap();
+    let cursor = store.cursor().unwrap().unwrap();
+    assert_eq!(
+        (cursor.slot, cursor.signature.as_str()),
+        (last.slot, last.signature.as_str())
+    );
+}
+
+#[test]
+fn builds_usage_history_and_enforcement_timeline() {
+    let mut store = Store::open_in_memory().unwrap();
+    store.ingest(&fixture()).unwrap();
+    let key = pda::api_key(0);
+
+    let day = |day: &str| UsageDay {
+        day: Some(day.to_string()),
+        recorded_units: 0,
+        duplicate_requests: 0,
+        checkpoints_applied: 0,
+        applied_usage: 0,
+        applied_cost: 0,
+        billed_usage: 0,
+        billed_cost: 0,
+    };
+    assert_eq!(
+        store.usage_history(&key).unwrap(),
+        vec![
+            UsageDay {
+                recorded_units: 5,
+                duplicate_requests: 1,
+                ..day("2026-01-05")
+            },
+            UsageDay {
+                checkpoints_applied: 1,
+                applied_usage: 5,
+                applied_cost: 10,
+                ..day("2026-01-06")
+            },
+            UsageDay {
+                billed_usage: 5,
+                billed_cost: 10,
+                ..day("2026-01-07")
+            },
+        ]
+    );
+    assert_eq!(
+        store.usage_history(&pda::api_key(1)).unwrap()[0].recorded_units,
+        9
+    );
+
+    let timeline: Vec<(String, String, Option<String>)> = store
+        .enforcement_timeline(&key)
+        .unwrap()
+        .into_iter()
+        .map(
+            |EnforcementChange {
+                 event,
+                 status,
+                 detail,
+                 ..
+             }| (event, status, detail),
+        )
+        .collect();
+    let change = |event: &str, status: &str, detail: Option<&str>| {
+        (
+            event.to_string(),
+            status.to_string(),
+            detail.map(str::to_string),
+        )
+    };
+    assert_eq!(
+        timeline,
+        vec![
+            change("EnforcementEvaluated", "throttled", Some("window usage 60")),
+            change(
+                "QuotaExhausted",
+                "blocked",
+                Some("daily quota 5 used 5, resets at 1767744000")
+            ),
+            change("KeyManuallyBlocked", "blocked", Some("manual")),
+            change("KeyManuallyUnblocked", "active", Some("manual")),
+        ]
+    );
+}
+
+#[test]
+fn resumes_and_skips_indexed_transactions() {
+    let transactions = fixture();
+    let dir = std::env::temp_dir().join(format!("limitlayer-indexer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("index.db");
+    let _ = std::fs::remove_file(&db);
+
+    Store::open(&db)
+        .unwrap()
+        .ingest(&transactions[..5])
+        .unwrap();
+
+    let mut store = Store::open(&db).unwrap();
+    assert_eq!(
+        store.cursor().unwrap().unwrap().signature,
+        transactions[4].signature
+    );
+    let stats = store.ingest(&transactions).unwrap();
+    assert_eq!((stats.transactions, stats.duplicates), (6, 5));
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM events"), 11);
+
+    // Replaying older transactions does not move the cursor back
+    store.ingest(&transactions[..2]).unwrap();
+    assert_eq!(store.cursor().unwrap().unwrap().slot, 3001);
+
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn records_undecodable_logs_and_moves_on() {
+    let mut store = Store::open_in_memory().unwrap();
+    let garbled = RecordedTransaction {
+        slot: 7,
+        signature: "garbled".to_string(),
+        block_time: None,
+        failed: false,
+        logs: vec![
+            format!("Program {ID} invoke [1]"),
+            "Program data: AAAAAAAAAAAAAAAA".to_string(),
+            format!("Program {ID} success"),
+        ],
+    };
+
+    let stats = store.ingest(&[garbled]).unwrap();
+    assert_eq!((stats.transactions, stats.undecodable), (1, 1));
+    assert_eq!(
+        count(
+            &store,
+            "SELECT COUNT(*) FROM transactions WHERE decode_error IS NOT NULL"
+        ),
+        1
+    );
+    assert_eq!(store.cursor().unwrap().unwrap().slot, 7);
+}
@@ -0,0 +1,158 @@
+//! Notifier configuration, a TOML file:
+//!
+//! ```toml
+//! [retry]
+//! max_attempts = 8
+//! initial_backoff_seconds = 10
+//! max_backoff_seconds = 3600
+//!
+//! [[endpoint]]
+//! service = "8kZk...service PDA"
+//! url = "https://hooks.example.com/limitlayer"
+//! secret_env = "SEARCH_WEBHOOK_SECRET"
+//! # Optional; every notification type by default
+//! events = ["EnforcementEvaluated", "KeyManuallyBlocked"]
+//! ```
+
+use std::{fs, path::Path};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::notification::EVENTS;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Attempts before a delivery moves to the dead-letter store
+    pub max_attempts: u32,
+    /// Wait after the first failure; doubled after each further one
+    pub initial_backoff_seconds: u64,
+    pub max_backoff_seconds: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_backoff_seconds: 10,
+            max_backoff_seconds: 3_600,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Seconds to wait after `attempts` failed attempts.
+    pub fn backoff(&self, attempts: u32) -> u64 {
+        let doublings = attempts.saturating_sub(1).min(32);
+        self.initial_backoff_seconds
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff_seconds)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEndpoint {
+    service: String,
+    url: String,
+    secret: Option<String>,
+    secret_env: Option<String>,
+    events: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    retry: RetryPolicy,
+    #[serde(default, rename = "endpoint")]
+    endpoints: Vec<RawEndpoint>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    pub service: Pubkey,
+    pub url: String,
+    pub secret: Vec<u8>,
+    /// Notification types delivered here; all when `None`
+    pub events: Option<Vec<String>>,
+}
+
+impl Endpoint {
+    pub fn wants(&self, service: &Pubkey, event: &str) -> bool {
+        self.service == *service
+            && self
+                .events
+                .as_ref()
+                .is_none_or(|events| events.iter().any(|e| e == event))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub retry: RetryPolicy,
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    /// Parses a config, reading `secret_env` secrets from the environment.
+    pub fn parse(text: &str) -> Result<Self> {
+        let raw: RawConfig = toml::from_str(text)?;
+        if raw.retry.max_attempts == 0 {
+            bail!("retry.max_attempts must be at least 1");
+        }
+
+        let endpoints = raw
+            .endpoints
+            .into_iter()
+            .map(|endpoint| {
+                let service = endpoint
+                    .service
+                    .parse()
+                    .with_context(|| format!("invalid service {}", endpoint.service))?;
+                let secret = match (endpoint.secret, endpoint.secret_env) {
+                    (Some(secret), None) => secret,
+                    (None, Some(name)) => std::env::var(&name)
+                        .with_context(|| format!("secret_env {name} is not set"))?,
+                    _ => bail!(
+                        "endpoint {} needs exactly one of secret and secret_env",
+                        endpoint.url
+                    ),
+                };
+                if secret.is_empty() {
+                    bail!("endpoint {} has an empty secret", endpoint.url);
+                }
+                if let Some(unknown) = endpoint
+                    .events
+                    .iter()
+                    .flatten()
+                    .find(|event| !EVENTS.contains(&event.as_str()))
+                {
+                    bail!(
+                        "endpoint {} subscribes to unknown event {unknown}",
+                        endpoint.url
+                    );
+                }
+                Ok(Endpoint {
+                    service,
+                    url: endpoint.url,
+                    secret: secret.into_bytes(),
+                    events: endpoint.events,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            retry: raw.retry,
+            endpoints,
+        })
+    }
+}
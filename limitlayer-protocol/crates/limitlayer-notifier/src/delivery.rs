@@ -0,0 +1,92 @@
+//! Sending queued deliveries, with retries and backoff.
+
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    signature::{sign, SIGNATURE_HEADER},
+    store::{Delivery, Store},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryStats {
+    pub delivered: usize,
+    /// Failed, and scheduled for another attempt
+    pub retrying: usize,
+    /// Failed for the last time, now in the dead-letter store
+    pub dead: usize,
+}
+
+pub struct Deliverer {
+    agent: ureq::Agent,
+}
+
+impl Default for Deliverer {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+impl Deliverer {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+
+    /// Attempts every delivery due at `now` once. Anything but a 2xx
+    /// response is a failure.
+    pub fn deliver_due(&self, store: &Store, config: &Config, now: i64) -> Result<DeliveryStats> {
+        let mut stats = DeliveryStats::default();
+        for delivery in store.due(now)? {
+            let outcome = match config
+                .endpoints
+                .iter()
+                .find(|endpoint| endpoint.url == delivery.url)
+            {
+                Some(endpoint) => self.send(&delivery, &endpoint.secret, now),
+                None => Err("endpoint is no longer configured".to_string()),
+            };
+
+            match outcome {
+                Ok(()) => {
+                    store.mark_delivered(delivery.id, now)?;
+                    stats.delivered += 1;
+                }
+                Err(error) => {
+                    let attempts = delivery.attempts + 1;
+                    let retry_at = (attempts < config.retry.max_attempts)
+                        .then(|| now + config.retry.backoff(attempts) as i64);
+                    store.mark_failed(delivery.id, &error, retry_at)?;
+                    match retry_at {
+                        Some(_) => stats.retrying += 1,
+                        None => stats.dead += 1,
+                    }
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    fn send(&self, delivery: &Delivery, secret: &[u8], now: i64) -> Result<(), String> {
+        let response = self
+            .agent
+            .post(&delivery.url)
+            .set("Content-Type", "application/json")
+            .set("X-LimitLayer-Event", &delivery.event)
+            .set("X-LimitLayer-Delivery", &delivery.notification_id)
+            .set(
+                SIGNATURE_HEADER,
+                &sign(secret, now, delivery.body.as_bytes()),
+            )
+            .send_string(&delivery.body);
+        match response {
+            Ok(response) if (200..300).contains(&response.status()) => Ok(()),
+            Ok(response) => Err(format!("HTTP {}", response.status())),
+            Err(ureq::Error::Status(status, _)) => Err(format!("HTTP {status}")),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Delivers webhooks for LimitLayer enforcement and key lifecycle events.
+//!
+//! Transactions are read with the indexer's RPC source. Key lifecycle
+//! events among them ([`notification`]) are queued in SQLite for every
+//! endpoint configured for the key's service ([`config`], [`store`]).
+//! Deliveries are signed with the endpoint's secret ([`signature`]) and
+//! retried with exponential backoff; those that run out of attempts are
+//! kept as dead letters until requeued ([`delivery`]).
+
+pub mod config;
+pub mod delivery;
+pub mod notification;
+pub mod signature;
+pub mod store;
+
+pub use config::{Config, Endpoint, RetryPolicy};
+pub use delivery::{Deliverer, DeliveryStats};
+pub use notification::Notification;
+pub use store::{Delivery, DeliveryState, IngestStats, Store};
@@ -0,0 +1,167 @@
+//! `limitlayer-notifier`: follows the program on an RPC node and delivers
+//! webhooks for key lifecycle events.
+
+use std::{
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use limitlayer_client::{program::ApiKeyAccount, rpc::RpcClient, ID};
+use limitlayer_indexer::{Cursor, RpcSource};
+use limitlayer_notifier::{Config, Deliverer, DeliveryState, IngestStats, Store};
+use serde_json::Value;
+
+#[derive(Parser)]
+#[command(
+    name = "limitlayer-notifier",
+    version,
+    about = "Deliver webhooks for LimitLayer key lifecycle events"
+)]
+struct Cli {
+    /// SQLite database holding the cursor and delivery queue, created if
+    /// missing
+    #[arg(long, env = "LIMITLAYER_NOTIFIER_DB", default_value = "notifier.db")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Follow the program and deliver notifications
+    Run {
+        #[arg(long, short = 'c', env = "LIMITLAYER_NOTIFIER_CONFIG")]
+        config: PathBuf,
+        #[arg(
+            long,
+            short = 'u',
+            env = "LIMITLAYER_RPC_URL",
+            default_value = "http://127.0.0.1:8899"
+        )]
+        url: String,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 5)]
+        poll_interval: u64,
+        /// On the first run, notify the program's whole history instead of
+        /// starting at its latest transaction
+        #[arg(long)]
+        replay_history: bool,
+        /// Process what is available and exit; deliveries waiting on a
+        /// backoff stay queued
+        #[arg(long)]
+        once: bool,
+    },
+    /// List deliveries that ran out of attempts
+    DeadLetters,
+    /// Requeue dead letters
+    Retry {
+        /// Delivery ids, as listed by dead-letters
+        #[arg(required_unless_present = "all")]
+        ids: Vec<i64>,
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
+    },
+}
+
+fn now() -> Result<i64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64)
+}
+
+/// The service of `api_key`, read from its account; `None` if the key is
+/// closed, or not created yet at this commitment.
+fn lookup_service(rpc: &RpcClient, api_key: &Pubkey) -> Result<Option<Pubkey>> {
+    let key: Option<ApiKeyAccount> = rpc.account(api_key)?;
+    Ok(key.map(|key| key.service))
+}
+
+fn report(stats: &IngestStats) {
+    if stats.notifications > 0 || stats.unattributed > 0 {
+        eprintln!(
+            "queued {} notifications as {} deliveries ({} without a known service)",
+            stats.notifications, stats.deliveries, stats.unattributed
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let Cli { db, command } = Cli::parse();
+    let mut store = Store::open(&db)?;
+
+    match command {
+        Command::Run {
+            config,
+            url,
+            poll_interval,
+            replay_history,
+            once,
+        } => {
+            let config = Config::load(&config)?;
+            let source = RpcSource::new(url.clone());
+            let rpc = RpcClient::new(url);
+            if !replay_history && store.cursor()?.is_none() {
+                let latest = rpc.signatures_for_address(&ID, None, None, 1)?;
+                if let Some(latest) = latest.first() {
+                    store.start_at(&Cursor {
+                        slot: latest.slot,
+                        signature: latest.signature.to_string(),
+                    })?;
+                }
+            }
+            let deliverer = Deliverer::default();
+            loop {
+                let cursor = store.cursor()?;
+                let ingested = source
+                    .fetch_after(cursor.as_ref().map(|c| c.signature.as_str()))
+                    .and_then(|transactions| {
+                        store.ingest(&transactions, &config.endpoints, now()?, |api_key| {
+                            lookup_service(&rpc, api_key)
+                        })
+                    });
+                match ingested {
+                    Ok(stats) => report(&stats),
+                    // Transient RPC errors are retried on the next poll
+                    Err(error) if !once => eprintln!("poll failed: {error:#}"),
+                    Err(error) => return Err(error),
+                }
+
+                let stats = deliverer.deliver_due(&store, &config, now()?)?;
+                if stats.retrying > 0 || stats.dead > 0 {
+                    eprintln!(
+                        "delivered {}, {} failed and will be retried, {} moved to dead letters",
+                        stats.delivered, stats.retrying, stats.dead
+                    );
+                }
+                if once {
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_secs(poll_interval));
+            }
+        }
+        Command::DeadLetters => {
+            for delivery in store.deliveries(DeliveryState::Dead)? {
+                let body: Value = serde_json::from_str(&delivery.body)?;
+                let status = body["data"]["status"].as_str().unwrap_or_default();
+                println!(
+                    "{}\t{}\t{} {}\t{}\t{} attempts: {}",
+                    delivery.id,
+                    delivery.url,
+                    delivery.event,
+                    status,
+                    body["api_key"].as_str().unwrap_or_default(),
+                    delivery.attempts,
+                    delivery.last_error.unwrap_or_default(),
+                );
+            }
+        }
+        Command::Retry { ids, all: _ } => {
+            let requeued = store.requeue(&ids, now()?)?;
+            eprintln!("requeued {requeued} deliveries");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! Which program events become notifications, and what they carry.
+
+use anchor_lang::prelude::Pubkey;
+use limitlayer_client::events::LimitLayerEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Event names endpoints can subscribe to.
+pub const EVENTS: &[&str] = &[
+    "ApiKeyCreated",
+    "PolicyAttachedToKey",
+    "ApiKeyStatusChanged",
+    "EnforcementEvaluated",
+    "QuotaExhausted",
+    "KeyManuallyBlocked",
+    "KeyManuallyUnblocked",
+    "ApiKeyRevoked",
+];
+
+/// `ApiKeyStatus` as events carry it.
+pub fn status_label(status: u8) -> &'static str {
+    match status {
+        0 => "active",
+        1 => "throttled",
+        2 => "blocked",
+        _ => "revoked",
+    }
+}
+
+const ACTIVE: u8 = 0;
+const BLOCKED: u8 = 2;
+const REVOKED: u8 = 3;
+
+/// The webhook body.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    /// `<signature>:<event index>`; the same for every redelivery
+    pub id: String,
+    /// Name of the event, one of [`EVENTS`]
+    #[serde(rename = "type")]
+    pub event: String,
+    pub service: String,
+    pub api_key: String,
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// The event's fields, statuses as names
+    pub data: Value,
+}
+
+/// A key lifecycle event, before the key's service is known.
+pub struct KeyEvent {
+    pub api_key: Pubkey,
+    /// Named by the event itself
+    pub service: Option<Pubkey>,
+    /// Status the key has after the event
+    pub status: Option<u8>,
+    /// Only notified when it changes the key's status
+    pub on_change_only: bool,
+    pub data: Value,
+}
+
+/// The key lifecycle event `event` is, if any.
+pub fn key_event(event: &LimitLayerEvent) -> Option<KeyEvent> {
+    let key_event = |api_key, service, status, data| KeyEvent {
+        api_key,
+        service,
+        status,
+        on_change_only: false,
+        data,
+    };
+    Some(match event {
+        LimitLayerEvent::ApiKeyCreated(e) => key_event(
+            e.api_key,
+            Some(e.service),
+            Some(ACTIVE),
+            json!({ "owner": e.owner.to_string(), "policy": e.policy.to_string() }),
+        ),
+        LimitLayerEvent::PolicyAttachedToKey(e) => key_event(
+            e.api_key,
+            Some(e.service),
+            None,
+            json!({ "policy": e.policy.to_string() }),
+        ),
+        LimitLayerEvent::ApiKeyStatusChanged(e) => key_event(
+            e.api_key,
+            Some(e.service),
+            Some(e.new_status),
+            json!({ "status": status_label(e.new_status) }),
+        ),
+        LimitLayerEvent::EnforcementEvaluated(e) => KeyEvent {
+            on_change_only: true,
+            ..key_event(
+                e.api_key,
+                None,
+                Some(e.new_status),
+                json!({ "status": status_label(e.new_status), "window_usage": e.usage }),
+            )
+        },
+        LimitLayerEvent::QuotaExhausted(e) => key_event(
+            e.api_key,
+            None,
+            Some(BLOCKED),
+            json!({
+                "status": status_label(BLOCKED),
+                "policy": e.policy.to_string(),
+                "period": if e.period == 0 { "daily" } else { "monthly" },
+                "usage": e.usage,
+                "quota": e.quota,
+                "resets_at": e.resets_at,
+            }),
+        ),
+        LimitLayerEvent::KeyManuallyBlocked(e) => key_event(
+            e.api_key,
+            Some(e.service),
+            Some(BLOCKED),
+            json!({ "status": status_label(BLOCKED) }),
+        ),
+        LimitLayerEvent::KeyManuallyUnblocked(e) => key_event(
+            e.api_key,
+            Some(e.service),
+            Some(ACTIVE),
+            json!({ "status": status_label(ACTIVE) }),
+        ),
+        LimitLayerEvent::ApiKeyRevoked(e) => key_event(
+            e.api_key,
+            Some(e.service),
+            Some(REVOKED),
+            json!({ "status": status_label(REVOKED) }),
+        ),
+        _ => return None,
+    })
+}
@@ -0,0 +1,335 @@
+//! SQLite state: the cursor, what is known about each key, and the
+//! delivery queue. Deliveries that run out of attempts stay in the queue as
+//! `dead` until requeued; that is the dead-letter store.
+
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use limitlayer_client::events::parse_logs;
+use limitlayer_indexer::{Cursor, RecordedTransaction};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use crate::{
+    config::Endpoint,
+    notification::{key_event, Notification},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    slot INTEGER NOT NULL,
+    signature TEXT NOT NULL
+);
+
+-- Service and last known status of each key seen in an event
+CREATE TABLE IF NOT EXISTS keys (
+    api_key TEXT PRIMARY KEY,
+    service TEXT,
+    status INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS deliveries (
+    id INTEGER PRIMARY KEY,
+    notification_id TEXT NOT NULL,
+    event TEXT NOT NULL,
+    service TEXT NOT NULL,
+    url TEXT NOT NULL,
+    body TEXT NOT NULL,
+    state TEXT NOT NULL DEFAULT 'pending' CHECK (state IN ('pending', 'delivered', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at INTEGER NOT NULL,
+    last_error TEXT,
+    created_at INTEGER NOT NULL,
+    delivered_at INTEGER,
+    UNIQUE (notification_id, url)
+);
+
+CREATE INDEX IF NOT EXISTS deliveries_due ON deliveries (state, next_attempt_at);
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryState {
+    Pending,
+    Delivered,
+    /// Out of attempts
+    Dead,
+}
+
+impl DeliveryState {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Dead => "dead",
+        }
+    }
+
+    fn parse(state: &str) -> Self {
+        match state {
+            "delivered" => Self::Delivered,
+            "dead" => Self::Dead,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// One notification queued for one endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delivery {
+    pub id: i64,
+    pub notification_id: String,
+    pub event: String,
+    pub service: String,
+    pub url: String,
+    pub body: String,
+    pub state: DeliveryState,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    pub transactions: usize,
+    pub notifications: usize,
+    /// Notifications times the endpoints they are queued for
+    pub deliveries: usize,
+    /// Events of keys whose service could not be found, not notified
+    pub unattributed: usize,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+const DELIVERY_COLUMNS: &str = "id, notification_id, event, service, url, body, state, attempts,
+    next_attempt_at, last_error";
+
+fn delivery(row: &rusqlite::Row) -> rusqlite::Result<Delivery> {
+    Ok(Delivery {
+        id: row.get(0)?,
+        notification_id: row.get(1)?,
+        event: row.get(2)?,
+        service: row.get(3)?,
+        url: row.get(4)?,
+        body: row.get(5)?,
+        state: DeliveryState::parse(&row.get::<_, String>(6)?),
+        attempts: row.get(7)?,
+        next_attempt_at: row.get(8)?,
+        last_error: row.get(9)?,
+    })
+}
+
+/// Service and status stored for `api_key`.
+fn known_key(tx: &Transaction, api_key: &Pubkey) -> Result<(Option<Pubkey>, Option<u8>)> {
+    let row: Option<(Option<String>, Option<u8>)> = tx
+        .query_row(
+            "SELECT service, status FROM keys WHERE api_key = ?1",
+            [api_key.to_string()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let (service, status) = row.unwrap_or_default();
+    Ok((service.and_then(|service| service.parse().ok()), status))
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn cursor(&self) -> Result<Option<Cursor>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT slot, signature FROM cursor WHERE id = 0",
+                [],
+                |row| {
+                    Ok(Cursor {
+                        slot: row.get::<_, i64>(0)? as u64,
+                        signature: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Sets the cursor, so following starts after `cursor`. Does nothing if
+    /// one is set already.
+    pub fn start_at(&self, cursor: &Cursor) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO cursor (id, slot, signature) VALUES (0, ?1, ?2)",
+            params![cursor.slot as i64, cursor.signature],
+        )?;
+        Ok(())
+    }
+
+    /// Queues notifications for the key lifecycle events of `transactions`,
+    /// given oldest first, for every endpoint that wants them. Keys that no
+    /// event has named the service of are looked up with `resolve_service`.
+    /// Everything is written in one database transaction, so an interrupted
+    /// run leaves the cursor where the queue ends.
+    pub fn ingest(
+        &mut self,
+        transactions: &[RecordedTransaction],
+        endpoints: &[Endpoint],
+        now: i64,
+        mut resolve_service: impl FnMut(&Pubkey) -> Result<Option<Pubkey>>,
+    ) -> Result<IngestStats> {
+        let mut stats = IngestStats::default();
+        let tx = self.conn.transaction()?;
+
+        for transaction in transactions {
+            stats.transactions += 1;
+            // Failed transactions emitted nothing; undecodable logs are the
+            // indexer's concern, and the cursor still moves past them
+            let events = match transaction.failed {
+                true => Vec::new(),
+                false => parse_logs(&transaction.logs).unwrap_or_default(),
+            };
+
+            for (index, event) in events.iter().enumerate() {
+                let Some(key_event) = key_event(event) else {
+                    continue;
+                };
+                let (known_service, known_status) = known_key(&tx, &key_event.api_key)?;
+                let service = match key_event.service.or(known_service) {
+                    Some(service) => Some(service),
+                    None => resolve_service(&key_event.api_key)?,
+                };
+                let status = key_event.status.or(known_status);
+                tx.execute(
+                    "INSERT INTO keys (api_key, service, status) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (api_key) DO UPDATE SET
+                         service = COALESCE(excluded.service, keys.service),
+                         status = COALESCE(excluded.status, keys.status)",
+                    params![
+                        key_event.api_key.to_string(),
+                        service.map(|service| service.to_string()),
+                        status,
+                    ],
+                )?;
+
+                // A key never seen before was presumably active
+                if key_event.on_change_only && status == Some(known_status.unwrap_or(0)) {
+                    continue;
+                }
+                let Some(service) = service else {
+                    stats.unattributed += 1;
+                    continue;
+                };
+
+                let notification = Notification {
+                    id: format!("{}:{index}", transaction.signature),
+                    event: event.name().to_string(),
+                    service: service.to_string(),
+                    api_key: key_event.api_key.to_string(),
+                    signature: transaction.signature.clone(),
+                    slot: transaction.slot,
+                    block_time: transaction.block_time,
+                    data: key_event.data,
+                };
+                let body = serde_json::to_string(&notification)?;
+                stats.notifications += 1;
+                for endpoint in endpoints
+                    .iter()
+                    .filter(|endpoint| endpoint.wants(&service, &notification.event))
+                {
+                    stats.deliveries += tx.execute(
+                        "INSERT OR IGNORE INTO deliveries
+                         (notification_id, event, service, url, body, next_attempt_at, created_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                        params![
+                            notification.id,
+                            notification.event,
+                            notification.service,
+                            endpoint.url,
+                            body,
+                            now,
+                        ],
+                    )?;
+                }
+            }
+
+            tx.execute(
+                "INSERT INTO cursor (id, slot, signature) VALUES (0, ?1, ?2)
+                 ON CONFLICT (id) DO UPDATE SET slot = excluded.slot, signature = excluded.signature
+                 WHERE excluded.slot >= cursor.slot",
+                params![transaction.slot as i64, transaction.signature],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(stats)
+    }
+
+    /// Pending deliveries due at `now`, oldest first.
+    pub fn due(&self, now: i64) -> Result<Vec<Delivery>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {DELIVERY_COLUMNS} FROM deliveries
+             WHERE state = 'pending' AND next_attempt_at <= ?1 ORDER BY id"
+        ))?;
+        let rows = statement.query_map([now], delivery)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Deliveries in `state`, oldest first.
+    pub fn deliveries(&self, state: DeliveryState) -> Result<Vec<Delivery>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {DELIVERY_COLUMNS} FROM deliveries WHERE state = ?1 ORDER BY id"
+        ))?;
+        let rows = statement.query_map([state.as_str()], delivery)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn mark_delivered(&self, id: i64, now: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE deliveries SET state = 'delivered', attempts = attempts + 1,
+                 delivered_at = ?2, last_error = NULL
+             WHERE id = ?1",
+            params![id, now],
+        )?;
+        Ok(())
+    }
+
+    /// Records a failed attempt. Retried at `retry_at`, or moved to the
+    /// dead-letter store when `None`.
+    pub fn mark_failed(&self, id: i64, error: &str, retry_at: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE deliveries SET attempts = attempts + 1, last_error = ?2,
+                 state = CASE WHEN ?3 IS NULL THEN 'dead' ELSE 'pending' END,
+                 next_attempt_at = COALESCE(?3, next_attempt_at)
+             WHERE id = ?1",
+            params![id, error, retry_at],
+        )?;
+        Ok(())
+    }
+
+    /// Moves dead letters back into the queue with fresh attempts, due at
+    /// `now`: those in `ids`, or all of them when `ids` is empty. Returns
+    /// how many were requeued.
+    pub fn requeue(&self, ids: &[i64], now: i64) -> Result<usize> {
+        let requeue = "UPDATE deliveries SET state = 'pending', attempts = 0, next_attempt_at = ?1
+                       WHERE state = 'dead'";
+        if ids.is_empty() {
+            return Ok(self.conn.execute(requeue, [now])?);
+        }
+        let mut requeued = 0;
+        for id in ids {
+            requeued += self
+                .conn
+                .execute(&format!("{requeue} AND id = ?2"), params![now, id])?;
+        }
+        Ok(requeued)
+    }
+}
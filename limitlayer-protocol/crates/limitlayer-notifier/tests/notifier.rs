@@ -0,0 +1,361 @@
+//! Queues notifications from recorded transactions and delivers them to a
+//! local HTTP receiver.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use anchor_lang::{prelude::Pubkey, Event};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use limitlayer_client::{program::events::*, ID};
+use limitlayer_indexer::RecordedTransaction;
+use limitlayer_notifier::{
+    signature::{verify, SIGNATURE_HEADER},
+    Config, Deliverer, DeliveryState, DeliveryStats, Endpoint, IngestStats, RetryPolicy, Store,
+};
+use serde_json::Value;
+
+const NOW: i64 = 1_767_607_200;
+
+/// A request the receiver got, header names lowercased.
+struct Received {
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Records requests and answers them with `status`.
+struct Receiver {
+    url: String,
+    status: Arc<AtomicU16>,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl Receiver {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        let status = Arc::new(AtomicU16::new(200));
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let (answer, log) = (status.clone(), received.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                    }
+                }
+                let length = headers["content-length"].parse().unwrap();
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                log.lock().unwrap().push(Received { headers, body });
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    answer.load(Ordering::SeqCst)
+                )
+                .unwrap();
+            }
+        });
+        Self {
+            url,
+            status,
+            received,
+        }
+    }
+
+    fn answer(&self, status: u16) {
+        self.status.store(status, Ordering::SeqCst);
+    }
+
+    fn bodies(&self) -> Vec<Value> {
+        self.received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect()
+    }
+}
+
+fn transaction(slot: u64, event: &impl Event) -> RecordedTransaction {
+    RecordedTransaction {
+        slot,
+        signature: format!("signature-{slot}"),
+        block_time: Some(NOW),
+        failed: false,
+        logs: vec![
+            format!("Program {ID} invoke [1]"),
+            format!("Program data: {}", STANDARD.encode(event.data())),
+            format!("Program {ID} success"),
+        ],
+    }
+}
+
+fn endpoint(service: Pubkey, url: &str, events: Option<&[&str]>) -> Endpoint {
+    Endpoint {
+        service,
+        url: url.to_string(),
+        secret: b"whsec-test".to_vec(),
+        events: events.map(|events| events.iter().map(|e| e.to_string()).collect()),
+    }
+}
+
+fn no_lookup(api_key: &Pubkey) -> anyhow::Result<Option<Pubkey>> {
+    panic!("unexpected lookup of {api_key}")
+}
+
+#[test]
+fn delivers_signed_notifications_on_status_changes() {
+    let receiver = Receiver::start();
+    let (search, billing) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (key, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let config = Config {
+        retry: RetryPolicy::default(),
+        endpoints: vec![
+            endpoint(search, &receiver.url, None),
+            // Not subscribed to anything that happens here
+            endpoint(billing, &receiver.url, Some(&["ApiKeyRevoked"])),
+        ],
+    };
+
+    let evaluated = |api_key, new_status| EnforcementEvaluated {
+        api_key,
+        new_status,
+        usage: 120,
+    };
+    let transactions = [
+        transaction(
+            1,
+            &ApiKeyCreated {
+                api_key: key,
+                service: search,
+                owner: Pubkey::new_unique(),
+                policy: Pubkey::new_unique(),
+            },
+        ),
+        // Evaluations that leave the status as it was are not news
+        transaction(2, &evaluated(key, 0)),
+        transaction(3, &evaluated(key, 2)),
+        transaction(4, &evaluated(key, 2)),
+        transaction(
+            5,
+            &KeyManuallyUnblocked {
+                api_key: key,
+                service: search,
+            },
+        ),
+        // A key created before the notifier started, found by lookup
+        transaction(6, &evaluated(other, 2)),
+        transaction(
+            7,
+            &KeyManuallyBlocked {
+                api_key: other,
+                service: billing,
+            },
+        ),
+    ];
+
+    let mut store = Store::open_in_memory().unwrap();
+    let mut lookups = Vec::new();
+    let stats = store
+        .ingest(&transactions, &config.endpoints, NOW, |api_key| {
+            lookups.push(*api_key);
+            Ok(Some(search))
+        })
+        .unwrap();
+    assert_eq!(lookups, [other]);
+    assert_eq!(
+        stats,
+        IngestStats {
+            transactions: 7,
+            notifications: 5,
+            deliveries: 4,
+            unattributed: 0,
+        }
+    );
+    assert_eq!(store.cursor().unwrap().unwrap().signature, "signature-7");
+
+    let stats = Deliverer::default()
+        .deliver_due(&store, &config, NOW)
+        .unwrap();
+    assert_eq!(stats.delivered, 4);
+    assert!(store.due(NOW).unwrap().is_empty());
+
+    let bodies = receiver.bodies();
+    let summary: Vec<(&str, &str, &str)> = bodies
+        .iter()
+        .map(|body| {
+            (
+                body["type"].as_str().unwrap(),
+                body["api_key"].as_str().unwrap(),
+                body["data"]["status"].as_str().unwrap_or_default(),
+            )
+        })
+        .collect();
+    let (key, other) = (key.to_string(), other.to_string());
+    assert_eq!(
+        summary,
+        [
+            ("ApiKeyCreated", key.as_str(), ""),
+            ("EnforcementEvaluated", key.as_str(), "blocked"),
+            ("KeyManuallyUnblocked", key.as_str(), "active"),
+            ("EnforcementEvaluated", other.as_str(), "blocked"),
+        ]
+    );
+    assert_eq!(bodies[1]["id"], "signature-3:0");
+    assert_eq!(bodies[1]["service"], search.to_string());
+    assert_eq!(bodies[1]["data"]["window_usage"], 120);
+
+    for request in receiver.received.lock().unwrap().iter() {
+        let signature = &request.headers[&SIGNATURE_HEADER.to_ascii_lowercase()];
+        assert!(verify(b"whsec-test", signature, &request.body, NOW, 300));
+        assert!(!verify(b"other-secret", signature, &request.body, NOW, 300));
+        assert!(!verify(b"whsec-test", signature, b"{}", NOW, 300));
+        // Too old to be anything but a replay
+        assert!(!verify(
+            b"whsec-test",
+            signature,
+            &request.body,
+            NOW + 301,
+            300
+        ));
+        assert_eq!(request.headers["content-type"], "application/json");
+    }
+}
+
+#[test]
+fn retries_with_backoff_then_dead_letters() {
+    let receiver = Receiver::start();
+    receiver.answer(503);
+    let service = Pubkey::new_unique();
+    let config = Config {
+        retry: RetryPolicy {
+            max_attempts: 3,
+            initial_backoff_seconds: 10,
+            max_backoff_seconds: 3_600,
+        },
+        endpoints: vec![endpoint(service, &receiver.url, None)],
+    };
+    let blocked = transaction(
+        1,
+        &KeyManuallyBlocked {
+            api_key: Pubkey::new_unique(),
+            service,
+        },
+    );
+
+    let mut store = Store::open_in_memory().unwrap();
+    store
+        .ingest(
+            std::slice::from_ref(&blocked),
+            &config.endpoints,
+            NOW,
+            no_lookup,
+        )
+        .unwrap();
+    let deliverer = Deliverer::default();
+    let deliver = |store: &Store, now| deliverer.deliver_due(store, &config, now).unwrap();
+    let retrying = DeliveryStats {
+        retrying: 1,
+        ..DeliveryStats::default()
+    };
+
+    assert_eq!(deliver(&store, NOW), retrying);
+    // Not due again until the backoff has passed, which then doubles
+    assert_eq!(deliver(&store, NOW + 9), DeliveryStats::default());
+    assert_eq!(deliver(&store, NOW + 10), retrying);
+    assert_eq!(store.due(NOW + 29).unwrap(), []);
+    assert_eq!(
+        deliver(&store, NOW + 30),
+        DeliveryStats {
+            dead: 1,
+            ..DeliveryStats::default()
+        }
+    );
+    assert_eq!(receiver.bodies().len(), 3);
+    assert_eq!(deliver(&store, NOW + 10_000), DeliveryStats::default());
+
+    let dead = store.deliveries(DeliveryState::Dead).unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(
+        (dead[0].attempts, dead[0].last_error.as_deref()),
+        (3, Some("HTTP 503"))
+    );
+
+    // Seeing the transaction again does not queue it twice
+    let stats = store
+        .ingest(&[blocked], &config.endpoints, NOW, no_lookup)
+        .unwrap();
+    assert_eq!((stats.notifications, stats.deliveries), (1, 0));
+
+    receiver.answer(204);
+    assert_eq!(store.requeue(&[], NOW + 20_000).unwrap(), 1);
+    assert_eq!(deliver(&store, NOW + 20_000).delivered, 1);
+    assert!(store.deliveries(DeliveryState::Dead).unwrap().is_empty());
+    let bodies = receiver.bodies();
+    assert_eq!(bodies[3]["id"], bodies[0]["id"]);
+}
+
+#[test]
+fn parses_config() {
+    std::env::set_var("LIMITLAYER_NOTIFIER_TEST_SECRET", "from-env");
+    let service = Pubkey::new_unique();
+    let config = Config::parse(&format!(
+        r#"
+        [retry]
+        max_attempts = 5
+        initial_backoff_seconds = 30
+        max_backoff_seconds = 100
+
+        [[endpoint]]
+        service = "{service}"
+        url = "https://hooks.example.com/a"
+        secret = "inline"
+
+        [[endpoint]]
+        service = "{service}"
+        url = "https://hooks.example.com/b"
+        secret_env = "LIMITLAYER_NOTIFIER_TEST_SECRET"
+        events = ["KeyManuallyBlocked"]
+        "#
+    ))
+    .unwrap();
+    assert_eq!(config.endpoints[1].secret, b"from-env");
+    assert!(config.endpoints[0].wants(&service, "QuotaExhausted"));
+    assert!(!config.endpoints[1].wants(&service, "QuotaExhausted"));
+    assert!(!config.endpoints[0].wants(&Pubkey::new_unique(), "QuotaExhausted"));
+    let backoff: Vec<u64> = (1..=4).map(|n| config.retry.backoff(n)).collect();
+    assert_eq!(backoff, [30, 60, 100, 100]);
+
+    let endpoint = |fields: &str| {
+        Config::parse(&format!(
+            "[[endpoint]]\nservice = \"{service}\"\nurl = \"https://x\"\n{fields}"
+        ))
+        .unwrap_err()
+        .to_string()
+    };
+    assert!(endpoint("").contains("exactly one of secret and secret_env"));
+    assert!(endpoint("secret = \"a\"\nsecret_env = \"B\"").contains("exactly one"));
+    assert!(
+        endpoint("secret = \"a\"\nevents = [\"UsageRecordedRealtime\"]")
+            .contains("unknown event UsageRecordedRealtime")
+    );
+}
@@ -0,0 +1,37 @@
+//! In-process SVM for testing the LimitLayer program without a validator.
+//!
+//! The program runs natively through its Anchor entrypoint, on accounts
+//! serialized in the loader's input format, so resizing, reassigning and
+//! invoking other programs behave as they do on chain. Transactions are
+//! atomic and the runtime's account rules are enforced: signer and writable
+//! privileges, ownership of data and lamports, and balanced instructions.
+//!
+//! [`Svm`] holds two layers sharing one clock: the base layer and the
+//! ephemeral rollup it delegates to. The system program, the MagicBlock
+//! delegation program and the magic program are native stand-ins
+//! implementing what LimitLayer uses of them:
+//!
+//! - delegating an account on the base layer makes it writable on the
+//!   rollup, where the program owns it
+//! - commits scheduled on the rollup are written to the base layer once the
+//!   transaction lands
+//! - a commit that undelegates also runs the delegation program's
+//!   undelegation on the base layer, which calls the program's
+//!   `process_undelegation`
+//!
+//! The instructions sysvar is loaded for every instruction, and the Ed25519
+//! precompile verifies its signatures, so gateway receipts can be applied.
+//! A transaction must fit in a packet as a legacy transaction.
+//!
+//! The program does not run as BPF, so its compute use is not measured: only
+//! syscalls (logs, events, CPIs, sysvars, return data) are charged to the
+//! transaction's compute budget, which makes [`Outcome::compute_units`] a
+//! lower bound. A transaction that passes here can still run out of compute
+//! on chain. Fees and rent collection are not simulated, nor are the other
+//! precompiles.
+
+mod programs;
+mod runtime;
+mod svm;
+
+pub use svm::{Account, Failure, InstructionError, Layer, Outcome, Svm};
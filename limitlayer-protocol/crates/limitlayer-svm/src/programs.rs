@@ -0,0 +1,520 @@
+//! Native stand-ins for the programs LimitLayer invokes. Each implements the
+//! instructions the program (and the ephemeral rollups SDK on its behalf)
+//! sends, with the checks that matter to a caller: privileges, ownership
+//! and balances. Account data the stand-ins keep for themselves, such as
+//! delegation records, is not laid out as the real programs lay it out.
+
+use anchor_lang::{
+    prelude::{borsh, AccountMeta, AnchorDeserialize, AnchorSerialize, Pubkey, Rent},
+    solana_program::{instruction::Instruction, program_error::ProgramError},
+    system_program,
+};
+use ed25519_dalek::{PublicKey, Signature, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use ephemeral_rollups_sdk::{
+    consts::EXTERNAL_UNDELEGATE_DISCRIMINATOR,
+    pda::{
+        DELEGATE_BUFFER_TAG, DELEGATION_METADATA_TAG, DELEGATION_RECORD_TAG, UNDELEGATE_BUFFER_TAG,
+    },
+};
+use magicblock_magic_program_api::instruction::MagicBlockInstruction;
+use solana_system_interface::{error::SystemError, instruction::SystemInstruction};
+
+use crate::{
+    runtime::{invoke_program, with, Context, Effect},
+    Account, InstructionError,
+};
+
+/// Largest account the system program allocates.
+const MAX_PERMITTED_DATA_LENGTH: u64 = 10 * 1024 * 1024;
+
+const DELEGATE: [u8; 8] = [0; 8];
+const UNDELEGATE: [u8; 8] = [3, 0, 0, 0, 0, 0, 0, 0];
+
+fn require_signer(meta: &AccountMeta) -> Result<(), InstructionError> {
+    if !meta.is_signer {
+        return Err(ProgramError::MissingRequiredSignature.into());
+    }
+    Ok(())
+}
+
+fn require_writable(meta: &AccountMeta) -> Result<(), InstructionError> {
+    if !meta.is_writable {
+        return Err(InstructionError::ReadonlyModified(meta.pubkey));
+    }
+    Ok(())
+}
+
+fn system_error(error: SystemError) -> InstructionError {
+    ProgramError::Custom(error as u32).into()
+}
+
+fn transfer(
+    c: &mut Context,
+    from: &Pubkey,
+    to: &Pubkey,
+    lamports: u64,
+) -> Result<(), InstructionError> {
+    let mut source = c.account(from);
+    source.lamports = source
+        .lamports
+        .checked_sub(lamports)
+        .ok_or_else(|| system_error(SystemError::ResultWithNegativeLamports))?;
+    c.accounts.insert(*from, source);
+
+    let mut destination = c.account(to);
+    destination.lamports = destination
+        .lamports
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    c.accounts.insert(*to, destination);
+    Ok(())
+}
+
+/// Closes `account`, crediting its lamports to `destination`.
+fn close(c: &mut Context, account: &Pubkey, destination: &Pubkey) -> Result<(), InstructionError> {
+    let lamports = c.account(account).lamports;
+    transfer(c, account, destination, lamports)?;
+    c.accounts.remove(account);
+    Ok(())
+}
+
+// SYSTEM PROGRAM
+pub(crate) fn system(accounts: &[AccountMeta], data: &[u8]) -> Result<(), InstructionError> {
+    let instruction: SystemInstruction =
+        bincode::deserialize(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    with(|c| match instruction {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            let [from, to, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys.into());
+            };
+            require_signer(to)?;
+            require_writable(to)?;
+            if c.account(&to.pubkey).lamports > 0 {
+                return Err(system_error(SystemError::AccountAlreadyInUse));
+            }
+            allocate(c, to, space, owner)?;
+            debit(c, from, &to.pubkey, lamports)
+        }
+        SystemInstruction::Assign { owner } => {
+            let [account, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys.into());
+            };
+            assign(c, account, owner)
+        }
+        SystemInstruction::Transfer { lamports } => {
+            let [from, to, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys.into());
+            };
+            require_writable(to)?;
+            debit(c, from, &to.pubkey, lamports)
+        }
+        SystemInstruction::Allocate { space } => {
+            let [account, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys.into());
+            };
+            allocate(c, account, space, system_program::ID)
+        }
+        _ => Err(ProgramError::InvalidInstructionData.into()),
+    })
+}
+
+/// Transfers from a system account that signed.
+fn debit(
+    c: &mut Context,
+    from: &AccountMeta,
+    to: &Pubkey,
+    lamports: u64,
+) -> Result<(), InstructionError> {
+    require_signer(from)?;
+    require_writable(from)?;
+    let source = c.account(&from.pubkey);
+    if !source.data.is_empty() || source.owner != system_program::ID {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    transfer(c, &from.pubkey, to, lamports)
+}
+
+fn allocate(
+    c: &mut Context,
+    meta: &AccountMeta,
+    space: u64,
+    owner: Pubkey,
+) -> Result<(), InstructionError> {
+    require_signer(meta)?;
+    require_writable(meta)?;
+    let mut account = c.account(&meta.pubkey);
+    if !account.data.is_empty() || account.owner != system_program::ID {
+        return Err(system_error(SystemError::AccountAlreadyInUse));
+    }
+    if space > MAX_PERMITTED_DATA_LENGTH {
+        return Err(system_error(SystemError::InvalidAccountDataLength));
+    }
+    account.data = vec![0; space as usize];
+    account.owner = owner;
+    c.accounts.insert(meta.pubkey, account);
+    Ok(())
+}
+
+fn assign(c: &mut Context, meta: &AccountMeta, owner: Pubkey) -> Result<(), InstructionError> {
+    require_signer(meta)?;
+    let mut account = c.account(&meta.pubkey);
+    if account.owner == owner {
+        return Ok(());
+    }
+    require_writable(meta)?;
+    if account.owner != system_program::ID {
+        return Err(InstructionError::ExternalAccountModified(meta.pubkey));
+    }
+    account.owner = owner;
+    c.accounts.insert(meta.pubkey, account);
+    Ok(())
+}
+
+// DELEGATION PROGRAM
+/// Arguments of `delegate`, as the SDK's `DelegateAccountArgs` encodes them.
+#[derive(AnchorDeserialize)]
+struct DelegateArgs {
+    commit_frequency_ms: u32,
+    seeds: Vec<Vec<u8>>,
+    validator: Option<Pubkey>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+struct DelegationRecord {
+    /// Validator of the ephemeral rollup; `None` lets any validator take it
+    validator: Option<Pubkey>,
+    owner: Pubkey,
+    delegation_slot: u64,
+    commit_frequency_ms: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+struct DelegationMetadata {
+    seeds: Vec<Vec<u8>>,
+    rent_payer: Pubkey,
+}
+
+fn dlp_pda(tag: &[u8], account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[tag, account.as_ref()], &ephemeral_rollups_sdk::id()).0
+}
+
+fn decode<T: AnchorDeserialize>(account: &Account) -> Result<T, InstructionError> {
+    if account.owner != ephemeral_rollups_sdk::id() {
+        return Err(ProgramError::UninitializedAccount.into());
+    }
+    T::try_from_slice(&account.data).map_err(|_| ProgramError::InvalidAccountData.into())
+}
+
+/// Creates an account of the delegation program holding `value`.
+fn create_state<T: AnchorSerialize>(
+    c: &mut Context,
+    payer: &Pubkey,
+    address: &Pubkey,
+    value: &T,
+) -> Result<(), InstructionError> {
+    if c.account(address).lamports > 0 {
+        return Err(ProgramError::AccountAlreadyInitialized.into());
+    }
+    let data = value
+        .try_to_vec()
+        .map_err(|error| ProgramError::BorshIoError(error.to_string()))?;
+    transfer(
+        c,
+        payer,
+        address,
+        Rent::default().minimum_balance(data.len()),
+    )?;
+    let account = c.accounts.get_mut(address).expect("just funded");
+    account.data = data;
+    account.owner = ephemeral_rollups_sdk::id();
+    Ok(())
+}
+
+pub(crate) fn delegation(accounts: &[AccountMeta], data: &[u8]) -> Result<(), InstructionError> {
+    let Some((discriminator, args)) = data.split_first_chunk::<8>() else {
+        return Err(ProgramError::InvalidInstructionData.into());
+    };
+    match *discriminator {
+        DELEGATE => delegate(accounts, args),
+        UNDELEGATE => undelegate(accounts),
+        _ => Err(ProgramError::InvalidInstructionData.into()),
+    }
+}
+
+/// Accounts: payer, delegated account, owner program, delegate buffer,
+/// delegation record, delegation metadata, system program. The owner
+/// program has already assigned the account to the delegation program and
+/// moved its data to the buffer.
+fn delegate(accounts: &[AccountMeta], args: &[u8]) -> Result<(), InstructionError> {
+    let [payer, pda, owner_program, buffer, record, metadata, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    };
+    let args =
+        DelegateArgs::try_from_slice(args).map_err(|_| ProgramError::InvalidInstructionData)?;
+    require_signer(payer)?;
+    require_signer(pda)?;
+    for meta in [payer, pda, buffer, record, metadata] {
+        require_writable(meta)?;
+    }
+
+    let owner = owner_program.pubkey;
+    let seeds: Vec<&[u8]> = args.seeds.iter().map(Vec::as_slice).collect();
+    if Pubkey::find_program_address(&seeds, &owner).0 != pda.pubkey
+        || Pubkey::find_program_address(&[DELEGATE_BUFFER_TAG, pda.pubkey.as_ref()], &owner).0
+            != buffer.pubkey
+        || dlp_pda(DELEGATION_RECORD_TAG, &pda.pubkey) != record.pubkey
+        || dlp_pda(DELEGATION_METADATA_TAG, &pda.pubkey) != metadata.pubkey
+    {
+        return Err(ProgramError::InvalidSeeds.into());
+    }
+
+    with(|c| {
+        let delegated = c.account(&pda.pubkey);
+        let staged = c.account(&buffer.pubkey);
+        if delegated.owner != ephemeral_rollups_sdk::id() || staged.owner != owner {
+            return Err(ProgramError::IncorrectProgramId.into());
+        }
+        if staged.data.len() != delegated.data.len() {
+            return Err(ProgramError::InvalidAccountData.into());
+        }
+        c.accounts
+            .get_mut(&pda.pubkey)
+            .expect("delegated account")
+            .data = staged.data;
+
+        create_state(
+            c,
+            &payer.pubkey,
+            &record.pubkey,
+            &DelegationRecord {
+                validator: args.validator,
+                owner,
+                delegation_slot: c.clock.slot,
+                commit_frequency_ms: args.commit_frequency_ms,
+            },
+        )?;
+        create_state(
+            c,
+            &payer.pubkey,
+            &metadata.pubkey,
+            &DelegationMetadata {
+                seeds: args.seeds.clone(),
+                rent_payer: payer.pubkey,
+            },
+        )?;
+
+        c.effects.push(Effect::Delegate {
+            account: pda.pubkey,
+            owner,
+        });
+        Ok(())
+    })
+}
+
+/// Undelegation of `account`, as the delegation program runs it on the base
+/// layer once the ephemeral rollup commits the final state, which is
+/// already in `account`.
+pub(crate) fn undelegate_instruction(
+    validator: Pubkey,
+    account: Pubkey,
+    base: &std::collections::HashMap<Pubkey, Account>,
+) -> Instruction {
+    let record = dlp_pda(DELEGATION_RECORD_TAG, &account);
+    let metadata = dlp_pda(DELEGATION_METADATA_TAG, &account);
+    let owner = base
+        .get(&record)
+        .and_then(|record| decode::<DelegationRecord>(record).ok())
+        .map(|record| record.owner)
+        .unwrap_or_default();
+    let rent_payer = base
+        .get(&metadata)
+        .and_then(|metadata| decode::<DelegationMetadata>(metadata).ok())
+        .map(|metadata| metadata.rent_payer)
+        .unwrap_or_default();
+
+    Instruction {
+        program_id: ephemeral_rollups_sdk::id(),
+        accounts: vec![
+            AccountMeta::new(validator, true),
+            AccountMeta::new(account, false),
+            AccountMeta::new_readonly(owner, false),
+            AccountMeta::new(dlp_pda(UNDELEGATE_BUFFER_TAG, &account), false),
+            AccountMeta::new(record, false),
+            AccountMeta::new(metadata, false),
+            AccountMeta::new(rent_payer, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: UNDELEGATE.to_vec(),
+    }
+}
+
+/// Accounts: validator, delegated account, owner program, undelegate
+/// buffer, delegation record, delegation metadata, rent payer, system
+/// program. Hands the account back to its owner through the owner's
+/// `process_undelegation` instruction, which recreates it from the buffer.
+fn undelegate(accounts: &[AccountMeta]) -> Result<(), InstructionError> {
+    let [validator, pda, owner_program, buffer, record, metadata, rent_payer, system] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    };
+    require_signer(validator)?;
+    for meta in [validator, pda, buffer, record, metadata, rent_payer] {
+        require_writable(meta)?;
+    }
+    if dlp_pda(UNDELEGATE_BUFFER_TAG, &pda.pubkey) != buffer.pubkey
+        || dlp_pda(DELEGATION_RECORD_TAG, &pda.pubkey) != record.pubkey
+        || dlp_pda(DELEGATION_METADATA_TAG, &pda.pubkey) != metadata.pubkey
+    {
+        return Err(ProgramError::InvalidSeeds.into());
+    }
+
+    let (seeds, lamports, rent) = with(|c| {
+        let delegation: DelegationRecord = decode(&c.account(&record.pubkey))?;
+        let stored: DelegationMetadata = decode(&c.account(&metadata.pubkey))?;
+        let delegated = c.account(&pda.pubkey);
+        if delegation.owner != owner_program.pubkey
+            || stored.rent_payer != rent_payer.pubkey
+            || delegated.owner != ephemeral_rollups_sdk::id()
+        {
+            return Err(ProgramError::InvalidAccountData.into());
+        }
+
+        // Close the account and move its state to the buffer, which the
+        // owner program recreates it from
+        close(c, &pda.pubkey, &validator.pubkey)?;
+        let rent = Rent::default().minimum_balance(delegated.data.len());
+        transfer(c, &validator.pubkey, &buffer.pubkey, rent)?;
+        let staged = c.accounts.get_mut(&buffer.pubkey).expect("just funded");
+        staged.data = delegated.data;
+        staged.owner = ephemeral_rollups_sdk::id();
+
+        Ok::<_, InstructionError>((stored.seeds, delegated.lamports, rent))
+    })?;
+
+    let mut data = EXTERNAL_UNDELEGATE_DISCRIMINATOR.to_vec();
+    seeds
+        .serialize(&mut data)
+        .map_err(|error| ProgramError::BorshIoError(error.to_string()))?;
+    let paid_before = with(|c| c.account(&validator.pubkey).lamports);
+    invoke_program(
+        owner_program.pubkey,
+        &[
+            AccountMeta::new(pda.pubkey, false),
+            AccountMeta::new(buffer.pubkey, true),
+            AccountMeta::new(validator.pubkey, true),
+            AccountMeta::new_readonly(system.pubkey, false),
+        ],
+        &data,
+    )?;
+
+    with(|c| {
+        // The owner program must have paid exactly the account's rent and
+        // restored the committed state
+        let paid = paid_before.checked_sub(c.account(&validator.pubkey).lamports);
+        if paid != Some(rent) || c.account(&pda.pubkey).data != c.account(&buffer.pubkey).data {
+            return Err(ProgramError::InvalidAccountData.into());
+        }
+
+        transfer(
+            c,
+            &validator.pubkey,
+            &pda.pubkey,
+            lamports.saturating_sub(rent),
+        )?;
+        close(c, &buffer.pubkey, &validator.pubkey)?;
+        close(c, &record.pubkey, &rent_payer.pubkey)?;
+        close(c, &metadata.pubkey, &rent_payer.pubkey)
+    })
+}
+
+// MAGIC PROGRAM
+/// Accounts: payer, magic context, then the accounts to commit, which must
+/// be delegated and owned by the program invoking the magic program.
+pub(crate) fn magic(accounts: &[AccountMeta], data: &[u8]) -> Result<(), InstructionError> {
+    let undelegate = match bincode::deserialize(data) {
+        Ok(MagicBlockInstruction::ScheduleCommit) => false,
+        Ok(MagicBlockInstruction::ScheduleCommitAndUndelegate) => true,
+        _ => return Err(ProgramError::InvalidInstructionData.into()),
+    };
+    let [payer, magic_context, committed @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    };
+    require_signer(payer)?;
+    require_writable(magic_context)?;
+    if magic_context.pubkey != ephemeral_rollups_sdk::consts::MAGIC_CONTEXT_ID {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    with(|c| {
+        let caller = c.caller();
+        for meta in committed {
+            if !c.delegated.contains(&meta.pubkey) {
+                return Err(InstructionError::AccountNotDelegated(meta.pubkey));
+            }
+            if Some(c.account(&meta.pubkey).owner) != caller {
+                return Err(ProgramError::IllegalOwner.into());
+            }
+        }
+
+        c.effects.push(Effect::Commit {
+            accounts: committed.iter().map(|meta| meta.pubkey).collect(),
+            undelegate,
+        });
+        Ok(())
+    })
+}
+
+// ED25519 PROGRAM
+/// Start of the signature offsets, after the count and a padding byte.
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+/// Instruction index standing for the precompile's own instruction.
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Verifies every signature the instruction lists, strictly, as the
+/// precompile does before the transaction runs; any failure fails it.
+pub(crate) fn ed25519(data: &[u8]) -> Result<(), InstructionError> {
+    let invalid = || InstructionError::from(ProgramError::InvalidInstructionData);
+    let count = *data.first().ok_or_else(invalid)? as usize;
+    let offsets_end = ED25519_OFFSETS_START + count * ED25519_OFFSETS_LEN;
+    if data.len() < offsets_end || (count == 0 && data.len() > ED25519_OFFSETS_START) {
+        return Err(invalid());
+    }
+
+    let transaction = with(|c| c.instruction_data.clone());
+    let slice = |instruction: u16, offset: u16, len: usize| {
+        let source = match instruction {
+            ED25519_CURRENT_INSTRUCTION => data,
+            index => transaction.get(index as usize).ok_or_else(invalid)?,
+        };
+        let offset = offset as usize;
+        source.get(offset..offset + len).ok_or_else(invalid)
+    };
+
+    for offsets in data[ED25519_OFFSETS_START..offsets_end].chunks(ED25519_OFFSETS_LEN) {
+        let field = |i: usize| u16::from_le_bytes([offsets[i * 2], offsets[i * 2 + 1]]);
+        let signature = slice(field(1), field(0), SIGNATURE_LENGTH)?;
+        let public_key = slice(field(3), field(2), PUBLIC_KEY_LENGTH)?;
+        let message = slice(field(6), field(4), field(5) as usize)?;
+
+        let public_key =
+            PublicKey::from_bytes(public_key).map_err(|_| InstructionError::InvalidSignature)?;
+        let signature =
+            Signature::from_bytes(signature).map_err(|_| InstructionError::InvalidSignature)?;
+        public_key
+            .verify_strict(message, &signature)
+            .map_err(|_| InstructionError::InvalidSignature)?;
+    }
+    Ok(())
+}
+
+/// Compute budget instructions take effect before the transaction runs (see
+/// `runtime::compute_limit`), so there is nothing left to do.
+pub(crate) fn compute_budget(_data: &[u8]) -> Result<(), InstructionError> {
+    Ok(())
+}
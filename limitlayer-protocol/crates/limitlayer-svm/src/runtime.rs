@@ -0,0 +1,677 @@
+//! Transaction execution. The LimitLayer program runs natively through its
+//! Anchor entrypoint, on accounts serialized in the loader's input format;
+//! what it reaches through syscalls (CPIs, logs, sysvars, return data) comes
+//! back here through `program_stubs`, and what it reads of the transaction
+//! from the instructions sysvar, which is loaded before each instruction.
+//! The runtime state of the transaction being executed lives in a
+//! thread-local [`Context`], so tests can run in parallel.
+//!
+//! Only syscalls are metered against the transaction's compute budget: the
+//! instructions a program executes between them run natively and are not
+//! counted, so the units charged are a lower bound on what the transaction
+//! uses on chain.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    slice,
+    sync::Once,
+};
+
+use anchor_lang::{
+    prelude::{AccountInfo, AccountMeta, Clock, Pubkey, Rent},
+    solana_program::{
+        entrypoint::{deserialize, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER, SUCCESS},
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+    },
+    system_program,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ephemeral_rollups_sdk::consts::MAGIC_PROGRAM_ID;
+use solana_instruction::{BorrowedAccountMeta, BorrowedInstruction};
+use solana_instructions_sysvar::{construct_instructions_data, store_current_index_checked};
+use solana_sdk_ids::{compute_budget, ed25519_program, sysvar};
+
+use crate::{programs, Account, Failure, InstructionError};
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+static STUBS: Once = Once::new();
+
+/// Compute budget per instruction of a transaction that sets none, and the
+/// most a transaction may set.
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+
+/// What the runtime charges for the syscalls metered here.
+const SYSCALL_BASE_COST: u64 = 100;
+const SYSVAR_BASE_COST: u64 = 100;
+const INVOKE_UNITS: u64 = 1_000;
+const CPI_BYTES_PER_UNIT: u64 = 250;
+
+/// Something a stand-in program scheduled for after the transaction lands.
+pub(crate) enum Effect {
+    /// The delegation program took `account` over for `owner`
+    Delegate { account: Pubkey, owner: Pubkey },
+    /// The magic program scheduled `accounts` to be committed to the base
+    /// layer, and undelegated if `undelegate` is set
+    Commit {
+        accounts: Vec<Pubkey>,
+        undelegate: bool,
+    },
+}
+
+struct Frame {
+    program_id: Pubkey,
+    /// The program's accounts, with privileges merged over duplicates
+    accounts: Vec<AccountMeta>,
+}
+
+impl Frame {
+    fn privileges(&self, key: &Pubkey) -> Option<&AccountMeta> {
+        self.accounts.iter().find(|meta| meta.pubkey == *key)
+    }
+}
+
+pub(crate) struct Context {
+    /// Every account of the layer, as of the last synchronization
+    pub accounts: HashMap<Pubkey, Account>,
+    /// Accounts no instruction may change, with their value
+    pub frozen: HashMap<Pubkey, Account>,
+    /// Accounts delegated to the ephemeral rollup; empty on the base layer
+    pub delegated: HashSet<Pubkey>,
+    pub clock: Clock,
+    pub logs: Vec<String>,
+    pub return_data: Option<(Pubkey, Vec<u8>)>,
+    pub effects: Vec<Effect>,
+    /// Data of the transaction's instructions, for precompiles reading it
+    pub instruction_data: Vec<Vec<u8>>,
+    /// Compute units charged for syscalls so far, out of `compute_limit`
+    pub compute_units: u64,
+    compute_limit: u64,
+    frames: Vec<Frame>,
+    /// First failed invocation; it fails the transaction even if the caller
+    /// carries on
+    failure: Option<InstructionError>,
+}
+
+impl Context {
+    pub fn new(accounts: HashMap<Pubkey, Account>, clock: Clock) -> Self {
+        Self {
+            accounts,
+            frozen: HashMap::new(),
+            delegated: HashSet::new(),
+            clock,
+            logs: Vec::new(),
+            return_data: None,
+            effects: Vec::new(),
+            instruction_data: Vec::new(),
+            compute_units: 0,
+            compute_limit: MAX_COMPUTE_UNIT_LIMIT,
+            frames: Vec::new(),
+            failure: None,
+        }
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Account {
+        self.accounts.get(key).cloned().unwrap_or_default()
+    }
+
+    /// Charges `units` to the transaction. Once its budget is spent the
+    /// transaction fails, even if the program carries on.
+    fn consume(&mut self, units: u64) -> Result<(), InstructionError> {
+        self.compute_units = self.compute_units.saturating_add(units);
+        if self.compute_units > self.compute_limit {
+            let error = InstructionError::ComputeBudgetExceeded(self.compute_limit);
+            self.failure.get_or_insert(error.clone());
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Program whose instruction invoked the running one, if any.
+    pub fn caller(&self) -> Option<Pubkey> {
+        let depth = self.frames.len();
+        (depth >= 2).then(|| self.frames[depth - 2].program_id)
+    }
+}
+
+pub(crate) fn with<R>(f: impl FnOnce(&mut Context) -> R) -> R {
+    CONTEXT.with(|context| {
+        f(context
+            .borrow_mut()
+            .as_mut()
+            .expect("syscall made outside of Svm::process"))
+    })
+}
+
+/// Charges `units` to the transaction being executed, if any.
+fn charge(units: u64) -> Result<(), InstructionError> {
+    CONTEXT.with(|context| match context.borrow_mut().as_mut() {
+        Some(context) => context.consume(units),
+        None => Ok(()),
+    })
+}
+
+fn log(line: String) {
+    CONTEXT.with(|context| match context.borrow_mut().as_mut() {
+        Some(context) => context.logs.push(line),
+        None => println!("{line}"),
+    })
+}
+
+/// Runs `instructions` in order against `context`. Signer privileges come
+/// from `signers`, the first of which pays for the transaction.
+pub(crate) fn execute(
+    context: Context,
+    instructions: &[Instruction],
+    signers: &[Pubkey],
+) -> Result<Context, Failure> {
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(Stubs));
+    });
+
+    CONTEXT.with(|cell| *cell.borrow_mut() = Some(context));
+    let result = run(instructions, signers);
+    let mut context = CONTEXT
+        .with(|cell| cell.borrow_mut().take())
+        .expect("context is set");
+    // The instructions sysvar only exists for the transaction
+    context.accounts.remove(&sysvar::instructions::ID);
+    context.frozen.remove(&sysvar::instructions::ID);
+
+    match result {
+        Ok(()) => Ok(context),
+        Err((instruction, error)) => Err(Failure {
+            instruction,
+            error,
+            logs: context.logs,
+        }),
+    }
+}
+
+fn run(instructions: &[Instruction], signers: &[Pubkey]) -> Result<(), (usize, InstructionError)> {
+    // Privileges are merged over the whole message, as when it is compiled;
+    // the fee payer is always a writable signer.
+    let mut privileges: HashMap<Pubkey, AccountMeta> = HashMap::new();
+    if let Some(payer) = signers.first() {
+        privileges.insert(*payer, AccountMeta::new(*payer, true));
+    }
+    for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+        let merged = privileges
+            .entry(meta.pubkey)
+            .or_insert_with(|| AccountMeta::new_readonly(meta.pubkey, false));
+        merged.is_signer |= meta.is_signer;
+        merged.is_writable |= meta.is_writable;
+    }
+
+    let borrowed: Vec<BorrowedInstruction> = instructions
+        .iter()
+        .map(|ix| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: privileges[&meta.pubkey].is_signer,
+                    is_writable: privileges[&meta.pubkey].is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect();
+    let mut instructions_sysvar = construct_instructions_data(&borrowed);
+    with(|c| {
+        c.instruction_data = instructions.iter().map(|ix| ix.data.clone()).collect();
+        c.compute_limit = compute_limit(instructions);
+    });
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        // Sysvars are read-only: freezing the account makes any change to it
+        // fail the instruction
+        store_current_index_checked(&mut instructions_sysvar, index as u16)
+            .expect("the sysvar ends with the current index");
+        let account = Account {
+            lamports: 1,
+            data: instructions_sysvar.clone(),
+            owner: sysvar::ID,
+            executable: false,
+        };
+        with(|c| {
+            c.accounts.insert(sysvar::instructions::ID, account.clone());
+            c.frozen.insert(sysvar::instructions::ID, account);
+        });
+
+        let accounts: Vec<AccountMeta> = instruction
+            .accounts
+            .iter()
+            .map(|meta| privileges[&meta.pubkey].clone())
+            .collect();
+        if let Some(unsigned) = accounts
+            .iter()
+            .find(|meta| meta.is_signer && !signers.contains(&meta.pubkey))
+        {
+            log(format!(
+                "Transaction is missing the signature of {}",
+                unsigned.pubkey
+            ));
+            return Err((index, ProgramError::MissingRequiredSignature.into()));
+        }
+
+        let result = invoke_program(instruction.program_id, &accounts, &instruction.data);
+        let failure = with(|c| c.failure.take());
+        if let Some(error) = failure.or(result.err()) {
+            return Err((index, error));
+        }
+
+        let modified = with(|c| {
+            c.frozen
+                .iter()
+                .find(|(key, account)| c.account(key) != **account)
+                .map(|(key, _)| *key)
+        });
+        if let Some(key) = modified {
+            return Err((index, InstructionError::AccountNotDelegated(key)));
+        }
+    }
+
+    Ok(())
+}
+
+/// The transaction's compute budget: what its SetComputeUnitLimit asks
+/// for, otherwise the default for each of its other instructions, capped at
+/// the maximum either way.
+fn compute_limit(instructions: &[Instruction]) -> u64 {
+    let requested = instructions
+        .iter()
+        .filter(|ix| ix.program_id == compute_budget::ID)
+        .find_map(|ix| match ix.data[..] {
+            // Borsh encoding of ComputeBudgetInstruction::SetComputeUnitLimit
+            [SET_COMPUTE_UNIT_LIMIT, a, b, c, d] => Some(u32::from_le_bytes([a, b, c, d]) as u64),
+            _ => None,
+        });
+    let budgeted = instructions
+        .iter()
+        .filter(|ix| ix.program_id != compute_budget::ID)
+        .count() as u64;
+    requested
+        .unwrap_or(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT * budgeted)
+        .min(MAX_COMPUTE_UNIT_LIMIT)
+}
+
+/// Runs one instruction, top-level or invoked. `accounts` carries the
+/// privileges the program gets.
+pub(crate) fn invoke_program(
+    program_id: Pubkey,
+    accounts: &[AccountMeta],
+    data: &[u8],
+) -> Result<(), InstructionError> {
+    let (height, deployed) = with(|c| {
+        c.frames.push(Frame {
+            program_id,
+            accounts: accounts.to_vec(),
+        });
+        (c.frames.len(), c.account(&program_id).executable)
+    });
+    log(format!("Program {program_id} invoke [{height}]"));
+
+    let result = if !deployed {
+        Err(InstructionError::UnsupportedProgram(program_id))
+    } else if program_id == limitlayer_protocol::ID {
+        run_native(program_id, accounts, data)
+    } else if program_id == system_program::ID {
+        programs::system(accounts, data)
+    } else if program_id == ephemeral_rollups_sdk::id() {
+        programs::delegation(accounts, data)
+    } else if program_id == MAGIC_PROGRAM_ID {
+        programs::magic(accounts, data)
+    } else if program_id == ed25519_program::ID {
+        programs::ed25519(data)
+    } else if program_id == compute_budget::ID {
+        programs::compute_budget(data)
+    } else {
+        Err(InstructionError::UnsupportedProgram(program_id))
+    };
+
+    with(|c| c.frames.pop());
+    match &result {
+        Ok(()) => log(format!("Program {program_id} success")),
+        Err(error) => log(format!("Program {program_id} failed: {error}")),
+    }
+    result
+}
+
+fn run_native(
+    program_id: Pubkey,
+    accounts: &[AccountMeta],
+    data: &[u8],
+) -> Result<(), InstructionError> {
+    let mut input = with(|c| Input::serialize(c, &program_id, accounts, data));
+
+    // SAFETY: the buffer is aligned and laid out as the loader lays out a
+    // program's input, which is what `deserialize` and `AccountInfo` expect.
+    let result = unsafe {
+        let (program_id, infos, data) = deserialize(input.as_mut_ptr());
+        limitlayer_protocol::entry(program_id, &infos, data)
+    };
+    result?;
+
+    let updates = input.accounts();
+    with(|c| {
+        for (meta, post) in &updates {
+            verify(&program_id, meta, &c.account(&meta.pubkey), post)?;
+        }
+        let lamports: u128 = updates.iter().map(|(_, post)| post.lamports as u128).sum();
+        if lamports != input.lamports {
+            return Err(InstructionError::UnbalancedInstruction);
+        }
+
+        for (meta, post) in updates {
+            c.accounts.insert(meta.pubkey, post);
+        }
+        Ok(())
+    })
+}
+
+/// Checks a change `program_id` made to an account against the runtime's
+/// rules: only writable accounts change, only the owner debits lamports or
+/// writes data, and only the owner reassigns an account, once it is zeroed.
+fn verify(
+    program_id: &Pubkey,
+    meta: &AccountMeta,
+    pre: &Account,
+    post: &Account,
+) -> Result<(), InstructionError> {
+    if pre == post {
+        return Ok(());
+    }
+    let key = meta.pubkey;
+    if !meta.is_writable || pre.executable != post.executable {
+        return Err(InstructionError::ReadonlyModified(key));
+    }
+
+    let owned = pre.owner == *program_id;
+    let debited = post.lamports < pre.lamports;
+    let written = pre.data != post.data;
+    let reassigned = pre.owner != post.owner && post.data.iter().any(|byte| *byte != 0);
+    if !owned && (debited || written || pre.owner != post.owner) || reassigned {
+        return Err(InstructionError::ExternalAccountModified(key));
+    }
+
+    Ok(())
+}
+
+fn invoke_signed(
+    instruction: &Instruction,
+    infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> Result<(), InstructionError> {
+    let (caller, granted) = with(|c| {
+        let frame = c.frames.last().expect("invoke made outside of a program");
+        (frame.program_id, frame.accounts.clone())
+    });
+    let caller_frame = Frame {
+        program_id: caller,
+        accounts: granted,
+    };
+
+    let pda_signers = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let accounts = merge(&instruction.accounts);
+    for meta in &accounts {
+        let granted = caller_frame
+            .privileges(&meta.pubkey)
+            .filter(|_| infos.iter().any(|info| info.key == &meta.pubkey))
+            .ok_or(InstructionError::MissingAccount(meta.pubkey))?;
+        let signs = granted.is_signer || pda_signers.contains(&meta.pubkey);
+        if (meta.is_writable && !granted.is_writable) || (meta.is_signer && !signs) {
+            return Err(InstructionError::PrivilegeEscalation(meta.pubkey));
+        }
+    }
+
+    // The callee sees what the caller changed so far...
+    for info in infos {
+        let Some(granted) = caller_frame.privileges(info.key) else {
+            continue;
+        };
+        let post = Account::from_info(info)?;
+        with(|c| {
+            verify(&caller, granted, &c.account(info.key), &post)?;
+            c.accounts.insert(*info.key, post);
+            Ok::<_, InstructionError>(())
+        })?;
+    }
+
+    invoke_program(instruction.program_id, &accounts, &instruction.data)?;
+
+    // ...and the caller what the callee changed
+    for info in infos {
+        if caller_frame.privileges(info.key).is_some() {
+            with(|c| c.account(info.key)).write_to(info)?;
+        }
+    }
+    Ok(())
+}
+
+/// Gives every occurrence of an account the union of its privileges.
+fn merge(accounts: &[AccountMeta]) -> Vec<AccountMeta> {
+    accounts
+        .iter()
+        .map(|meta| {
+            let mut merged = meta.clone();
+            for other in accounts.iter().filter(|other| other.pubkey == meta.pubkey) {
+                merged.is_signer |= other.is_signer;
+                merged.is_writable |= other.is_writable;
+            }
+            merged
+        })
+        .collect()
+}
+
+impl Account {
+    fn from_info(info: &AccountInfo) -> Result<Self, ProgramError> {
+        Ok(Self {
+            lamports: info.lamports(),
+            data: info.try_borrow_data()?.to_vec(),
+            owner: *info.owner,
+            executable: info.executable,
+        })
+    }
+
+    fn write_to(&self, info: &AccountInfo) -> Result<(), ProgramError> {
+        if info.lamports() != self.lamports {
+            **info.try_borrow_mut_lamports()? = self.lamports;
+        }
+        if *info.owner != self.owner {
+            info.assign(&self.owner);
+        }
+        if **info.try_borrow_data()? != self.data[..] {
+            info.resize(self.data.len())?;
+            info.try_borrow_mut_data()?.copy_from_slice(&self.data);
+        }
+        Ok(())
+    }
+}
+
+/// A program's input region, in the loader's aligned serialization format.
+struct Input {
+    buffer: Vec<u64>,
+    /// Unique accounts, with the offset of their key
+    accounts: Vec<(AccountMeta, usize)>,
+    /// Lamports of the unique accounts when the program was entered
+    lamports: u128,
+}
+
+impl Input {
+    fn serialize(
+        context: &Context,
+        program_id: &Pubkey,
+        accounts: &[AccountMeta],
+        data: &[u8],
+    ) -> Self {
+        let mut bytes = Vec::new();
+        let mut unique = Vec::new();
+        let mut lamports = 0u128;
+
+        bytes.extend((accounts.len() as u64).to_le_bytes());
+        for (index, meta) in accounts.iter().enumerate() {
+            if let Some(first) = accounts[..index]
+                .iter()
+                .position(|other| other.pubkey == meta.pubkey)
+            {
+                bytes.push(first as u8);
+                bytes.extend([0; 7]);
+                continue;
+            }
+
+            let account = context.account(&meta.pubkey);
+            lamports += account.lamports as u128;
+            bytes.extend([
+                NON_DUP_MARKER,
+                meta.is_signer as u8,
+                meta.is_writable as u8,
+                account.executable as u8,
+            ]);
+            bytes.extend((account.data.len() as u32).to_le_bytes());
+            unique.push((meta.clone(), bytes.len()));
+            bytes.extend(meta.pubkey.as_ref());
+            bytes.extend(account.owner.as_ref());
+            bytes.extend(account.lamports.to_le_bytes());
+            bytes.extend((account.data.len() as u64).to_le_bytes());
+            bytes.extend(&account.data);
+            bytes.resize(
+                (bytes.len() + MAX_PERMITTED_DATA_INCREASE).next_multiple_of(8),
+                0,
+            );
+            bytes.extend(u64::MAX.to_le_bytes());
+        }
+        bytes.extend((data.len() as u64).to_le_bytes());
+        bytes.extend(data);
+        bytes.extend(program_id.as_ref());
+
+        let mut input = Self {
+            buffer: vec![0; bytes.len().div_ceil(8)],
+            accounts: unique,
+            lamports,
+        };
+        input.bytes_mut()[..bytes.len()].copy_from_slice(&bytes);
+        input
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.buffer.as_mut_ptr().cast()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: any u64 buffer is a valid byte buffer eight times as long
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr().cast(), self.buffer.len() * 8) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as in `bytes`
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.buffer.len() * 8) }
+    }
+
+    /// The unique accounts as the program left them.
+    fn accounts(&self) -> Vec<(AccountMeta, Account)> {
+        let bytes = self.bytes();
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        self.accounts
+            .iter()
+            .map(|(meta, key)| {
+                let data_len = u64_at(key + 72) as usize;
+                let account = Account {
+                    lamports: u64_at(key + 64),
+                    data: bytes[key + 80..key + 80 + data_len].to_vec(),
+                    owner: Pubkey::try_from(&bytes[key + 32..key + 64]).unwrap(),
+                    executable: bytes[key - 5] != 0,
+                };
+                (meta.clone(), account)
+            })
+            .collect()
+    }
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    // A syscall that runs out of compute units fails the transaction through
+    // the context, once the program returns
+    fn sol_log(&self, message: &str) {
+        let _ = charge(SYSCALL_BASE_COST.max(message.len() as u64));
+        log(format!("Program log: {message}"));
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let bytes: usize = fields.iter().map(|field| field.len()).sum();
+        let _ = charge(SYSCALL_BASE_COST * (1 + fields.len() as u64) + bytes as u64);
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        log(format!("Program data: {}", fields.join(" ")));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> Result<(), ProgramError> {
+        charge(INVOKE_UNITS + instruction.data.len() as u64 / CPI_BYTES_PER_UNIT)
+            .map_err(|error| error.to_program_error())?;
+        invoke_signed(instruction, account_infos, signers_seeds).map_err(|error| {
+            // A failed invocation fails the whole transaction, whatever the
+            // caller does with the error
+            let returned = error.to_program_error();
+            with(|c| {
+                c.failure.get_or_insert(error);
+            });
+            returned
+        })
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let _ = charge(SYSVAR_BASE_COST + size_of::<Clock>() as u64);
+        let clock = with(|c| c.clock.clone());
+        // SAFETY: the sysvar getter passes a pointer to a Clock
+        unsafe { var_addr.cast::<Clock>().write(clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let _ = charge(SYSVAR_BASE_COST + size_of::<Rent>() as u64);
+        // SAFETY: the sysvar getter passes a pointer to a Rent
+        unsafe { var_addr.cast::<Rent>().write(Rent::default()) };
+        SUCCESS
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        let return_data = with(|c| c.return_data.clone());
+        let bytes = return_data.as_ref().map_or(0, |(_, data)| data.len());
+        let _ = charge(SYSCALL_BASE_COST + bytes as u64 / CPI_BYTES_PER_UNIT);
+        return_data
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let _ = charge(SYSCALL_BASE_COST + data.len() as u64 / CPI_BYTES_PER_UNIT);
+        with(|c| {
+            let program_id = c.frames.last().expect("called by a program").program_id;
+            c.return_data = (!data.is_empty()).then(|| (program_id, data.to_vec()));
+        })
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        let _ = charge(SYSCALL_BASE_COST);
+        with(|c| c.frames.len() as u64)
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::{HashMap, HashSet};
+
+use anchor_lang::{
+    prelude::{pubkey, Clock, Pubkey},
+    solana_program::{instruction::Instruction, program_error::ProgramError},
+    system_program, AccountDeserialize,
+};
+use ephemeral_rollups_sdk::consts::{MAGIC_CONTEXT_ID, MAGIC_PROGRAM_ID};
+use limitlayer_client::events::{parse_logs, LimitLayerEvent};
+use solana_message::Message;
+use solana_packet::PACKET_DATA_SIZE;
+use solana_sdk_ids::{compute_budget, ed25519_program};
+use solana_transaction::Transaction;
+use thiserror::Error;
+
+use crate::{
+    programs,
+    runtime::{self, Context, Effect},
+};
+
+/// 2026-01-05T00:00:00Z, a Monday
+const GENESIS_TIMESTAMP: i64 = 1_767_571_200;
+
+const BPF_LOADER_UPGRADEABLE: Pubkey = pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+const NATIVE_LOADER: Pubkey = pubkey!("NativeLoader1111111111111111111111111111111");
+
+/// Lamports the ephemeral rollup's validator starts with on the base layer.
+const VALIDATOR_LAMPORTS: u64 = 1_000_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Base,
+    /// The ephemeral rollup the base layer delegates to
+    Ephemeral,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+impl Account {
+    fn program(loader: Pubkey) -> Self {
+        Self {
+            lamports: 1,
+            data: Vec::new(),
+            owner: loader,
+            executable: true,
+        }
+    }
+}
+
+/// Why an instruction failed.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum InstructionError {
+    #[error("{0}")]
+    Program(ProgramError),
+    #[error("account {0} is not passed to the invoked instruction")]
+    MissingAccount(Pubkey),
+    #[error("privileges of account {0} escalated by an invocation")]
+    PrivilegeEscalation(Pubkey),
+    #[error("read-only account {0} modified")]
+    ReadonlyModified(Pubkey),
+    #[error("account {0} modified by a program that does not own it")]
+    ExternalAccountModified(Pubkey),
+    #[error("sum of account balances changed")]
+    UnbalancedInstruction,
+    #[error("program {0} is not deployed on this layer")]
+    UnsupportedProgram(Pubkey),
+    #[error("account {0} is not delegated to the ephemeral rollup")]
+    AccountNotDelegated(Pubkey),
+    #[error("signature verification failed")]
+    InvalidSignature,
+    /// Syscalls alone used up the transaction's compute budget
+    #[error("compute budget of {0} units exceeded")]
+    ComputeBudgetExceeded(u64),
+    /// The transaction does not fit in a packet; reported against its first
+    /// instruction, as nothing ran
+    #[error("transaction of {0} bytes exceeds the {PACKET_DATA_SIZE} bytes of a packet")]
+    TransactionTooLarge(usize),
+}
+
+impl InstructionError {
+    /// Custom program error code, such as an Anchor `ErrorCode` converted
+    /// with `.into()`.
+    pub fn custom(&self) -> Option<u32> {
+        match self {
+            Self::Program(ProgramError::Custom(code)) => Some(*code),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn to_program_error(&self) -> ProgramError {
+        match self {
+            Self::Program(error) => error.clone(),
+            Self::MissingAccount(_) => ProgramError::NotEnoughAccountKeys,
+            Self::PrivilegeEscalation(_) => ProgramError::MissingRequiredSignature,
+            _ => ProgramError::InvalidArgument,
+        }
+    }
+}
+
+impl From<ProgramError> for InstructionError {
+    fn from(error: ProgramError) -> Self {
+        Self::Program(error)
+    }
+}
+
+/// A transaction that failed; nothing it did was kept.
+#[derive(Debug, Error)]
+#[error("instruction {instruction} failed: {error}")]
+pub struct Failure {
+    /// Index of the failed instruction in the transaction
+    pub instruction: usize,
+    pub error: InstructionError,
+    pub logs: Vec<String>,
+}
+
+/// A transaction that succeeded.
+#[derive(Debug)]
+pub struct Outcome {
+    pub logs: Vec<String>,
+    pub return_data: Option<(Pubkey, Vec<u8>)>,
+    /// Compute units charged for the transaction's syscalls, a lower bound
+    /// on what it uses on chain
+    pub compute_units: u64,
+}
+
+impl Outcome {
+    /// Events the LimitLayer program emitted, in order.
+    pub fn events(&self) -> limitlayer_client::Result<Vec<LimitLayerEvent>> {
+        parse_logs(&self.logs)
+    }
+}
+
+/// A base layer and the ephemeral rollup it delegates to, sharing one clock.
+pub struct Svm {
+    base: HashMap<Pubkey, Account>,
+    ephemeral: HashMap<Pubkey, Account>,
+    clock: Clock,
+    validator: Pubkey,
+}
+
+/// Bytes `instructions` take on the wire as a legacy transaction signed by
+/// `signers`, the first paying.
+fn transaction_size(instructions: &[Instruction], signers: &[Pubkey]) -> usize {
+    let transaction = Transaction::new_unsigned(Message::new(instructions, signers.first()));
+    bincode::serialized_size(&transaction).expect("a transaction serializes") as usize
+}
+
+impl Default for Svm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        let validator = Pubkey::new_unique();
+        let program = Account::program(BPF_LOADER_UPGRADEABLE);
+        let native = Account::program(NATIVE_LOADER);
+
+        let base = HashMap::from([
+            (limitlayer_protocol::ID, program.clone()),
+            (ephemeral_rollups_sdk::id(), program.clone()),
+            (system_program::ID, native.clone()),
+            (ed25519_program::ID, native.clone()),
+            (compute_budget::ID, native.clone()),
+            (
+                validator,
+                Account {
+                    lamports: VALIDATOR_LAMPORTS,
+                    ..Account::default()
+                },
+            ),
+        ]);
+        let ephemeral = HashMap::from([
+            (limitlayer_protocol::ID, program),
+            (MAGIC_PROGRAM_ID, native.clone()),
+            (system_program::ID, native.clone()),
+            (ed25519_program::ID, native.clone()),
+            (compute_budget::ID, native),
+            (
+                MAGIC_CONTEXT_ID,
+                Account {
+                    lamports: 1,
+                    data: Vec::new(),
+                    owner: MAGIC_PROGRAM_ID,
+                    executable: false,
+                },
+            ),
+        ]);
+
+        Self {
+            base,
+            ephemeral,
+            clock: Clock {
+                slot: 1,
+                unix_timestamp: GENESIS_TIMESTAMP,
+                ..Clock::default()
+            },
+            validator,
+        }
+    }
+
+    /// Identity of the ephemeral rollup's validator, the one delegations
+    /// should name.
+    pub fn validator(&self) -> Pubkey {
+        self.validator
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Moves the clock forward by `seconds`, one slot per second.
+    pub fn advance(&mut self, seconds: i64) {
+        self.clock.unix_timestamp += seconds;
+        self.clock.slot += seconds.max(0) as u64;
+    }
+
+    /// Credits `lamports` to `address` on the base layer.
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        self.base.entry(*address).or_default().lamports += lamports;
+    }
+
+    /// `address` as a transaction on `layer` would load it. Accounts that
+    /// are not delegated read the same on both layers.
+    pub fn account(&self, layer: Layer, address: &Pubkey) -> Option<&Account> {
+        match layer {
+            Layer::Base => self.base.get(address),
+            Layer::Ephemeral => self
+                .ephemeral
+                .get(address)
+                .or_else(|| self.base.get(address)),
+        }
+    }
+
+    /// Deserializes an Anchor account, checking its discriminator.
+    pub fn get<T: AccountDeserialize>(&self, layer: Layer, address: &Pubkey) -> Option<T> {
+        let account = self.account(layer, address)?;
+        T::try_deserialize(&mut account.data.as_slice()).ok()
+    }
+
+    /// Whether `address` is delegated to the ephemeral rollup.
+    pub fn is_delegated(&self, address: &Pubkey) -> bool {
+        self.ephemeral
+            .get(address)
+            .is_some_and(|account| !account.executable && account.owner != MAGIC_PROGRAM_ID)
+    }
+
+    /// Runs `instructions` as one atomic transaction on `layer`. The first
+    /// signer pays for it, and it must fit in a packet as a legacy
+    /// transaction. On the ephemeral rollup, accounts that are not
+    /// delegated are loaded from the base layer and may not be modified;
+    /// commits it schedules are applied to the base layer before this
+    /// returns, including undelegation.
+    pub fn process(
+        &mut self,
+        layer: Layer,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> Result<Outcome, Failure> {
+        let size = transaction_size(instructions, signers);
+        if size > PACKET_DATA_SIZE {
+            return Err(Failure {
+                instruction: 0,
+                error: InstructionError::TransactionTooLarge(size),
+                logs: Vec::new(),
+            });
+        }
+        match layer {
+            Layer::Base => self.process_base(instructions, signers),
+            Layer::Ephemeral => self.process_ephemeral(instructions, signers),
+        }
+    }
+
+    fn process_base(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> Result<Outcome, Failure> {
+        let context = Context::new(self.base.clone(), self.clock.clone());
+        let mut context = runtime::execute(context, instructions, signers)?;
+
+        context.accounts.retain(|_, account| account.lamports > 0);
+        self.base = context.accounts;
+        for effect in context.effects {
+            if let Effect::Delegate { account, owner } = effect {
+                let delegated = Account {
+                    owner,
+                    ..self.base[&account].clone()
+                };
+                self.ephemeral.insert(account, delegated);
+            }
+        }
+
+        Ok(Outcome {
+            logs: context.logs,
+            return_data: context.return_data,
+            compute_units: context.compute_units,
+        })
+    }
+
+    fn process_ephemeral(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> Result<Outcome, Failure> {
+        let mut context = Context::new(self.ephemeral.clone(), self.clock.clone());
+        context.delegated = self
+            .ephemeral
+            .keys()
+            .filter(|address| self.is_delegated(address))
+            .copied()
+            .collect();
+        let loaded: HashSet<Pubkey> = instructions
+            .iter()
+            .flat_map(|ix| ix.accounts.iter().map(|meta| meta.pubkey))
+            .chain(signers.iter().copied())
+            .filter(|address| !self.ephemeral.contains_key(address))
+            .collect();
+        for address in loaded {
+            let account = self.base.get(&address).cloned().unwrap_or_default();
+            context.accounts.insert(address, account.clone());
+            context.frozen.insert(address, account);
+        }
+
+        let context = runtime::execute(context, instructions, signers)?;
+        let compute_units = context.compute_units;
+
+        for (address, account) in context.accounts {
+            if !context.frozen.contains_key(&address) {
+                self.ephemeral.insert(address, account);
+            }
+        }
+        for effect in context.effects {
+            if let Effect::Commit {
+                accounts,
+                undelegate,
+            } = effect
+            {
+                for account in accounts {
+                    self.commit(account, undelegate);
+                }
+            }
+        }
+
+        Ok(Outcome {
+            logs: context.logs,
+            return_data: context.return_data,
+            compute_units,
+        })
+    }
+
+    /// Writes the rollup's state of `account` to the base layer, as the
+    /// delegation program's periodic commits do, without running the
+    /// program.
+    pub fn auto_commit(&mut self, account: &Pubkey) {
+        self.commit(*account, false);
+    }
+
+    /// The rollup goes away without a final commit: the delegation program
+    /// hands `account` back to its owner with the last state committed to
+    /// the base layer.
+    pub fn abandon(&mut self, account: &Pubkey) {
+        if self.ephemeral.remove(account).is_some() {
+            self.undelegate(*account);
+        }
+    }
+
+    /// Writes the rollup's state of `account` to the base layer, then hands
+    /// it back to its owner if `undelegate` is set.
+    fn commit(&mut self, account: Pubkey, undelegate: bool) {
+        let Some(committed) = self.ephemeral.get(&account) else {
+            return;
+        };
+        let data = committed.data.clone();
+        if let Some(base) = self.base.get_mut(&account) {
+            base.data = data;
+        }
+        if !undelegate {
+            return;
+        }
+
+        self.ephemeral.remove(&account);
+        self.undelegate(account);
+    }
+
+    fn undelegate(&mut self, account: Pubkey) {
+        let instruction = programs::undelegate_instruction(self.validator, account, &self.base);
+        if let Err(failure) = self.process_base(&[instruction], &[self.validator]) {
+            panic!("undelegation of {account} failed on the base layer: {failure:#?}");
+        }
+    }
+}
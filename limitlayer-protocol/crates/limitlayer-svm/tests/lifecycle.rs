@@ -0,0 +1,1128 @@
+use anchor_lang::prelude::Pubkey;
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use limitlayer_client::{
+    events::LimitLayerEvent,
+    instructions::{self, Submitter, UsageBatchEntry},
+    pda,
+    program::{
+        enums::{CheckpointStatus, DisputeReason},
+        error::ErrorCode,
+        instruction as ix, merkle, ApiKeyAccount, DelegatedUsageAccount, RequestLogLeaf,
+        ServiceAccount, UsageCheckpoint, UsageReceipt, MAX_USAGE_BATCH_SIZE, MAX_WINDOW_ROOTS,
+    },
+    ID,
+};
+use limitlayer_svm::{Failure, InstructionError, Layer, Outcome, Svm};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+/// Most keys a `record_usage_batch` transaction with request ids has room
+/// for, signed by the service authority
+const MAX_BATCH_KEYS: u64 = 11;
+
+/// A protocol with one service, policy, execution region and API key.
+struct Fixture {
+    svm: Svm,
+    admin: Pubkey,
+    authority: Pubkey,
+    service: Pubkey,
+    policy: Pubkey,
+    api_key: Pubkey,
+    usage: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let mut svm = Svm::new();
+        let admin = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        svm.airdrop(&admin, 10 * LAMPORTS_PER_SOL);
+        svm.airdrop(&authority, 10 * LAMPORTS_PER_SOL);
+
+        let service = pda::service(0);
+        let policy = pda::policy(&service, 0);
+        let api_key = pda::api_key(0);
+        let validator = svm.validator();
+        svm.process(
+            Layer::Base,
+            &[
+                instructions::initialize_protocol(admin, 100, admin),
+                instructions::register_execution_region(admin, validator, "eu-west".into()),
+            ],
+            &[admin],
+        )
+        .unwrap();
+        svm.process(
+            Layer::Base,
+            &[
+                instructions::create_service(authority, 0, "search".into(), Pubkey::default()),
+                instructions::create_policy(
+                    authority,
+                    service,
+                    0,
+                    ix::CreatePolicy {
+                        requests_per_window: 100,
+                        window_seconds: 60,
+                        burst_limit: 10,
+                        cost_per_request: 2,
+                        daily_quota: 0,
+                        monthly_quota: 0,
+                    },
+                ),
+                instructions::create_api_key(authority, service, 0, Pubkey::new_unique(), policy),
+            ],
+            &[authority],
+        )
+        .unwrap();
+
+        Self {
+            svm,
+            admin,
+            authority,
+            service,
+            policy,
+            api_key,
+            usage: pda::delegated_usage(&api_key),
+        }
+    }
+
+    fn submitter(&self) -> Submitter {
+        Submitter::authority(self.authority, self.service)
+    }
+
+    fn prepare(&self) -> anchor_lang::solana_program::instruction::Instruction {
+        instructions::prepare_delegation(
+            self.authority,
+            self.service,
+            self.api_key,
+            self.policy,
+            self.svm.validator(),
+            0,
+            ix::PrepareDelegation {
+                commit_frequency_ms: 0,
+                lease_seconds: 0,
+            },
+        )
+    }
+
+    fn delegate(&mut self) -> Outcome {
+        let instructions = [
+            self.prepare(),
+            instructions::delegate_usage(self.authority, self.api_key, 0, self.svm.validator()),
+        ];
+        self.svm
+            .process(Layer::Base, &instructions, &[self.authority])
+            .unwrap()
+    }
+
+    fn record(&mut self, amount: u64, request_id: Option<u64>) -> Result<Outcome, Failure> {
+        let instruction = instructions::record_usage_realtime(
+            self.submitter(),
+            self.usage,
+            self.api_key,
+            self.policy,
+            amount,
+            request_id,
+        );
+        self.svm
+            .process(Layer::Ephemeral, &[instruction], &[self.authority])
+    }
+
+    fn usage(&self, layer: Layer) -> DelegatedUsageAccount {
+        self.svm.get(layer, &self.usage).unwrap()
+    }
+}
+
+#[test]
+fn usage_round_trips_through_the_rollup() {
+    let mut f = Fixture::new();
+    let lamports = f.svm.account(Layer::Base, &f.usage).unwrap().lamports;
+
+    // Delegation hands the account to the delegation program on the base
+    // layer and to the program on the rollup
+    let events = f.delegate().events().unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [LimitLayerEvent::UsageDelegated(e)] if e.delegated_usage == f.usage
+    ));
+    assert!(f.svm.is_delegated(&f.usage));
+    let base = f.svm.account(Layer::Base, &f.usage).unwrap();
+    assert_eq!(base.owner, ephemeral_rollups_sdk::id());
+    assert_eq!(f.svm.account(Layer::Ephemeral, &f.usage).unwrap().owner, ID);
+    assert!(f.usage(Layer::Base).delegated);
+
+    f.record(3, Some(1)).unwrap();
+    f.record(4, Some(2)).unwrap();
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 7);
+    assert_eq!(f.usage(Layer::Base).current_window_usage, 0);
+
+    // The checkpoint commits the closed window to the base layer...
+    let events = f
+        .svm
+        .process(
+            Layer::Ephemeral,
+            &[instructions::submit_usage_checkpoint(
+                f.submitter(),
+                f.api_key,
+                1,
+                [7; 32],
+            )],
+            &[f.authority],
+        )
+        .unwrap()
+        .events()
+        .unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [LimitLayerEvent::UsageCheckpointSubmitted(e)]
+            if e.service == f.service && e.window_usage == 7
+    ));
+    let committed = f.usage(Layer::Base);
+    assert_eq!(committed.checkpoint_seq, 1);
+    assert_eq!(committed.checkpointed_usage, 7);
+    assert_eq!(committed.last_request_log_root, [7; 32]);
+
+    // ...where it is applied
+    let apply = instructions::apply_usage_checkpoint(
+        f.authority,
+        f.service,
+        f.api_key,
+        f.policy,
+        committed.checkpoint_seq,
+    );
+    let events = f
+        .svm
+        .process(Layer::Base, &[apply], &[f.authority])
+        .unwrap()
+        .events()
+        .unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [LimitLayerEvent::UsageCheckpointApplied(e)] if e.request_count == 7 && e.cost == 14
+    ));
+    let checkpoint: UsageCheckpoint = f
+        .svm
+        .get(Layer::Base, &pda::usage_checkpoint(&f.api_key, 1))
+        .unwrap();
+    assert_eq!(
+        (checkpoint.request_count, checkpoint.cost_accumulated),
+        (7, 14)
+    );
+    let key: ApiKeyAccount = f.svm.get(Layer::Base, &f.api_key).unwrap();
+    assert_eq!(key.applied_usage, 7);
+
+    // Undelegation returns the account to the program with its rent
+    f.record(2, None).unwrap();
+    let outcome = f
+        .svm
+        .process(
+            Layer::Ephemeral,
+            &[instructions::undelegate_usage(f.authority, f.usage)],
+            &[f.authority],
+        )
+        .unwrap();
+    assert!(matches!(
+        outcome.events().unwrap().as_slice(),
+        [LimitLayerEvent::UsageUndelegated(_)]
+    ));
+    assert!(!f.svm.is_delegated(&f.usage));
+    let base = f.svm.account(Layer::Base, &f.usage).unwrap();
+    assert_eq!((base.owner, base.lamports), (ID, lamports));
+    let returned = f.usage(Layer::Base);
+    assert!(!returned.delegated);
+    assert_eq!(
+        (returned.checkpoint_seq, returned.checkpointed_usage),
+        (2, 9)
+    );
+
+    // The key records on the base layer again, and can be redelegated
+    f.svm
+        .process(
+            Layer::Base,
+            &[instructions::record_usage_direct(
+                f.submitter(),
+                f.api_key,
+                f.policy,
+                5,
+                None,
+            )],
+            &[f.authority],
+        )
+        .unwrap();
+    assert_eq!(f.usage(Layer::Base).current_window_usage, 5);
+
+    f.delegate();
+    let redelegated = f.usage(Layer::Ephemeral);
+    assert_eq!(redelegated.delegation_seq, 2);
+    assert_eq!(redelegated.checkpointed_usage, 14);
+    f.record(1, None).unwrap();
+}
+
+#[test]
+fn rejected_usage_is_rolled_back() {
+    let mut f = Fixture::new();
+    f.delegate();
+    f.record(3, Some(1)).unwrap();
+
+    let failure = f.record(8, None).unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::BurstLimitExceeded.into())
+    );
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 3);
+
+    // A retried request is acknowledged without being counted
+    let events = f.record(3, Some(1)).unwrap().events().unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [LimitLayerEvent::DuplicateUsageIgnored(e)] if e.request_id == 1
+    ));
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 3);
+
+    // Nothing an earlier instruction did survives a later failure
+    let submitter = f.submitter();
+    let record = |amount| {
+        instructions::record_usage_realtime(submitter, f.usage, f.api_key, f.policy, amount, None)
+    };
+    let failure = f
+        .svm
+        .process(Layer::Ephemeral, &[record(2), record(9)], &[f.authority])
+        .unwrap_err();
+    assert_eq!(failure.instruction, 1);
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 3);
+}
+
+#[test]
+fn transactions_must_fit_in_a_packet() {
+    let mut f = Fixture::new();
+    f.delegate();
+
+    // Each key adds its usage and key accounts to the message, so a legacy
+    // transaction holds far fewer than MAX_USAGE_BATCH_SIZE keys
+    let submitter = f.submitter();
+    let policy = f.policy;
+    let batch = |keys: u64| {
+        let entries: Vec<UsageBatchEntry> = (0..keys)
+            .map(|index| {
+                let api_key = pda::api_key(index);
+                UsageBatchEntry {
+                    delegated_usage: pda::delegated_usage(&api_key),
+                    api_key,
+                    policy,
+                    amount: 1,
+                    request_id: Some(index + 1),
+                }
+            })
+            .collect();
+        instructions::record_usage_batch(submitter, &entries)
+    };
+    f.svm
+        .process(Layer::Ephemeral, &[batch(MAX_BATCH_KEYS)], &[f.authority])
+        .unwrap();
+    for keys in [MAX_BATCH_KEYS + 1, MAX_USAGE_BATCH_SIZE as u64] {
+        let failure = f
+            .svm
+            .process(Layer::Ephemeral, &[batch(keys)], &[f.authority])
+            .unwrap_err();
+        assert!(matches!(
+            failure.error,
+            InstructionError::TransactionTooLarge(_)
+        ));
+    }
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 1);
+}
+
+#[test]
+fn syscalls_are_charged_to_the_compute_budget() {
+    let mut f = Fixture::new();
+    f.delegate();
+    let record =
+        instructions::record_usage_realtime(f.submitter(), f.usage, f.api_key, f.policy, 1, None);
+    let used = f
+        .svm
+        .process(
+            Layer::Ephemeral,
+            std::slice::from_ref(&record),
+            &[f.authority],
+        )
+        .unwrap()
+        .compute_units;
+    assert!(used > 0);
+
+    let limit = ComputeBudgetInstruction::set_compute_unit_limit(used as u32 - 1);
+    let failure = f
+        .svm
+        .process(Layer::Ephemeral, &[limit, record], &[f.authority])
+        .unwrap_err();
+    assert_eq!(failure.instruction, 1);
+    assert_eq!(
+        failure.error,
+        InstructionError::ComputeBudgetExceeded(used - 1)
+    );
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 1);
+}
+
+#[test]
+fn rollup_only_writes_delegated_accounts() {
+    let mut f = Fixture::new();
+
+    // Prepared but never delegated: the rollup reads the base layer's
+    // account and refuses to change it
+    let prepare = f.prepare();
+    f.svm
+        .process(Layer::Base, &[prepare], &[f.authority])
+        .unwrap();
+    assert!(f.usage(Layer::Ephemeral).delegated);
+    let failure = f.record(1, None).unwrap_err();
+    assert_eq!(
+        failure.error,
+        InstructionError::AccountNotDelegated(f.usage)
+    );
+    assert_eq!(f.usage(Layer::Base).current_window_usage, 0);
+
+    // Nor can the magic program commit it
+    let submit = instructions::submit_usage_checkpoint(f.submitter(), f.api_key, 1, [0; 32]);
+    let failure = f
+        .svm
+        .process(Layer::Ephemeral, &[submit], &[f.authority])
+        .unwrap_err();
+    assert_eq!(
+        failure.error,
+        InstructionError::AccountNotDelegated(f.usage)
+    );
+}
+
+#[test]
+fn late_checkpoints_are_flagged() {
+    let mut f = Fixture::new();
+    f.delegate();
+    f.record(3, None).unwrap();
+
+    // The gateway keeps the window open well past the 60s policy window,
+    // the 30s commit interval and the grace period
+    f.svm.advance(60 + 30 + 300 + 1);
+    f.svm
+        .process(
+            Layer::Ephemeral,
+            &[instructions::submit_usage_checkpoint(
+                f.submitter(),
+                f.api_key,
+                1,
+                [0; 32],
+            )],
+            &[f.authority],
+        )
+        .unwrap();
+    let apply =
+        instructions::apply_usage_checkpoint(f.authority, f.service, f.api_key, f.policy, 1);
+    let events = f
+        .svm
+        .process(Layer::Base, &[apply], &[f.authority])
+        .unwrap()
+        .events()
+        .unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [
+            LimitLayerEvent::UsageCheckpointApplied(applied),
+            LimitLayerEvent::UsageCheckpointLagging(lagging),
+        ] if applied.request_count == 3
+            && lagging.lag_seconds == 391
+            && lagging.max_lag_seconds == 390
+    ));
+    let checkpoint: UsageCheckpoint = f
+        .svm
+        .get(Layer::Base, &pda::usage_checkpoint(&f.api_key, 1))
+        .unwrap();
+    assert!(checkpoint.lagging);
+    assert_eq!(checkpoint.request_count, 3);
+}
+
+#[test]
+fn returned_delegations_are_reconciled_from_the_last_checkpoint() {
+    let mut f = Fixture::new();
+    f.delegate();
+    f.record(3, None).unwrap();
+    let submit = instructions::submit_usage_checkpoint(f.submitter(), f.api_key, 1, [0; 32]);
+    f.svm
+        .process(Layer::Ephemeral, &[submit], &[f.authority])
+        .unwrap();
+
+    // Later usage reaches the base layer through an automatic commit only,
+    // then the region goes away
+    f.svm.advance(3_600);
+    f.record(2, None).unwrap();
+    f.svm.auto_commit(&f.usage);
+    f.svm.abandon(&f.usage);
+    let first_window = f.usage(Layer::Base).window_start_ts;
+
+    let reconcile = |f: &Fixture| {
+        instructions::reconcile_returned_delegation(f.authority, f.service, f.api_key, f.usage)
+    };
+    // Staleness counts from the checkpoint, not from the committed usage
+    f.svm.advance(3_599);
+    let failure = f
+        .svm
+        .process(Layer::Base, &[reconcile(&f)], &[f.authority])
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::DelegationNotStale.into())
+    );
+    f.svm.advance(1);
+    f.svm
+        .process(Layer::Base, &[reconcile(&f)], &[f.authority])
+        .unwrap();
+    let reconciled = f.usage(Layer::Base);
+    assert!(!reconciled.delegated);
+    assert_eq!(
+        (
+            reconciled.disputed_window_start,
+            reconciled.disputed_window_usage,
+            reconciled.disputed_window_cost
+        ),
+        (first_window, 2, 4)
+    );
+
+    // A second reconciliation adds to the usage set aside by the first
+    f.delegate();
+    f.record(4, None).unwrap();
+    f.svm.auto_commit(&f.usage);
+    f.svm.abandon(&f.usage);
+    f.svm.advance(7_200);
+    let events = f
+        .svm
+        .process(Layer::Base, &[reconcile(&f)], &[f.authority])
+        .unwrap()
+        .events()
+        .unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [LimitLayerEvent::DelegationReconciled(e)]
+            if e.disputed_window_start == first_window
+                && e.disputed_window_usage == 6
+                && e.disputed_window_cost == 12
+    ));
+}
+
+#[test]
+fn enforcement_counts_every_shard_once() {
+    let mut f = Fixture::new();
+    let shards = [1, 2]
+        .map(|index| instructions::create_usage_shard(f.authority, f.service, f.api_key, index));
+    f.svm.process(Layer::Base, &shards, &[f.authority]).unwrap();
+
+    let complete = instructions::evaluate_enforcement(f.api_key, f.policy, 3);
+    let mut missing = complete.clone();
+    missing.accounts.pop();
+    let mut repeated = missing.clone();
+    repeated.accounts.push(repeated.accounts[3].clone());
+    for instruction in [missing, repeated] {
+        let failure = f
+            .svm
+            .process(Layer::Base, &[instruction], &[f.authority])
+            .unwrap_err();
+        assert_eq!(
+            failure.error.custom(),
+            Some(ErrorCode::IncompleteUsageShards.into())
+        );
+    }
+
+    let events = f
+        .svm
+        .process(Layer::Base, &[complete], &[f.authority])
+        .unwrap()
+        .events()
+        .unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [LimitLayerEvent::EnforcementEvaluated(e)] if e.new_status == 0
+    ));
+}
+
+#[test]
+fn region_migration_moves_every_shard() {
+    let mut f = Fixture::new();
+    let target = Pubkey::new_unique();
+    let register = instructions::register_execution_region(f.admin, target, "us-east".into());
+    f.svm.process(Layer::Base, &[register], &[f.admin]).unwrap();
+    let shards = [1, 2]
+        .map(|index| instructions::create_usage_shard(f.authority, f.service, f.api_key, index));
+    f.svm.process(Layer::Base, &shards, &[f.authority]).unwrap();
+    for shard in 0..3 {
+        let delegate = [
+            instructions::prepare_delegation(
+                f.authority,
+                f.service,
+                f.api_key,
+                f.policy,
+                f.svm.validator(),
+                shard,
+                ix::PrepareDelegation {
+                    commit_frequency_ms: 0,
+                    lease_seconds: 0,
+                },
+            ),
+            instructions::delegate_usage(f.authority, f.api_key, shard, f.svm.validator()),
+        ];
+        f.svm
+            .process(Layer::Base, &delegate, &[f.authority])
+            .unwrap();
+    }
+    let begin = |usage_shards| {
+        instructions::begin_region_migration(
+            f.authority,
+            f.authority,
+            f.service,
+            f.api_key,
+            usage_shards,
+            target,
+        )
+    };
+
+    // Leaving shards behind would split the key between two regions
+    let failure = f
+        .svm
+        .process(Layer::Ephemeral, &[begin(2)], &[f.authority])
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::IncompleteUsageShards.into())
+    );
+
+    f.svm
+        .process(Layer::Ephemeral, &[begin(3)], &[f.authority])
+        .unwrap();
+    let complete =
+        instructions::complete_region_migration(f.authority, f.service, f.api_key, 3, target);
+    let events = f
+        .svm
+        .process(Layer::Base, &[complete], &[f.authority])
+        .unwrap()
+        .events()
+        .unwrap();
+    assert_eq!(events.len(), 3);
+    for shard in 0..3 {
+        let address = pda::usage_shard(&f.api_key, shard);
+        let usage: DelegatedUsageAccount = f.svm.get(Layer::Base, &address).unwrap();
+        assert_eq!(usage.execution_region, target);
+        assert_eq!(usage.migration_target, Pubkey::default());
+        assert!(events.iter().any(|event| matches!(
+            event,
+            LimitLayerEvent::UsageRegionMigrated(migrated) if migrated.delegated_usage == address
+        )));
+    }
+}
+
+#[test]
+fn enforcement_reads_delegated_usage_from_its_committed_state() {
+    let mut f = Fixture::new();
+    f.delegate();
+    f.record(5, None).unwrap();
+
+    let evaluate = |f: &mut Fixture| {
+        let instruction = instructions::evaluate_enforcement(f.api_key, f.policy, 1);
+        let events = f
+            .svm
+            .process(Layer::Base, &[instruction], &[f.authority])
+            .unwrap()
+            .events()
+            .unwrap();
+        match events.as_slice() {
+            [LimitLayerEvent::EnforcementEvaluated(e)] => e.usage,
+            _ => panic!("expected one EnforcementEvaluated event"),
+        }
+    };
+    // The base layer only sees usage once it is committed
+    assert_eq!(evaluate(&mut f), 0);
+    f.svm.auto_commit(&f.usage);
+    assert_eq!(evaluate(&mut f), 5);
+
+    // Another policy of the service does not stand in for the key's own
+    let other = pda::policy(&f.service, 1);
+    let create = [instructions::create_policy(
+        f.authority,
+        f.service,
+        1,
+        ix::CreatePolicy {
+            requests_per_window: 4,
+            window_seconds: 60,
+            burst_limit: 4,
+            cost_per_request: 1,
+            daily_quota: 0,
+            monthly_quota: 0,
+        },
+    )];
+    f.svm.process(Layer::Base, &create, &[f.authority]).unwrap();
+    let service: ServiceAccount = f.svm.get(Layer::Base, &f.service).unwrap();
+    assert_eq!(service.policy_count, 2);
+    let failure = f
+        .svm
+        .process(
+            Layer::Base,
+            &[instructions::evaluate_enforcement(f.api_key, other, 1)],
+            &[f.authority],
+        )
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::InvalidPolicy.into())
+    );
+}
+
+#[test]
+fn reservations_need_the_authority_or_a_gateway() {
+    let mut f = Fixture::new();
+    f.delegate();
+    let gateway = Pubkey::new_unique();
+    f.svm.airdrop(&gateway, LAMPORTS_PER_SOL);
+    let reserve =
+        |submitter| instructions::reserve_usage(submitter, f.usage, f.api_key, f.policy, 3, 0);
+
+    // An unregistered key cannot hold units against the key's burst
+    let failure = f
+        .svm
+        .process(
+            Layer::Ephemeral,
+            &[reserve(Submitter::authority(gateway, f.service))],
+            &[gateway],
+        )
+        .unwrap_err();
+    assert_eq!(failure.error.custom(), Some(ErrorCode::Unauthorized.into()));
+
+    let register = instructions::register_gateway(f.authority, f.service, gateway);
+    f.svm
+        .process(Layer::Base, &[register], &[f.authority])
+        .unwrap();
+    let events = f
+        .svm
+        .process(
+            Layer::Ephemeral,
+            &[reserve(Submitter::gateway(gateway, f.service))],
+            &[gateway],
+        )
+        .unwrap()
+        .events()
+        .unwrap();
+    let [LimitLayerEvent::UsageReserved(reserved)] = events.as_slice() else {
+        panic!("expected UsageReserved");
+    };
+    let release =
+        instructions::release_usage(f.submitter(), f.usage, f.api_key, reserved.reservation_id);
+    f.svm
+        .process(Layer::Ephemeral, &[release], &[f.authority])
+        .unwrap();
+    assert!(f
+        .usage(Layer::Ephemeral)
+        .reservations
+        .iter()
+        .all(|r| r.id == 0));
+}
+
+#[test]
+fn reserved_units_are_settled_after_the_key_is_blocked() {
+    let mut f = Fixture::new();
+    f.delegate();
+    let reserve = |f: &mut Fixture, amount| {
+        let reserve =
+            instructions::reserve_usage(f.submitter(), f.usage, f.api_key, f.policy, amount, 0);
+        let events = f
+            .svm
+            .process(Layer::Ephemeral, &[reserve], &[f.authority])
+            .unwrap()
+            .events()
+            .unwrap();
+        match events.as_slice() {
+            [LimitLayerEvent::UsageReserved(reserved)] => reserved.reservation_id,
+            _ => panic!("expected UsageReserved"),
+        }
+    };
+    let (first, second) = (reserve(&mut f, 3), reserve(&mut f, 3));
+    let block = instructions::manual_block_key(f.authority, f.service, f.api_key);
+    f.svm
+        .process(Layer::Base, &[block], &[f.authority])
+        .unwrap();
+    let settle = |f: &mut Fixture, reservation_id, amount| {
+        let settle = instructions::settle_usage(
+            f.submitter(),
+            f.usage,
+            f.api_key,
+            f.policy,
+            reservation_id,
+            amount,
+        );
+        f.svm.process(Layer::Ephemeral, &[settle], &[f.authority])
+    };
+
+    // Units reserved before the block were admitted, so they are charged
+    settle(&mut f, first, 2).unwrap();
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 2);
+
+    // An excess is new usage, which the block refuses
+    let failure = settle(&mut f, second, 4).unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::ApiKeyBlocked.into())
+    );
+    settle(&mut f, second, 3).unwrap();
+    assert_eq!(f.usage(Layer::Ephemeral).current_window_usage, 5);
+}
+
+#[test]
+fn checkpoints_past_the_kept_window_roots_are_flagged() {
+    let mut f = Fixture::new();
+    f.delegate();
+
+    // One window more than the usage account keeps roots for
+    let windows = MAX_WINDOW_ROOTS as u64 + 1;
+    let roots: Vec<[u8; 32]> = (1..=windows).map(|seq| [seq as u8; 32]).collect();
+    for root in &roots {
+        f.record(1, None).unwrap();
+        let submit = instructions::submit_usage_checkpoint(f.submitter(), f.api_key, 1, *root);
+        f.svm
+            .process(Layer::Ephemeral, &[submit], &[f.authority])
+            .unwrap();
+    }
+    let apply =
+        instructions::apply_usage_checkpoint(f.authority, f.service, f.api_key, f.policy, windows);
+    let events = f
+        .svm
+        .process(Layer::Base, &[apply], &[f.authority])
+        .unwrap()
+        .events()
+        .unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [LimitLayerEvent::UsageCheckpointApplied(e)] if e.request_log_truncated
+    ));
+
+    // Every window is billed, but only the last ones are provable
+    let checkpoint: UsageCheckpoint = f
+        .svm
+        .get(Layer::Base, &pda::usage_checkpoint(&f.api_key, windows))
+        .unwrap();
+    assert_eq!(checkpoint.request_count, windows);
+    assert!(checkpoint.request_log_truncated);
+    assert_eq!(checkpoint.request_log_root, merkle::root(&roots[1..]));
+}
+
+#[test]
+fn every_window_stays_provable_when_applied_together() {
+    let mut f = Fixture::new();
+    f.delegate();
+    let leaf = |request_id| RequestLogLeaf {
+        request_id,
+        amount: 1,
+        cost: 2,
+        timestamp: 0,
+    };
+    let logs = [[leaf(1), leaf(2)], [leaf(3), leaf(4)]]
+        .map(|log| log.map(|leaf| merkle::leaf_hash(&leaf).unwrap()));
+    let roots = logs.map(|log| merkle::root(&log));
+
+    // Two windows close before either is applied
+    for root in roots {
+        f.record(2, None).unwrap();
+        let submit = instructions::submit_usage_checkpoint(f.submitter(), f.api_key, 1, root);
+        f.svm
+            .process(Layer::Ephemeral, &[submit], &[f.authority])
+            .unwrap();
+    }
+    let apply =
+        instructions::apply_usage_checkpoint(f.authority, f.service, f.api_key, f.policy, 2);
+    f.svm
+        .process(Layer::Base, &[apply], &[f.authority])
+        .unwrap();
+    let checkpoint = pda::usage_checkpoint(&f.api_key, 2);
+    let applied: UsageCheckpoint = f.svm.get(Layer::Base, &checkpoint).unwrap();
+    assert_eq!(applied.request_count, 4);
+    assert_eq!(applied.request_log_root, merkle::root(&roots));
+    assert!(!applied.request_log_truncated);
+
+    // A request of either window is proven by its path within the window
+    // followed by the other window's root
+    let verify = |leaf, proof| instructions::verify_request_inclusion(checkpoint, leaf, proof);
+    for (leaf, proof) in [
+        (leaf(1), vec![logs[0][1], roots[1]]),
+        (leaf(4), vec![logs[1][0], roots[0]]),
+    ] {
+        f.svm
+            .process(Layer::Base, &[verify(leaf, proof)], &[f.authority])
+            .unwrap();
+    }
+    let failure = f
+        .svm
+        .process(
+            Layer::Base,
+            &[verify(leaf(1), vec![logs[0][1]])],
+            &[f.authority],
+        )
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::InvalidMerkleProof.into())
+    );
+
+    // The next checkpoint covers only the window closed after it
+    f.record(2, None).unwrap();
+    let submit = instructions::submit_usage_checkpoint(f.submitter(), f.api_key, 1, [0; 32]);
+    f.svm
+        .process(Layer::Ephemeral, &[submit], &[f.authority])
+        .unwrap();
+    let apply =
+        instructions::apply_usage_checkpoint(f.authority, f.service, f.api_key, f.policy, 3);
+    f.svm
+        .process(Layer::Base, &[apply], &[f.authority])
+        .unwrap();
+    let next: UsageCheckpoint = f
+        .svm
+        .get(Layer::Base, &pda::usage_checkpoint(&f.api_key, 3))
+        .unwrap();
+    assert_eq!(next.request_log_root, [0; 32]);
+}
+
+#[test]
+fn checkpoints_need_the_authority_or_a_gateway() {
+    let mut f = Fixture::new();
+    f.delegate();
+    let stranger = Pubkey::new_unique();
+    f.svm.airdrop(&stranger, LAMPORTS_PER_SOL);
+    let submit = instructions::submit_usage_checkpoint(
+        Submitter::authority(stranger, f.service),
+        f.api_key,
+        1,
+        [9; 32],
+    );
+    let failure = f
+        .svm
+        .process(Layer::Ephemeral, &[submit], &[stranger])
+        .unwrap_err();
+    assert_eq!(failure.error.custom(), Some(ErrorCode::Unauthorized.into()));
+    assert_eq!(f.usage(Layer::Ephemeral).checkpoint_seq, 0);
+}
+
+#[test]
+fn gateway_receipts_are_applied_once() {
+    let mut f = Fixture::new();
+    let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+    let public = PublicKey::from(&secret);
+    let gateway = Keypair { secret, public };
+    let gateway_key = Pubkey::new_from_array(gateway.public.to_bytes());
+    let register = instructions::register_gateway(f.authority, f.service, gateway_key);
+    f.svm
+        .process(Layer::Base, &[register], &[f.authority])
+        .unwrap();
+
+    let window_start = f.svm.clock().unix_timestamp;
+    let receipt = |nonce| UsageReceipt {
+        api_key: f.api_key,
+        window_start,
+        request_count: 5,
+        cost: 10,
+        request_log_root: [3; 32],
+        nonce,
+    };
+    let apply = |receipt: UsageReceipt, signature: [u8; 64], checkpoint_seq| {
+        let message = receipt.message().unwrap();
+        [
+            instructions::ed25519_verify(&gateway_key, &signature, &message),
+            instructions::apply_usage_receipt(
+                f.authority,
+                f.service,
+                f.policy,
+                gateway_key,
+                checkpoint_seq,
+                receipt,
+            ),
+        ]
+    };
+    let sign = |receipt: &UsageReceipt| gateway.sign(&receipt.message().unwrap()).to_bytes();
+
+    let events = f
+        .svm
+        .process(
+            Layer::Base,
+            &apply(receipt(1), sign(&receipt(1)), 0),
+            &[f.authority],
+        )
+        .unwrap()
+        .events()
+        .unwrap();
+    assert!(matches!(
+        events.as_slice(),
+        [
+            LimitLayerEvent::UsageCheckpointApplied(applied),
+            LimitLayerEvent::UsageReceiptApplied(receipt),
+        ] if applied.request_count == 5 && receipt.gateway_key == gateway_key
+    ));
+    let checkpoint: UsageCheckpoint = f
+        .svm
+        .get(Layer::Base, &pda::usage_checkpoint(&f.api_key, 1))
+        .unwrap();
+    assert_eq!(
+        (checkpoint.request_count, checkpoint.cost_accumulated),
+        (5, 10)
+    );
+    assert_eq!(checkpoint.request_log_root, [3; 32]);
+
+    // The precompile rejects a signature over another receipt...
+    let forged = sign(&receipt(3));
+    let failure = f
+        .svm
+        .process(Layer::Base, &apply(receipt(2), forged, 1), &[f.authority])
+        .unwrap_err();
+    assert_eq!(
+        (failure.instruction, failure.error),
+        (0, InstructionError::InvalidSignature)
+    );
+
+    // ...the program a receipt without one right before it...
+    let [_, alone] = apply(receipt(2), sign(&receipt(2)), 1);
+    let failure = f
+        .svm
+        .process(Layer::Base, &[alone], &[f.authority])
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::InvalidReceiptSignature.into())
+    );
+
+    // ...and a receipt applied before
+    let failure = f
+        .svm
+        .process(
+            Layer::Base,
+            &apply(receipt(1), sign(&receipt(1)), 1),
+            &[f.authority],
+        )
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::ReceiptReplayed.into())
+    );
+    assert_eq!(f.usage(Layer::Base).checkpoint_seq, 1);
+}
+
+#[test]
+fn checkpoints_are_billed_once_undisputed_or_resolved() {
+    let mut f = Fixture::new();
+    let auditor = Pubkey::new_unique();
+    let register = instructions::register_auditor(f.authority, f.service, auditor);
+    f.svm
+        .process(Layer::Base, &[register], &[f.authority])
+        .unwrap();
+
+    // Each window is recorded on the rollup, checkpointed and applied
+    f.delegate();
+    let close_window = |f: &mut Fixture, amount, checkpoint_seq| {
+        f.record(amount, None).unwrap();
+        let submit = instructions::submit_usage_checkpoint(f.submitter(), f.api_key, 1, [0; 32]);
+        let apply = instructions::apply_usage_checkpoint(
+            f.authority,
+            f.service,
+            f.api_key,
+            f.policy,
+            checkpoint_seq,
+        );
+        f.svm
+            .process(Layer::Ephemeral, &[submit], &[f.authority])
+            .unwrap();
+        f.svm
+            .process(Layer::Base, &[apply], &[f.authority])
+            .unwrap();
+        pda::usage_checkpoint(&f.api_key, checkpoint_seq)
+    };
+    let checkpoint =
+        |f: &Fixture, address| -> UsageCheckpoint { f.svm.get(Layer::Base, &address).unwrap() };
+    let finalize = |f: &Fixture, address| {
+        instructions::finalize_usage_checkpoint(f.service, f.api_key, address)
+    };
+
+    // An undisputed checkpoint is billed once its challenge period is over
+    let first = close_window(&mut f, 3, 1);
+    let finalizes_at = checkpoint(&f, first).finalizes_at;
+    f.svm
+        .advance(finalizes_at - f.svm.clock().unix_timestamp - 1);
+    let failure = f
+        .svm
+        .process(Layer::Base, &[finalize(&f, first)], &[f.authority])
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::ChallengePeriodActive.into())
+    );
+    f.svm.advance(1);
+    f.svm
+        .process(Layer::Base, &[finalize(&f, first)], &[f.authority])
+        .unwrap();
+    assert!(checkpoint(&f, first).status == CheckpointStatus::Finalized);
+
+    // A disputed one waits for the service authority and the admin
+    let second = close_window(&mut f, 4, 2);
+    let dispute = |disputer, as_auditor| {
+        instructions::dispute_usage_checkpoint(
+            disputer,
+            f.service,
+            f.api_key,
+            second,
+            as_auditor,
+            DisputeReason::ConflictingReceipt,
+            [1; 32],
+        )
+    };
+    let stranger = Pubkey::new_unique();
+    let failure = f
+        .svm
+        .process(Layer::Base, &[dispute(stranger, false)], &[stranger])
+        .unwrap_err();
+    assert_eq!(failure.error.custom(), Some(ErrorCode::Unauthorized.into()));
+    f.svm
+        .process(Layer::Base, &[dispute(auditor, true)], &[auditor])
+        .unwrap();
+    f.svm
+        .advance(checkpoint(&f, second).finalizes_at - f.svm.clock().unix_timestamp);
+    let failure = f
+        .svm
+        .process(Layer::Base, &[finalize(&f, second)], &[f.authority])
+        .unwrap_err();
+    assert_eq!(
+        failure.error.custom(),
+        Some(ErrorCode::CheckpointNotPending.into())
+    );
+
+    let resolve = |billed| {
+        instructions::resolve_checkpoint_dispute(
+            f.authority,
+            f.admin,
+            f.service,
+            f.api_key,
+            second,
+            billed,
+            billed * 2,
+        )
+    };
+    // A resolution can only lower the bill
+    let failure = f
+        .svm
+        .process(Layer::Base, &[resolve(5)], &[f.authority, f.admin])
+        .unwrap_err();
+    assert_eq!(failure.error.custom(), Some(ErrorCode::InvalidInput.into()));
+    f.svm
+        .process(Layer::Base, &[resolve(2)], &[f.authority, f.admin])
+        .unwrap();
+    let resolved = checkpoint(&f, second);
+    assert!(resolved.status == CheckpointStatus::Finalized);
+    assert_eq!(
+        (resolved.billed_request_count, resolved.billed_cost),
+        (2, 4)
+    );
+
+    let key: ApiKeyAccount = f.svm.get(Layer::Base, &f.api_key).unwrap();
+    assert_eq!((key.lifetime_usage, key.lifetime_cost), (5, 10));
+    let service: ServiceAccount = f.svm.get(Layer::Base, &f.service).unwrap();
+    assert_eq!(
+        (service.total_usage_units, service.total_cost_units),
+        (5, 10)
+    );
+}
@@ -17,16 +17,48 @@ pub const DELEGATED_USAGE_SEED: &str = "delegated_usage";
 pub const REPUTATION_SEED: &str = "reputation";
 #[constant]
 pub const ABUSE_SIGNAL_SEED: &str = "abuse_signal";
+#[constant]
+pub const EXECUTION_REGION_SEED: &str = "execution_region";
+#[constant]
+pub const GATEWAY_SEED: &str = "gateway";
+#[constant]
+pub const AUDITOR_SEED: &str = "auditor";
+
+/// Prefix of every signed usage receipt, so gateway signatures can't be
+/// replayed as other messages
+#[constant]
+pub const USAGE_RECEIPT_DOMAIN: &str = "limitlayer:usage_receipt:v1";
 
 /// GENERAL LIMITS
 #[constant]
 pub const MAX_NAME_LEN: u32 = 64;
 #[constant]
+pub const MAX_REGION_CODE_LEN: u32 = 16;
+#[constant]
 pub const MAX_FLAGS: u32 = u32::MAX;
 #[constant]
 pub const MAX_BPS: u16 = 10_000;
 #[constant]
 pub const MAX_SEVERITY: u8 = 10;
+#[constant]
+pub const MAX_USAGE_BATCH_SIZE: u32 = 16;
+/// Accounts per batch entry: delegated usage, api key, policy
+#[constant]
+pub const USAGE_BATCH_ACCOUNTS_PER_ENTRY: u32 = 3;
+/// Usage accounts per key, including the primary one
+#[constant]
+pub const MAX_USAGE_SHARDS: u8 = 16;
+/// Open reservations per usage account
+#[constant]
+pub const MAX_USAGE_RESERVATIONS: u32 = 8;
+/// Request ids remembered per usage account for duplicate detection; a retry
+/// is only recognized on the same account, within this many newer ids
+#[constant]
+pub const RECENT_REQUEST_IDS: u32 = 32;
+/// Request log roots of closed windows kept per usage account until their
+/// checkpoint is applied
+#[constant]
+pub const MAX_WINDOW_ROOTS: u32 = 8;
 
 /// DEFAULTS
 #[constant]
@@ -35,6 +67,44 @@ pub const DEFAULT_REPUTATION_SCORE: i64 = 0;
 pub const DEFAULT_WINDOW_SECONDS: u64 = 60;
 #[constant]
 pub const MIN_WINDOW_SECONDS: u64 = 1;
+#[constant]
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Automatic ER -> base layer commit interval bounds
+#[constant]
+pub const DEFAULT_COMMIT_FREQUENCY_MS: u32 = 30_000;
+#[constant]
+pub const MIN_COMMIT_FREQUENCY_MS: u32 = 1_000;
+#[constant]
+pub const MAX_COMMIT_FREQUENCY_MS: u32 = 3_600_000;
+/// Time allowed, on top of the policy window and the commit interval,
+/// between a delegated window closing and its checkpoint being applied on
+/// the base layer; checkpoints applied later are flagged as lagging
+#[constant]
+pub const CHECKPOINT_APPLY_GRACE_SECONDS: i64 = 300;
+/// No checkpoint committed for this long (2x the max commit interval) lets
+/// a delegation handed back without its final commit be reconciled
+#[constant]
+pub const DELEGATION_STALENESS_TIMEOUT_SECONDS: i64 = 7_200;
+
+/// Delegation lease bounds; a lease of 0 never expires
+#[constant]
+pub const MIN_LEASE_SECONDS: u64 = 60;
+#[constant]
+pub const MAX_LEASE_SECONDS: u64 = 30 * 86_400;
+
+/// Reservation lifetime bounds; unsettled reservations lapse after this
+#[constant]
+pub const DEFAULT_RESERVATION_TTL_SECONDS: u64 = 300;
+#[constant]
+pub const MAX_RESERVATION_TTL_SECONDS: u64 = 3_600;
+
+/// How long applied checkpoints stay open to disputes before they count
+/// towards lifetime usage and billing
+#[constant]
+pub const DEFAULT_CHALLENGE_PERIOD_SECONDS: u64 = 86_400;
+#[constant]
+pub const MAX_CHALLENGE_PERIOD_SECONDS: u64 = 30 * 86_400;
 
 /// Reputation bounds (prevent runaway math)
 #[constant]
@@ -15,8 +15,79 @@ pub enum ApiKeyStatus {
     Revoked,
 }
 
+impl From<ApiKeyStatus> for limitlayer_core::KeyStatus {
+    fn from(status: ApiKeyStatus) -> Self {
+        match status {
+            ApiKeyStatus::Active => Self::Active,
+            ApiKeyStatus::Throttled => Self::Throttled,
+            ApiKeyStatus::Blocked => Self::Blocked,
+            ApiKeyStatus::Revoked => Self::Revoked,
+        }
+    }
+}
+
+impl From<limitlayer_core::KeyStatus> for ApiKeyStatus {
+    fn from(status: limitlayer_core::KeyStatus) -> Self {
+        match status {
+            limitlayer_core::KeyStatus::Active => Self::Active,
+            limitlayer_core::KeyStatus::Throttled => Self::Throttled,
+            limitlayer_core::KeyStatus::Blocked => Self::Blocked,
+            limitlayer_core::KeyStatus::Revoked => Self::Revoked,
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Copy)]
 pub enum PolicyStatus {
     Active,
     Disabled,
-}
\ No newline at end of file
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Copy)]
+pub enum UsageRecordResult {
+    Recorded,
+    InvalidBinding,
+    NotDelegated,
+    ApiKeyBlocked,
+    BurstLimitExceeded,
+    MathOverflow,
+    LeaseExpired,
+    Duplicate,
+    /// Any other rejection
+    Rejected,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Copy)]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+impl From<limitlayer_core::QuotaPeriod> for QuotaPeriod {
+    fn from(period: limitlayer_core::QuotaPeriod) -> Self {
+        match period {
+            limitlayer_core::QuotaPeriod::Daily => Self::Daily,
+            limitlayer_core::QuotaPeriod::Monthly => Self::Monthly,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Copy)]
+pub enum ExecutionRegionStatus {
+    Active,
+    Disabled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Copy)]
+pub enum CheckpointStatus {
+    Pending,
+    Disputed,
+    Finalized,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Copy)]
+pub enum DisputeReason {
+    ConflictingReceipt,
+    InvalidMerkleProof,
+    Other,
+}
@@ -89,4 +89,76 @@ pub enum ErrorCode {
     ReputationTooLow,
     #[msg("Reputation overflow")]
     ReputationOverflow,
-}
\ No newline at end of file
+
+    // Usage batching
+    #[msg("Invalid usage batch")]
+    InvalidUsageBatch,
+
+    // Delegation commit frequency
+    #[msg("Commit frequency out of bounds")]
+    InvalidCommitFrequency,
+
+    // Returned delegations
+    #[msg("Delegation has not exceeded the staleness timeout")]
+    DelegationNotStale,
+
+    // Delegation leases
+    #[msg("Invalid lease duration")]
+    InvalidLeaseDuration,
+    #[msg("Delegation lease expired")]
+    LeaseExpired,
+    #[msg("Delegation lease has not expired")]
+    LeaseNotExpired,
+
+    // Usage shards
+    #[msg("Invalid usage shard")]
+    InvalidUsageShard,
+
+    // Usage reservations
+    #[msg("Too many open usage reservations")]
+    TooManyReservations,
+    #[msg("Usage reservation not found")]
+    ReservationNotFound,
+    #[msg("Usage reservation expired")]
+    ReservationExpired,
+
+    // Gateway receipts
+    #[msg("Missing or invalid Ed25519 receipt signature")]
+    InvalidReceiptSignature,
+    #[msg("Usage receipt nonce already used")]
+    ReceiptReplayed,
+
+    // Request log commitments
+    #[msg("Merkle proof does not match the checkpoint's request log root")]
+    InvalidMerkleProof,
+
+    // Checkpoint finalization
+    #[msg("Invalid challenge period")]
+    InvalidChallengePeriod,
+    #[msg("Checkpoint is not pending")]
+    CheckpointNotPending,
+    #[msg("Checkpoint is not disputed")]
+    CheckpointNotDisputed,
+    #[msg("Challenge period has not ended")]
+    ChallengePeriodActive,
+    #[msg("Challenge period has ended")]
+    ChallengePeriodOver,
+
+    // Enforcement
+    #[msg("Every other usage shard of the key must be passed exactly once")]
+    IncompleteUsageShards,
+}
+
+impl From<limitlayer_core::Rejection> for ErrorCode {
+    fn from(rejection: limitlayer_core::Rejection) -> Self {
+        use limitlayer_core::Rejection;
+        match rejection {
+            Rejection::NotDelegated => ErrorCode::NotDelegated,
+            Rejection::AlreadyDelegated => ErrorCode::AlreadyDelegated,
+            Rejection::LeaseExpired => ErrorCode::LeaseExpired,
+            Rejection::KeyBlocked => ErrorCode::ApiKeyBlocked,
+            Rejection::BurstLimitExceeded => ErrorCode::BurstLimitExceeded,
+            Rejection::MathOverflow => ErrorCode::MathOverflow,
+        }
+    }
+}
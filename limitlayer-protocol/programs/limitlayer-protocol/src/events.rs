@@ -30,6 +30,7 @@ pub struct ServiceUpdated {
     pub service: Pubkey,
     pub new_authority: Option<Pubkey>,
     pub new_default_policy: Option<Pubkey>,
+    pub new_challenge_period_seconds: Option<u64>,
 }
 
 #[event]
@@ -46,6 +47,8 @@ pub struct PolicyCreated {
     pub window_seconds: u64,
     pub burst_limit: u64,
     pub cost_per_request: u64,
+    pub daily_quota: u64,
+    pub monthly_quota: u64,
 }
 
 #[event]
@@ -55,6 +58,8 @@ pub struct PolicyUpdated {
     pub window_seconds: Option<u64>,
     pub burst_limit: Option<u64>,
     pub cost_per_request: Option<u64>,
+    pub daily_quota: Option<u64>,
+    pub monthly_quota: Option<u64>,
 }
 
 #[event]
@@ -85,12 +90,36 @@ pub struct ApiKeyStatusChanged {
     pub new_status: u8,
 }
 
+#[event]
+pub struct ExecutionRegionRegistered {
+    pub execution_region: Pubkey,
+    pub validator: Pubkey,
+    pub region_code: String,
+}
+
+#[event]
+pub struct ExecutionRegionStatusChanged {
+    pub execution_region: Pubkey,
+    pub validator: Pubkey,
+    pub new_status: u8,
+}
+
 #[event]
 pub struct UsageDelegated {
     pub delegated_usage: Pubkey,
     pub api_key: Pubkey,
     pub policy: Pubkey,
     pub execution_region: Pubkey,
+    pub commit_frequency_ms: u32,
+    pub lease_expires_at: i64,
+}
+
+#[event]
+pub struct UsageShardCreated {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub shard_index: u8,
+    pub usage_shards: u8,
 }
 
 #[event]
@@ -99,6 +128,41 @@ pub struct UsageUndelegated {
     pub api_key: Pubkey,
 }
 
+#[event]
+pub struct UsageRegionMigrated {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub from_region: Pubkey,
+    pub to_region: Pubkey,
+    pub delegation_seq: u64,
+}
+
+#[event]
+pub struct DelegationReconciled {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub execution_region: Pubkey,
+    pub delegation_seq: u64,
+    pub last_commit_ts: i64,
+    pub disputed_window_start: i64,
+    pub disputed_window_usage: u64,
+    pub disputed_window_cost: u64,
+}
+
+#[event]
+pub struct DelegationLeaseRenewed {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub lease_expires_at: i64,
+}
+
+#[event]
+pub struct DelegationLeaseExpired {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub lease_expires_at: i64,
+}
+
 #[event]
 pub struct UsageRecordedRealtime {
     pub delegated_usage: Pubkey,
@@ -107,12 +171,101 @@ pub struct UsageRecordedRealtime {
     pub window_usage: u64,
 }
 
+#[event]
+pub struct UsageRecordedDirect {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub amount: u64,
+    pub window_usage: u64,
+}
+
+#[event]
+pub struct DuplicateUsageIgnored {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub request_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct UsageReserved {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub reservation_id: u64,
+    pub amount: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct UsageSettled {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub reservation_id: u64,
+    pub reserved: u64,
+    pub amount: u64,
+    pub window_usage: u64,
+}
+
+#[event]
+pub struct UsageReleased {
+    pub delegated_usage: Pubkey,
+    pub api_key: Pubkey,
+    pub reservation_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct UsageBatchRecorded {
+    pub recorded: u16,
+    pub rejected: u16,
+    pub total_amount: u64,
+    /// One UsageRecordResult code per entry, in submission order
+    pub results: Vec<u8>,
+}
+
 #[event]
 pub struct UsageCheckpointSubmitted {
     pub delegated_usage: Pubkey,
     pub api_key: Pubkey,
     pub service: Pubkey,
+    pub checkpoint_seq: u64,
     pub window_usage: u64,
+    pub window_cost: u64,
+    pub request_log_root: [u8; 32],
+}
+
+#[event]
+pub struct UsageCheckpointApplied {
+    pub usage_checkpoint: Pubkey,
+    pub api_key: Pubkey,
+    pub service: Pubkey,
+    pub checkpoint_seq: u64,
+    pub request_count: u64,
+    pub cost: u64,
+    pub request_log_root: [u8; 32],
+    /// Set when the oldest windows' request logs are missing from the root
+    pub request_log_truncated: bool,
+    pub finalizes_at: i64,
+}
+
+#[event]
+pub struct UsageCheckpointLagging {
+    pub usage_checkpoint: Pubkey,
+    pub api_key: Pubkey,
+    pub checkpoint_seq: u64,
+    pub window_start: i64,
+    pub lag_seconds: i64,
+    pub max_lag_seconds: i64,
+}
+
+#[event]
+pub struct QuotaExhausted {
+    pub api_key: Pubkey,
+    pub policy: Pubkey,
+    pub period: u8,
+    pub usage: u64,
+    pub quota: u64,
+    pub resets_at: i64,
 }
 
 #[event]
@@ -150,3 +303,81 @@ pub struct ReputationUpdated {
     pub delta: i64,
     pub new_score: i64,
 }
+
+#[event]
+pub struct GatewayRegistered {
+    pub gateway: Pubkey,
+    pub service: Pubkey,
+    pub gateway_key: Pubkey,
+}
+
+#[event]
+pub struct GatewayRevoked {
+    pub gateway: Pubkey,
+    pub service: Pubkey,
+    pub gateway_key: Pubkey,
+}
+
+#[event]
+pub struct UsageReceiptApplied {
+    pub usage_checkpoint: Pubkey,
+    pub api_key: Pubkey,
+    pub gateway_key: Pubkey,
+    pub window_start: i64,
+    pub request_count: u64,
+    pub cost: u64,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct RequestInclusionVerified {
+    pub usage_checkpoint: Pubkey,
+    pub api_key: Pubkey,
+    pub checkpoint_seq: u64,
+    pub request_id: u64,
+    pub amount: u64,
+    pub cost: u64,
+}
+
+#[event]
+pub struct AuditorRegistered {
+    pub auditor_account: Pubkey,
+    pub service: Pubkey,
+    pub auditor: Pubkey,
+}
+
+#[event]
+pub struct AuditorRevoked {
+    pub auditor_account: Pubkey,
+    pub service: Pubkey,
+    pub auditor: Pubkey,
+}
+
+#[event]
+pub struct UsageCheckpointDisputed {
+    pub usage_checkpoint: Pubkey,
+    pub api_key: Pubkey,
+    pub disputer: Pubkey,
+    pub reason: u8,
+    pub evidence_hash: [u8; 32],
+}
+
+#[event]
+pub struct UsageCheckpointDisputeResolved {
+    pub usage_checkpoint: Pubkey,
+    pub api_key: Pubkey,
+    pub reported_request_count: u64,
+    pub reported_cost: u64,
+    pub billed_request_count: u64,
+    pub billed_cost: u64,
+}
+
+#[event]
+pub struct UsageCheckpointFinalized {
+    pub usage_checkpoint: Pubkey,
+    pub api_key: Pubkey,
+    pub service: Pubkey,
+    pub checkpoint_seq: u64,
+    pub billed_request_count: u64,
+    pub billed_cost: u64,
+}
@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 
 use crate::{
     constants::*,
-    events::AbuseSignalEmitted,
     error::ErrorCode,
+    events::AbuseSignalEmitted,
     state::{AbuseSignal, ReputationAccount, ServiceAccount},
 };
 
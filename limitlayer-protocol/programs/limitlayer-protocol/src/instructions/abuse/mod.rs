@@ -2,4 +2,4 @@ pub mod emit_abuse_signal;
 pub mod update_reputation;
 
 pub use emit_abuse_signal::*;
-pub use update_reputation::*;
\ No newline at end of file
+pub use update_reputation::*;
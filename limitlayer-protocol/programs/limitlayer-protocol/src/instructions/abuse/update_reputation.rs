@@ -1,11 +1,6 @@
 use anchor_lang::prelude::*;
 
-use crate::{
-    constants::*,
-    error::ErrorCode,
-    events::ReputationUpdated,
-    state::ReputationAccount,
-};
+use crate::{constants::*, error::ErrorCode, events::ReputationUpdated, state::ReputationAccount};
 
 #[derive(Accounts)]
 pub struct UpdateReputation<'info> {
@@ -22,13 +17,7 @@ impl<'info> UpdateReputation<'info> {
             .checked_add(delta)
             .ok_or(ErrorCode::ReputationOverflow)?;
 
-        if rep.global_score < REPUTATION_MIN {
-            rep.global_score = REPUTATION_MIN;
-        }
-
-        if rep.global_score > REPUTATION_MAX {
-            rep.global_score = REPUTATION_MAX;
-        }
+        rep.global_score = rep.global_score.clamp(REPUTATION_MIN, REPUTATION_MAX);
 
         rep.last_updated_ts = Clock::get()?.unix_timestamp;
 
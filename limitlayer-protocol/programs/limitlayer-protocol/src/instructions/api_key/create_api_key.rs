@@ -2,12 +2,11 @@ use anchor_lang::prelude::*;
 
 use crate::{
     constants::*,
-    events::ApiKeyCreated,
     enums::ApiKeyStatus,
     error::ErrorCode,
+    events::ApiKeyCreated,
     state::{
-        ApiKeyAccount, DelegatedUsageAccount, ProtocolState, ReputationAccount,
-        ServiceAccount,
+        ApiKeyAccount, DelegatedUsageAccount, ProtocolState, ReputationAccount, ServiceAccount,
     },
 };
 
@@ -82,7 +81,17 @@ impl<'info> CreateApiKey<'info> {
             reputation: self.reputation.key(),
             status: ApiKeyStatus::Active,
             lifetime_usage: 0,
+            lifetime_cost: 0,
+            applied_usage: 0,
+            applied_cost: 0,
+            applied_checkpoint_seq: 0,
             last_checkpoint_ts: 0,
+            day_start_ts: 0,
+            daily_usage: 0,
+            month_start_ts: 0,
+            monthly_usage: 0,
+            quota_blocked_until: 0,
+            usage_shards: 1,
             bump: bumps.api_key,
         });
 
@@ -100,15 +109,35 @@ impl<'info> CreateApiKey<'info> {
         let now = Clock::get()?.unix_timestamp;
         self.delegated_usage.set_inner(DelegatedUsageAccount {
             api_key: self.api_key.key(),
+            shard_index: 0,
             policy,
             execution_region: Pubkey::default(),
             delegated: false,
             delegation_seq: 0,
             window_start_ts: now,
             current_window_usage: 0,
+            current_window_cost: 0,
             burst_counter: 0,
             last_update_ts: now,
             delegated_at: 0,
+            last_commit_ts: 0,
+            commit_frequency_ms: 0,
+            lease_expires_at: 0,
+            migration_target: Pubkey::default(),
+            checkpoint_seq: 0,
+            checkpointed_usage: 0,
+            checkpointed_cost: 0,
+            last_checkpoint_window_start: 0,
+            last_request_log_root: [0; 32],
+            window_roots: [[0; 32]; MAX_WINDOW_ROOTS as usize],
+            disputed_window_start: 0,
+            disputed_window_usage: 0,
+            disputed_window_cost: 0,
+            reservations: Default::default(),
+            next_reservation_id: 0,
+            recent_request_ids: [0; RECENT_REQUEST_IDS as usize],
+            recent_request_cursor: 0,
+            last_receipt_nonce: 0,
             bump: bumps.delegated_usage,
         });
 
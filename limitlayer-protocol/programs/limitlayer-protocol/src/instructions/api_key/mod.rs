@@ -4,4 +4,4 @@ pub mod set_api_key_status;
 
 pub use create_api_key::*;
 pub use revoke_api_key::*;
-pub use set_api_key_status::*;
\ No newline at end of file
+pub use set_api_key_status::*;
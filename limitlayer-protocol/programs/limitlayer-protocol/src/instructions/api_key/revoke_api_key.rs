@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 
 use crate::{
     enums::ApiKeyStatus,
-    events::ApiKeyRevoked,
     error::ErrorCode,
+    events::ApiKeyRevoked,
     state::{ApiKeyAccount, ServiceAccount},
 };
 
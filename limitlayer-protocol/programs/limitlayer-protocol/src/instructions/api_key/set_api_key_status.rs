@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 
 use crate::{
     enums::ApiKeyStatus,
-    events::ApiKeyStatusChanged,
     error::ErrorCode,
+    events::ApiKeyStatusChanged,
     state::{ApiKeyAccount, ServiceAccount},
 };
 
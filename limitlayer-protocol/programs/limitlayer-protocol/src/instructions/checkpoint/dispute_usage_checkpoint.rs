@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    enums::{CheckpointStatus, DisputeReason},
+    error::ErrorCode,
+    events::UsageCheckpointDisputed,
+    state::{ApiKeyAccount, AuditorAccount, ServiceAccount, UsageCheckpoint},
+};
+
+/// Base layer: holds a pending checkpoint back from finalization until the
+/// dispute is resolved. Open to the key owner, or to a registered auditor
+/// of the service who passes their auditor account.
+#[derive(Accounts)]
+pub struct DisputeUsageCheckpoint<'info> {
+    pub disputer: Signer<'info>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        mut,
+        constraint = usage_checkpoint.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub usage_checkpoint: Account<'info, UsageCheckpoint>,
+
+    #[account(
+        seeds = [AUDITOR_SEED.as_bytes(), service.key().as_ref(), disputer.key().as_ref()],
+        bump = auditor.bump
+    )]
+    pub auditor: Option<Account<'info, AuditorAccount>>,
+}
+
+impl<'info> DisputeUsageCheckpoint<'info> {
+    pub fn dispute_usage_checkpoint(
+        &mut self,
+        reason: DisputeReason,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            self.disputer.key() == self.api_key.owner || self.auditor.is_some(),
+            ErrorCode::Unauthorized
+        );
+
+        let checkpoint = &mut self.usage_checkpoint;
+
+        require!(
+            checkpoint.status == CheckpointStatus::Pending,
+            ErrorCode::CheckpointNotPending
+        );
+        require!(
+            Clock::get()?.unix_timestamp < checkpoint.finalizes_at,
+            ErrorCode::ChallengePeriodOver
+        );
+
+        checkpoint.status = CheckpointStatus::Disputed;
+        checkpoint.disputer = self.disputer.key();
+        checkpoint.dispute_reason = Some(reason);
+        checkpoint.evidence_hash = evidence_hash;
+
+        emit!(UsageCheckpointDisputed {
+            usage_checkpoint: checkpoint.key(),
+            api_key: checkpoint.api_key,
+            disputer: checkpoint.disputer,
+            reason: reason as u8,
+            evidence_hash,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    enums::CheckpointStatus,
+    error::ErrorCode,
+    events::UsageCheckpointFinalized,
+    state::{ApiKeyAccount, ServiceAccount, UsageCheckpoint},
+};
+
+/// Base layer crank: finalizes an undisputed checkpoint once its challenge
+/// period is over. Permissionless.
+#[derive(Accounts)]
+pub struct FinalizeUsageCheckpoint<'info> {
+    #[account(mut)]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        mut,
+        constraint = usage_checkpoint.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub usage_checkpoint: Account<'info, UsageCheckpoint>,
+}
+
+impl<'info> FinalizeUsageCheckpoint<'info> {
+    pub fn finalize_usage_checkpoint(&mut self) -> Result<()> {
+        let checkpoint = &self.usage_checkpoint;
+
+        require!(
+            checkpoint.status == CheckpointStatus::Pending,
+            ErrorCode::CheckpointNotPending
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= checkpoint.finalizes_at,
+            ErrorCode::ChallengePeriodActive
+        );
+
+        let (request_count, cost) = (checkpoint.request_count, checkpoint.cost_accumulated);
+        finalize_checkpoint(
+            &mut self.service,
+            &mut self.api_key,
+            &mut self.usage_checkpoint,
+            request_count,
+            cost,
+        )
+    }
+}
+
+/// Bills `request_count` / `cost` for the checkpoint: adds them to the key's
+/// lifetime totals and the service totals and marks it finalized.
+pub(crate) fn finalize_checkpoint<'info>(
+    service: &mut Account<'info, ServiceAccount>,
+    key: &mut Account<'info, ApiKeyAccount>,
+    checkpoint: &mut Account<'info, UsageCheckpoint>,
+    request_count: u64,
+    cost: u64,
+) -> Result<()> {
+    key.lifetime_usage = key
+        .lifetime_usage
+        .checked_add(request_count as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    key.lifetime_cost = key
+        .lifetime_cost
+        .checked_add(cost as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    service.total_usage_units = service
+        .total_usage_units
+        .checked_add(request_count as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    service.total_cost_units = service
+        .total_cost_units
+        .checked_add(cost as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    checkpoint.status = CheckpointStatus::Finalized;
+    checkpoint.billed_request_count = request_count;
+    checkpoint.billed_cost = cost;
+    checkpoint.last_updated = Clock::get()?.unix_timestamp;
+
+    emit!(UsageCheckpointFinalized {
+        usage_checkpoint: checkpoint.key(),
+        api_key: key.key(),
+        service: service.key(),
+        checkpoint_seq: checkpoint.checkpoint_seq,
+        billed_request_count: request_count,
+        billed_cost: cost,
+    });
+
+    Ok(())
+}
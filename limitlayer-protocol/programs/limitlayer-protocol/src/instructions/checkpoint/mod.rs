@@ -0,0 +1,11 @@
+pub mod dispute_usage_checkpoint;
+pub mod finalize_usage_checkpoint;
+pub mod register_auditor;
+pub mod resolve_checkpoint_dispute;
+pub mod revoke_auditor;
+
+pub use dispute_usage_checkpoint::*;
+pub use finalize_usage_checkpoint::*;
+pub use register_auditor::*;
+pub use resolve_checkpoint_dispute::*;
+pub use revoke_auditor::*;
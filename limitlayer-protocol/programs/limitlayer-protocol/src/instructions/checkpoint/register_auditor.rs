@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::AuditorRegistered,
+    state::{AuditorAccount, ServiceAccount},
+};
+
+#[derive(Accounts)]
+#[instruction(auditor: Pubkey)]
+pub struct RegisterAuditor<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AuditorAccount::INIT_SPACE,
+        seeds = [AUDITOR_SEED.as_bytes(), service.key().as_ref(), auditor.as_ref()],
+        bump
+    )]
+    pub auditor_account: Account<'info, AuditorAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RegisterAuditor<'info> {
+    pub fn register_auditor(&mut self, auditor: Pubkey, bumps: RegisterAuditorBumps) -> Result<()> {
+        require!(auditor != Pubkey::default(), ErrorCode::InvalidInput);
+
+        self.auditor_account.set_inner(AuditorAccount {
+            service: self.service.key(),
+            auditor,
+            registered_ts: Clock::get()?.unix_timestamp,
+            bump: bumps.auditor_account,
+        });
+
+        emit!(AuditorRegistered {
+            auditor_account: self.auditor_account.key(),
+            service: self.service.key(),
+            auditor,
+        });
+
+        Ok(())
+    }
+}
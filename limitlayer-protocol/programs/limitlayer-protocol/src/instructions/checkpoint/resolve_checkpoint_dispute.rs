@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+
+use super::finalize_usage_checkpoint::finalize_checkpoint;
+use crate::{
+    constants::*,
+    enums::CheckpointStatus,
+    error::ErrorCode,
+    events::UsageCheckpointDisputeResolved,
+    state::{ApiKeyAccount, ProtocolState, ServiceAccount, UsageCheckpoint},
+};
+
+/// Base layer: settles a disputed checkpoint and finalizes it. Needs both
+/// the service authority and the protocol admin, so neither side of a
+/// billing dispute decides alone.
+#[derive(Accounts)]
+pub struct ResolveCheckpointDispute<'info> {
+    pub authority: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [PROTOCOL_SEED.as_bytes()],
+        bump = protocol.bump,
+        constraint = admin.key() == protocol.admin_authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        mut,
+        constraint = usage_checkpoint.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub usage_checkpoint: Account<'info, UsageCheckpoint>,
+}
+
+impl<'info> ResolveCheckpointDispute<'info> {
+    pub fn resolve_checkpoint_dispute(
+        &mut self,
+        billed_request_count: u64,
+        billed_cost: u64,
+    ) -> Result<()> {
+        let checkpoint = &self.usage_checkpoint;
+
+        require!(
+            checkpoint.status == CheckpointStatus::Disputed,
+            ErrorCode::CheckpointNotDisputed
+        );
+        // A resolution can only reduce what the key is billed
+        require!(
+            billed_request_count <= checkpoint.request_count
+                && billed_cost <= checkpoint.cost_accumulated,
+            ErrorCode::InvalidInput
+        );
+
+        emit!(UsageCheckpointDisputeResolved {
+            usage_checkpoint: checkpoint.key(),
+            api_key: checkpoint.api_key,
+            reported_request_count: checkpoint.request_count,
+            reported_cost: checkpoint.cost_accumulated,
+            billed_request_count,
+            billed_cost,
+        });
+
+        finalize_checkpoint(
+            &mut self.service,
+            &mut self.api_key,
+            &mut self.usage_checkpoint,
+            billed_request_count,
+            billed_cost,
+        )
+    }
+}
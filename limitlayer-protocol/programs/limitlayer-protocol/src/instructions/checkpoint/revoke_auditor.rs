@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::AuditorRevoked,
+    state::{AuditorAccount, ServiceAccount},
+};
+
+/// Closes an auditor registration; disputes already opened stay open.
+#[derive(Accounts)]
+pub struct RevokeAuditor<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [
+            AUDITOR_SEED.as_bytes(),
+            service.key().as_ref(),
+            auditor_account.auditor.as_ref()
+        ],
+        bump = auditor_account.bump
+    )]
+    pub auditor_account: Account<'info, AuditorAccount>,
+}
+
+impl<'info> RevokeAuditor<'info> {
+    pub fn revoke_auditor(&mut self) -> Result<()> {
+        emit!(AuditorRevoked {
+            auditor_account: self.auditor_account.key(),
+            service: self.service.key(),
+            auditor: self.auditor_account.auditor,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    enums::{CheckpointStatus, QuotaPeriod},
+    error::ErrorCode,
+    events::{QuotaExhausted, UsageCheckpointApplied, UsageCheckpointLagging},
+    state::{
+        ApiKeyAccount, DelegatedUsageAccount, ProtocolState, RateLimitPolicy, ServiceAccount,
+        UsageCheckpoint,
+    },
+};
+
+/// Base layer: records committed delegated usage as a pending checkpoint.
+/// Permissionless; everything applied is read from committed state.
+#[derive(Accounts)]
+#[instruction(checkpoint_seq: u64)]
+pub struct ApplyUsageCheckpoint<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED.as_bytes()],
+        bump = protocol.bump
+    )]
+    pub protocol: Account<'info, ProtocolState>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        constraint = policy.key() == api_key.policy @ ErrorCode::InvalidPolicy
+    )]
+    pub policy: Account<'info, RateLimitPolicy>,
+
+    /// CHECK: owned by us or by the delegation program; read via load_committed
+    #[account(
+        seeds = [DELEGATED_USAGE_SEED.as_bytes(), api_key.key().as_ref()],
+        bump
+    )]
+    pub delegated_usage: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UsageCheckpoint::INIT_SPACE,
+        seeds = [
+            USAGE_SEED.as_bytes(),
+            api_key.key().as_ref(),
+            checkpoint_seq.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub usage_checkpoint: Account<'info, UsageCheckpoint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ApplyUsageCheckpoint<'info> {
+    pub fn apply_usage_checkpoint(
+        &mut self,
+        checkpoint_seq: u64,
+        bumps: ApplyUsageCheckpointBumps,
+    ) -> Result<()> {
+        let d = DelegatedUsageAccount::load_committed(&self.delegated_usage)?;
+
+        require!(
+            checkpoint_seq == d.checkpoint_seq,
+            ErrorCode::InvalidCheckpointSequence
+        );
+
+        apply_committed_usage(
+            &mut self.protocol,
+            &self.service,
+            &mut self.api_key,
+            &self.policy,
+            &mut self.usage_checkpoint,
+            &d,
+            bumps.usage_checkpoint,
+        )
+    }
+}
+
+/// Writes the usage closed on `d` since the last applied checkpoint to a
+/// pending `usage_checkpoint`. Quotas count it right away; lifetime usage and
+/// billing totals only once it is finalized.
+pub(crate) fn apply_committed_usage<'info>(
+    protocol: &mut Account<'info, ProtocolState>,
+    service: &Account<'info, ServiceAccount>,
+    key: &mut Account<'info, ApiKeyAccount>,
+    policy: &Account<'info, RateLimitPolicy>,
+    usage_checkpoint: &mut Account<'info, UsageCheckpoint>,
+    d: &DelegatedUsageAccount,
+    bump: u8,
+) -> Result<()> {
+    // Totals only grow, so the delta covers every window closed since the
+    // last applied checkpoint, even if some were never applied one by one.
+    let request_count = d
+        .checkpointed_usage
+        .checked_sub(key.applied_usage)
+        .ok_or(ErrorCode::CheckpointRegression)?;
+    let cost = d
+        .checkpointed_cost
+        .checked_sub(key.applied_cost)
+        .ok_or(ErrorCode::CheckpointRegression)?;
+    let request_count = u64::try_from(request_count).map_err(|_| ErrorCode::MathOverflow)?;
+    let cost = u64::try_from(cost).map_err(|_| ErrorCode::MathOverflow)?;
+
+    let now = Clock::get()?.unix_timestamp;
+
+    // One root per window closed since the last applied checkpoint, folded
+    // so each window's log stays provable against this checkpoint. Only the
+    // last MAX_WINDOW_ROOTS are kept; refusing the rest would leave the key's
+    // usage unbillable, so the checkpoint is flagged instead.
+    let request_log_root = d.request_log_root_since(key.applied_checkpoint_seq);
+    let request_log_truncated = d.request_logs_truncated_since(key.applied_checkpoint_seq);
+
+    key.applied_usage = d.checkpointed_usage;
+    key.applied_cost = d.checkpointed_cost;
+    key.applied_checkpoint_seq = d.checkpoint_seq;
+    key.last_checkpoint_ts = now;
+
+    // Quota periods follow the time the checkpoint lands on the base layer.
+    if let Some(exhaustion) = key.charge_quota(policy, request_count, now) {
+        emit!(QuotaExhausted {
+            api_key: key.key(),
+            policy: policy.key(),
+            period: QuotaPeriod::from(exhaustion.period) as u8,
+            usage: exhaustion.usage,
+            quota: exhaustion.quota,
+            resets_at: exhaustion.resets_at,
+        });
+    }
+
+    // Windows only close when the gateway submits them, so automatic commits
+    // alone never move usage here. A delegated window applied long after it
+    // opened is kept but flagged for auditors.
+    let lag_seconds = now.saturating_sub(d.last_checkpoint_window_start);
+    let max_lag_seconds = d.max_checkpoint_lag(policy);
+    let lagging = d.delegated && lag_seconds > max_lag_seconds;
+
+    let finalizes_at = now
+        .checked_add(service.challenge_period_seconds as i64)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    protocol.total_usage_checkpoints = protocol
+        .total_usage_checkpoints
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    usage_checkpoint.set_inner(UsageCheckpoint {
+        api_key: key.key(),
+        checkpoint_seq: d.checkpoint_seq,
+        window_start: d.last_checkpoint_window_start,
+        request_count,
+        cost_accumulated: cost,
+        last_updated: now,
+        request_log_root,
+        status: CheckpointStatus::Pending,
+        finalizes_at,
+        disputer: Pubkey::default(),
+        dispute_reason: None,
+        evidence_hash: [0; 32],
+        billed_request_count: 0,
+        billed_cost: 0,
+        lagging,
+        request_log_truncated,
+        bump,
+    });
+
+    emit!(UsageCheckpointApplied {
+        usage_checkpoint: usage_checkpoint.key(),
+        api_key: key.key(),
+        service: service.key(),
+        checkpoint_seq: d.checkpoint_seq,
+        request_count,
+        cost,
+        request_log_root,
+        request_log_truncated,
+        finalizes_at,
+    });
+
+    if lagging {
+        emit!(UsageCheckpointLagging {
+            usage_checkpoint: usage_checkpoint.key(),
+            api_key: key.key(),
+            checkpoint_seq: d.checkpoint_seq,
+            window_start: d.last_checkpoint_window_start,
+            lag_seconds,
+            max_lag_seconds,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+use ephemeral_rollups_sdk::anchor::commit;
+use ephemeral_rollups_sdk::ephem::commit_and_undelegate_accounts;
+
+use crate::{
+    constants::EXECUTION_REGION_SEED,
+    enums::ExecutionRegionStatus,
+    error::ErrorCode,
+    state::{ApiKeyAccount, DelegatedUsageAccount, ExecutionRegionAccount, ServiceAccount},
+};
+
+/// ER side of a region migration: commits and undelegates the key's usage
+/// accounts with their window state intact. The other shards of the key,
+/// `1..usage_shards`, are passed in order (writable) through
+/// remaining_accounts, so the key moves as a whole. Finish with
+/// complete_region_migration + delegate_usage for every shard on the base
+/// layer.
+#[commit]
+#[derive(Accounts)]
+pub struct BeginRegionMigration<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        mut,
+        constraint = delegated_usage.api_key == api_key.key() @ ErrorCode::InvalidApiKey,
+        constraint = delegated_usage.shard_index == 0 @ ErrorCode::InvalidUsageShard
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    /// Target region
+    #[account(
+        seeds = [EXECUTION_REGION_SEED.as_bytes(), execution_region.validator.as_ref()],
+        bump = execution_region.bump,
+        constraint = execution_region.status == ExecutionRegionStatus::Active
+            @ ErrorCode::InvalidExecutionRegion
+    )]
+    pub execution_region: Account<'info, ExecutionRegionAccount>,
+}
+
+impl<'info> BeginRegionMigration<'info> {
+    pub fn begin_region_migration(
+        &mut self,
+        shard_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        let new_execution_region = self.execution_region.validator;
+        let d = &mut self.delegated_usage;
+
+        require!(d.delegated, ErrorCode::NotDelegated);
+        require!(
+            new_execution_region != d.execution_region,
+            ErrorCode::InvalidExecutionRegion
+        );
+        require!(
+            shard_accounts.len() + 1 == self.api_key.usage_shards.max(1) as usize,
+            ErrorCode::IncompleteUsageShards
+        );
+
+        d.migration_target = new_execution_region;
+        d.delegated = false;
+
+        d.exit(&crate::ID)?;
+
+        let mut accounts = vec![d.to_account_info()];
+        for (i, info) in shard_accounts.iter().enumerate() {
+            require!(info.is_writable, ErrorCode::InvalidUsageShard);
+            let mut shard = Account::<DelegatedUsageAccount>::try_from(info)?;
+            require!(
+                shard.api_key == d.api_key
+                    && shard.shard_index as usize == i + 1
+                    && shard.execution_region == d.execution_region,
+                ErrorCode::InvalidUsageShard
+            );
+            shard.verify_address(info.key)?;
+            require!(shard.delegated, ErrorCode::NotDelegated);
+
+            shard.migration_target = new_execution_region;
+            shard.delegated = false;
+            shard.exit(&crate::ID)?;
+            accounts.push(info.clone());
+        }
+
+        commit_and_undelegate_accounts(
+            &self.payer,
+            accounts.iter().collect(),
+            &self.magic_context,
+            &self.magic_program,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    enums::ExecutionRegionStatus,
+    error::ErrorCode,
+    events::UsageRegionMigrated,
+    state::{ApiKeyAccount, DelegatedUsageAccount, ExecutionRegionAccount, ServiceAccount},
+};
+
+/// Base layer side of a region migration. Unlike prepare_delegation, the
+/// window counters carry over; follow with delegate_usage to the new region
+/// for every shard. The other shards of the key, `1..usage_shards`, are
+/// passed in order (writable) through remaining_accounts.
+#[derive(Accounts)]
+pub struct CompleteRegionMigration<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            DELEGATED_USAGE_SEED.as_bytes(),
+            api_key.key().as_ref(),
+            delegated_usage.shard_seed()
+        ],
+        bump = delegated_usage.bump,
+        constraint = delegated_usage.shard_index == 0 @ ErrorCode::InvalidUsageShard,
+        constraint = delegated_usage.migration_target != Pubkey::default()
+            @ ErrorCode::InvalidDelegationState
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    /// Migration target; must still be active when the migration lands
+    #[account(
+        seeds = [EXECUTION_REGION_SEED.as_bytes(), execution_region.validator.as_ref()],
+        bump = execution_region.bump,
+        constraint = execution_region.validator == delegated_usage.migration_target
+            @ ErrorCode::InvalidExecutionRegion,
+        constraint = execution_region.status == ExecutionRegionStatus::Active
+            @ ErrorCode::InvalidExecutionRegion
+    )]
+    pub execution_region: Account<'info, ExecutionRegionAccount>,
+}
+
+impl<'info> CompleteRegionMigration<'info> {
+    pub fn complete_region_migration(
+        &mut self,
+        shard_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        require!(
+            shard_accounts.len() + 1 == self.api_key.usage_shards.max(1) as usize,
+            ErrorCode::IncompleteUsageShards
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let d = &mut self.delegated_usage;
+        let target = d.migration_target;
+        land_migration(d, now)?;
+
+        for (i, info) in shard_accounts.iter().enumerate() {
+            require!(info.is_writable, ErrorCode::InvalidUsageShard);
+            let mut shard = Account::<DelegatedUsageAccount>::try_from(info)?;
+            require!(
+                shard.api_key == d.api_key && shard.shard_index as usize == i + 1,
+                ErrorCode::InvalidUsageShard
+            );
+            shard.verify_address(info.key)?;
+            require!(
+                shard.migration_target == target,
+                ErrorCode::InvalidDelegationState
+            );
+
+            land_migration(&mut shard, now)?;
+            shard.exit(&crate::ID)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves an undelegated usage account to its migration target.
+fn land_migration(d: &mut Account<DelegatedUsageAccount>, now: i64) -> Result<()> {
+    require!(!d.delegated, ErrorCode::AlreadyDelegated);
+
+    let from_region = d.execution_region;
+    d.execution_region = d.migration_target;
+    d.migration_target = Pubkey::default();
+
+    d.delegation_seq = d
+        .delegation_seq
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    d.delegated = true;
+    d.delegated_at = now;
+
+    emit!(UsageRegionMigrated {
+        delegated_usage: d.key(),
+        api_key: d.api_key,
+        from_region,
+        to_region: d.execution_region,
+        delegation_seq: d.delegation_seq,
+    });
+
+    Ok(())
+}
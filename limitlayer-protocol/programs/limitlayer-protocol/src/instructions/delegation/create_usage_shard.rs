@@ -0,0 +1,105 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::UsageShardCreated,
+    state::{ApiKeyAccount, DelegatedUsageAccount, ServiceAccount},
+};
+
+/// Base layer: adds another usage account for a high-throughput key so
+/// gateways can spread writes. Shards are created in order, starting at 1.
+#[derive(Accounts)]
+#[instruction(shard_index: u8)]
+pub struct CreateUsageShard<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + DelegatedUsageAccount::INIT_SPACE,
+        seeds = [
+            DELEGATED_USAGE_SEED.as_bytes(),
+            api_key.key().as_ref(),
+            &[shard_index]
+        ],
+        bump
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateUsageShard<'info> {
+    pub fn create_usage_shard(
+        &mut self,
+        shard_index: u8,
+        bumps: CreateUsageShardBumps,
+    ) -> Result<()> {
+        let key = &mut self.api_key;
+
+        require!(
+            shard_index == key.usage_shards && shard_index < MAX_USAGE_SHARDS,
+            ErrorCode::InvalidUsageShard
+        );
+
+        key.usage_shards += 1;
+
+        let now = Clock::get()?.unix_timestamp;
+
+        self.delegated_usage.set_inner(DelegatedUsageAccount {
+            api_key: key.key(),
+            shard_index,
+            policy: key.policy,
+            execution_region: Pubkey::default(),
+            delegated: false,
+            delegation_seq: 0,
+            window_start_ts: now,
+            current_window_usage: 0,
+            current_window_cost: 0,
+            burst_counter: 0,
+            last_update_ts: now,
+            delegated_at: 0,
+            last_commit_ts: 0,
+            commit_frequency_ms: 0,
+            lease_expires_at: 0,
+            migration_target: Pubkey::default(),
+            checkpoint_seq: 0,
+            checkpointed_usage: 0,
+            checkpointed_cost: 0,
+            last_checkpoint_window_start: 0,
+            last_request_log_root: [0; 32],
+            window_roots: [[0; 32]; MAX_WINDOW_ROOTS as usize],
+            disputed_window_start: 0,
+            disputed_window_usage: 0,
+            disputed_window_cost: 0,
+            reservations: Default::default(),
+            next_reservation_id: 0,
+            recent_request_ids: [0; RECENT_REQUEST_IDS as usize],
+            recent_request_cursor: 0,
+            last_receipt_nonce: 0,
+            bump: bumps.delegated_usage,
+        });
+
+        emit!(UsageShardCreated {
+            delegated_usage: self.delegated_usage.key(),
+            api_key: key.key(),
+            shard_index,
+            usage_shards: key.usage_shards,
+        });
+
+        Ok(())
+    }
+}
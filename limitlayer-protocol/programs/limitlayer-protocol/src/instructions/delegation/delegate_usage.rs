@@ -2,7 +2,12 @@ use anchor_lang::prelude::*;
 use ephemeral_rollups_sdk::anchor::delegate;
 use ephemeral_rollups_sdk::cpi::DelegateConfig;
 
-use crate::{constants::*, events::UsageDelegated, state::ApiKeyAccount};
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::UsageDelegated,
+    state::{ApiKeyAccount, DelegatedUsageAccount},
+};
 
 /// Minimal delegate: payer + PDA to delegate. Call after prepare_delegation.
 #[delegate]
@@ -24,12 +29,28 @@ impl<'info> DelegateUsage<'info> {
         execution_region: Pubkey,
         _bumps: DelegateUsageBumps,
     ) -> Result<()> {
+        let prepared = DelegatedUsageAccount::load_committed(&self.pda)?;
+        require!(
+            prepared.delegated && prepared.api_key == self.api_key.key(),
+            ErrorCode::InvalidDelegationState
+        );
+        require!(
+            prepared.execution_region == execution_region,
+            ErrorCode::InvalidExecutionRegion
+        );
+        let commit_frequency_ms = prepared.commit_frequency_ms;
+        let lease_expires_at = prepared.lease_expires_at;
+
         self.delegate_pda(
             &self.payer,
-            &[DELEGATED_USAGE_SEED.as_bytes(), self.api_key.key().as_ref()],
+            &[
+                DELEGATED_USAGE_SEED.as_bytes(),
+                self.api_key.key().as_ref(),
+                prepared.shard_seed(),
+            ],
             DelegateConfig {
+                commit_frequency_ms,
                 validator: Some(execution_region),
-                ..Default::default()
             },
         )?;
 
@@ -38,6 +59,8 @@ impl<'info> DelegateUsage<'info> {
             api_key: self.api_key.key(),
             policy: self.api_key.policy,
             execution_region,
+            commit_frequency_ms,
+            lease_expires_at,
         });
 
         Ok(())
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use ephemeral_rollups_sdk::anchor::commit;
+use ephemeral_rollups_sdk::ephem::commit_and_undelegate_accounts;
+
+use crate::{error::ErrorCode, events::DelegationLeaseExpired, state::DelegatedUsageAccount};
+
+/// ER crank: commits and undelegates a key whose lease has run out.
+/// Permissionless, so idle keys don't keep rollup resources pinned.
+#[commit]
+#[derive(Accounts)]
+pub struct ExpireDelegationLease<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+}
+
+impl<'info> ExpireDelegationLease<'info> {
+    pub fn expire_delegation_lease(&mut self) -> Result<()> {
+        let d = &mut self.delegated_usage;
+
+        require!(d.delegated, ErrorCode::NotDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(d.lease_expired(now), ErrorCode::LeaseNotExpired);
+        // A shard's usage only reaches the base layer through the primary
+        // account's checkpoint, so it must be folded in first.
+        require!(
+            d.shard_index == 0 || d.current_window_usage == 0,
+            ErrorCode::DelegationRequiresCheckpoint
+        );
+
+        d.close_window(now)?;
+        d.delegated = false;
+
+        emit!(DelegationLeaseExpired {
+            delegated_usage: d.key(),
+            api_key: d.api_key,
+            lease_expires_at: d.lease_expires_at,
+        });
+
+        d.exit(&crate::ID)?;
+
+        commit_and_undelegate_accounts(
+            &self.payer,
+            vec![&self.delegated_usage.to_account_info()],
+            &self.magic_context,
+            &self.magic_program,
+        )?;
+
+        Ok(())
+    }
+}
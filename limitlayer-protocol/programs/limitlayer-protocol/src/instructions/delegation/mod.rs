@@ -1,11 +1,37 @@
+pub mod apply_usage_checkpoint;
+pub mod begin_region_migration;
+pub mod complete_region_migration;
+pub mod create_usage_shard;
 pub mod delegate_usage;
+pub mod expire_delegation_lease;
 pub mod prepare_delegation;
+pub mod reconcile_returned_delegation;
+pub mod record_usage_batch;
+pub mod record_usage_direct;
 pub mod record_usage_realtime;
+pub mod release_usage;
+pub mod renew_delegation_lease;
+pub mod reserve_usage;
+pub mod settle_usage;
 pub mod submit_usage_checkpoint;
 pub mod undelegate_usage;
+pub mod verify_request_inclusion;
 
+pub use apply_usage_checkpoint::*;
+pub use begin_region_migration::*;
+pub use complete_region_migration::*;
+pub use create_usage_shard::*;
 pub use delegate_usage::*;
+pub use expire_delegation_lease::*;
 pub use prepare_delegation::*;
+pub use reconcile_returned_delegation::*;
+pub use record_usage_batch::*;
+pub use record_usage_direct::*;
 pub use record_usage_realtime::*;
+pub use release_usage::*;
+pub use renew_delegation_lease::*;
+pub use reserve_usage::*;
+pub use settle_usage::*;
 pub use submit_usage_checkpoint::*;
-pub use undelegate_usage::*;
\ No newline at end of file
+pub use undelegate_usage::*;
+pub use verify_request_inclusion::*;
@@ -1,9 +1,13 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    constants::DELEGATED_USAGE_SEED,
+    constants::*,
+    enums::ExecutionRegionStatus,
     error::ErrorCode,
-    state::{ApiKeyAccount, DelegatedUsageAccount, RateLimitPolicy, ServiceAccount},
+    state::{
+        ApiKeyAccount, DelegatedUsageAccount, ExecutionRegionAccount, RateLimitPolicy,
+        ServiceAccount,
+    },
 };
 
 #[derive(Accounts)]
@@ -26,35 +30,72 @@ pub struct PrepareDelegation<'info> {
     )]
     pub policy: Account<'info, RateLimitPolicy>,
 
+    #[account(
+        seeds = [EXECUTION_REGION_SEED.as_bytes(), execution_region.validator.as_ref()],
+        bump = execution_region.bump,
+        constraint = execution_region.status == ExecutionRegionStatus::Active
+            @ ErrorCode::InvalidExecutionRegion
+    )]
+    pub execution_region: Account<'info, ExecutionRegionAccount>,
+
     #[account(
         mut,
-        seeds = [DELEGATED_USAGE_SEED.as_bytes(), api_key.key().as_ref()],
+        seeds = [
+            DELEGATED_USAGE_SEED.as_bytes(),
+            api_key.key().as_ref(),
+            delegated_usage.shard_seed()
+        ],
         bump = delegated_usage.bump
     )]
     pub delegated_usage: Account<'info, DelegatedUsageAccount>,
 }
 
 impl<'info> PrepareDelegation<'info> {
-    pub fn prepare_delegation(&mut self, execution_region: Pubkey) -> Result<()> {
+    pub fn prepare_delegation(
+        &mut self,
+        commit_frequency_ms: u32,
+        lease_seconds: u64,
+    ) -> Result<()> {
         let d = &mut self.delegated_usage;
 
         require!(!d.delegated, ErrorCode::AlreadyDelegated);
 
+        // Bounded commit interval => bounded lag between ER and canonical state
+        let commit_frequency_ms = match commit_frequency_ms {
+            0 => DEFAULT_COMMIT_FREQUENCY_MS,
+            f => f,
+        };
+        require!(
+            (MIN_COMMIT_FREQUENCY_MS..=MAX_COMMIT_FREQUENCY_MS).contains(&commit_frequency_ms),
+            ErrorCode::InvalidCommitFrequency
+        );
+
         d.api_key = self.api_key.key();
         d.policy = self.policy.key();
-        d.execution_region = execution_region;
+        d.execution_region = self.execution_region.validator;
+        d.commit_frequency_ms = commit_frequency_ms;
+        // A fresh delegation abandons any pending migration
+        d.migration_target = Pubkey::default();
 
         d.delegation_seq = d
             .delegation_seq
             .checked_add(1)
             .ok_or(ErrorCode::MathOverflow)?;
         let now = Clock::get()?.unix_timestamp;
+        // Usage recorded directly on the base layer is kept as a closed
+        // window instead of being dropped with the reset below.
+        if d.current_window_usage > 0 {
+            d.close_window(now)?;
+        }
         d.window_start_ts = now;
         d.current_window_usage = 0;
+        d.current_window_cost = 0;
         d.burst_counter = 0;
+        d.reservations = Default::default();
         d.last_update_ts = now;
         d.delegated = true;
         d.delegated_at = now;
+        d.lease_expires_at = DelegatedUsageAccount::lease_expiry(now, lease_seconds)?;
 
         Ok(())
     }
@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::DelegationReconciled,
+    state::{ApiKeyAccount, DelegatedUsageAccount, ServiceAccount},
+};
+
+/// Base layer cleanup for a usage account the delegation program handed back
+/// without the region's final commit. Undelegation then restores the last
+/// committed state, in which `delegated` is still true, so neither
+/// prepare_delegation nor a new delegation can run. This resynchronizes the
+/// flag and sets aside the uncheckpointed window.
+///
+/// It cannot take the account back from an unresponsive region: the
+/// delegation program's Undelegate must be signed by the region's validator,
+/// after a commit of its own has allowed undelegation, so no instruction here
+/// can force it.
+#[derive(Accounts)]
+pub struct ReconcileReturnedDelegation<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    /// Fails with AccountOwnedByWrongProgram while the delegation program
+    /// still holds the account.
+    #[account(
+        mut,
+        seeds = [
+            DELEGATED_USAGE_SEED.as_bytes(),
+            api_key.key().as_ref(),
+            delegated_usage.shard_seed()
+        ],
+        bump = delegated_usage.bump
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+}
+
+impl<'info> ReconcileReturnedDelegation<'info> {
+    pub fn reconcile_returned_delegation(&mut self) -> Result<()> {
+        let d = &mut self.delegated_usage;
+
+        require!(d.delegated, ErrorCode::NotDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        let last_commit_ts = d.last_commit_ts.max(d.delegated_at);
+        require!(
+            now.saturating_sub(last_commit_ts) >= DELEGATION_STALENESS_TIMEOUT_SECONDS,
+            ErrorCode::DelegationNotStale
+        );
+
+        // The open window was never checkpointed; keep it out of the
+        // checkpointed totals and flag it for billing instead. Nothing bills
+        // a window set aside earlier, so add to it rather than replace it.
+        if d.disputed_window_usage == 0 && d.disputed_window_cost == 0 {
+            d.disputed_window_start = d.window_start_ts;
+        }
+        d.disputed_window_usage = d
+            .disputed_window_usage
+            .checked_add(d.current_window_usage)
+            .ok_or(ErrorCode::MathOverflow)?;
+        d.disputed_window_cost = d
+            .disputed_window_cost
+            .checked_add(d.current_window_cost)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        d.current_window_usage = 0;
+        d.current_window_cost = 0;
+        d.burst_counter = 0;
+        d.window_start_ts = now;
+        d.last_update_ts = now;
+        d.migration_target = Pubkey::default();
+        d.delegated = false;
+
+        emit!(DelegationReconciled {
+            delegated_usage: d.key(),
+            api_key: d.api_key,
+            execution_region: d.execution_region,
+            delegation_seq: d.delegation_seq,
+            last_commit_ts,
+            disputed_window_start: d.disputed_window_start,
+            disputed_window_usage: d.disputed_window_usage,
+            disputed_window_cost: d.disputed_window_cost,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    enums::UsageRecordResult,
+    error::ErrorCode,
+    events::UsageBatchRecorded,
+    state::{
+        require_usage_submitter, ApiKeyAccount, DelegatedUsageAccount, GatewayAccount,
+        RateLimitPolicy, ServiceAccount,
+    },
+};
+
+/// Records usage for many keys of one service at once. Entries are passed
+/// through remaining_accounts as [delegated_usage (mut), api_key, policy]
+/// triples, one triple per amount. `request_ids` is either empty or holds an
+/// optional idempotency key per entry; entries already recorded are reported
+/// as duplicates and not counted again.
+#[derive(Accounts)]
+pub struct RecordUsageBatch<'info> {
+    /// Service authority, or a gateway registered for the service
+    pub submitter: Signer<'info>,
+
+    /// Every entry's key must belong to this service
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Required unless `submitter` is the service authority
+    #[account(
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), submitter.key().as_ref()],
+        bump = gateway.bump
+    )]
+    pub gateway: Option<Account<'info, GatewayAccount>>,
+}
+
+impl<'info> RecordUsageBatch<'info> {
+    pub fn record_usage_batch(
+        &mut self,
+        amounts: Vec<u64>,
+        request_ids: Vec<Option<u64>>,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        require_usage_submitter(&self.submitter.key(), &self.service, &self.gateway)?;
+        require!(
+            !amounts.is_empty() && amounts.len() <= MAX_USAGE_BATCH_SIZE as usize,
+            ErrorCode::InvalidUsageBatch
+        );
+        require!(
+            remaining_accounts.len() == amounts.len() * USAGE_BATCH_ACCOUNTS_PER_ENTRY as usize,
+            ErrorCode::InvalidUsageBatch
+        );
+        require!(
+            request_ids.is_empty() || request_ids.len() == amounts.len(),
+            ErrorCode::InvalidUsageBatch
+        );
+        require!(
+            !request_ids.contains(&Some(0)),
+            ErrorCode::InvalidUsageBatch
+        );
+
+        let service = self.service.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut results = Vec::with_capacity(amounts.len());
+        let mut recorded: u16 = 0;
+        let mut total_amount: u64 = 0;
+
+        for (i, (entry, amount)) in remaining_accounts
+            .chunks_exact(USAGE_BATCH_ACCOUNTS_PER_ENTRY as usize)
+            .zip(amounts)
+            .enumerate()
+        {
+            let request_id = request_ids.get(i).copied().flatten();
+            let result = record_entry(
+                &entry[0], &entry[1], &entry[2], &service, amount, request_id, now,
+            )?;
+
+            if result == UsageRecordResult::Recorded {
+                recorded += 1;
+                total_amount = total_amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+            results.push(result as u8);
+        }
+
+        emit!(UsageBatchRecorded {
+            recorded,
+            rejected: results.len() as u16 - recorded,
+            total_amount,
+            results,
+        });
+
+        Ok(())
+    }
+}
+
+/// Applies a single batch entry. Binding and limit failures are reported as a
+/// result code; only errors persisting an accepted entry abort the batch.
+fn record_entry<'info>(
+    delegated_usage_info: &'info AccountInfo<'info>,
+    api_key_info: &'info AccountInfo<'info>,
+    policy_info: &'info AccountInfo<'info>,
+    service: &Pubkey,
+    amount: u64,
+    request_id: Option<u64>,
+    now: i64,
+) -> Result<UsageRecordResult> {
+    if !delegated_usage_info.is_writable {
+        return Ok(UsageRecordResult::InvalidBinding);
+    }
+
+    let (Ok(mut d), Ok(api_key), Ok(policy)) = (
+        Account::<DelegatedUsageAccount>::try_from(delegated_usage_info),
+        Account::<ApiKeyAccount>::try_from(api_key_info),
+        Account::<RateLimitPolicy>::try_from(policy_info),
+    ) else {
+        return Ok(UsageRecordResult::InvalidBinding);
+    };
+
+    // The key's current policy, not the one the usage account last saw
+    if d.api_key != api_key.key() || api_key.service != *service || api_key.policy != policy.key() {
+        return Ok(UsageRecordResult::InvalidBinding);
+    }
+
+    if request_id.is_some_and(|id| d.seen_request(id)) {
+        return Ok(UsageRecordResult::Duplicate);
+    }
+
+    let result = match d.apply_usage(&policy, &api_key, amount, now) {
+        Ok(()) => UsageRecordResult::Recorded,
+        Err(ErrorCode::NotDelegated) => UsageRecordResult::NotDelegated,
+        Err(ErrorCode::ApiKeyBlocked) => UsageRecordResult::ApiKeyBlocked,
+        Err(ErrorCode::BurstLimitExceeded) => UsageRecordResult::BurstLimitExceeded,
+        Err(ErrorCode::LeaseExpired) => UsageRecordResult::LeaseExpired,
+        Err(ErrorCode::MathOverflow) => UsageRecordResult::MathOverflow,
+        Err(_) => UsageRecordResult::Rejected,
+    };
+
+    if result == UsageRecordResult::Recorded {
+        if let Some(request_id) = request_id {
+            d.remember_request(request_id);
+        }
+        d.exit(&crate::ID)?;
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,106 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::{DuplicateUsageIgnored, UsageCheckpointSubmitted, UsageRecordedDirect},
+    state::{
+        require_usage_submitter, ApiKeyAccount, DelegatedUsageAccount, GatewayAccount,
+        RateLimitPolicy, ServiceAccount,
+    },
+};
+
+/// Base layer: records usage for a key that is not delegated, for low-volume
+/// keys and rollup outages. Fails with AlreadyDelegated while the key is
+/// delegated, so the two modes never count the same window.
+#[derive(Accounts)]
+pub struct RecordUsageDirect<'info> {
+    /// Service authority, or a gateway registered for the key's service
+    pub submitter: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [DELEGATED_USAGE_SEED.as_bytes(), api_key.key().as_ref()],
+        bump = delegated_usage.bump
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        constraint = policy.key() == api_key.policy @ ErrorCode::InvalidPolicy
+    )]
+    pub policy: Account<'info, RateLimitPolicy>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Required unless `submitter` is the service authority
+    #[account(
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), submitter.key().as_ref()],
+        bump = gateway.bump
+    )]
+    pub gateway: Option<Account<'info, GatewayAccount>>,
+}
+
+impl<'info> RecordUsageDirect<'info> {
+    pub fn record_usage_direct(&mut self, amount: u64, request_id: Option<u64>) -> Result<()> {
+        require_usage_submitter(&self.submitter.key(), &self.service, &self.gateway)?;
+
+        let d = &mut self.delegated_usage;
+
+        require!(!d.delegated, ErrorCode::AlreadyDelegated);
+
+        if let Some(request_id) = request_id {
+            require!(request_id != 0, ErrorCode::InvalidInput);
+            if d.seen_request(request_id) {
+                emit!(DuplicateUsageIgnored {
+                    delegated_usage: d.key(),
+                    api_key: self.api_key.key(),
+                    request_id,
+                    amount,
+                });
+                return Ok(());
+            }
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+
+        // No rollup closes windows here, so close them once they run out;
+        // apply_usage_checkpoint then picks them up as usual.
+        let window_end = d
+            .window_start_ts
+            .saturating_add(self.policy.window_seconds as i64);
+        if now >= window_end {
+            let window_usage = d.current_window_usage;
+            let window_cost = d.current_window_cost;
+            d.close_window(now)?;
+
+            emit!(UsageCheckpointSubmitted {
+                delegated_usage: d.key(),
+                api_key: d.api_key,
+                service: self.service.key(),
+                checkpoint_seq: d.checkpoint_seq,
+                window_usage,
+                window_cost,
+                request_log_root: d.last_request_log_root,
+            });
+        }
+
+        d.apply_usage_direct(&self.policy, &self.api_key, amount, now)?;
+        if let Some(request_id) = request_id {
+            d.remember_request(request_id);
+        }
+
+        emit!(UsageRecordedDirect {
+            delegated_usage: d.key(),
+            api_key: self.api_key.key(),
+            amount,
+            window_usage: d.current_window_usage,
+        });
+
+        Ok(())
+    }
+}
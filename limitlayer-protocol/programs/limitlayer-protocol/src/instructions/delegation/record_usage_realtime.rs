@@ -1,52 +1,76 @@
 use anchor_lang::prelude::*;
 
 use crate::{
+    constants::*,
     error::ErrorCode,
-    events::UsageRecordedRealtime,
-    enums::ApiKeyStatus,
-    state::{ApiKeyAccount, DelegatedUsageAccount, RateLimitPolicy},
+    events::{DuplicateUsageIgnored, UsageRecordedRealtime},
+    state::{
+        require_usage_submitter, ApiKeyAccount, DelegatedUsageAccount, GatewayAccount,
+        RateLimitPolicy, ServiceAccount,
+    },
 };
 
 #[derive(Accounts)]
 pub struct RecordUsageRealtime<'info> {
-    #[account(mut)]
+    /// Service authority, or a gateway registered for the key's service
+    pub submitter: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = delegated_usage.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
     pub delegated_usage: Account<'info, DelegatedUsageAccount>,
 
     /// Read-only; only delegated_usage can be written on ER
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
     pub api_key: Account<'info, ApiKeyAccount>,
 
+    #[account(
+        constraint = policy.key() == api_key.policy @ ErrorCode::InvalidPolicy
+    )]
     pub policy: Account<'info, RateLimitPolicy>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Required unless `submitter` is the service authority
+    #[account(
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), submitter.key().as_ref()],
+        bump = gateway.bump
+    )]
+    pub gateway: Option<Account<'info, GatewayAccount>>,
 }
 
 impl<'info> RecordUsageRealtime<'info> {
-    pub fn record_usage_realtime(&mut self, amount: u64) -> Result<()> {
-        let d = &mut self.delegated_usage;
+    pub fn record_usage_realtime(&mut self, amount: u64, request_id: Option<u64>) -> Result<()> {
+        require_usage_submitter(&self.submitter.key(), &self.service, &self.gateway)?;
 
-        require!(d.delegated, ErrorCode::NotDelegated);
+        let d = &mut self.delegated_usage;
 
-        match self.api_key.status {
-            ApiKeyStatus::Blocked | ApiKeyStatus::Revoked => {
-                return err!(ErrorCode::ApiKeyBlocked)
+        // A retried request is acknowledged without being counted again
+        if let Some(request_id) = request_id {
+            require!(request_id != 0, ErrorCode::InvalidInput);
+            if d.seen_request(request_id) {
+                emit!(DuplicateUsageIgnored {
+                    delegated_usage: d.key(),
+                    api_key: self.api_key.key(),
+                    request_id,
+                    amount,
+                });
+                return Ok(());
             }
-            _ => {}
         }
 
-        d.current_window_usage = d
-            .current_window_usage
-            .checked_add(amount)
-            .ok_or(ErrorCode::MathOverflow)?;
-
-        d.burst_counter = d
-            .burst_counter
-            .checked_add(amount)
-            .ok_or(ErrorCode::MathOverflow)?;
-
-        require!(
-            d.burst_counter <= self.policy.burst_limit,
-            ErrorCode::BurstLimitExceeded
-        );
-
-        d.last_update_ts = Clock::get()?.unix_timestamp;
+        d.apply_usage(
+            &self.policy,
+            &self.api_key,
+            amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        if let Some(request_id) = request_id {
+            d.remember_request(request_id);
+        }
 
         emit!(UsageRecordedRealtime {
             delegated_usage: d.key(),
@@ -57,4 +81,4 @@ impl<'info> RecordUsageRealtime<'info> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::UsageReleased,
+    state::{
+        require_usage_submitter, ApiKeyAccount, DelegatedUsageAccount, GatewayAccount,
+        ServiceAccount,
+    },
+};
+
+/// ER: drops a reservation without charging it, e.g. when the request failed.
+#[derive(Accounts)]
+pub struct ReleaseUsage<'info> {
+    /// Service authority, or a gateway registered for the key's service
+    pub submitter: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = delegated_usage.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Required unless `submitter` is the service authority
+    #[account(
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), submitter.key().as_ref()],
+        bump = gateway.bump
+    )]
+    pub gateway: Option<Account<'info, GatewayAccount>>,
+}
+
+impl<'info> ReleaseUsage<'info> {
+    pub fn release_usage(&mut self, reservation_id: u64) -> Result<()> {
+        require_usage_submitter(&self.submitter.key(), &self.service, &self.gateway)?;
+
+        let d = &mut self.delegated_usage;
+        let now = Clock::get()?.unix_timestamp;
+
+        let reservation = d.take_reservation(reservation_id, now)?;
+        d.last_update_ts = now;
+
+        emit!(UsageReleased {
+            delegated_usage: d.key(),
+            api_key: self.api_key.key(),
+            reservation_id,
+            amount: reservation.amount,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    events::DelegationLeaseRenewed,
+    state::{ApiKeyAccount, DelegatedUsageAccount, ServiceAccount},
+};
+
+/// ER: extends (or reopens) the lease of a delegated key.
+#[derive(Accounts)]
+pub struct RenewDelegationLease<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        mut,
+        constraint = delegated_usage.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+}
+
+impl<'info> RenewDelegationLease<'info> {
+    pub fn renew_delegation_lease(&mut self, lease_seconds: u64) -> Result<()> {
+        let d = &mut self.delegated_usage;
+
+        require!(d.delegated, ErrorCode::NotDelegated);
+
+        let now = Clock::get()?.unix_timestamp;
+        d.lease_expires_at = DelegatedUsageAccount::lease_expiry(now, lease_seconds)?;
+
+        emit!(DelegationLeaseRenewed {
+            delegated_usage: d.key(),
+            api_key: d.api_key,
+            lease_expires_at: d.lease_expires_at,
+        });
+
+        Ok(())
+    }
+}
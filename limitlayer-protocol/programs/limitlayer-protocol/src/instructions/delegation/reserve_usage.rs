@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::UsageReserved,
+    state::{
+        require_usage_submitter, ApiKeyAccount, DelegatedUsageAccount, GatewayAccount,
+        RateLimitPolicy, ServiceAccount,
+    },
+};
+
+/// ER: holds units against the window before the final amount is known.
+/// Returns the reservation id to pass to settle_usage or release_usage.
+#[derive(Accounts)]
+pub struct ReserveUsage<'info> {
+    /// Service authority, or a gateway registered for the key's service
+    pub submitter: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = delegated_usage.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    /// Read-only; only delegated_usage can be written on ER
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        constraint = policy.key() == api_key.policy @ ErrorCode::InvalidPolicy
+    )]
+    pub policy: Account<'info, RateLimitPolicy>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Required unless `submitter` is the service authority
+    #[account(
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), submitter.key().as_ref()],
+        bump = gateway.bump
+    )]
+    pub gateway: Option<Account<'info, GatewayAccount>>,
+}
+
+impl<'info> ReserveUsage<'info> {
+    pub fn reserve_usage(&mut self, amount: u64, ttl_seconds: u64) -> Result<u64> {
+        require_usage_submitter(&self.submitter.key(), &self.service, &self.gateway)?;
+
+        let d = &mut self.delegated_usage;
+
+        let reservation = d.reserve(
+            &self.policy,
+            &self.api_key,
+            amount,
+            ttl_seconds,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        emit!(UsageReserved {
+            delegated_usage: d.key(),
+            api_key: self.api_key.key(),
+            reservation_id: reservation.id,
+            amount,
+            expires_at: reservation.expires_at,
+        });
+
+        Ok(reservation.id)
+    }
+}
@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::UsageSettled,
+    state::{
+        require_usage_submitter, ApiKeyAccount, DelegatedUsageAccount, GatewayAccount,
+        RateLimitPolicy, ServiceAccount,
+    },
+};
+
+/// ER: closes a reservation and charges the actual amount. Units up to the
+/// reserved amount are charged even if the key was blocked or its lease ran
+/// out since they were reserved; any excess is checked like a regular
+/// record.
+#[derive(Accounts)]
+pub struct SettleUsage<'info> {
+    /// Service authority, or a gateway registered for the key's service
+    pub submitter: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = delegated_usage.api_key == api_key.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    #[account(
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        constraint = policy.key() == api_key.policy @ ErrorCode::InvalidPolicy
+    )]
+    pub policy: Account<'info, RateLimitPolicy>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Required unless `submitter` is the service authority
+    #[account(
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), submitter.key().as_ref()],
+        bump = gateway.bump
+    )]
+    pub gateway: Option<Account<'info, GatewayAccount>>,
+}
+
+impl<'info> SettleUsage<'info> {
+    pub fn settle_usage(&mut self, reservation_id: u64, amount: u64) -> Result<()> {
+        require_usage_submitter(&self.submitter.key(), &self.service, &self.gateway)?;
+
+        let d = &mut self.delegated_usage;
+        let now = Clock::get()?.unix_timestamp;
+
+        let reservation = d.take_reservation(reservation_id, now)?;
+        d.settle(&self.policy, &self.api_key, reservation.amount, amount, now)?;
+
+        emit!(UsageSettled {
+            delegated_usage: d.key(),
+            api_key: self.api_key.key(),
+            reservation_id,
+            reserved: reservation.amount,
+            amount,
+            window_usage: d.current_window_usage,
+        });
+
+        Ok(())
+    }
+}
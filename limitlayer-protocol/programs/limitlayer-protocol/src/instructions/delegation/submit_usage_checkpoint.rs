@@ -3,49 +3,101 @@ use ephemeral_rollups_sdk::anchor::commit;
 use ephemeral_rollups_sdk::ephem::commit_accounts;
 
 use crate::{
+    constants::*,
     error::ErrorCode,
     events::UsageCheckpointSubmitted,
-    state::DelegatedUsageAccount,
+    state::{
+        require_usage_submitter, ApiKeyAccount, DelegatedUsageAccount, GatewayAccount,
+        ServiceAccount,
+    },
 };
 
+/// ER: closes the window and commits it. Other shards of the key are passed
+/// (writable) through remaining_accounts; their windows fold into this one.
 #[commit]
 #[derive(Accounts)]
 pub struct SubmitUsageCheckpoint<'info> {
+    /// Service authority, or a gateway registered for the key's service
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = delegated_usage.shard_index == 0 @ ErrorCode::InvalidUsageShard
+    )]
     pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    #[account(
+        constraint = delegated_usage.api_key == api_key.key() @ ErrorCode::InvalidApiKey,
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Required unless `payer` is the service authority
+    #[account(
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), payer.key().as_ref()],
+        bump = gateway.bump
+    )]
+    pub gateway: Option<Account<'info, GatewayAccount>>,
 }
 
 impl<'info> SubmitUsageCheckpoint<'info> {
-    pub fn submit_usage_checkpoint(&mut self) -> Result<()> {
+    pub fn submit_usage_checkpoint(
+        &mut self,
+        request_log_root: [u8; 32],
+        shard_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        require_usage_submitter(&self.payer.key(), &self.service, &self.gateway)?;
+
         let d = &mut self.delegated_usage;
 
         require!(d.delegated, ErrorCode::NotDelegated);
 
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut accounts = vec![d.to_account_info()];
+        for info in shard_accounts {
+            require!(info.is_writable, ErrorCode::InvalidUsageShard);
+            let mut shard = Account::<DelegatedUsageAccount>::try_from(info)?;
+            require!(
+                shard.api_key == d.api_key && shard.shard_index != 0 && shard.delegated,
+                ErrorCode::InvalidUsageShard
+            );
+
+            d.absorb_shard(&mut shard)?;
+            shard.last_commit_ts = now;
+            shard.exit(&crate::ID)?;
+            accounts.push(info.clone());
+        }
+
         let window_usage = d.current_window_usage;
+        let window_cost = d.current_window_cost;
 
         // Only delegated_usage is writable on ER; api_key/service/protocol updates
-        // can be applied via apply_checkpoint on base layer after commit confirms.
-        d.current_window_usage = 0;
-        d.burst_counter = 0;
-        d.window_start_ts = Clock::get()?.unix_timestamp;
+        // are applied via apply_usage_checkpoint on base layer after commit confirms.
+        d.close_window(now)?;
+        d.set_request_log_root(request_log_root);
+        d.last_commit_ts = now;
 
         emit!(UsageCheckpointSubmitted {
             delegated_usage: d.key(),
             api_key: d.api_key,
-            service: Pubkey::default(),
+            service: self.service.key(),
+            checkpoint_seq: d.checkpoint_seq,
             window_usage,
+            window_cost,
+            request_log_root,
         });
 
         commit_accounts(
             &self.payer,
-            vec![&self.delegated_usage.to_account_info()],
+            accounts.iter().collect(),
             &self.magic_context,
             &self.magic_program,
         )?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
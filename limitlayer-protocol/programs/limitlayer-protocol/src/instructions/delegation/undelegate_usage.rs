@@ -21,6 +21,16 @@ impl<'info> UndelegateUsage<'info> {
         require!(d.delegated, ErrorCode::NotDelegated);
 
         let api_key = d.api_key;
+        // A shard's usage only reaches the base layer through the primary
+        // account's checkpoint, so it must be folded in first.
+        require!(
+            d.shard_index == 0 || d.current_window_usage == 0,
+            ErrorCode::DelegationRequiresCheckpoint
+        );
+
+        // Close the open window so its usage reaches the base layer with the
+        // final commit and can still be applied as a checkpoint.
+        d.close_window(Clock::get()?.unix_timestamp)?;
         d.delegated = false;
 
         emit!(UsageUndelegated {
@@ -39,4 +49,4 @@ impl<'info> UndelegateUsage<'info> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    events::RequestInclusionVerified,
+    merkle,
+    state::{RequestLogLeaf, UsageCheckpoint},
+};
+
+/// Proves that a request was part of the window billed by a checkpoint.
+/// Fails with InvalidMerkleProof otherwise; read-only, anyone can call it.
+#[derive(Accounts)]
+pub struct VerifyRequestInclusion<'info> {
+    pub usage_checkpoint: Account<'info, UsageCheckpoint>,
+}
+
+impl<'info> VerifyRequestInclusion<'info> {
+    pub fn verify_request_inclusion(
+        &mut self,
+        leaf: RequestLogLeaf,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let checkpoint = &self.usage_checkpoint;
+
+        require!(
+            merkle::verify(&checkpoint.request_log_root, &leaf, &proof)?,
+            ErrorCode::InvalidMerkleProof
+        );
+
+        emit!(RequestInclusionVerified {
+            usage_checkpoint: checkpoint.key(),
+            api_key: checkpoint.api_key,
+            checkpoint_seq: checkpoint.checkpoint_seq,
+            request_id: leaf.request_id,
+            amount: leaf.amount,
+            cost: leaf.cost,
+        });
+
+        Ok(())
+    }
+}
@@ -2,33 +2,68 @@ use anchor_lang::prelude::*;
 
 use crate::{
     enums::ApiKeyStatus,
+    error::ErrorCode,
     events::EnforcementEvaluated,
     state::{ApiKeyAccount, DelegatedUsageAccount, RateLimitPolicy},
 };
 
+/// Usage is read from the committed state of the key's usage accounts, so a
+/// delegated key can be evaluated on the base layer. The other shards of the
+/// key, `1..usage_shards`, are passed once each through remaining_accounts.
 #[derive(Accounts)]
 pub struct EvaluateEnforcement<'info> {
     #[account(mut)]
     pub api_key: Account<'info, ApiKeyAccount>,
 
+    #[account(
+        constraint = policy.key() == api_key.policy @ ErrorCode::InvalidPolicy
+    )]
     pub policy: Account<'info, RateLimitPolicy>,
 
-    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+    /// CHECK: owned by us or by the delegation program while the key is
+    /// delegated; read via load_committed
+    pub delegated_usage: UncheckedAccount<'info>,
 }
 
 impl<'info> EvaluateEnforcement<'info> {
-    pub fn evaluate_enforcement(&mut self) -> Result<()> {
-        let usage = self.delegated_usage.current_window_usage;
-
-        if usage >= self.policy.requests_per_window {
-            self.api_key.status = ApiKeyStatus::Blocked;
-        } else if usage >= self.policy.requests_per_window / 2 {
-            self.api_key.status = ApiKeyStatus::Throttled;
-        } else {
-            if self.api_key.status != ApiKeyStatus::Blocked {
-                self.api_key.status = ApiKeyStatus::Active;
-            }
+    pub fn evaluate_enforcement(&mut self, shard_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        self.api_key
+            .roll_quota_periods(Clock::get()?.unix_timestamp);
+
+        let primary = DelegatedUsageAccount::load_committed(&self.delegated_usage)?;
+        require!(
+            primary.api_key == self.api_key.key() && primary.shard_index == 0,
+            ErrorCode::InvalidUsageShard
+        );
+        primary.verify_address(self.delegated_usage.key)?;
+
+        let mut usage = primary.current_window_usage;
+        // Bit i is set once shard i has been counted; the primary is bit 0
+        let mut seen: u32 = 1;
+        for info in shard_accounts {
+            let shard = DelegatedUsageAccount::load_committed(info)?;
+            require!(
+                shard.api_key == self.api_key.key()
+                    && shard.shard_index != 0
+                    && shard.shard_index < self.api_key.usage_shards,
+                ErrorCode::InvalidUsageShard
+            );
+            shard.verify_address(info.key)?;
+
+            let bit = 1u32 << shard.shard_index;
+            require!(seen & bit == 0, ErrorCode::IncompleteUsageShards);
+            seen |= bit;
+
+            usage = usage
+                .checked_add(shard.current_window_usage)
+                .ok_or(ErrorCode::MathOverflow)?;
         }
+        let all = (1u32 << self.api_key.usage_shards.max(1)) - 1;
+        require!(seen == all, ErrorCode::IncompleteUsageShards);
+
+        self.api_key.status =
+            limitlayer_core::evaluate(self.api_key.status.into(), usage, &self.policy.limits())
+                .into();
 
         let status_u8 = match self.api_key.status {
             ApiKeyStatus::Active => 0,
@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 
 use crate::{
     enums::ApiKeyStatus,
-    events::KeyManuallyBlocked,
     error::ErrorCode,
+    events::KeyManuallyBlocked,
     state::{ApiKeyAccount, ServiceAccount},
 };
 
@@ -25,6 +25,8 @@ pub struct ManualBlockKey<'info> {
 
 impl<'info> ManualBlockKey<'info> {
     pub fn manual_block_key(&mut self) -> Result<()> {
+        // Manual decisions supersede any pending quota reset
+        self.api_key.quota_blocked_until = 0;
         self.api_key.status = ApiKeyStatus::Blocked;
         emit!(KeyManuallyBlocked {
             api_key: self.api_key.key(),
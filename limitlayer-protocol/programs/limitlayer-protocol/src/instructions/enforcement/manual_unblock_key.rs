@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 
 use crate::{
     enums::ApiKeyStatus,
-    events::KeyManuallyUnblocked,
     error::ErrorCode,
+    events::KeyManuallyUnblocked,
     state::{ApiKeyAccount, ServiceAccount},
 };
 
@@ -30,6 +30,8 @@ impl<'info> ManualUnblockKey<'info> {
             ErrorCode::InvalidApiKeyStatusTransition
         );
 
+        // Manual decisions supersede any pending quota reset
+        self.api_key.quota_blocked_until = 0;
         self.api_key.status = ApiKeyStatus::Active;
         emit!(KeyManuallyUnblocked {
             api_key: self.api_key.key(),
@@ -4,4 +4,4 @@ pub mod manual_unblock_key;
 
 pub use evaluate_enforcement::*;
 pub use manual_block_key::*;
-pub use manual_unblock_key::*;
\ No newline at end of file
+pub use manual_unblock_key::*;
@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::UsageReceiptApplied,
+    instructions::delegation::apply_usage_checkpoint::apply_committed_usage,
+    state::{
+        ApiKeyAccount, DelegatedUsageAccount, GatewayAccount, ProtocolState, RateLimitPolicy,
+        ServiceAccount, UsageCheckpoint, UsageReceipt,
+    },
+    utils::verify_ed25519_instruction,
+};
+
+/// Base layer: applies a usage summary signed by one of the service's
+/// gateways as a checkpoint. The transaction must carry an Ed25519 precompile
+/// instruction over `receipt.message()` right before this one. Permissionless;
+/// the signature is what is trusted.
+#[derive(Accounts)]
+pub struct ApplyUsageReceipt<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROTOCOL_SEED.as_bytes()],
+        bump = protocol.bump
+    )]
+    pub protocol: Account<'info, ProtocolState>,
+
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        constraint = api_key.service == service.key() @ ErrorCode::InvalidApiKey
+    )]
+    pub api_key: Account<'info, ApiKeyAccount>,
+
+    #[account(
+        constraint = policy.key() == api_key.policy @ ErrorCode::InvalidPolicy
+    )]
+    pub policy: Account<'info, RateLimitPolicy>,
+
+    #[account(
+        seeds = [
+            GATEWAY_SEED.as_bytes(),
+            service.key().as_ref(),
+            gateway.gateway_key.as_ref()
+        ],
+        bump = gateway.bump
+    )]
+    pub gateway: Account<'info, GatewayAccount>,
+
+    /// Receipts stand in for a rollup, so the key must not be delegated
+    #[account(
+        mut,
+        seeds = [DELEGATED_USAGE_SEED.as_bytes(), api_key.key().as_ref()],
+        bump = delegated_usage.bump,
+        constraint = !delegated_usage.delegated @ ErrorCode::AlreadyDelegated
+    )]
+    pub delegated_usage: Account<'info, DelegatedUsageAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UsageCheckpoint::INIT_SPACE,
+        seeds = [
+            USAGE_SEED.as_bytes(),
+            api_key.key().as_ref(),
+            (delegated_usage.checkpoint_seq + 1).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub usage_checkpoint: Account<'info, UsageCheckpoint>,
+
+    /// CHECK: instructions sysvar, read for the Ed25519 instruction
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ApplyUsageReceipt<'info> {
+    pub fn apply_usage_receipt(
+        &mut self,
+        receipt: UsageReceipt,
+        bumps: ApplyUsageReceiptBumps,
+    ) -> Result<()> {
+        verify_ed25519_instruction(
+            &self.instructions,
+            &self.gateway.gateway_key,
+            &receipt.message()?,
+        )?;
+
+        require_keys_eq!(
+            receipt.api_key,
+            self.api_key.key(),
+            ErrorCode::InvalidApiKey
+        );
+
+        let d = &mut self.delegated_usage;
+        require!(
+            receipt.nonce > d.last_receipt_nonce,
+            ErrorCode::ReceiptReplayed
+        );
+        d.last_receipt_nonce = receipt.nonce;
+        d.close_receipt_window(
+            receipt.window_start,
+            receipt.request_count,
+            receipt.cost,
+            receipt.request_log_root,
+        )?;
+
+        apply_committed_usage(
+            &mut self.protocol,
+            &self.service,
+            &mut self.api_key,
+            &self.policy,
+            &mut self.usage_checkpoint,
+            &self.delegated_usage,
+            bumps.usage_checkpoint,
+        )?;
+
+        emit!(UsageReceiptApplied {
+            usage_checkpoint: self.usage_checkpoint.key(),
+            api_key: receipt.api_key,
+            gateway_key: self.gateway.gateway_key,
+            window_start: receipt.window_start,
+            request_count: receipt.request_count,
+            cost: receipt.cost,
+            nonce: receipt.nonce,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod apply_usage_receipt;
+pub mod register_gateway;
+pub mod revoke_gateway;
+
+pub use apply_usage_receipt::*;
+pub use register_gateway::*;
+pub use revoke_gateway::*;
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::GatewayRegistered,
+    state::{GatewayAccount, ServiceAccount},
+};
+
+#[derive(Accounts)]
+#[instruction(gateway_key: Pubkey)]
+pub struct RegisterGateway<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GatewayAccount::INIT_SPACE,
+        seeds = [GATEWAY_SEED.as_bytes(), service.key().as_ref(), gateway_key.as_ref()],
+        bump
+    )]
+    pub gateway: Account<'info, GatewayAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RegisterGateway<'info> {
+    pub fn register_gateway(
+        &mut self,
+        gateway_key: Pubkey,
+        bumps: RegisterGatewayBumps,
+    ) -> Result<()> {
+        require!(gateway_key != Pubkey::default(), ErrorCode::InvalidInput);
+
+        self.gateway.set_inner(GatewayAccount {
+            service: self.service.key(),
+            gateway_key,
+            registered_ts: Clock::get()?.unix_timestamp,
+            bump: bumps.gateway,
+        });
+
+        emit!(GatewayRegistered {
+            gateway: self.gateway.key(),
+            service: self.service.key(),
+            gateway_key,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::GatewayRevoked,
+    state::{GatewayAccount, ServiceAccount},
+};
+
+/// Closes a gateway registration; receipts it signs are no longer accepted.
+#[derive(Accounts)]
+pub struct RevokeGateway<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = service.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [
+            GATEWAY_SEED.as_bytes(),
+            service.key().as_ref(),
+            gateway.gateway_key.as_ref()
+        ],
+        bump = gateway.bump
+    )]
+    pub gateway: Account<'info, GatewayAccount>,
+}
+
+impl<'info> RevokeGateway<'info> {
+    pub fn revoke_gateway(&mut self) -> Result<()> {
+        emit!(GatewayRevoked {
+            gateway: self.gateway.key(),
+            service: self.service.key(),
+            gateway_key: self.gateway.gateway_key,
+        });
+
+        Ok(())
+    }
+}
@@ -5,6 +5,9 @@ mod api_key;
 mod delegation;
 mod enforcement;
 mod abuse;
+mod region;
+mod gateway;
+mod checkpoint;
 
 pub use protocol::*;
 pub use service::*;
@@ -12,4 +15,7 @@ pub use policy::*;
 pub use api_key::*;
 pub use delegation::*;
 pub use enforcement::*;
-pub use abuse::*;
\ No newline at end of file
+pub use abuse::*;
+pub use region::*;
+pub use gateway::*;
+pub use checkpoint::*;
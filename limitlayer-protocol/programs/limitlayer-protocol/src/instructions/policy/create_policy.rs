@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 
 use crate::{
     constants::*,
-    events::PolicyCreated,
     error::ErrorCode,
+    events::PolicyCreated,
     state::{RateLimitPolicy, ServiceAccount},
 };
 
@@ -25,7 +25,7 @@ pub struct CreatePolicy<'info> {
         seeds = [
             POLICY_SEED.as_bytes(),
             service.key().as_ref(),
-            service.total_usage_units.to_le_bytes().as_ref() // simple deterministic seed slot
+            service.policy_count.to_le_bytes().as_ref()
         ],
         bump
     )]
@@ -35,12 +35,15 @@ pub struct CreatePolicy<'info> {
 }
 
 impl<'info> CreatePolicy<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn create_policy(
         &mut self,
         requests_per_window: u64,
         window_seconds: u64,
         burst_limit: u64,
         cost_per_request: u64,
+        daily_quota: u64,
+        monthly_quota: u64,
         bumps: CreatePolicyBumps,
     ) -> Result<()> {
         require!(requests_per_window > 0, ErrorCode::InvalidRateLimitConfig);
@@ -52,6 +55,16 @@ impl<'info> CreatePolicy<'info> {
             burst_limit <= requests_per_window,
             ErrorCode::InvalidRateLimitConfig
         );
+        require!(
+            daily_quota == 0 || monthly_quota == 0 || daily_quota <= monthly_quota,
+            ErrorCode::InvalidRateLimitConfig
+        );
+
+        self.service.policy_count = self
+            .service
+            .policy_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
 
         self.policy.set_inner(RateLimitPolicy {
             service: self.service.key(),
@@ -59,6 +72,8 @@ impl<'info> CreatePolicy<'info> {
             window_seconds,
             burst_limit,
             cost_per_request,
+            daily_quota,
+            monthly_quota,
             bump: bumps.policy,
         });
 
@@ -69,6 +84,8 @@ impl<'info> CreatePolicy<'info> {
             window_seconds,
             burst_limit,
             cost_per_request,
+            daily_quota,
+            monthly_quota,
         });
 
         Ok(())
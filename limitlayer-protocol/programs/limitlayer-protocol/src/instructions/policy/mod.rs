@@ -1,7 +1,7 @@
+pub mod attach_policy_to_key;
 pub mod create_policy;
 pub mod update_policy;
-pub mod attach_policy_to_key;
 
+pub use attach_policy_to_key::*;
 pub use create_policy::*;
 pub use update_policy::*;
-pub use attach_policy_to_key::*;
\ No newline at end of file
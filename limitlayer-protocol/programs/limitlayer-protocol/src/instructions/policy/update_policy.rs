@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 
 use crate::{
     constants::*,
-    events::PolicyUpdated,
     error::ErrorCode,
+    events::PolicyUpdated,
     state::{RateLimitPolicy, ServiceAccount},
 };
 
@@ -30,6 +30,8 @@ impl<'info> UpdatePolicy<'info> {
         window_seconds: Option<u64>,
         burst_limit: Option<u64>,
         cost_per_request: Option<u64>,
+        daily_quota: Option<u64>,
+        monthly_quota: Option<u64>,
     ) -> Result<()> {
         let policy = &mut self.policy;
 
@@ -55,12 +57,30 @@ impl<'info> UpdatePolicy<'info> {
             policy.cost_per_request = c;
         }
 
+        // Some(0) clears a quota
+        if let Some(d) = daily_quota {
+            policy.daily_quota = d;
+        }
+
+        if let Some(m) = monthly_quota {
+            policy.monthly_quota = m;
+        }
+
+        require!(
+            policy.daily_quota == 0
+                || policy.monthly_quota == 0
+                || policy.daily_quota <= policy.monthly_quota,
+            ErrorCode::InvalidRateLimitConfig
+        );
+
         emit!(PolicyUpdated {
             policy: self.policy.key(),
             requests_per_window,
             window_seconds,
             burst_limit,
             cost_per_request,
+            daily_quota,
+            monthly_quota,
         });
 
         Ok(())
@@ -1,11 +1,6 @@
 use anchor_lang::prelude::*;
 
-use crate::{
-    events::ProtocolInitialized,
-    constants::*,
-    error::ErrorCode,
-    state::ProtocolState,
-};
+use crate::{constants::*, error::ErrorCode, events::ProtocolInitialized, state::ProtocolState};
 
 #[derive(Accounts)]
 pub struct InitializeProtocol<'info> {
@@ -56,4 +51,4 @@ impl<'info> InitializeProtocol<'info> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
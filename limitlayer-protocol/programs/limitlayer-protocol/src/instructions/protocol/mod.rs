@@ -2,4 +2,4 @@ pub mod initialize_protocol;
 pub mod update_protocol;
 
 pub use initialize_protocol::*;
-pub use update_protocol::*;
\ No newline at end of file
+pub use update_protocol::*;
@@ -1,11 +1,6 @@
 use anchor_lang::prelude::*;
 
-use crate::{
-    events::ProtocolUpdated,
-    constants::*,
-    error::ErrorCode,
-    state::ProtocolState,
-};
+use crate::{constants::*, error::ErrorCode, events::ProtocolUpdated, state::ProtocolState};
 
 #[derive(Accounts)]
 pub struct UpdateProtocol<'info> {
@@ -53,4 +48,4 @@ impl<'info> UpdateProtocol<'info> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
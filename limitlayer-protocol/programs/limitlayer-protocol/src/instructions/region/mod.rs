@@ -0,0 +1,5 @@
+pub mod register_execution_region;
+pub mod set_execution_region_status;
+
+pub use register_execution_region::*;
+pub use set_execution_region_status::*;
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    enums::ExecutionRegionStatus,
+    error::ErrorCode,
+    events::ExecutionRegionRegistered,
+    state::{ExecutionRegionAccount, ProtocolState},
+};
+
+#[derive(Accounts)]
+#[instruction(validator: Pubkey)]
+pub struct RegisterExecutionRegion<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [PROTOCOL_SEED.as_bytes()],
+        bump = protocol.bump,
+        constraint = admin.key() == protocol.admin_authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol: Account<'info, ProtocolState>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ExecutionRegionAccount::INIT_SPACE,
+        seeds = [EXECUTION_REGION_SEED.as_bytes(), validator.as_ref()],
+        bump
+    )]
+    pub execution_region: Account<'info, ExecutionRegionAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RegisterExecutionRegion<'info> {
+    pub fn register_execution_region(
+        &mut self,
+        validator: Pubkey,
+        region_code: String,
+        bumps: RegisterExecutionRegionBumps,
+    ) -> Result<()> {
+        require!(validator != Pubkey::default(), ErrorCode::InvalidInput);
+        require!(
+            !region_code.is_empty() && region_code.len() <= MAX_REGION_CODE_LEN as usize,
+            ErrorCode::InvalidInput
+        );
+
+        self.execution_region.set_inner(ExecutionRegionAccount {
+            validator,
+            region_code: region_code.clone(),
+            status: ExecutionRegionStatus::Active,
+            registered_ts: Clock::get()?.unix_timestamp,
+            bump: bumps.execution_region,
+        });
+
+        emit!(ExecutionRegionRegistered {
+            execution_region: self.execution_region.key(),
+            validator,
+            region_code,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::*,
+    enums::ExecutionRegionStatus,
+    error::ErrorCode,
+    events::ExecutionRegionStatusChanged,
+    state::{ExecutionRegionAccount, ProtocolState},
+};
+
+#[derive(Accounts)]
+pub struct SetExecutionRegionStatus<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [PROTOCOL_SEED.as_bytes()],
+        bump = protocol.bump,
+        constraint = admin.key() == protocol.admin_authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol: Account<'info, ProtocolState>,
+
+    #[account(
+        mut,
+        seeds = [EXECUTION_REGION_SEED.as_bytes(), execution_region.validator.as_ref()],
+        bump = execution_region.bump
+    )]
+    pub execution_region: Account<'info, ExecutionRegionAccount>,
+}
+
+impl<'info> SetExecutionRegionStatus<'info> {
+    /// Disabling a region blocks new delegations to it; existing delegations
+    /// keep running until they are undelegated or migrated.
+    pub fn set_execution_region_status(&mut self, new_status: ExecutionRegionStatus) -> Result<()> {
+        self.execution_region.status = new_status;
+
+        let status_u8 = match new_status {
+            ExecutionRegionStatus::Active => 0,
+            ExecutionRegionStatus::Disabled => 1,
+        };
+        emit!(ExecutionRegionStatusChanged {
+            execution_region: self.execution_region.key(),
+            validator: self.execution_region.validator,
+            new_status: status_u8,
+        });
+
+        Ok(())
+    }
+}
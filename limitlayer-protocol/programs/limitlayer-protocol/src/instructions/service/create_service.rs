@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    constants::{*, MAX_NAME_LEN},
+    constants::{MAX_NAME_LEN, *},
     enums::ServiceStatus,
     error::ErrorCode,
     events::ServiceCreated,
@@ -43,8 +43,11 @@ impl<'info> CreateService<'info> {
         default_policy: Pubkey,
         bumps: CreateServiceBumps,
     ) -> Result<()> {
-        require!(name.len() <= MAX_NAME_LEN as usize, ErrorCode::ServiceNameTooLong);
-        
+        require!(
+            name.len() <= MAX_NAME_LEN as usize,
+            ErrorCode::ServiceNameTooLong
+        );
+
         let protocol = &mut self.protocol;
 
         protocol.service_count = protocol
@@ -57,7 +60,10 @@ impl<'info> CreateService<'info> {
             name: name.clone(),
             status: ServiceStatus::Active,
             default_policy,
+            policy_count: 0,
             total_usage_units: 0,
+            total_cost_units: 0,
+            challenge_period_seconds: DEFAULT_CHALLENGE_PERIOD_SECONDS,
             created_ts: Clock::get()?.unix_timestamp,
             bump: bumps.service,
         });
@@ -72,4 +78,4 @@ impl<'info> CreateService<'info> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,7 @@
 pub mod create_service;
-pub mod update_service;
 pub mod set_service_status;
+pub mod update_service;
 
 pub use create_service::*;
+pub use set_service_status::*;
 pub use update_service::*;
-pub use set_service_status::*;
\ No newline at end of file
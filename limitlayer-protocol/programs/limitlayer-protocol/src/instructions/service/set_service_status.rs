@@ -1,10 +1,7 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    error::ErrorCode,
-    events::ServiceStatusChanged,
-    state::ServiceAccount,
-    enums::ServiceStatus,
+    enums::ServiceStatus, error::ErrorCode, events::ServiceStatusChanged, state::ServiceAccount,
 };
 
 #[derive(Accounts)]
@@ -19,10 +16,7 @@ pub struct SetServiceStatus<'info> {
 }
 
 impl<'info> SetServiceStatus<'info> {
-    pub fn set_service_status(
-        &mut self,
-        new_status: ServiceStatus,
-    ) -> Result<()> {
+    pub fn set_service_status(&mut self, new_status: ServiceStatus) -> Result<()> {
         let service = &mut self.service;
 
         if service.status == ServiceStatus::Disabled && new_status != ServiceStatus::Disabled {
@@ -50,4 +44,4 @@ impl<'info> SetServiceStatus<'info> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
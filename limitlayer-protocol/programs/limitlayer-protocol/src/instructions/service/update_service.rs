@@ -1,10 +1,8 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    error::ErrorCode,
-    events::ServiceUpdated,
-    state::ServiceAccount,
-    enums::ServiceStatus,
+    constants::MAX_CHALLENGE_PERIOD_SECONDS, enums::ServiceStatus, error::ErrorCode,
+    events::ServiceUpdated, state::ServiceAccount,
 };
 
 #[derive(Accounts)]
@@ -23,10 +21,14 @@ impl<'info> UpdateService<'info> {
         &mut self,
         new_authority: Option<Pubkey>,
         new_default_policy: Option<Pubkey>,
+        new_challenge_period_seconds: Option<u64>,
     ) -> Result<()> {
         let service = &mut self.service;
 
-        require!(service.status != ServiceStatus::Disabled, ErrorCode::ServiceDisabled);
+        require!(
+            service.status != ServiceStatus::Disabled,
+            ErrorCode::ServiceDisabled
+        );
 
         if let Some(a) = new_authority {
             service.authority = a;
@@ -36,12 +38,21 @@ impl<'info> UpdateService<'info> {
             service.default_policy = p;
         }
 
+        if let Some(c) = new_challenge_period_seconds {
+            require!(
+                c <= MAX_CHALLENGE_PERIOD_SECONDS,
+                ErrorCode::InvalidChallengePeriod
+            );
+            service.challenge_period_seconds = c;
+        }
+
         emit!(ServiceUpdated {
             service: self.service.key(),
             new_authority,
             new_default_policy,
+            new_challenge_period_seconds,
         });
 
         Ok(())
     }
-}
\ No newline at end of file
+}
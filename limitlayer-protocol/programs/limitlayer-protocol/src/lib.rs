@@ -1,3 +1,5 @@
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 use ephemeral_rollups_sdk::anchor::ephemeral;
 
@@ -6,7 +8,9 @@ pub mod enums;
 pub mod error;
 pub mod events;
 pub mod instructions;
+pub mod merkle;
 pub mod state;
+pub mod utils;
 
 pub use constants::*;
 pub use events::*;
@@ -55,9 +59,13 @@ pub mod limitlayer_protocol {
         ctx: Context<UpdateService>,
         new_authority: Option<Pubkey>,
         new_default_policy: Option<Pubkey>,
+        new_challenge_period_seconds: Option<u64>,
     ) -> Result<()> {
-        ctx.accounts
-            .update_service(new_authority, new_default_policy)
+        ctx.accounts.update_service(
+            new_authority,
+            new_default_policy,
+            new_challenge_period_seconds,
+        )
     }
 
     pub fn set_service_status(
@@ -74,12 +82,16 @@ pub mod limitlayer_protocol {
         window_seconds: u64,
         burst_limit: u64,
         cost_per_request: u64,
+        daily_quota: u64,
+        monthly_quota: u64,
     ) -> Result<()> {
         ctx.accounts.create_policy(
             requests_per_window,
             window_seconds,
             burst_limit,
             cost_per_request,
+            daily_quota,
+            monthly_quota,
             ctx.bumps,
         )
     }
@@ -90,12 +102,16 @@ pub mod limitlayer_protocol {
         window_seconds: Option<u64>,
         burst_limit: Option<u64>,
         cost_per_request: Option<u64>,
+        daily_quota: Option<u64>,
+        monthly_quota: Option<u64>,
     ) -> Result<()> {
         ctx.accounts.update_policy(
             requests_per_window,
             window_seconds,
             burst_limit,
             cost_per_request,
+            daily_quota,
+            monthly_quota,
         )
     }
 
@@ -126,12 +142,57 @@ pub mod limitlayer_protocol {
         ctx.accounts.set_api_key_status(new_status)
     }
 
+    // EXECUTION REGIONS
+    pub fn register_execution_region(
+        ctx: Context<RegisterExecutionRegion>,
+        validator: Pubkey,
+        region_code: String,
+    ) -> Result<()> {
+        ctx.accounts
+            .register_execution_region(validator, region_code, ctx.bumps)
+    }
+
+    pub fn set_execution_region_status(
+        ctx: Context<SetExecutionRegionStatus>,
+        new_status: ExecutionRegionStatus,
+    ) -> Result<()> {
+        ctx.accounts.set_execution_region_status(new_status)
+    }
+
+    // GATEWAYS
+    pub fn register_gateway(
+        ctx: Context<RegisterGateway>,
+        gateway_key: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.register_gateway(gateway_key, ctx.bumps)
+    }
+
+    pub fn revoke_gateway(ctx: Context<RevokeGateway>) -> Result<()> {
+        ctx.accounts.revoke_gateway()
+    }
+
+    pub fn apply_usage_receipt(
+        ctx: Context<ApplyUsageReceipt>,
+        receipt: UsageReceipt,
+    ) -> Result<()> {
+        ctx.accounts.apply_usage_receipt(receipt, ctx.bumps)
+    }
+
     // MAGICBLOCK DELEGATION
+    pub fn create_usage_shard(
+        ctx: Context<CreateUsageShard>,
+        shard_index: u8,
+    ) -> Result<()> {
+        ctx.accounts.create_usage_shard(shard_index, ctx.bumps)
+    }
+
     pub fn prepare_delegation(
         ctx: Context<PrepareDelegation>,
-        execution_region: Pubkey,
+        commit_frequency_ms: u32,
+        lease_seconds: u64,
     ) -> Result<()> {
-        ctx.accounts.prepare_delegation(execution_region)
+        ctx.accounts
+            .prepare_delegation(commit_frequency_ms, lease_seconds)
     }
 
     pub fn delegate_usage(
@@ -144,14 +205,73 @@ pub mod limitlayer_protocol {
     pub fn record_usage_realtime(
         ctx: Context<RecordUsageRealtime>,
         amount: u64,
+        request_id: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.record_usage_realtime(amount, request_id)
+    }
+
+    pub fn record_usage_direct(
+        ctx: Context<RecordUsageDirect>,
+        amount: u64,
+        request_id: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.record_usage_direct(amount, request_id)
+    }
+
+    pub fn reserve_usage(
+        ctx: Context<ReserveUsage>,
+        amount: u64,
+        ttl_seconds: u64,
+    ) -> Result<u64> {
+        ctx.accounts.reserve_usage(amount, ttl_seconds)
+    }
+
+    pub fn settle_usage(
+        ctx: Context<SettleUsage>,
+        reservation_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.settle_usage(reservation_id, amount)
+    }
+
+    pub fn release_usage(
+        ctx: Context<ReleaseUsage>,
+        reservation_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.release_usage(reservation_id)
+    }
+
+    pub fn record_usage_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RecordUsageBatch<'info>>,
+        amounts: Vec<u64>,
+        request_ids: Vec<Option<u64>>,
     ) -> Result<()> {
-        ctx.accounts.record_usage_realtime(amount)
+        ctx.accounts
+            .record_usage_batch(amounts, request_ids, ctx.remaining_accounts)
+    }
+
+    pub fn submit_usage_checkpoint<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SubmitUsageCheckpoint<'info>>,
+        request_log_root: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts
+            .submit_usage_checkpoint(request_log_root, ctx.remaining_accounts)
     }
 
-    pub fn submit_usage_checkpoint(
-        ctx: Context<SubmitUsageCheckpoint>,
+    pub fn apply_usage_checkpoint(
+        ctx: Context<ApplyUsageCheckpoint>,
+        checkpoint_seq: u64,
     ) -> Result<()> {
-        ctx.accounts.submit_usage_checkpoint()
+        ctx.accounts
+            .apply_usage_checkpoint(checkpoint_seq, ctx.bumps)
+    }
+
+    pub fn verify_request_inclusion(
+        ctx: Context<VerifyRequestInclusion>,
+        leaf: RequestLogLeaf,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        ctx.accounts.verify_request_inclusion(leaf, proof)
     }
 
     pub fn undelegate_usage(
@@ -160,11 +280,78 @@ pub mod limitlayer_protocol {
         ctx.accounts.undelegate_usage()
     }
 
+    pub fn begin_region_migration<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BeginRegionMigration<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.begin_region_migration(ctx.remaining_accounts)
+    }
+
+    pub fn complete_region_migration<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CompleteRegionMigration<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.complete_region_migration(ctx.remaining_accounts)
+    }
+
+    pub fn renew_delegation_lease(
+        ctx: Context<RenewDelegationLease>,
+        lease_seconds: u64,
+    ) -> Result<()> {
+        ctx.accounts.renew_delegation_lease(lease_seconds)
+    }
+
+    pub fn expire_delegation_lease(
+        ctx: Context<ExpireDelegationLease>,
+    ) -> Result<()> {
+        ctx.accounts.expire_delegation_lease()
+    }
+
+    pub fn reconcile_returned_delegation(
+        ctx: Context<ReconcileReturnedDelegation>,
+    ) -> Result<()> {
+        ctx.accounts.reconcile_returned_delegation()
+    }
+
+    // CHECKPOINT FINALIZATION
+    pub fn register_auditor(
+        ctx: Context<RegisterAuditor>,
+        auditor: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.register_auditor(auditor, ctx.bumps)
+    }
+
+    pub fn revoke_auditor(ctx: Context<RevokeAuditor>) -> Result<()> {
+        ctx.accounts.revoke_auditor()
+    }
+
+    pub fn finalize_usage_checkpoint(
+        ctx: Context<FinalizeUsageCheckpoint>,
+    ) -> Result<()> {
+        ctx.accounts.finalize_usage_checkpoint()
+    }
+
+    pub fn dispute_usage_checkpoint(
+        ctx: Context<DisputeUsageCheckpoint>,
+        reason: DisputeReason,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts
+            .dispute_usage_checkpoint(reason, evidence_hash)
+    }
+
+    pub fn resolve_checkpoint_dispute(
+        ctx: Context<ResolveCheckpointDispute>,
+        billed_request_count: u64,
+        billed_cost: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .resolve_checkpoint_dispute(billed_request_count, billed_cost)
+    }
+
     // ENFORCEMENT
-    pub fn evaluate_enforcement(
-        ctx: Context<EvaluateEnforcement>,
+    pub fn evaluate_enforcement<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EvaluateEnforcement<'info>>,
     ) -> Result<()> {
-        ctx.accounts.evaluate_enforcement()
+        ctx.accounts.evaluate_enforcement(ctx.remaining_accounts)
     }
 
     pub fn manual_block_key(
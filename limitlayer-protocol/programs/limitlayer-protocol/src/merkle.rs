@@ -0,0 +1,51 @@
+//! Merkle commitments over per-window request logs.
+//!
+//! Leaves are `sha256(0x00 || borsh(RequestLogLeaf))`, inner nodes are
+//! `sha256(0x01 || min(a, b) || max(a, b))`. Sorting each pair means a proof
+//! is just the list of sibling hashes from leaf to root; the prefixes keep a
+//! leaf from being passed off as an inner node. An odd node at any level is
+//! carried up unchanged.
+
+use anchor_lang::{prelude::*, solana_program::hash::hashv};
+
+use crate::state::RequestLogLeaf;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn leaf_hash(leaf: &RequestLogLeaf) -> Result<[u8; 32]> {
+    let data = leaf.try_to_vec()?;
+    Ok(hashv(&[LEAF_PREFIX, &data]).to_bytes())
+}
+
+pub fn node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, lo, hi]).to_bytes()
+}
+
+/// Root over already-hashed leaves, in log order; all zeros for an empty log.
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => node_hash(a, b),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// Whether `leaf` is included under `root` given its sibling path.
+pub fn verify(root: &[u8; 32], leaf: &RequestLogLeaf, proof: &[[u8; 32]]) -> Result<bool> {
+    let computed = proof
+        .iter()
+        .fold(leaf_hash(leaf)?, |node, sibling| node_hash(&node, sibling));
+    Ok(root != &[0; 32] && &computed == root)
+}
@@ -10,4 +10,3 @@ pub struct AbuseSignal {
     pub created_ts: i64,
     pub bump: u8,
 }
-
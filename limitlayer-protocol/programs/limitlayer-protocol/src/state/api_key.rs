@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
 
-use crate::ApiKeyStatus;
+use limitlayer_core::{QuotaExhaustion, QuotaState};
+
+use crate::{enums::ApiKeyStatus, state::RateLimitPolicy};
 
 #[account]
 #[derive(InitSpace)]
@@ -10,7 +12,77 @@ pub struct ApiKeyAccount {
     pub policy: Pubkey,
     pub reputation: Pubkey,
     pub status: ApiKeyStatus,
+    /// Finalized usage; this is what gets billed
     pub lifetime_usage: u128,
+    pub lifetime_cost: u128,
+    /// Usage covered by applied checkpoints, pending or finalized
+    pub applied_usage: u128,
+    pub applied_cost: u128,
+    /// checkpoint_seq of the last applied checkpoint
+    pub applied_checkpoint_seq: u64,
     pub last_checkpoint_ts: i64,
+    /// Calendar-aligned quota counters, advanced when checkpoints are applied
+    pub day_start_ts: i64,
+    pub daily_usage: u64,
+    pub month_start_ts: i64,
+    pub monthly_usage: u64,
+    /// Set while the key is blocked for an exhausted quota; 0 otherwise
+    pub quota_blocked_until: i64,
+    /// Usage accounts counting this key, including the primary one
+    pub usage_shards: u8,
     pub bump: u8,
 }
+
+impl ApiKeyAccount {
+    pub fn quota_state(&self) -> QuotaState {
+        QuotaState {
+            status: self.status.into(),
+            day_start_ts: self.day_start_ts,
+            daily_usage: self.daily_usage,
+            month_start_ts: self.month_start_ts,
+            monthly_usage: self.monthly_usage,
+            blocked_until: self.quota_blocked_until,
+        }
+    }
+
+    fn set_quota_state(&mut self, quota: QuotaState) {
+        self.status = quota.status.into();
+        self.day_start_ts = quota.day_start_ts;
+        self.daily_usage = quota.daily_usage;
+        self.month_start_ts = quota.month_start_ts;
+        self.monthly_usage = quota.monthly_usage;
+        self.quota_blocked_until = quota.blocked_until;
+    }
+
+    /// Starts new quota periods once their boundary has passed and lifts a
+    /// quota block whose period has reset.
+    pub fn roll_quota_periods(&mut self, now: i64) {
+        let mut quota = self.quota_state();
+        quota.roll(now);
+        self.set_quota_state(quota);
+    }
+
+    /// Status usage is admitted against at `now`. A quota block whose period
+    /// has reset no longer counts, even before a checkpoint or
+    /// evaluate_enforcement writes the reset back to the key, which the
+    /// execution region cannot do.
+    pub fn admission_status(&self, now: i64) -> ApiKeyStatus {
+        let mut quota = self.quota_state();
+        quota.roll(now);
+        quota.status.into()
+    }
+
+    /// Counts checkpointed usage against the quotas, blocking the key if
+    /// one runs out.
+    pub fn charge_quota(
+        &mut self,
+        policy: &RateLimitPolicy,
+        request_count: u64,
+        now: i64,
+    ) -> Option<QuotaExhaustion> {
+        let mut quota = self.quota_state();
+        let exhaustion = quota.charge(&policy.limits(), request_count, now);
+        self.set_quota_state(quota);
+        exhaustion
+    }
+}
@@ -0,0 +1,11 @@
+use anchor_lang::prelude::*;
+
+/// Third party a service allows to dispute its usage checkpoints
+#[account]
+#[derive(InitSpace)]
+pub struct AuditorAccount {
+    pub service: Pubkey,
+    pub auditor: Pubkey,
+    pub registered_ts: i64,
+    pub bump: u8,
+}
@@ -1,17 +1,414 @@
 use anchor_lang::prelude::*;
+use ephemeral_rollups_sdk::anchor::DelegationProgram;
+use limitlayer_core::{Charge, UsageState};
+
+use crate::{
+    constants::{
+        CHECKPOINT_APPLY_GRACE_SECONDS, DEFAULT_RESERVATION_TTL_SECONDS, DELEGATED_USAGE_SEED,
+        MAX_LEASE_SECONDS, MAX_RESERVATION_TTL_SECONDS, MAX_USAGE_RESERVATIONS, MAX_WINDOW_ROOTS,
+        MIN_LEASE_SECONDS, RECENT_REQUEST_IDS,
+    },
+    error::ErrorCode,
+    merkle,
+    state::{ApiKeyAccount, RateLimitPolicy},
+};
+
+/// Extra PDA seed for usage shard `shard_index`. Empty for the primary
+/// account (shard 0), so its address stays ["delegated_usage", api_key].
+pub fn shard_seed(shard_index: &u8) -> &[u8] {
+    if *shard_index == 0 {
+        &[]
+    } else {
+        std::slice::from_ref(shard_index)
+    }
+}
+
+/// Units held by reserve_usage until settled, released or expired.
+/// A slot with id 0 is free.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct UsageReservation {
+    pub id: u64,
+    pub amount: u64,
+    pub expires_at: i64,
+}
+
+impl UsageReservation {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.id != 0 && now < self.expires_at
+    }
+}
 
 #[account]
 #[derive(InitSpace)]
 pub struct DelegatedUsageAccount {
     pub api_key: Pubkey,
+    /// 0 for the primary account, which checkpoints; other shards fold into it
+    pub shard_index: u8,
     pub policy: Pubkey,
     pub execution_region: Pubkey,
     pub delegated: bool,
     pub delegation_seq: u64,
     pub window_start_ts: i64,
     pub current_window_usage: u64,
+    pub current_window_cost: u64,
     pub burst_counter: u64,
     pub last_update_ts: i64,
     pub delegated_at: i64,
+    /// When submit_usage_checkpoint last committed the account from the
+    /// execution region; automatic commits do not run the program and leave
+    /// it alone
+    pub last_commit_ts: i64,
+    /// Automatic commit interval passed to the delegation program
+    pub commit_frequency_ms: u32,
+    /// Usage is refused from this time on until renewed; 0 = no lease
+    pub lease_expires_at: i64,
+    /// Region a pending migration will redelegate to; default when none
+    pub migration_target: Pubkey,
+    /// Number of windows closed by submit_usage_checkpoint
+    pub checkpoint_seq: u64,
+    /// Running totals over every closed window; base layer applies the delta
+    pub checkpointed_usage: u128,
+    pub checkpointed_cost: u128,
+    pub last_checkpoint_window_start: i64,
+    /// Request log root of the last closed window, supplied by the gateway
+    pub last_request_log_root: [u8; 32],
+    /// Roots of the last closed windows, window `seq` at `seq % MAX_WINDOW_ROOTS`
+    pub window_roots: [[u8; 32]; MAX_WINDOW_ROOTS as usize],
+    /// Usage set aside by reconcile_returned_delegation, unverified and not
+    /// billed; later reconciliations add to it, keeping the earliest window
+    pub disputed_window_start: i64,
+    pub disputed_window_usage: u64,
+    pub disputed_window_cost: u64,
+    /// Open reservations; they count against the burst allowance
+    pub reservations: [UsageReservation; MAX_USAGE_RESERVATIONS as usize],
+    pub next_reservation_id: u64,
+    /// Ring buffer of idempotency keys of recently recorded requests
+    pub recent_request_ids: [u64; RECENT_REQUEST_IDS as usize],
+    pub recent_request_cursor: u32,
+    /// Nonce of the last gateway receipt applied for the key
+    pub last_receipt_nonce: u64,
     pub bump: u8,
 }
+
+impl DelegatedUsageAccount {
+    /// What the rate-limit core decides on for this account at `now`.
+    pub fn usage_state(&self, api_key: &ApiKeyAccount, now: i64) -> UsageState {
+        UsageState {
+            status: api_key.admission_status(now).into(),
+            shards: api_key.usage_shards,
+            shard: self.shard_index,
+            delegated: self.delegated,
+            lease_expires_at: self.lease_expires_at,
+            window_usage: self.current_window_usage,
+            window_cost: self.current_window_cost,
+            burst_counter: self.burst_counter,
+            reserved_units: self.reserved_units(now),
+        }
+    }
+
+    /// Charges `amount` units against the current window. Nothing is written
+    /// unless every check passes, so callers can keep going after a rejection.
+    pub fn apply_usage(
+        &mut self,
+        policy: &RateLimitPolicy,
+        api_key: &ApiKeyAccount,
+        amount: u64,
+        now: i64,
+    ) -> std::result::Result<(), ErrorCode> {
+        let charge = limitlayer_core::apply_usage(
+            &self.usage_state(api_key, now),
+            &policy.limits(),
+            now,
+            amount,
+        )?;
+        self.record_charge(charge, now);
+        Ok(())
+    }
+
+    /// Base-layer counterpart of apply_usage for a key that is not delegated.
+    pub fn apply_usage_direct(
+        &mut self,
+        policy: &RateLimitPolicy,
+        api_key: &ApiKeyAccount,
+        amount: u64,
+        now: i64,
+    ) -> std::result::Result<(), ErrorCode> {
+        let charge = limitlayer_core::apply_usage_direct(
+            &self.usage_state(api_key, now),
+            &policy.limits(),
+            amount,
+        )?;
+        self.record_charge(charge, now);
+        Ok(())
+    }
+
+    /// Charges `amount` units for a reservation of `reserved` just taken;
+    /// only an excess over the reservation is checked.
+    pub fn settle(
+        &mut self,
+        policy: &RateLimitPolicy,
+        api_key: &ApiKeyAccount,
+        reserved: u64,
+        amount: u64,
+        now: i64,
+    ) -> std::result::Result<(), ErrorCode> {
+        let charge = limitlayer_core::settle(
+            &self.usage_state(api_key, now),
+            &policy.limits(),
+            now,
+            reserved,
+            amount,
+        )?;
+        self.record_charge(charge, now);
+        Ok(())
+    }
+
+    fn record_charge(&mut self, charge: Charge, now: i64) {
+        self.current_window_usage = charge.window_usage;
+        self.current_window_cost = charge.window_cost;
+        self.burst_counter = charge.burst_counter;
+        self.last_update_ts = now;
+    }
+
+    /// Units held by unexpired reservations.
+    pub fn reserved_units(&self, now: i64) -> u64 {
+        self.reservations
+            .iter()
+            .filter(|r| r.is_active(now))
+            .fold(0u64, |total, r| total.saturating_add(r.amount))
+    }
+
+    /// Holds `amount` units for up to `ttl_seconds` (0 = default) and returns
+    /// the reservation id. Expired reservations free their slot lazily.
+    pub fn reserve(
+        &mut self,
+        policy: &RateLimitPolicy,
+        api_key: &ApiKeyAccount,
+        amount: u64,
+        ttl_seconds: u64,
+        now: i64,
+    ) -> Result<UsageReservation> {
+        let ttl_seconds = match ttl_seconds {
+            0 => DEFAULT_RESERVATION_TTL_SECONDS,
+            t => t,
+        };
+        require!(
+            amount > 0 && ttl_seconds <= MAX_RESERVATION_TTL_SECONDS,
+            ErrorCode::InvalidInput
+        );
+
+        limitlayer_core::reserve(
+            &self.usage_state(api_key, now),
+            &policy.limits(),
+            now,
+            amount,
+        )
+        .map_err(ErrorCode::from)?;
+
+        let slot = self
+            .reservations
+            .iter_mut()
+            .find(|r| !r.is_active(now))
+            .ok_or(ErrorCode::TooManyReservations)?;
+
+        self.next_reservation_id = self
+            .next_reservation_id
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        *slot = UsageReservation {
+            id: self.next_reservation_id,
+            amount,
+            expires_at: now
+                .checked_add(ttl_seconds as i64)
+                .ok_or(ErrorCode::MathOverflow)?,
+        };
+        self.last_update_ts = now;
+
+        Ok(*slot)
+    }
+
+    /// Frees the slot of an open reservation and returns it.
+    pub fn take_reservation(&mut self, reservation_id: u64, now: i64) -> Result<UsageReservation> {
+        require!(reservation_id != 0, ErrorCode::ReservationNotFound);
+        let slot = self
+            .reservations
+            .iter_mut()
+            .find(|r| r.id == reservation_id)
+            .ok_or(ErrorCode::ReservationNotFound)?;
+        let reservation = std::mem::take(slot);
+        require!(reservation.is_active(now), ErrorCode::ReservationExpired);
+        Ok(reservation)
+    }
+
+    /// Whether a request with this idempotency key was among the last
+    /// `RECENT_REQUEST_IDS` recorded on this account. Other shards of the key
+    /// keep their own ids, so a retry is only recognized on the same shard.
+    pub fn seen_request(&self, request_id: u64) -> bool {
+        request_id != 0 && self.recent_request_ids.contains(&request_id)
+    }
+
+    /// Remembers a recorded request, overwriting the oldest entry.
+    pub fn remember_request(&mut self, request_id: u64) {
+        let slot = self.recent_request_cursor as usize % self.recent_request_ids.len();
+        self.recent_request_ids[slot] = request_id;
+        self.recent_request_cursor = ((slot + 1) % self.recent_request_ids.len()) as u32;
+    }
+
+    pub fn lease_expired(&self, now: i64) -> bool {
+        limitlayer_core::lease_expired(self.lease_expires_at, now)
+    }
+
+    /// Expiry timestamp for a lease of `lease_seconds` starting at `now`.
+    pub fn lease_expiry(now: i64, lease_seconds: u64) -> Result<i64> {
+        if lease_seconds == 0 {
+            return Ok(0);
+        }
+        require!(
+            (MIN_LEASE_SECONDS..=MAX_LEASE_SECONDS).contains(&lease_seconds),
+            ErrorCode::InvalidLeaseDuration
+        );
+        now.checked_add(lease_seconds as i64)
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    pub fn shard_seed(&self) -> &[u8] {
+        shard_seed(&self.shard_index)
+    }
+
+    /// Checks that `address` is the usage PDA this account's data describes.
+    /// Needed when reading committed state through an UncheckedAccount.
+    pub fn verify_address(&self, address: &Pubkey) -> Result<()> {
+        let expected = Pubkey::create_program_address(
+            &[
+                DELEGATED_USAGE_SEED.as_bytes(),
+                self.api_key.as_ref(),
+                self.shard_seed(),
+                &[self.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::InvalidUsageShard)?;
+        require_keys_eq!(expected, *address, ErrorCode::InvalidUsageShard);
+        Ok(())
+    }
+
+    /// Moves a shard's open window into this (primary) account's window.
+    pub fn absorb_shard(&mut self, shard: &mut DelegatedUsageAccount) -> Result<()> {
+        self.current_window_usage = self
+            .current_window_usage
+            .checked_add(shard.current_window_usage)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.current_window_cost = self
+            .current_window_cost
+            .checked_add(shard.current_window_cost)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        shard.current_window_usage = 0;
+        shard.current_window_cost = 0;
+        shard.burst_counter = 0;
+        shard.window_start_ts = self.window_start_ts;
+
+        Ok(())
+    }
+
+    /// Folds the current window into the checkpointed totals and opens a new one.
+    pub fn close_window(&mut self, now: i64) -> Result<()> {
+        self.checkpoint_seq = self
+            .checkpoint_seq
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.checkpointed_usage = self
+            .checkpointed_usage
+            .checked_add(self.current_window_usage as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.checkpointed_cost = self
+            .checkpointed_cost
+            .checked_add(self.current_window_cost as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.last_checkpoint_window_start = self.window_start_ts;
+        self.set_request_log_root([0; 32]);
+
+        self.current_window_usage = 0;
+        self.current_window_cost = 0;
+        self.burst_counter = 0;
+        self.window_start_ts = now;
+
+        Ok(())
+    }
+
+    /// Longest a delegated window may have been open before its checkpoint
+    /// is applied: the policy window, one automatic commit interval and
+    /// CHECKPOINT_APPLY_GRACE_SECONDS.
+    pub fn max_checkpoint_lag(&self, policy: &RateLimitPolicy) -> i64 {
+        let commit_seconds = (self.commit_frequency_ms as i64 + 999) / 1_000;
+        (policy.window_seconds.min(i64::MAX as u64) as i64)
+            .saturating_add(commit_seconds)
+            .saturating_add(CHECKPOINT_APPLY_GRACE_SECONDS)
+    }
+
+    /// Closes a window reported by a gateway receipt instead of recorded here.
+    pub fn close_receipt_window(
+        &mut self,
+        window_start: i64,
+        request_count: u64,
+        cost: u64,
+        request_log_root: [u8; 32],
+    ) -> Result<()> {
+        self.checkpoint_seq = self
+            .checkpoint_seq
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.checkpointed_usage = self
+            .checkpointed_usage
+            .checked_add(request_count as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.checkpointed_cost = self
+            .checkpointed_cost
+            .checked_add(cost as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.last_checkpoint_window_start = window_start;
+        self.set_request_log_root(request_log_root);
+
+        Ok(())
+    }
+
+    /// Sets the request log root of the window closed last.
+    pub fn set_request_log_root(&mut self, request_log_root: [u8; 32]) {
+        self.last_request_log_root = request_log_root;
+        self.window_roots[(self.checkpoint_seq % MAX_WINDOW_ROOTS as u64) as usize] =
+            request_log_root;
+    }
+
+    /// Root over the request logs of the windows closed after checkpoint
+    /// `applied_seq`: the Merkle root of their non-zero roots, oldest first,
+    /// so a window's root when it is the only one. Windows older than the
+    /// last MAX_WINDOW_ROOTS are left out (see request_logs_truncated_since).
+    pub fn request_log_root_since(&self, applied_seq: u64) -> [u8; 32] {
+        let first = applied_seq
+            .max(self.checkpoint_seq.saturating_sub(MAX_WINDOW_ROOTS as u64))
+            .saturating_add(1);
+        let roots: Vec<[u8; 32]> = (first..=self.checkpoint_seq)
+            .map(|seq| self.window_roots[(seq % MAX_WINDOW_ROOTS as u64) as usize])
+            .filter(|root| root != &[0; 32])
+            .collect();
+        merkle::root(&roots)
+    }
+
+    /// Whether more than MAX_WINDOW_ROOTS windows closed after checkpoint
+    /// `applied_seq`, so the oldest of them are missing from
+    /// request_log_root_since.
+    pub fn request_logs_truncated_since(&self, applied_seq: u64) -> bool {
+        self.checkpoint_seq.saturating_sub(applied_seq) > MAX_WINDOW_ROOTS as u64
+    }
+
+    /// Reads the last committed state on the base layer, where the account is
+    /// owned by the delegation program while delegated and by us otherwise.
+    pub fn load_committed(info: &AccountInfo) -> Result<Self> {
+        require!(
+            info.owner == &crate::ID || info.owner == &DelegationProgram::id(),
+            ErrorCode::InvalidDelegationState
+        );
+        let data = info.try_borrow_data()?;
+        Self::try_deserialize(&mut &data[..])
+    }
+}
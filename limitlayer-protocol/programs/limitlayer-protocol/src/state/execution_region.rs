@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::MAX_REGION_CODE_LEN, enums::ExecutionRegionStatus};
+
+#[account]
+#[derive(InitSpace)]
+pub struct ExecutionRegionAccount {
+    /// MagicBlock validator identity delegations are sent to
+    pub validator: Pubkey,
+    #[max_len(MAX_REGION_CODE_LEN)]
+    pub region_code: String,
+    pub status: ExecutionRegionStatus,
+    pub registered_ts: i64,
+    pub bump: u8,
+}
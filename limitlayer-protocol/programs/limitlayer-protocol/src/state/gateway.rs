@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::USAGE_RECEIPT_DOMAIN, error::ErrorCode, state::ServiceAccount};
+
+/// Ed25519 key a service's gateway signs usage receipts with
+#[account]
+#[derive(InitSpace)]
+pub struct GatewayAccount {
+    pub service: Pubkey,
+    pub gateway_key: Pubkey,
+    pub registered_ts: i64,
+    pub bump: u8,
+}
+
+/// Usage summary signed by a gateway. The signed message is
+/// USAGE_RECEIPT_DOMAIN followed by the Borsh encoding of this struct.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct UsageReceipt {
+    pub api_key: Pubkey,
+    pub window_start: i64,
+    pub request_count: u64,
+    pub cost: u64,
+    /// Merkle root over the window's request log; zeros if not kept
+    pub request_log_root: [u8; 32],
+    /// Must increase with every receipt applied for the key
+    pub nonce: u64,
+}
+
+impl UsageReceipt {
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut message = USAGE_RECEIPT_DOMAIN.as_bytes().to_vec();
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+/// Checks that `submitter` may report usage for the keys of `service`: its
+/// authority, or a gateway whose registration was passed as `gateway`. The
+/// registration's seeds tie it to the service and to the submitter.
+pub fn require_usage_submitter(
+    submitter: &Pubkey,
+    service: &ServiceAccount,
+    gateway: &Option<Account<GatewayAccount>>,
+) -> Result<()> {
+    require!(
+        *submitter == service.authority || gateway.is_some(),
+        ErrorCode::Unauthorized
+    );
+    Ok(())
+}
@@ -1,17 +1,23 @@
-pub mod protocol;
-pub mod service;
+pub mod abuse_signal;
 pub mod api_key;
-pub mod policy;
-pub mod usage;
+pub mod auditor;
 pub mod delegated_usage;
+pub mod execution_region;
+pub mod gateway;
+pub mod policy;
+pub mod protocol;
 pub mod reputation;
-pub mod abuse_signal;
+pub mod service;
+pub mod usage;
 
-pub use protocol::*;
-pub use service::*;
+pub use abuse_signal::*;
 pub use api_key::*;
-pub use policy::*;
-pub use usage::*;
+pub use auditor::*;
 pub use delegated_usage::*;
+pub use execution_region::*;
+pub use gateway::*;
+pub use policy::*;
+pub use protocol::*;
 pub use reputation::*;
-pub use abuse_signal::*;
\ No newline at end of file
+pub use service::*;
+pub use usage::*;
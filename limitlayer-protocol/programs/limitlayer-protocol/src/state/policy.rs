@@ -8,5 +8,23 @@ pub struct RateLimitPolicy {
     pub window_seconds: u64,
     pub burst_limit: u64,
     pub cost_per_request: u64,
+    /// Requests per UTC day; 0 = unlimited
+    pub daily_quota: u64,
+    /// Requests per UTC calendar month; 0 = unlimited
+    pub monthly_quota: u64,
     pub bump: u8,
-}
\ No newline at end of file
+}
+
+impl RateLimitPolicy {
+    /// The limits the rate-limit core enforces.
+    pub fn limits(&self) -> limitlayer_core::Policy {
+        limitlayer_core::Policy {
+            requests_per_window: self.requests_per_window,
+            window_seconds: self.window_seconds,
+            burst_limit: self.burst_limit,
+            cost_per_request: self.cost_per_request,
+            daily_quota: self.daily_quota,
+            monthly_quota: self.monthly_quota,
+        }
+    }
+}
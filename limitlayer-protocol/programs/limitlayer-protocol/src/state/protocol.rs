@@ -11,4 +11,4 @@ pub struct ProtocolState {
     pub api_key_count: u64,
     pub total_usage_checkpoints: u64,
     pub bump: u8,
-}
\ No newline at end of file
+}
@@ -1,5 +1,5 @@
+use crate::{constants::MAX_NAME_LEN, enums::ServiceStatus};
 use anchor_lang::prelude::*;
-use crate::{enums::ServiceStatus, constants::MAX_NAME_LEN};
 
 #[account]
 #[derive(InitSpace)]
@@ -9,7 +9,12 @@ pub struct ServiceAccount {
     pub name: String,
     pub status: ServiceStatus,
     pub default_policy: Pubkey,
+    /// Policies created for the service, which seeds the next one
+    pub policy_count: u64,
     pub total_usage_units: u128,
+    pub total_cost_units: u128,
+    /// Seconds an applied checkpoint stays disputable before finalization
+    pub challenge_period_seconds: u64,
     pub created_ts: i64,
     pub bump: u8,
 }
@@ -1,12 +1,46 @@
 use anchor_lang::prelude::*;
 
+use crate::enums::{CheckpointStatus, DisputeReason};
+
 #[account]
 #[derive(InitSpace)]
 pub struct UsageCheckpoint {
     pub api_key: Pubkey,
+    pub checkpoint_seq: u64,
     pub window_start: i64,
     pub request_count: u64,
     pub cost_accumulated: u64,
     pub last_updated: i64,
+    /// Merkle root over the request log roots of the windows covered, or
+    /// the one window's root; all zeros when none was closed with a log
+    pub request_log_root: [u8; 32],
+    pub status: CheckpointStatus,
+    /// End of the challenge period; disputes are accepted until then
+    pub finalizes_at: i64,
+    pub disputer: Pubkey,
+    pub dispute_reason: Option<DisputeReason>,
+    /// Hash of the off-chain evidence (signed receipt, Merkle proof, ...)
+    pub evidence_hash: [u8; 32],
+    /// Amounts counted at finalization; below the reported ones when a
+    /// dispute was upheld
+    pub billed_request_count: u64,
+    pub billed_cost: u64,
+    /// Applied more than the delegation's lag bound after its window opened,
+    /// so canonical state lagged the execution region
+    pub lagging: bool,
+    /// Covers more windows than the usage account keeps roots for, so the
+    /// request logs of the oldest are not in `request_log_root` and their
+    /// charges cannot be proven
+    pub request_log_truncated: bool,
     pub bump: u8,
 }
+
+/// One entry of a gateway's per-window request log, as hashed into the
+/// checkpoint's Merkle tree (see crate::merkle).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct RequestLogLeaf {
+    pub request_id: u64,
+    pub amount: u64,
+    pub cost: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,72 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{ed25519_program, sysvar::instructions as instructions_sysvar},
+};
+
+use crate::error::ErrorCode;
+
+// Layout of an Ed25519 precompile instruction carrying one signature.
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+const ED25519_PUBKEY_LEN: usize = 32;
+const ED25519_SIGNATURE_LEN: usize = 64;
+/// Instruction index meaning "the Ed25519 instruction itself"
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Checks that the instruction right before the current one is an Ed25519
+/// precompile instruction verifying a single signature by `signer` over
+/// exactly `message`. The precompile fails the transaction on a bad
+/// signature, so finding it is enough.
+pub fn verify_ed25519_instruction(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let current = instructions_sysvar::load_current_index_checked(instructions)?;
+    require!(current > 0, ErrorCode::InvalidReceiptSignature);
+    let ix = instructions_sysvar::load_instruction_at_checked(current as usize - 1, instructions)?;
+    require_keys_eq!(
+        ix.program_id,
+        ed25519_program::ID,
+        ErrorCode::InvalidReceiptSignature
+    );
+
+    let data = &ix.data;
+    require!(
+        data.len() >= ED25519_OFFSETS_START + ED25519_OFFSETS_LEN && data[0] == 1,
+        ErrorCode::InvalidReceiptSignature
+    );
+    let offset = |i: usize| {
+        let at = ED25519_OFFSETS_START + i * 2;
+        u16::from_le_bytes([data[at], data[at + 1]])
+    };
+    let (signature_offset, signature_ix) = (offset(0) as usize, offset(1));
+    let (pubkey_offset, pubkey_ix) = (offset(2) as usize, offset(3));
+    let (message_offset, message_len, message_ix) =
+        (offset(4) as usize, offset(5) as usize, offset(6));
+
+    // Signature, key and message must all live in the precompile instruction
+    require!(
+        [signature_ix, pubkey_ix, message_ix]
+            .iter()
+            .all(|&i| i == ED25519_CURRENT_INSTRUCTION),
+        ErrorCode::InvalidReceiptSignature
+    );
+    require!(
+        data.len() >= signature_offset + ED25519_SIGNATURE_LEN,
+        ErrorCode::InvalidReceiptSignature
+    );
+
+    let signed_key = data
+        .get(pubkey_offset..pubkey_offset + ED25519_PUBKEY_LEN)
+        .ok_or(ErrorCode::InvalidReceiptSignature)?;
+    let signed_message = data
+        .get(message_offset..message_offset + message_len)
+        .ok_or(ErrorCode::InvalidReceiptSignature)?;
+    require!(
+        signed_key == signer.as_ref() && signed_message == message,
+        ErrorCode::InvalidReceiptSignature
+    );
+
+    Ok(())
+}
@@ -65,6 +65,7 @@ describe("02_service", () => {
     expect(service.name).to.equal(name);
     expect(service.status.active !== undefined).to.be.true;
     expect(service.defaultPolicy.toString()).to.equal(defaultPolicyPlaceholder.toString());
+    expect(service.policyCount.toNumber()).to.equal(0);
     expect(service.totalUsageUnits.toNumber()).to.equal(0);
     expect(service.bump).to.be.greaterThan(0);
   });
@@ -164,7 +165,7 @@ describe("02_service", () => {
   it("updates service authority", async () => {
     const newAuthority = otherUser.publicKey;
     let sig = await program.methods
-      .updateService(newAuthority, null)
+      .updateService(newAuthority, null, null)
       .accounts({
         authority: admin.publicKey,
         service: servicePda0,
@@ -177,7 +178,7 @@ describe("02_service", () => {
     expect(service.authority.toString()).to.equal(newAuthority.toString());
 
     sig = await program.methods
-      .updateService(admin.publicKey, null)
+      .updateService(admin.publicKey, null, null)
       .accounts({
         authority: otherUser.publicKey,
         service: servicePda0,
@@ -193,7 +194,7 @@ describe("02_service", () => {
   it("updates service default policy", async () => {
     const newPolicy = Keypair.generate().publicKey;
     const sig = await program.methods
-      .updateService(null, newPolicy)
+      .updateService(null, newPolicy, null)
       .accounts({
         authority: admin.publicKey,
         service: servicePda0,
@@ -206,6 +207,38 @@ describe("02_service", () => {
     expect(service.defaultPolicy.toString()).to.equal(newPolicy.toString());
   });
 
+  it("updates service checkpoint challenge period", async () => {
+    const service0 = await program.account.serviceAccount.fetch(servicePda0);
+    expect(service0.challengePeriodSeconds.toNumber()).to.equal(86_400);
+
+    try {
+      await program.methods
+        .updateService(null, null, new anchor.BN(31 * 86_400))
+        .accounts({
+          authority: admin.publicKey,
+          service: servicePda0,
+        })
+        .signers([admin])
+        .rpc();
+      expect.fail("expected InvalidChallengePeriod");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6048);
+    }
+
+    const sig = await program.methods
+      .updateService(null, null, new anchor.BN(0))
+      .accounts({
+        authority: admin.publicKey,
+        service: servicePda0,
+      })
+      .signers([admin])
+      .rpc();
+    saveTransaction(currentTestName, sig);
+
+    const service = await program.account.serviceAccount.fetch(servicePda0);
+    expect(service.challengePeriodSeconds.toNumber()).to.equal(0);
+  });
+
   it("set_service_status: Active -> Paused", async () => {
     const sig = await program.methods
       .setServiceStatus({ paused: {} })
@@ -270,7 +303,7 @@ describe("02_service", () => {
   it("rejects update_service when service is disabled", async () => {
     try {
       await program.methods
-        .updateService(otherUser.publicKey, null)
+        .updateService(otherUser.publicKey, null, null)
         .accounts({
           authority: admin.publicKey,
           service: servicePda0,
@@ -41,7 +41,7 @@ describe("03_policy", () => {
     const count = protocol.serviceCount.toNumber();
     [servicePda0] = servicePda(program.programId, count > 0 ? 0 : 0);
     const service = await program.account.serviceAccount.fetch(servicePda0);
-    policy0 = policyPda(program.programId, servicePda0, service.totalUsageUnits);
+    policy0 = policyPda(program.programId, servicePda0, service.policyCount);
   });
 
   it("creates a policy with valid params", async () => {
@@ -55,7 +55,9 @@ describe("03_policy", () => {
         new anchor.BN(requestsPerWindow),
         new anchor.BN(windowSeconds),
         new anchor.BN(burstLimit),
-        new anchor.BN(costPerRequest)
+        new anchor.BN(costPerRequest),
+        new anchor.BN(0),
+        new anchor.BN(0)
       )
       .accounts({
         authority: admin.publicKey,
@@ -79,11 +81,11 @@ describe("03_policy", () => {
   it("rejects create_policy with invalid config (burst > requests_per_window)", async () => {
     const [svcPda] = servicePda(program.programId, 1);
     const svc = await program.account.serviceAccount.fetch(svcPda);
-    const policyKey = policyPda(program.programId, svcPda, svc.totalUsageUnits);
+    const policyKey = policyPda(program.programId, svcPda, svc.policyCount);
 
     try {
       await program.methods
-        .createPolicy(new anchor.BN(10), new anchor.BN(60), new anchor.BN(20), new anchor.BN(0))
+        .createPolicy(new anchor.BN(10), new anchor.BN(60), new anchor.BN(20), new anchor.BN(0), new anchor.BN(0), new anchor.BN(0))
         .accounts({
           authority: admin.publicKey,
           service: svcPda,
@@ -102,11 +104,11 @@ describe("03_policy", () => {
   it("rejects create_policy when called by non-authority", async () => {
     const [svcPda] = servicePda(program.programId, 1);
     const svc = await program.account.serviceAccount.fetch(svcPda);
-    const policyKey = policyPda(program.programId, svcPda, svc.totalUsageUnits);
+    const policyKey = policyPda(program.programId, svcPda, svc.policyCount);
 
     try {
       await program.methods
-        .createPolicy(new anchor.BN(100), new anchor.BN(60), new anchor.BN(20), new anchor.BN(0))
+        .createPolicy(new anchor.BN(100), new anchor.BN(60), new anchor.BN(20), new anchor.BN(0), new anchor.BN(0), new anchor.BN(0))
         .accounts({
           authority: otherUser.publicKey,
           service: svcPda,
@@ -131,6 +133,8 @@ describe("03_policy", () => {
         new anchor.BN(newRequestsPerWindow),
         new anchor.BN(newWindowSeconds),
         null,
+        null,
+        null,
         null
       )
       .accounts({
@@ -146,4 +150,38 @@ describe("03_policy", () => {
     expect(policy.requestsPerWindow.toNumber()).to.equal(newRequestsPerWindow);
     expect(policy.windowSeconds.toNumber()).to.equal(newWindowSeconds);
   });
+
+  it("sets daily and monthly quotas", async () => {
+    const sig = await program.methods
+      .updatePolicy(null, null, null, null, new anchor.BN(5_000), new anchor.BN(100_000))
+      .accounts({
+        authority: admin.publicKey,
+        service: servicePda0,
+        policy: policy0,
+      })
+      .signers([admin])
+      .rpc();
+    saveTransaction(currentTestName, sig);
+
+    const policy = await program.account.rateLimitPolicy.fetch(policy0);
+    expect(policy.dailyQuota.toNumber()).to.equal(5_000);
+    expect(policy.monthlyQuota.toNumber()).to.equal(100_000);
+  });
+
+  it("rejects a daily quota above the monthly quota", async () => {
+    try {
+      await program.methods
+        .updatePolicy(null, null, null, null, new anchor.BN(200_000), null)
+        .accounts({
+          authority: admin.publicKey,
+          service: servicePda0,
+          policy: policy0,
+        })
+        .signers([admin])
+        .rpc();
+      expect.fail("expected InvalidRateLimitConfig");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6012);
+    }
+  });
 });
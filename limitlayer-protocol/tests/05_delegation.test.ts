@@ -12,11 +12,16 @@ import { LimitlayerProtocol } from "../target/types/limitlayer_protocol";
 import {
   apiKeyPda,
   delegatedUsagePda,
+  executionRegionPda,
+  getErrorCode,
   policyPda,
   protocolPda,
   reputationPda,
   saveTransaction,
   servicePda,
+  transactionEvents,
+  usageCheckpointPda,
+  usageShardPda,
 } from "./helpers";
 
 const DELEGATION_PROGRAM_ID = new PublicKey(
@@ -68,6 +73,17 @@ describe("05_delegation", () => {
   let apiKey0: PublicKey;
   let delegatedUsage0: PublicKey;
 
+  const isLocal =
+    providerEphemeralRollup.connection.rpcEndpoint.includes("localhost") ||
+    providerEphemeralRollup.connection.rpcEndpoint.includes("127.0.0.1");
+  const executionRegion = isLocal
+    ? LOCAL_VALIDATOR_PUBKEY
+    : DEVNET_AS_VALIDATOR;
+  const executionRegionKey = executionRegionPda(
+    program.programId,
+    executionRegion
+  );
+
   before(async () => {
     admin = provider.wallet.payer as Keypair;
     const balance = await provider.connection.getBalance(admin.publicKey);
@@ -79,8 +95,7 @@ describe("05_delegation", () => {
     );
 
     [servicePda0] = servicePda(program.programId, 0);
-    const service = await program.account.serviceAccount.fetch(servicePda0);
-    policy0 = policyPda(program.programId, servicePda0, service.totalUsageUnits);
+    policy0 = policyPda(program.programId, servicePda0, new anchor.BN(0));
 
     const protocol = await program.account.protocolState.fetch(protocolPdaKey);
 
@@ -102,15 +117,99 @@ describe("05_delegation", () => {
       .signers([admin])
       .rpc();
     delegatedUsage0 = delegatedUsagePda(program.programId, apiKey0);
+
+    // Checkpoints in this suite are finalized right after being applied
+    await program.methods
+      .updateService(null, null, new anchor.BN(0))
+      .accounts({ authority: admin.publicKey, service: servicePda0 })
+      .signers([admin])
+      .rpc();
+
+    // Delegation only targets registered regions
+    const region =
+      await program.account.executionRegionAccount.fetchNullable(
+        executionRegionKey
+      );
+    if (!region) {
+      await program.methods
+        .registerExecutionRegion(executionRegion, isLocal ? "local" : "devnet-as")
+        .accountsPartial({
+          admin: admin.publicKey,
+          protocol: protocolPdaKey,
+          executionRegion: executionRegionKey,
+        })
+        .signers([admin])
+        .rpc();
+    }
+  });
+
+  it("create_usage_shard adds shards in order", async () => {
+    const createShard = (shardIndex: number) =>
+      program.methods
+        .createUsageShard(shardIndex)
+        .accountsPartial({
+          authority: admin.publicKey,
+          service: servicePda0,
+          apiKey: apiKey0,
+          delegatedUsage: usageShardPda(program.programId, apiKey0, shardIndex),
+        })
+        .signers([admin])
+        .rpc();
+
+    try {
+      await createShard(2);
+      expect.fail("expected InvalidUsageShard");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6041);
+    }
+
+    const txHash = await createShard(1);
+    saveTransaction(currentTestName, txHash);
+
+    const key = await program.account.apiKeyAccount.fetch(apiKey0);
+    expect(key.usageShards).to.equal(2);
+    const shard = await program.account.delegatedUsageAccount.fetch(
+      usageShardPda(program.programId, apiKey0, 1)
+    );
+    expect(shard.shardIndex).to.equal(1);
+    expect(shard.apiKey.toString()).to.equal(apiKey0.toString());
+  });
+
+  it("prepare_delegation rejects a disabled execution region", async () => {
+    const setStatus = (newStatus: object) =>
+      program.methods
+        .setExecutionRegionStatus(newStatus as never)
+        .accountsPartial({
+          admin: admin.publicKey,
+          protocol: protocolPdaKey,
+          executionRegion: executionRegionKey,
+        })
+        .signers([admin])
+        .rpc();
+
+    await setStatus({ disabled: {} });
+    try {
+      await program.methods
+        .prepareDelegation(0, new anchor.BN(0))
+        .accountsPartial({
+          authority: admin.publicKey,
+          service: servicePda0,
+          apiKey: apiKey0,
+          policy: policy0,
+          executionRegion: executionRegionKey,
+          delegatedUsage: delegatedUsage0,
+        })
+        .signers([admin])
+        .rpc();
+      expect.fail("expected InvalidExecutionRegion");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6021);
+    } finally {
+      await setStatus({ active: {} });
+    }
   });
 
   it("prepare_delegation + delegate_usage on Solana (base layer)", async () => {
-    const isLocal =
-      providerEphemeralRollup.connection.rpcEndpoint.includes("localhost") ||
-      providerEphemeralRollup.connection.rpcEndpoint.includes("127.0.0.1");
-    const executionRegion = isLocal
-      ? LOCAL_VALIDATOR_PUBKEY
-      : DEVNET_AS_VALIDATOR;
     const remainingAccounts = isLocal
       ? [
           {
@@ -123,12 +222,13 @@ describe("05_delegation", () => {
 
     // const start = Date.now();
     const prepareIx = await program.methods
-      .prepareDelegation(executionRegion)
+      .prepareDelegation(10_000, new anchor.BN(3_600))
       .accountsPartial({
         authority: admin.publicKey,
         service: servicePda0,
         apiKey: apiKey0,
         policy: policy0,
+        executionRegion: executionRegionKey,
         delegatedUsage: delegatedUsage0,
       })
       .instruction();
@@ -164,6 +264,14 @@ describe("05_delegation", () => {
     expect(accInfo?.owner.toString()).to.equal(
       DELEGATION_PROGRAM_ID.toString()
     );
+    const prepared = program.coder.accounts.decode(
+      "delegatedUsageAccount",
+      accInfo!.data
+    );
+    expect(prepared.commitFrequencyMs).to.equal(10_000);
+    expect(
+      prepared.leaseExpiresAt.toNumber() - prepared.delegatedAt.toNumber()
+    ).to.equal(3_600);
 
     await new Promise((resolve) => setTimeout(resolve, 3000));
   });
@@ -171,8 +279,11 @@ describe("05_delegation", () => {
   it("record_usage_realtime on ER", async () => {
     // const start = Date.now();
     let tx = await program.methods
-      .recordUsageRealtime(new anchor.BN(5))
-      .accounts({
+      .recordUsageRealtime(new anchor.BN(5), null)
+      .accountsPartial({
+        submitter: providerEphemeralRollup.wallet.publicKey,
+        service: servicePda0,
+        gateway: null,
         delegatedUsage: delegatedUsage0,
         apiKey: apiKey0,
         policy: policy0,
@@ -201,13 +312,211 @@ describe("05_delegation", () => {
     expect(delegated.currentWindowUsage.toNumber()).to.be.greaterThanOrEqual(5);
   });
 
+  it("record_usage_realtime ignores a retried request id", async () => {
+    const programER = new anchor.Program(
+      program.idl!,
+      providerEphemeralRollup
+    ) as Program<LimitlayerProtocol>;
+    const before =
+      await programER.account.delegatedUsageAccount.fetch(delegatedUsage0);
+
+    // Same idempotency key twice, as a gateway retry after a timeout would
+    const requestId = new anchor.BN(Date.now());
+    for (let i = 0; i < 2; i++) {
+      let tx = await program.methods
+        .recordUsageRealtime(new anchor.BN(3), requestId)
+        .accountsPartial({
+          submitter: providerEphemeralRollup.wallet.publicKey,
+          service: servicePda0,
+          gateway: null,
+          delegatedUsage: delegatedUsage0,
+          apiKey: apiKey0,
+          policy: policy0,
+        })
+        .transaction();
+      tx.feePayer = providerEphemeralRollup.wallet.publicKey;
+      tx.recentBlockhash = (
+        await providerEphemeralRollup.connection.getLatestBlockhash()
+      ).blockhash;
+      tx = await providerEphemeralRollup.wallet.signTransaction(tx);
+      const txHash = await providerEphemeralRollup.sendAndConfirm(tx);
+      saveTransaction(currentTestName, txHash);
+    }
+
+    const after =
+      await programER.account.delegatedUsageAccount.fetch(delegatedUsage0);
+    expect(
+      after.currentWindowUsage.sub(before.currentWindowUsage).toNumber()
+    ).to.equal(3);
+  });
+
+  it("reserve_usage, settle_usage and release_usage on ER", async () => {
+    const programER = new anchor.Program(
+      program.idl!,
+      providerEphemeralRollup
+    ) as Program<LimitlayerProtocol>;
+    const sendER = async (tx: anchor.web3.Transaction) => {
+      tx.feePayer = providerEphemeralRollup.wallet.publicKey;
+      tx.recentBlockhash = (
+        await providerEphemeralRollup.connection.getLatestBlockhash()
+      ).blockhash;
+      tx = await providerEphemeralRollup.wallet.signTransaction(tx);
+      const txHash = await providerEphemeralRollup.sendAndConfirm(tx);
+      saveTransaction(currentTestName, txHash);
+    };
+    // The ER wallet is the service authority, so no gateway is passed
+    const submitter = {
+      submitter: providerEphemeralRollup.wallet.publicKey,
+      service: servicePda0,
+      gateway: null,
+    };
+    const accounts = {
+      ...submitter,
+      delegatedUsage: delegatedUsage0,
+      apiKey: apiKey0,
+      policy: policy0,
+    };
+    const latestReservationId = async () =>
+      (
+        await programER.account.delegatedUsageAccount.fetch(delegatedUsage0)
+      ).nextReservationId;
+
+    const before =
+      await programER.account.delegatedUsageAccount.fetch(delegatedUsage0);
+
+    // Reserve 3, settle at 2: only the actual amount is charged
+    await sendER(
+      await program.methods
+        .reserveUsage(new anchor.BN(3), new anchor.BN(0))
+        .accountsPartial(accounts)
+        .transaction()
+    );
+    const settledId = await latestReservationId();
+    await sendER(
+      await program.methods
+        .settleUsage(settledId, new anchor.BN(2))
+        .accountsPartial(accounts)
+        .transaction()
+    );
+
+    // Reserve and release: nothing is charged
+    await sendER(
+      await program.methods
+        .reserveUsage(new anchor.BN(4), new anchor.BN(60))
+        .accountsPartial(accounts)
+        .transaction()
+    );
+    const releasedId = await latestReservationId();
+    await sendER(
+      await program.methods
+        .releaseUsage(releasedId)
+        .accountsPartial({
+          ...submitter,
+          delegatedUsage: delegatedUsage0,
+          apiKey: apiKey0,
+        })
+        .transaction()
+    );
+
+    const after =
+      await programER.account.delegatedUsageAccount.fetch(delegatedUsage0);
+    expect(
+      after.currentWindowUsage.sub(before.currentWindowUsage).toNumber()
+    ).to.equal(2);
+    expect(after.reservations.every((r) => r.id.isZero())).to.equal(true);
+  });
+
+  it("renew_delegation_lease on ER extends the lease", async () => {
+    let tx = await program.methods
+      .renewDelegationLease(new anchor.BN(7_200))
+      .accountsPartial({
+        authority: providerEphemeralRollup.wallet.publicKey,
+        service: servicePda0,
+        apiKey: apiKey0,
+        delegatedUsage: delegatedUsage0,
+      })
+      .transaction();
+
+    tx.feePayer = providerEphemeralRollup.wallet.publicKey;
+    tx.recentBlockhash = (
+      await providerEphemeralRollup.connection.getLatestBlockhash()
+    ).blockhash;
+    tx = await providerEphemeralRollup.wallet.signTransaction(tx);
+
+    const txHash = await providerEphemeralRollup.sendAndConfirm(tx);
+    saveTransaction(currentTestName, txHash);
+
+    const programER = new anchor.Program(
+      program.idl!,
+      providerEphemeralRollup
+    ) as Program<LimitlayerProtocol>;
+    const delegated =
+      await programER.account.delegatedUsageAccount.fetch(delegatedUsage0);
+    expect(delegated.leaseExpiresAt.toNumber()).to.be.greaterThan(
+      delegated.delegatedAt.toNumber() + 3_600
+    );
+  });
+
+  it("record_usage_batch on ER reports per-entry results", async () => {
+    let tx = await program.methods
+      .recordUsageBatch([new anchor.BN(1), new anchor.BN(2)], [])
+      .accountsPartial({
+        submitter: providerEphemeralRollup.wallet.publicKey,
+        service: servicePda0,
+        gateway: null,
+      })
+      .remainingAccounts([
+        { pubkey: delegatedUsage0, isSigner: false, isWritable: true },
+        { pubkey: apiKey0, isSigner: false, isWritable: false },
+        { pubkey: policy0, isSigner: false, isWritable: false },
+        // Mismatched binding: api key and policy swapped
+        { pubkey: delegatedUsage0, isSigner: false, isWritable: true },
+        { pubkey: policy0, isSigner: false, isWritable: false },
+        { pubkey: apiKey0, isSigner: false, isWritable: false },
+      ])
+      .transaction();
+
+    tx.feePayer = providerEphemeralRollup.wallet.publicKey;
+    tx.recentBlockhash = (
+      await providerEphemeralRollup.connection.getLatestBlockhash()
+    ).blockhash;
+    tx = await providerEphemeralRollup.wallet.signTransaction(tx);
+
+    const txHash = await providerEphemeralRollup.sendAndConfirm(tx);
+    saveTransaction(currentTestName, txHash);
+
+    const programER = new anchor.Program(
+      program.idl!,
+      providerEphemeralRollup
+    ) as Program<LimitlayerProtocol>;
+    const delegated =
+      await programER.account.delegatedUsageAccount.fetch(delegatedUsage0);
+    expect(delegated.currentWindowUsage.toNumber()).to.be.greaterThanOrEqual(6);
+
+    const events = await transactionEvents(
+      programER,
+      providerEphemeralRollup.connection,
+      txHash
+    );
+    const batch = events.find((event) => event.name === "usageBatchRecorded");
+    expect(batch).to.not.be.undefined;
+    // Recorded, then InvalidBinding for the swapped entry
+    expect(Array.from(batch!.data.results as number[])).to.deep.equal([0, 1]);
+    expect(batch!.data.recorded).to.equal(1);
+    expect(batch!.data.rejected).to.equal(1);
+    expect(batch!.data.totalAmount.toNumber()).to.equal(1);
+  });
+
   it("submit_usage_checkpoint on ER and confirm on base layer", async () => {
     const start = Date.now();
     let tx = await program.methods
-      .submitUsageCheckpoint()
+      .submitUsageCheckpoint(Array(32).fill(0))
       .accountsPartial({
         payer: providerEphemeralRollup.wallet.publicKey,
         delegatedUsage: delegatedUsage0,
+        apiKey: apiKey0,
+        service: servicePda0,
+        gateway: null,
         magicContext: MAGIC_CONTEXT_ID,
         magicProgram: MAGIC_PROGRAM_ID,
       })
@@ -237,6 +546,70 @@ describe("05_delegation", () => {
     // );
   });
 
+  it("apply_usage_checkpoint folds committed usage and cost into the api key", async () => {
+    const raw = await provider.connection.getAccountInfo(delegatedUsage0);
+    const committed = program.coder.accounts.decode(
+      "delegatedUsageAccount",
+      raw!.data
+    );
+    const before = await program.account.apiKeyAccount.fetch(apiKey0);
+
+    const sig = await program.methods
+      .applyUsageCheckpoint(committed.checkpointSeq)
+      .accountsPartial({
+        payer: admin.publicKey,
+        protocol: protocolPdaKey,
+        service: servicePda0,
+        apiKey: apiKey0,
+        policy: policy0,
+        delegatedUsage: delegatedUsage0,
+        usageCheckpoint: usageCheckpointPda(
+          program.programId,
+          apiKey0,
+          committed.checkpointSeq
+        ),
+      })
+      .signers([admin])
+      .rpc();
+    saveTransaction(currentTestName, sig);
+
+    const checkpointKey = usageCheckpointPda(
+      program.programId,
+      apiKey0,
+      committed.checkpointSeq
+    );
+    const pending = await program.account.usageCheckpoint.fetch(checkpointKey);
+    expect(pending.status.pending !== undefined).to.be.true;
+
+    const applied = await program.account.apiKeyAccount.fetch(apiKey0);
+    expect(applied.appliedUsage.toString()).to.equal(
+      committed.checkpointedUsage.toString()
+    );
+    expect(applied.appliedCost.toString()).to.equal(
+      committed.checkpointedCost.toString()
+    );
+    // Billing totals only move once the checkpoint is finalized
+    expect(applied.lifetimeUsage.toString()).to.equal(
+      before.lifetimeUsage.toString()
+    );
+
+    const finalizeSig = await program.methods
+      .finalizeUsageCheckpoint()
+      .accounts({
+        service: servicePda0,
+        apiKey: apiKey0,
+        usageCheckpoint: checkpointKey,
+      })
+      .rpc();
+    saveTransaction(currentTestName, finalizeSig);
+
+    const after = await program.account.apiKeyAccount.fetch(apiKey0);
+    expect(after.lifetimeUsage.sub(before.lifetimeUsage).toString()).to.equal(
+      pending.requestCount.toString()
+    );
+    expect(after.lifetimeUsage.gt(before.lifetimeUsage)).to.be.true;
+  });
+
   it("undelegate_usage on ER to Solana", async () => {
     const start = Date.now();
     let tx = await program.methods
@@ -265,4 +638,68 @@ describe("05_delegation", () => {
     const duration = Date.now() - start;
     // console.log(`${duration}ms (ER) Undelegate txHash: ${txHash}`);
   });
+
+  it("complete_region_migration rejects when no migration is pending", async () => {
+    // Wait for the undelegation to land back on the base layer
+    await new Promise((resolve) => setTimeout(resolve, 5000));
+    try {
+      await program.methods
+        .completeRegionMigration()
+        .accountsPartial({
+          authority: admin.publicKey,
+          service: servicePda0,
+          apiKey: apiKey0,
+          delegatedUsage: delegatedUsage0,
+          executionRegion: executionRegionKey,
+        })
+        .signers([admin])
+        .rpc();
+      expect.fail("expected InvalidDelegationState");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6020);
+    }
+  });
+
+  it("reconcile_returned_delegation rejects a cleanly undelegated key", async () => {
+    try {
+      await program.methods
+        .reconcileReturnedDelegation()
+        .accountsPartial({
+          authority: admin.publicKey,
+          service: servicePda0,
+          apiKey: apiKey0,
+          delegatedUsage: delegatedUsage0,
+        })
+        .signers([admin])
+        .rpc();
+      expect.fail("expected NotDelegated");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6019);
+    }
+  });
+
+  it("record_usage_direct records on the base layer once undelegated", async () => {
+    const before =
+      await program.account.delegatedUsageAccount.fetch(delegatedUsage0);
+    expect(before.delegated).to.equal(false);
+
+    const txHash = await program.methods
+      .recordUsageDirect(new anchor.BN(4), null)
+      .accountsPartial({
+        submitter: admin.publicKey,
+        service: servicePda0,
+        gateway: null,
+        delegatedUsage: delegatedUsage0,
+        apiKey: apiKey0,
+        policy: policy0,
+      })
+      .rpc();
+    saveTransaction(currentTestName, txHash);
+
+    const after =
+      await program.account.delegatedUsageAccount.fetch(delegatedUsage0);
+    // A new window may have been opened if the previous one ran out
+    expect(after.currentWindowUsage.toNumber()).to.be.greaterThanOrEqual(4);
+    expect(after.delegated).to.equal(false);
+  });
 });
@@ -53,8 +53,7 @@ describe("06_enforcement", () => {
     await (provider as anchor.AnchorProvider).sendAndConfirm(tx);
 
     [servicePda0] = servicePda(program.programId, 0);
-    const service = await program.account.serviceAccount.fetch(servicePda0);
-    policy0 = policyPda(program.programId, servicePda0, service.totalUsageUnits);
+    policy0 = policyPda(program.programId, servicePda0, new anchor.BN(0));
 
     const protocol = await program.account.protocolState.fetch(protocolPdaKey);
     apiKey0 = apiKeyPda(program.programId, protocol.apiKeyCount);
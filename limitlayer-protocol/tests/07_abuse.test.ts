@@ -61,8 +61,7 @@ describe("07_abuse", () => {
     );
 
     [servicePda0] = servicePda(program.programId, 0);
-    const service = await program.account.serviceAccount.fetch(servicePda0);
-    policy0 = policyPda(program.programId, servicePda0, service.totalUsageUnits);
+    policy0 = policyPda(program.programId, servicePda0, new anchor.BN(0));
     reputation0 = reputationPda(program.programId, subject.publicKey);
     severityTestReputation = reputationPda(program.programId, severityTestSubject.publicKey);
 
@@ -0,0 +1,352 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  Ed25519Program,
+  Keypair,
+  PublicKey,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Transaction,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import { LimitlayerProtocol } from "../target/types/limitlayer_protocol";
+import {
+  apiKeyPda,
+  auditorPda,
+  delegatedUsagePda,
+  gatewayPda,
+  getErrorCode,
+  protocolPda,
+  RequestLogLeaf,
+  requestLogProof,
+  reputationPda,
+  saveTransaction,
+  servicePda,
+  usageCheckpointPda,
+  usageReceiptMessage,
+} from "./helpers";
+
+describe("09_receipts", () => {
+  let currentTestName = "";
+  beforeEach(function (this: Mocha.Context) {
+    currentTestName = this.currentTest?.fullTitle() ?? "unknown";
+  });
+
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace.limitlayerProtocol as Program<LimitlayerProtocol>;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+  const protocolPdaKey = protocolPda(program.programId);
+
+  const gatewayKey = Keypair.generate();
+  let admin: Keypair;
+  let servicePda0: PublicKey;
+  let policy0: PublicKey;
+  let apiKey0: PublicKey;
+  let delegatedUsage0: PublicKey;
+
+  const applyReceipt = async (
+    signer: Keypair,
+    receipt: {
+      apiKey: PublicKey;
+      windowStart: anchor.BN;
+      requestCount: anchor.BN;
+      cost: anchor.BN;
+      requestLogRoot: number[];
+      nonce: anchor.BN;
+    }
+  ) => {
+    const usage = await program.account.delegatedUsageAccount.fetch(
+      delegatedUsage0
+    );
+    const verifyIx = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: signer.secretKey,
+      message: usageReceiptMessage(receipt),
+    });
+    const applyIx = await program.methods
+      .applyUsageReceipt(receipt)
+      .accountsPartial({
+        payer: admin.publicKey,
+        protocol: protocolPdaKey,
+        service: servicePda0,
+        apiKey: apiKey0,
+        policy: policy0,
+        gateway: gatewayPda(program.programId, servicePda0, gatewayKey.publicKey),
+        delegatedUsage: delegatedUsage0,
+        usageCheckpoint: usageCheckpointPda(
+          program.programId,
+          apiKey0,
+          usage.checkpointSeq.addn(1)
+        ),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .instruction();
+    return provider.sendAndConfirm(new Transaction().add(verifyIx, applyIx), [
+      admin,
+    ]);
+  };
+
+  before(async () => {
+    admin = provider.wallet.payer as Keypair;
+    [servicePda0] = servicePda(program.programId, 0);
+
+    const policies = await program.account.rateLimitPolicy.all();
+    policy0 = policies.find((p) => p.account.service.equals(servicePda0))!
+      .publicKey;
+
+    const protocol = await program.account.protocolState.fetch(protocolPdaKey);
+    apiKey0 = apiKeyPda(program.programId, protocol.apiKeyCount);
+    delegatedUsage0 = delegatedUsagePda(program.programId, apiKey0);
+    await program.methods
+      .createApiKey(policy0)
+      .accounts({
+        authority: admin.publicKey,
+        // @ts-ignore
+        protocol: protocolPdaKey,
+        service: servicePda0,
+        apiKey: apiKey0,
+        delegatedUsage: delegatedUsage0,
+        reputation: reputationPda(program.programId, admin.publicKey),
+        owner: admin.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([admin])
+      .rpc();
+
+    // Checkpoints in this suite are finalized right after being applied
+    await program.methods
+      .updateService(null, null, new anchor.BN(0))
+      .accounts({ authority: admin.publicKey, service: servicePda0 })
+      .signers([admin])
+      .rpc();
+  });
+
+  it("register_gateway registers a receipt signing key", async () => {
+    const sig = await program.methods
+      .registerGateway(gatewayKey.publicKey)
+      .accountsPartial({
+        authority: admin.publicKey,
+        service: servicePda0,
+        gateway: gatewayPda(program.programId, servicePda0, gatewayKey.publicKey),
+      })
+      .signers([admin])
+      .rpc();
+    saveTransaction(currentTestName, sig);
+
+    const gateway = await program.account.gatewayAccount.fetch(
+      gatewayPda(program.programId, servicePda0, gatewayKey.publicKey)
+    );
+    expect(gateway.gatewayKey.toString()).to.equal(
+      gatewayKey.publicKey.toString()
+    );
+  });
+
+  it("apply_usage_receipt applies a gateway-signed receipt as a checkpoint", async () => {
+    const sig = await applyReceipt(gatewayKey, {
+      apiKey: apiKey0,
+      windowStart: new anchor.BN(Math.floor(Date.now() / 1000) - 60),
+      requestCount: new anchor.BN(10),
+      cost: new anchor.BN(25),
+      requestLogRoot: Array(32).fill(0),
+      nonce: new anchor.BN(1),
+    });
+    saveTransaction(currentTestName, sig);
+
+    const usage = await program.account.delegatedUsageAccount.fetch(
+      delegatedUsage0
+    );
+    await program.methods
+      .finalizeUsageCheckpoint()
+      .accounts({
+        service: servicePda0,
+        apiKey: apiKey0,
+        usageCheckpoint: usageCheckpointPda(
+          program.programId,
+          apiKey0,
+          usage.checkpointSeq
+        ),
+      })
+      .rpc();
+
+    const key = await program.account.apiKeyAccount.fetch(apiKey0);
+    expect(key.lifetimeUsage.toNumber()).to.equal(10);
+    expect(key.lifetimeCost.toNumber()).to.equal(25);
+  });
+
+  it("apply_usage_receipt rejects a replayed nonce", async () => {
+    try {
+      await applyReceipt(gatewayKey, {
+        apiKey: apiKey0,
+        windowStart: new anchor.BN(0),
+        requestCount: new anchor.BN(10),
+        cost: new anchor.BN(25),
+        requestLogRoot: Array(32).fill(0),
+        nonce: new anchor.BN(1),
+      });
+      expect.fail("expected ReceiptReplayed");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6046);
+    }
+  });
+
+  it("apply_usage_receipt rejects a receipt signed by another key", async () => {
+    try {
+      await applyReceipt(Keypair.generate(), {
+        apiKey: apiKey0,
+        windowStart: new anchor.BN(0),
+        requestCount: new anchor.BN(1),
+        cost: new anchor.BN(1),
+        requestLogRoot: Array(32).fill(0),
+        nonce: new anchor.BN(2),
+      });
+      expect.fail("expected InvalidReceiptSignature");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6045);
+    }
+  });
+
+  it("verify_request_inclusion proves a request billed by a checkpoint", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const log: RequestLogLeaf[] = [1, 2, 3].map((i) => ({
+      requestId: new anchor.BN(i),
+      amount: new anchor.BN(1),
+      cost: new anchor.BN(2),
+      timestamp: new anchor.BN(now - 10 + i),
+    }));
+    const { root, proof } = requestLogProof(log, 2);
+
+    await applyReceipt(gatewayKey, {
+      apiKey: apiKey0,
+      windowStart: new anchor.BN(now - 60),
+      requestCount: new anchor.BN(3),
+      cost: new anchor.BN(6),
+      requestLogRoot: root,
+      nonce: new anchor.BN(3),
+    });
+    const usage = await program.account.delegatedUsageAccount.fetch(
+      delegatedUsage0
+    );
+    const checkpoint = usageCheckpointPda(
+      program.programId,
+      apiKey0,
+      usage.checkpointSeq
+    );
+
+    const sig = await program.methods
+      .verifyRequestInclusion(log[2], proof)
+      .accounts({ usageCheckpoint: checkpoint })
+      .rpc();
+    saveTransaction(currentTestName, sig);
+
+    try {
+      await program.methods
+        .verifyRequestInclusion({ ...log[2], amount: new anchor.BN(100) }, proof)
+        .accounts({ usageCheckpoint: checkpoint })
+        .rpc();
+      expect.fail("expected InvalidMerkleProof");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6047);
+    }
+  });
+
+  it("register_auditor and revoke_auditor manage service auditors", async () => {
+    const auditor = Keypair.generate().publicKey;
+    const auditorAccount = auditorPda(program.programId, servicePda0, auditor);
+
+    const sig = await program.methods
+      .registerAuditor(auditor)
+      .accountsPartial({
+        authority: admin.publicKey,
+        service: servicePda0,
+        auditorAccount,
+      })
+      .signers([admin])
+      .rpc();
+    saveTransaction(currentTestName, sig);
+
+    const registered = await program.account.auditorAccount.fetch(auditorAccount);
+    expect(registered.auditor.toString()).to.equal(auditor.toString());
+
+    await program.methods
+      .revokeAuditor()
+      .accountsPartial({
+        authority: admin.publicKey,
+        service: servicePda0,
+        auditorAccount,
+      })
+      .signers([admin])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(auditorAccount)).to.be.null;
+  });
+
+  it("dispute_usage_checkpoint holds a checkpoint until both parties resolve it", async () => {
+    const setChallengePeriod = (seconds: number) =>
+      program.methods
+        .updateService(null, null, new anchor.BN(seconds))
+        .accounts({ authority: admin.publicKey, service: servicePda0 })
+        .signers([admin])
+        .rpc();
+
+    await setChallengePeriod(3_600);
+    try {
+      await applyReceipt(gatewayKey, {
+        apiKey: apiKey0,
+        windowStart: new anchor.BN(Math.floor(Date.now() / 1000) - 60),
+        requestCount: new anchor.BN(8),
+        cost: new anchor.BN(16),
+        requestLogRoot: Array(32).fill(0),
+        nonce: new anchor.BN(4),
+      });
+    } finally {
+      await setChallengePeriod(0);
+    }
+    const usage = await program.account.delegatedUsageAccount.fetch(
+      delegatedUsage0
+    );
+    const checkpoint = usageCheckpointPda(
+      program.programId,
+      apiKey0,
+      usage.checkpointSeq
+    );
+    const accounts = {
+      service: servicePda0,
+      apiKey: apiKey0,
+      usageCheckpoint: checkpoint,
+    };
+
+    try {
+      await program.methods
+        .finalizeUsageCheckpoint()
+        .accounts(accounts)
+        .rpc();
+      expect.fail("expected ChallengePeriodActive");
+    } catch (err: unknown) {
+      expect(getErrorCode(err)).to.equal(6051);
+    }
+
+    // The key owner disputes; no auditor account needed
+    const disputeSig = await program.methods
+      .disputeUsageCheckpoint({ invalidMerkleProof: {} }, Array(32).fill(7))
+      .accountsPartial({ disputer: admin.publicKey, ...accounts, auditor: null })
+      .signers([admin])
+      .rpc();
+    saveTransaction(currentTestName, disputeSig);
+
+    const before = await program.account.apiKeyAccount.fetch(apiKey0);
+    const resolveSig = await program.methods
+      .resolveCheckpointDispute(new anchor.BN(5), new anchor.BN(10))
+      .accountsPartial({
+        authority: admin.publicKey,
+        admin: admin.publicKey,
+        protocol: protocolPdaKey,
+        ...accounts,
+      })
+      .signers([admin])
+      .rpc();
+    saveTransaction(currentTestName, resolveSig);
+
+    const resolved = await program.account.usageCheckpoint.fetch(checkpoint);
+    expect(resolved.status.finalized !== undefined).to.be.true;
+    expect(resolved.billedRequestCount.toNumber()).to.equal(5);
+    const after = await program.account.apiKeyAccount.fetch(apiKey0);
+    expect(after.lifetimeUsage.sub(before.lifetimeUsage).toNumber()).to.equal(5);
+  });
+});
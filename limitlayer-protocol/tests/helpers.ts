@@ -1,6 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { AnchorError } from "@coral-xyz/anchor";
 import { PublicKey } from "@solana/web3.js";
+import { createHash } from "crypto";
 import * as fs from "fs";
 import * as path from "path";
 
@@ -23,6 +24,20 @@ export function getErrorCode(err: unknown): number | undefined {
   return e?.error?.errorCode?.number ?? e?.code;
 }
 
+/** Events a confirmed transaction emitted, decoded from its logs. */
+export async function transactionEvents(
+  program: anchor.Program<any>,
+  connection: anchor.web3.Connection,
+  signature: string
+): Promise<anchor.Event[]> {
+  const tx = await connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const parser = new anchor.EventParser(program.programId, program.coder);
+  return Array.from(parser.parseLogs(tx?.meta?.logMessages ?? []));
+}
+
 export const LAMPORTS_PER_SOL = 1e9;
 export const TEST_SOL = 0.05 * LAMPORTS_PER_SOL;
 
@@ -48,10 +63,10 @@ export function servicePda(
 export function policyPda(
   programId: PublicKey,
   service: PublicKey,
-  totalUsageUnits: anchor.BN
+  policyCount: anchor.BN
 ): PublicKey {
   const [pda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("policy"), service.toBuffer(), totalUsageUnits.toArrayLike(Buffer, "le", 16)],
+    [Buffer.from("policy"), service.toBuffer(), policyCount.toArrayLike(Buffer, "le", 8)],
     programId
   );
   return pda;
@@ -81,6 +96,129 @@ export function delegatedUsagePda(programId: PublicKey, apiKey: PublicKey): Publ
   return pda;
 }
 
+export function usageShardPda(
+  programId: PublicKey,
+  apiKey: PublicKey,
+  shardIndex: number
+): PublicKey {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("delegated_usage"), apiKey.toBuffer(), Buffer.from([shardIndex])],
+    programId
+  );
+  return pda;
+}
+
+export function executionRegionPda(programId: PublicKey, validator: PublicKey): PublicKey {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("execution_region"), validator.toBuffer()],
+    programId
+  );
+  return pda;
+}
+
+export function gatewayPda(
+  programId: PublicKey,
+  service: PublicKey,
+  gatewayKey: PublicKey
+): PublicKey {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("gateway"), service.toBuffer(), gatewayKey.toBuffer()],
+    programId
+  );
+  return pda;
+}
+
+export function auditorPda(
+  programId: PublicKey,
+  service: PublicKey,
+  auditor: PublicKey
+): PublicKey {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("auditor"), service.toBuffer(), auditor.toBuffer()],
+    programId
+  );
+  return pda;
+}
+
+/** Message a gateway signs: domain prefix + Borsh-encoded UsageReceipt. */
+export function usageReceiptMessage(receipt: {
+  apiKey: PublicKey;
+  windowStart: anchor.BN;
+  requestCount: anchor.BN;
+  cost: anchor.BN;
+  requestLogRoot: number[];
+  nonce: anchor.BN;
+}): Buffer {
+  return Buffer.concat([
+    Buffer.from("limitlayer:usage_receipt:v1"),
+    receipt.apiKey.toBuffer(),
+    receipt.windowStart.toTwos(64).toArrayLike(Buffer, "le", 8),
+    receipt.requestCount.toArrayLike(Buffer, "le", 8),
+    receipt.cost.toArrayLike(Buffer, "le", 8),
+    Buffer.from(receipt.requestLogRoot),
+    receipt.nonce.toArrayLike(Buffer, "le", 8),
+  ]);
+}
+
+export type RequestLogLeaf = {
+  requestId: anchor.BN;
+  amount: anchor.BN;
+  cost: anchor.BN;
+  timestamp: anchor.BN;
+};
+
+const sha256 = (...parts: Buffer[]) =>
+  createHash("sha256").update(Buffer.concat(parts)).digest();
+
+/** Mirrors the program's merkle module: prefixed leaves, sorted pairs. */
+export function requestLogLeafHash(leaf: RequestLogLeaf): Buffer {
+  return sha256(
+    Buffer.from([0]),
+    leaf.requestId.toArrayLike(Buffer, "le", 8),
+    leaf.amount.toArrayLike(Buffer, "le", 8),
+    leaf.cost.toArrayLike(Buffer, "le", 8),
+    leaf.timestamp.toTwos(64).toArrayLike(Buffer, "le", 8)
+  );
+}
+
+function requestLogNode(a: Buffer, b: Buffer): Buffer {
+  return Buffer.compare(a, b) <= 0
+    ? sha256(Buffer.from([1]), a, b)
+    : sha256(Buffer.from([1]), b, a);
+}
+
+/** Root and the proof for leaf `index` over a request log. */
+export function requestLogProof(
+  leaves: RequestLogLeaf[],
+  index: number
+): { root: number[]; proof: number[][] } {
+  let level = leaves.map(requestLogLeafHash);
+  const proof: number[][] = [];
+  while (level.length > 1) {
+    const sibling = index ^ 1;
+    if (sibling < level.length) proof.push([...level[sibling]]);
+    const next: Buffer[] = [];
+    for (let i = 0; i < level.length; i += 2) {
+      next.push(i + 1 < level.length ? requestLogNode(level[i], level[i + 1]) : level[i]);
+    }
+    level = next;
+    index = Math.floor(index / 2);
+  }
+  return { root: [...level[0]], proof };
+}
+
+export function usageCheckpointPda(
+  programId: PublicKey,
+  apiKey: PublicKey,
+  checkpointSeq: anchor.BN
+): PublicKey {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("usage"), apiKey.toBuffer(), checkpointSeq.toArrayLike(Buffer, "le", 8)],
+    programId
+  );
+  return pda;
+}
+
 export function abuseSignalPda(
   programId: PublicKey,
   subject: PublicKey,